@@ -0,0 +1,181 @@
+//! Compares the index backends, and a sharded interner, on the workloads
+//! that matter when picking one: interning new strings, interning strings
+//! that are already there, reading strings back, and several threads
+//! interning a mix of both at once.
+//!
+//! Run with `cargo bench --bench backends`, or pick out one workload with
+//! e.g. `cargo bench --bench backends -- intern_hit`.
+
+use criterion::{
+  criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion,
+  Throughput,
+};
+use std::{sync::Arc, thread};
+use str_interning::sync::{
+  IndexBackend, Intern, Interner, InternerBuilder, ShardedInterner,
+};
+
+const THREADS: usize = 4;
+const SHARDS: usize = 16;
+
+#[derive(Clone, Copy)]
+enum Backend {
+  Trie,
+  Hash,
+  Sharded,
+}
+
+impl Backend {
+  const ALL: [Self; 3] = [Self::Trie, Self::Hash, Self::Sharded];
+
+  fn name(self) -> &'static str {
+    match self {
+      Self::Trie => "trie",
+      Self::Hash => "hash",
+      Self::Sharded => "sharded",
+    }
+  }
+
+  fn build(self) -> Built {
+    match self {
+      Self::Trie => Built::Single(Interner::new()),
+      Self::Hash => {
+        Built::Single(InternerBuilder::new().index(IndexBackend::Hash).build())
+      },
+      Self::Sharded => {
+        Built::Sharded(InternerBuilder::new().build_sharded(SHARDS))
+      },
+    }
+  }
+}
+
+enum Built {
+  Single(Arc<Interner>),
+  Sharded(ShardedInterner),
+}
+
+impl Built {
+  fn intern(&self, s: &str) -> Intern {
+    match self {
+      Self::Single(interner) => interner.intern(s),
+      Self::Sharded(interner) => interner.intern(s),
+    }
+  }
+
+  fn intern_all(&self, words: &[String]) -> Vec<Intern> {
+    words.iter().map(|word| self.intern(word)).collect()
+  }
+}
+
+/// Identifiers of the kind a compiler sees, many of which share prefixes
+fn identifiers() -> Vec<String> {
+  let verbs = ["get", "set", "is", "make", "parse", "into", "try", "with"];
+  let nouns = [
+    "name", "value", "index", "buffer", "token", "symbol", "span", "node",
+    "parent", "child", "length", "capacity",
+  ];
+
+  let mut out = Vec::new();
+  for verb in verbs {
+    for noun in nouns {
+      for suffix in ["", "_mut", "_unchecked", "_or_default"] {
+        out.push(format!("{verb}_{noun}{suffix}"));
+      }
+    }
+  }
+  for i in 0..4000 {
+    out.push(format!("tmp{i}"));
+  }
+  out
+}
+
+fn intern_miss(c: &mut Criterion) {
+  let words = identifiers();
+  let mut group = c.benchmark_group("intern_miss");
+  group.throughput(Throughput::Elements(words.len() as u64));
+
+  for backend in Backend::ALL {
+    group.bench_function(backend.name(), |b| {
+      b.iter_batched(
+        || backend.build(),
+        |built| built.intern_all(&words),
+        BatchSize::LargeInput,
+      );
+    });
+  }
+  group.finish();
+}
+
+fn intern_hit(c: &mut Criterion) {
+  let words = identifiers();
+  let mut group = c.benchmark_group("intern_hit");
+  group.throughput(Throughput::Elements(words.len() as u64));
+
+  for backend in Backend::ALL {
+    let built = backend.build();
+    let _held = built.intern_all(&words);
+    group.bench_function(backend.name(), |b| {
+      b.iter(|| built.intern_all(&words));
+    });
+  }
+  group.finish();
+}
+
+fn resolve(c: &mut Criterion) {
+  let words = identifiers();
+  let mut group = c.benchmark_group("resolve");
+  group.throughput(Throughput::Elements(words.len() as u64));
+
+  for backend in Backend::ALL {
+    let built = backend.build();
+    let interns = built.intern_all(&words);
+    group.bench_function(backend.name(), |b| {
+      b.iter(|| {
+        interns
+          .iter()
+          .map(|intern| intern.get_ref().len())
+          .sum::<usize>()
+      });
+    });
+  }
+  group.finish();
+}
+
+/// Every thread interns every word, starting at a different place, so about
+/// one in [THREADS] interns is a miss and the rest are hits
+fn mixed_threads(c: &mut Criterion) {
+  let words = identifiers();
+  let mut group = c.benchmark_group("mixed_threads");
+  group.throughput(Throughput::Elements((words.len() * THREADS) as u64));
+
+  for backend in Backend::ALL {
+    group.bench_with_input(
+      BenchmarkId::new(backend.name(), THREADS),
+      &words,
+      |b, words| {
+        b.iter_batched(
+          || backend.build(),
+          |built| {
+            thread::scope(|scope| {
+              for t in 0..THREADS {
+                let built = &built;
+                scope.spawn(move || {
+                  let start = words.len() * t / THREADS;
+                  for word in words[start..].iter().chain(&words[..start]) {
+                    built.intern(word);
+                  }
+                });
+              }
+            });
+            built
+          },
+          BatchSize::LargeInput,
+        );
+      },
+    );
+  }
+  group.finish();
+}
+
+criterion_group!(benches, intern_miss, intern_hit, resolve, mixed_threads);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+//! Compares the size of the interned-string trie against what a trie with one
+//! node per character would need, for a workload of typical identifiers.
+//!
+//! Run with `cargo bench --bench memory`.
+
+use std::collections::HashSet;
+use str_interning::sync::Interner;
+
+fn identifiers() -> Vec<String> {
+  let verbs = ["get", "set", "is", "has", "make", "parse", "into", "try"];
+  let nouns = [
+    "name", "value", "index", "buffer", "token", "symbol", "span", "node",
+    "parent", "child", "length", "capacity", "interner", "string",
+  ];
+
+  let mut out = Vec::new();
+  for verb in verbs {
+    for noun in nouns {
+      for suffix in ["", "_mut", "_unchecked", "_or_default"] {
+        out.push(format!("{verb}_{noun}{suffix}"));
+        out.push(format!("{verb}{}{suffix}", capitalize(noun)));
+      }
+    }
+  }
+  for i in 0..2000 {
+    out.push(format!("tmp{i}"));
+    out.push(format!("__local_var_{i}"));
+  }
+
+  out
+}
+
+fn capitalize(s: &str) -> String {
+  let mut chars = s.chars();
+  chars
+    .next()
+    .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+    .unwrap_or_default()
+}
+
+fn main() {
+  let idents = identifiers();
+  let interner = Interner::new();
+  for ident in &idents {
+    interner.intern(ident);
+  }
+
+  let char_trie_nodes = idents
+    .iter()
+    .flat_map(|s| (1..=s.len()).map(move |i| &s[..i]))
+    .collect::<HashSet<_>>()
+    .len();
+  let stats = interner.stats();
+
+  println!("identifiers:          {}", idents.len());
+  println!("stored bytes:         {}", stats.stored_bytes);
+  println!("per-character nodes:  {char_trie_nodes}");
+  println!("radix trie nodes:     {}", stats.trie_nodes);
+  println!(
+    "reduction:            {:.1}x",
+    char_trie_nodes as f64 / stats.trie_nodes as f64
+  );
+}
@@ -0,0 +1,6 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use str_interning::testing::{check_unsync, Op};
+
+fuzz_target!(|ops: Vec<Op>| check_unsync(&ops));
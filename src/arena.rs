@@ -0,0 +1,156 @@
+use std::{cell::RefCell, collections::HashSet, fmt};
+
+const MIN_CHUNK: usize = 1024;
+
+/// An interner that hands out plain `&str`s, which live as long as the
+/// interner itself. Strings are never moved or freed before the interner is
+/// dropped, so there are no reference counts to keep up and nothing to
+/// wait for, and the borrow checker makes sure that no string outlives the
+/// interner. The price is that unused strings can't be collected, which
+/// suits a program that interns everything up front, such as a single-pass
+/// compiler, and then drops the whole interner at once.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::arena::Interner;
+///
+/// let interner = Interner::new();
+///
+/// let hello = interner.intern("hello");
+///
+/// assert!(std::ptr::eq(hello, interner.intern("hello")));
+/// assert_eq!(interner.get("hello"), Some(hello));
+/// assert_eq!(interner.get("world"), None);
+/// ```
+///
+/// Strings can't be used once the interner is gone.
+///
+/// ```compile_fail
+/// use str_interning::arena::Interner;
+///
+/// let interner = Interner::new();
+/// let hello = interner.intern("hello");
+/// drop(interner);
+///
+/// println!("{hello}");
+/// ```
+#[derive(Default)]
+pub struct Interner {
+  /// Where the strings live. A chunk is never pushed to past its capacity,
+  /// so it never reallocates, and strings in it stay put until the
+  /// interner is dropped.
+  chunks: RefCell<Vec<String>>,
+  /// Every distinct string, pointing into `chunks`. They are really only
+  /// valid for as long as the interner is.
+  strings: RefCell<HashSet<&'static str>>,
+}
+
+impl Interner {
+  /// Constructs an empty Interner
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Constructs an Interner with room for roughly `bytes` bytes of string
+  /// data and `entries` distinct strings before it needs to allocate
+  pub fn with_capacity(bytes: usize, entries: usize) -> Self {
+    Self {
+      chunks: RefCell::new(vec![String::with_capacity(bytes)]),
+      strings: RefCell::new(HashSet::with_capacity(entries)),
+    }
+  }
+
+  /// Interns `s`, returning the copy of it that the interner holds. Interning
+  /// the same text again returns the very same `&str`.
+  pub fn intern(&self, s: &str) -> &str {
+    if let Some(interned) = self.get(s) {
+      return interned;
+    }
+
+    let interned = self.alloc(s);
+    self.strings.borrow_mut().insert(interned);
+    interned
+  }
+
+  /// Finds a previously interned string
+  pub fn get(&self, s: &str) -> Option<&str> {
+    self.strings.borrow().get(s).copied()
+  }
+
+  /// Number of distinct strings
+  pub fn len(&self) -> usize {
+    self.strings.borrow().len()
+  }
+
+  /// Whether there are no strings at all
+  pub fn is_empty(&self) -> bool {
+    self.strings.borrow().is_empty()
+  }
+
+  /// How many bytes of string data are held
+  pub fn stored_bytes(&self) -> usize {
+    self.chunks.borrow().iter().map(String::len).sum()
+  }
+
+  /// Copies `s` into a chunk with room for it
+  fn alloc(&self, s: &str) -> &'static str {
+    let mut chunks = self.chunks.borrow_mut();
+    let fits = chunks
+      .last()
+      .is_some_and(|chunk| chunk.capacity() - chunk.len() >= s.len());
+    if !fits {
+      let last = chunks.last().map_or(0, String::capacity);
+      let capacity = (last * 2).max(s.len()).max(MIN_CHUNK);
+      chunks.push(String::with_capacity(capacity));
+    }
+
+    let chunk = chunks.last_mut().unwrap();
+    let start = chunk.len();
+    chunk.push_str(s);
+    let interned: *const str = &chunk[start..];
+
+    // SAFETY: the chunk had room, so it didn't reallocate, and it won't be
+    //         touched again until the interner is dropped. Only borrows of
+    //         the interner are handed out, which can't outlive it.
+    unsafe { &*interned }
+  }
+}
+
+impl fmt::Debug for Interner {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Interner")
+      .field("len", &self.len())
+      .finish_non_exhaustive()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::ptr;
+
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn arena_interner() {
+    let interner = Interner::with_capacity(4, 2);
+    let words = (0..1000)
+      .map(|i| interner.intern(&format!("word{i}")))
+      .collect::<Vec<_>>();
+    let long = "long".repeat(1000);
+
+    assert!(ptr::eq(interner.intern(&long), interner.intern(&long)));
+    for (i, word) in words.iter().enumerate() {
+      assert_eq!(*word, format!("word{i}"));
+      assert!(ptr::eq(*word, interner.intern(word)));
+    }
+    assert_eq!(interner.intern(""), "");
+    assert_eq!(interner.len(), 1002);
+    assert_eq!(
+      interner.stored_bytes(),
+      words.iter().map(|word| word.len()).sum::<usize>() + long.len()
+    );
+    assert_eq!(interner.get("word1000"), None);
+    assert!(Interner::new().is_empty());
+  }
+}
@@ -0,0 +1,75 @@
+use crate::sync::{Intern, Interner};
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+static GLOBAL: Lazy<Arc<Interner>> = Lazy::new(Interner::new);
+
+/// The process-wide [Interner], created the first time it is used.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::global;
+///
+/// let hello = global::interner().intern("hello");
+///
+/// assert_eq!(hello, global::intern("hello"));
+/// ```
+pub fn interner() -> &'static Arc<Interner> {
+  &GLOBAL
+}
+
+/// Creates an interned string in the process-wide [Interner]
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::global::intern;
+///
+/// let hello = intern("hello");
+///
+/// assert_eq!(&*hello.get_ref(), "hello");
+/// ```
+pub fn intern<S: AsRef<str>>(s: S) -> Intern {
+  GLOBAL.intern(s)
+}
+
+/// Interns a string literal in the process-wide interner. The resulting
+/// [Intern] is cached at the call site, so evaluating the same `intern!`
+/// repeatedly only interns once.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::{global, intern};
+///
+/// let hello = intern!("hello");
+///
+/// assert_eq!(hello, global::intern("hello"));
+/// ```
+#[macro_export]
+macro_rules! intern {
+  ($s:literal) => {{
+    static CACHED: $crate::global::OnceCell<$crate::sync::Intern> =
+      $crate::global::OnceCell::new();
+    CACHED.get_or_init(|| $crate::global::intern($s)).clone()
+  }};
+}
+
+#[doc(hidden)]
+pub use once_cell::sync::OnceCell;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::thread;
+
+  #[test]
+  fn global_interner_is_shared() {
+    let here = intern("shared");
+    let there = thread::spawn(|| crate::intern!("shared")).join().unwrap();
+
+    assert_eq!(here, there);
+    assert!(Arc::ptr_eq(interner(), interner()));
+  }
+}
@@ -1,2 +1,185 @@
+#![forbid(unsafe_op_in_unsafe_fn)]
+#![deny(clippy::undocumented_unsafe_blocks)]
+
+pub mod arena;
+#[cfg(feature = "global")]
+pub mod global;
+#[cfg(feature = "pyo3")]
+pub mod python;
 pub mod sync;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod traits;
 pub mod unsync;
+
+#[cfg(feature = "global")]
+pub use global::intern;
+
+/// Interns formatted text, like [format!] followed by interning the result,
+/// but without building a temporary [String]. Works with both
+/// [sync::Interner::intern_fmt] and [unsync::Interner::intern_fmt].
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::{intern_format, sync::Interner};
+///
+/// let interner = Interner::new();
+///
+/// let temp = intern_format!(interner, "tmp{}", 3);
+///
+/// assert_eq!(temp, interner.intern("tmp3"));
+/// ```
+#[macro_export]
+macro_rules! intern_format {
+  ($interner:expr, $($arg:tt)*) => {
+    $interner.intern_fmt(::std::format_args!($($arg)*))
+  };
+}
+
+/// Declares a list of keywords for [sync::InternerBuilder::with_keywords] or
+/// [unsync::InternerBuilder::with_keywords], along with a constant holding
+/// the id that each keyword will get, so that a compiler can check for a
+/// keyword with a plain integer comparison, or `match` on ids. The first
+/// line names the list, and each line after it names a constant, its type,
+/// which is [sync::SymbolId] or [unsync::SymbolId], and its keyword.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::{
+///   keywords,
+///   sync::{InternerBuilder, SymbolId},
+/// };
+///
+/// keywords! {
+///   pub const KEYWORDS;
+///   pub const KW_FN: SymbolId = "fn";
+///   pub const KW_LET: SymbolId = "let";
+/// }
+///
+/// let interner = InternerBuilder::new().with_keywords(KEYWORDS).build();
+///
+/// assert_eq!(KEYWORDS, ["fn", "let"]);
+/// assert_eq!(interner.intern("let").id(), KW_LET);
+/// assert!(matches!(interner.intern_symbol("fn").id(), KW_FN));
+/// ```
+#[macro_export]
+macro_rules! keywords {
+  (
+    $list_vis:vis const $list:ident;
+    $($vis:vis const $name:ident: $ty:ident = $keyword:literal;)*
+  ) => {
+    $list_vis const $list: &[&str] = &[$($keyword),*];
+    $crate::keywords!(@ids { $($name,)* } $($vis const $name: $ty;)*);
+  };
+  (@ids $names:tt $($vis:vis const $name:ident: $ty:ident;)*) => {
+    $(
+      $vis const $name: $ty = {
+        // the position of each keyword, counted by the compiler
+        #[allow(dead_code, non_camel_case_types)]
+        #[repr(u32)]
+        enum Position $names
+        $ty(Position::$name as u32)
+      };
+    )*
+  };
+}
+
+/// Declares groups of symbols that are known ahead of time, such as the
+/// keywords and well-known identifiers of a language, in the style of
+/// rustc's `kw` and `sym`. Best placed in a module of its own, where it
+/// generates:
+///
+/// - an uninhabited type for each group, with a constant holding the
+///   [SymbolId](sync::SymbolId) of each of its symbols, `ALL` listing its
+///   strings, and `contains` checking whether an id is one of its symbols
+/// - `SYMBOLS`, listing every string in order, which is ready for
+///   [InternerBuilder::with_keywords](sync::InternerBuilder::with_keywords)
+/// - `interner()`, which constructs an [Interner](sync::Interner) seeded
+///   with `SYMBOLS`, so that the ids of the constants are right
+///
+/// Ids are handed out in order across groups, starting from 0. The
+/// constants are [sync] types, unless the symbols start with `unsync;`.
+///
+/// # Examples
+///
+/// ```
+/// mod symbols {
+///   str_interning::symbols! {
+///     Kw { Fn: "fn", Let: "let" },
+///     Sym { Len: "len" },
+///   }
+/// }
+///
+/// use symbols::{Kw, Sym};
+///
+/// let interner = symbols::interner();
+/// let len = interner.intern("len").id();
+///
+/// assert_eq!(len, Sym::Len);
+/// assert_eq!(interner.intern_symbol("let").id(), Kw::Let);
+/// assert!(Kw::contains(Kw::Fn) && !Kw::contains(len));
+/// assert!(Sym::contains(interner.intern("len").id()));
+/// assert_eq!(symbols::SYMBOLS, ["fn", "let", "len"]);
+/// ```
+#[macro_export]
+macro_rules! symbols {
+  (@symbols $flavor:ident, $interner:ty; $(
+    $group:ident { $($name:ident: $symbol:literal),* $(,)? }
+  ),* $(,)?) => {
+    pub const SYMBOLS: &[&str] = &[$($($symbol),*),*];
+
+    pub fn interner() -> $interner {
+      $crate::$flavor::InternerBuilder::new()
+        .with_keywords(SYMBOLS)
+        .build()
+    }
+
+    $crate::symbols!(@groups $flavor 0; $($group { $($name: $symbol),* })*);
+  };
+  (@groups $flavor:ident $first:expr;
+    $group:ident { $($name:ident: $symbol:literal),* }
+    $($rest:tt)*
+  ) => {
+    pub enum $group {}
+
+    #[allow(dead_code, non_upper_case_globals)]
+    impl $group {
+      pub const ALL: &'static [&'static str] = &[$($symbol),*];
+      const FIRST: u32 = $first;
+
+      $crate::symbols!(@ids $flavor { $($name,)* } $($name)*);
+
+      pub const fn contains(id: $crate::$flavor::SymbolId) -> bool {
+        id.0 >= Self::FIRST && id.0 - Self::FIRST < Self::ALL.len() as u32
+      }
+    }
+
+    $crate::symbols!(
+      @groups $flavor $group::FIRST + $group::ALL.len() as u32; $($rest)*
+    );
+  };
+  (@groups $flavor:ident $first:expr;) => {};
+  (@ids $flavor:ident $names:tt $($name:ident)*) => {
+    $(
+      pub const $name: $crate::$flavor::SymbolId = {
+        // the position of each symbol in the group, counted by the compiler
+        #[allow(dead_code, non_camel_case_types)]
+        #[repr(u32)]
+        enum Position $names
+        $crate::$flavor::SymbolId(Self::FIRST + Position::$name as u32)
+      };
+    )*
+  };
+  (unsync; $($groups:tt)*) => {
+    $crate::symbols!(
+      @symbols unsync, ::std::rc::Rc<$crate::unsync::Interner>; $($groups)*
+    );
+  };
+  ($($groups:tt)*) => {
+    $crate::symbols!(
+      @symbols sync, ::std::sync::Arc<$crate::sync::Interner>; $($groups)*
+    );
+  };
+}
@@ -0,0 +1,191 @@
+use crate::sync::{self, Intern};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyString};
+use std::{
+  hash::{DefaultHasher, Hash, Hasher},
+  sync::Arc,
+};
+
+/// An interner, as the `Interner` Python class
+#[pyclass(name = "Interner", module = "str_interning", frozen)]
+pub struct PyInterner {
+  interner: Arc<sync::Interner>,
+}
+
+#[pymethods]
+impl PyInterner {
+  #[new]
+  fn new() -> Self {
+    Self::from(sync::Interner::new())
+  }
+
+  /// Interns `s`, returning the Symbol that stands for it
+  fn intern(&self, s: &str) -> PySymbol {
+    PySymbol(self.interner.intern(s))
+  }
+
+  /// Interns every string in `strings`, such as a column of data, returning
+  /// their Symbols in the same order
+  fn intern_all(&self, strings: Vec<String>) -> Vec<PySymbol> {
+    self
+      .interner
+      .intern_all(strings)
+      .into_iter()
+      .map(PySymbol)
+      .collect()
+  }
+
+  /// Finds the Symbol of a previously interned string, if there is one
+  fn get(&self, s: &str) -> Option<PySymbol> {
+    self.interner.get(s).map(PySymbol)
+  }
+
+  /// Reads back the string that `symbol` stands for. Raises ValueError if
+  /// it came from another Interner.
+  fn resolve<'py>(
+    &self,
+    py: Python<'py>,
+    symbol: &PySymbol,
+  ) -> PyResult<Bound<'py, PyString>> {
+    if !symbol.0.is_from(&self.interner) {
+      return Err(PyValueError::new_err(
+        "symbol does not belong to this interner",
+      ));
+    }
+
+    Ok(symbol.0.with_str(|s| PyString::new(py, s)))
+  }
+
+  /// Frees strings that no Symbol stands for anymore, returning how many
+  /// bytes were freed
+  fn collect(&self, py: Python<'_>) -> usize {
+    py.allow_threads(|| self.interner.collect())
+  }
+
+  fn __len__(&self) -> usize {
+    self.interner.stats().entries
+  }
+
+  fn __repr__(&self) -> String {
+    format!("<Interner of {} strings>", self.__len__())
+  }
+}
+
+impl From<Arc<sync::Interner>> for PyInterner {
+  fn from(interner: Arc<sync::Interner>) -> Self {
+    Self { interner }
+  }
+}
+
+/// An interned string, as the `Symbol` Python class. Symbols from the same
+/// Interner are equal if their strings are, and keep their strings from
+/// being collected.
+#[pyclass(name = "Symbol", module = "str_interning", frozen)]
+pub struct PySymbol(Intern);
+
+#[pymethods]
+impl PySymbol {
+  /// A dense integer that identifies the string within its Interner
+  #[getter]
+  fn id(&self) -> u32 {
+    self.0.id().0
+  }
+
+  fn __str__<'py>(&self, py: Python<'py>) -> Bound<'py, PyString> {
+    self.0.with_str(|s| PyString::new(py, s))
+  }
+
+  fn __repr__(&self) -> String {
+    format!("Symbol({:?})", self.0)
+  }
+
+  fn __eq__(&self, other: &Self) -> bool {
+    self.0 == other.0
+  }
+
+  fn __hash__(&self) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    self.0.hash(&mut hasher);
+    hasher.finish()
+  }
+
+  fn __len__(&self) -> usize {
+    self.0.len()
+  }
+}
+
+impl From<Intern> for PySymbol {
+  fn from(intern: Intern) -> Self {
+    Self(intern)
+  }
+}
+
+impl From<PySymbol> for Intern {
+  fn from(symbol: PySymbol) -> Self {
+    symbol.0
+  }
+}
+
+/// The `str_interning` Python module, which lets Python code intern through
+/// a [sync::Interner], such as to deduplicate the strings in a column of
+/// data. A `cdylib` crate that depends on this one can export it as an
+/// extension module by enabling pyo3's `extension-module` feature, and be
+/// built with a tool such as maturin.
+///
+/// ```python
+/// from str_interning import Interner
+///
+/// interner = Interner()
+/// column = interner.intern_all(["red", "green", "red"])
+///
+/// assert column[0] == column[2]
+/// assert interner.resolve(column[1]) == "green"
+/// assert len(interner) == 2
+/// ```
+#[pymodule]
+pub fn str_interning(module: &Bound<'_, PyModule>) -> PyResult<()> {
+  module.add_class::<PyInterner>()?;
+  module.add_class::<PySymbol>()?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pyo3::types::PyDict;
+  use std::ffi::CString;
+
+  #[test]
+  fn python_bindings() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+      let module = PyModule::new(py, "str_interning").unwrap();
+      str_interning(&module).unwrap();
+      let globals = PyDict::new(py);
+      globals.set_item("str_interning", module).unwrap();
+
+      let script = CString::new(
+        r#"
+interner = str_interning.Interner()
+column = interner.intern_all(["red", "green", "red"])
+
+assert column[0] == column[2] and column[0] != column[1]
+assert hash(column[0]) == hash(interner.intern("red"))
+assert interner.resolve(column[1]) == "green" == str(column[1])
+assert interner.get("blue") is None
+assert len(interner) == 2 and column[1].id == 1
+
+try:
+  str_interning.Interner().resolve(column[0])
+  assert False
+except ValueError:
+  pass
+
+del column
+assert interner.collect() == len("redgreen")
+"#,
+      )
+      .unwrap();
+      py.run(&script, Some(&globals), None).unwrap();
+    });
+  }
+}
@@ -1,6 +1,72 @@
+#[cfg(feature = "tokio")]
+mod async_interner;
+mod automaton;
+mod builder;
+mod bytes;
+mod compact;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+mod dictionary;
 mod error;
+mod escape;
+mod folding;
+mod frozen;
+mod index;
 mod interning;
+mod leaks;
+mod lock;
+#[cfg(feature = "aho-corasick")]
+mod matcher;
+mod merge;
+#[cfg(feature = "memmap2")]
+mod mmap;
+mod namespace;
+mod normalization;
+mod observer;
+mod parked;
+mod path;
+mod persistence;
+mod scribe;
+mod sequence;
+#[cfg(feature = "serde")]
+mod serialization;
+mod sharded;
+mod source;
+mod stats;
+mod store;
+mod symbol;
 mod trie;
 
-pub use error::InternError;
-pub use interning::{Intern, InternRef, Interner};
+#[cfg(feature = "tokio")]
+pub use async_interner::AsyncInterner;
+pub use builder::{InternerBuilder, RefsHeldPolicy, StoreFullPolicy};
+pub use bytes::{ByteInterner, InternBytes, InternBytesRef};
+pub use compact::{CompactIntern, INLINE_CAPACITY};
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::{DiagnosticsReport, Holder};
+pub use error::{InternError, PartsError, RefError, SliceError, UnescapeError};
+pub use escape::EscapeStyle;
+pub use folding::CaseFolding;
+pub use frozen::{FrozenIntern, FrozenInterner};
+pub use index::IndexBackend;
+pub use interning::{
+  DetachedIntern, Intern, InternDisplay, InternRef, InternScope, InternStr,
+  Interner, InternerIter, PendingIntern, WeakIntern,
+};
+#[cfg(feature = "aho-corasick")]
+pub use matcher::InternMatcher;
+pub use merge::MergeMap;
+#[cfg(feature = "memmap2")]
+pub use mmap::{MmapIntern, MmapInterner};
+pub use namespace::{NamespacedInterner, TaggedIntern};
+pub use normalization::Normalization;
+pub use observer::InternerObserver;
+pub use path::InternPath;
+pub use scribe::{LocalScribe, ProvisionalId};
+pub use sequence::{InternSequence, SequenceInterner};
+#[cfg(feature = "serde")]
+pub use serialization::InternSeed;
+pub use sharded::ShardedInterner;
+pub use source::{LineColumn, SourceId};
+pub use stats::InternerStats;
+pub use symbol::{Symbol, SymbolId};
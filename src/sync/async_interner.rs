@@ -0,0 +1,182 @@
+use crate::sync::{Intern, InternError, Interner};
+use std::{fmt, panic, sync::Arc};
+
+/// A handle to an [Interner] for async code running on tokio. Interning
+/// never holds the lock for long, but a task that waits for it still ties
+/// up its worker thread, and every other task scheduled there. Here a
+/// string is interned on the spot if the lock is free, and otherwise the
+/// wait is handed to tokio's blocking thread pool, so the task awaits it
+/// instead.
+///
+/// Readers never hold up interning, so [InternRef](crate::sync::InternRef)s
+/// can be held across `.await`s as usual.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::AsyncInterner;
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+/// let interner = AsyncInterner::new();
+///
+/// let hello = runtime.block_on(interner.intern("hello"));
+///
+/// assert_eq!(&*hello.get_ref(), "hello");
+/// assert_eq!(hello, interner.interner().intern("hello"));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct AsyncInterner {
+  interner: Arc<Interner>,
+}
+
+impl AsyncInterner {
+  /// Constructs an AsyncInterner around a new [Interner] with the default
+  /// configuration. Use [AsyncInterner::from] to wrap a configured one.
+  pub fn new() -> Self {
+    Self::from(Interner::new())
+  }
+
+  /// The [Interner] underneath, which can be used to reach the rest of its
+  /// API
+  pub fn interner(&self) -> &Arc<Interner> {
+    &self.interner
+  }
+
+  /// Creates an interned string, like [Interner::intern], awaiting the lock
+  /// rather than blocking on it
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern], or if it has
+  /// to wait outside of a tokio runtime.
+  pub async fn intern<S: AsRef<str>>(&self, s: S) -> Intern {
+    match self.try_intern(s).await {
+      Ok(intern) => intern,
+      Err(e) => panic!("{e}"),
+    }
+  }
+
+  /// Creates an interned string, like [Interner::try_intern], awaiting the
+  /// lock rather than blocking on it
+  ///
+  /// # Panics
+  ///
+  /// Panics if it has to wait outside of a tokio runtime.
+  pub async fn try_intern<S: AsRef<str>>(
+    &self,
+    s: S,
+  ) -> Result<Intern, InternError> {
+    match self.interner.try_intern_nonblocking(s.as_ref()) {
+      Err(InternError::WouldBlock) => {},
+      result => return result,
+    }
+
+    let s = s.as_ref().to_owned();
+    self.blocking(move |interner| interner.try_intern(s)).await
+  }
+
+  /// Frees unused strings, like [Interner::collect], awaiting the lock
+  /// rather than blocking on it
+  ///
+  /// # Panics
+  ///
+  /// Panics if called outside of a tokio runtime.
+  pub async fn collect(&self) -> usize {
+    self.blocking(|interner| interner.collect()).await
+  }
+
+  /// Runs `f` on tokio's blocking thread pool
+  async fn blocking<R: Send + 'static>(
+    &self,
+    f: impl FnOnce(Arc<Interner>) -> R + Send + 'static,
+  ) -> R {
+    let interner = Arc::clone(&self.interner);
+    match tokio::task::spawn_blocking(move || f(interner)).await {
+      Ok(result) => result,
+      Err(e) => panic::resume_unwind(e.into_panic()),
+    }
+  }
+}
+
+impl Default for AsyncInterner {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl From<Arc<Interner>> for AsyncInterner {
+  fn from(interner: Arc<Interner>) -> Self {
+    Self { interner }
+  }
+}
+
+impl fmt::Debug for AsyncInterner {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("AsyncInterner").finish_non_exhaustive()
+  }
+}
+
+// contends for the lock, which single-threaded builds panic on instead
+#[cfg(all(test, not(feature = "single-threaded")))]
+mod tests {
+  use super::*;
+  use std::{
+    sync::{mpsc, Barrier},
+    thread,
+  };
+  use tokio::runtime::Builder;
+
+  /// Holds up formatting, and with it the interner's lock, until told to
+  /// go on
+  struct Stall<'a> {
+    locked: &'a Barrier,
+    unlock: mpsc::Receiver<()>,
+  }
+
+  impl fmt::Display for Stall<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      self.locked.wait();
+      self.unlock.recv().unwrap();
+      f.write_str("stalled")
+    }
+  }
+
+  #[test]
+  fn async_interner_awaits_the_lock() {
+    let runtime = Builder::new_current_thread().build().unwrap();
+    let interner = AsyncInterner::new();
+    let hello = interner.interner().intern("hello");
+    let locked = Barrier::new(2);
+    let (unlock, stalled) = mpsc::channel();
+
+    let world = thread::scope(|scope| {
+      scope.spawn(|| {
+        let stall = Stall {
+          locked: &locked,
+          unlock: stalled,
+        };
+        interner.interner().intern_fmt(format_args!("{stall}"))
+      });
+      locked.wait();
+
+      runtime.block_on(async {
+        let world = tokio::spawn({
+          let interner = interner.clone();
+          async move { interner.intern("world").await }
+        });
+        // the runtime's only thread is free to run other tasks meanwhile
+        assert_eq!(tokio::spawn(async { 1 }).await.unwrap(), 1);
+        unlock.send(()).unwrap();
+        world.await.unwrap()
+      })
+    });
+
+    assert_eq!(&*world.get_ref(), "world");
+    runtime.block_on(async {
+      assert_eq!(interner.intern("hello").await, hello);
+      drop(world);
+      assert_eq!(interner.collect().await, 12);
+    });
+  }
+}
@@ -0,0 +1,190 @@
+use crate::sync::trie::Span;
+
+const ROOT: usize = 0;
+
+/// A generalized suffix automaton over every string in the store. It
+/// recognizes exactly the substrings of the stored strings, and for each one
+/// can say where an occurrence ends, all in space linear in the size of the
+/// store.
+#[derive(Debug)]
+pub(crate) struct SuffixAutomaton {
+  states: Vec<State>,
+}
+
+#[derive(Clone, Debug)]
+struct State {
+  /// Length of the longest string this state recognizes
+  len: usize,
+  /// Suffix link, which is only `None` for the root
+  link: Option<usize>,
+  /// Store offset just past the first occurrence of this state's strings
+  end: usize,
+  /// Outgoing transitions, sorted by byte
+  next: Vec<(u8, usize)>,
+}
+
+impl State {
+  fn next(&self, b: u8) -> Option<usize> {
+    let i = self.next.binary_search_by_key(&b, |&(b, _)| b).ok()?;
+    Some(self.next[i].1)
+  }
+
+  fn set_next(&mut self, b: u8, state: usize) {
+    match self.next.binary_search_by_key(&b, |&(b, _)| b) {
+      Ok(i) => self.next[i].1 = state,
+      Err(i) => self.next.insert(i, (b, state)),
+    }
+  }
+}
+
+impl SuffixAutomaton {
+  pub(crate) fn new() -> Self {
+    Self {
+      states: vec![State {
+        len: 0,
+        link: None,
+        end: 0,
+        next: Vec::new(),
+      }],
+    }
+  }
+
+  /// Number of states, including the root
+  pub(crate) fn states(&self) -> usize {
+    self.states.len()
+  }
+
+  pub(crate) fn shrink_to_fit(&mut self) {
+    self.states.shrink_to_fit();
+    for state in &mut self.states {
+      state.next.shrink_to_fit();
+    }
+  }
+
+  /// Finds where `key` occurs in the store, if it occurs at all
+  pub(crate) fn find(&self, key: &[u8]) -> Option<Span> {
+    let mut cursor = ROOT;
+    for &b in key {
+      cursor = self.states[cursor].next(b)?;
+    }
+
+    let end = self.states[cursor].end;
+    Some((end - key.len())..end)
+  }
+
+  /// Adds the substrings of `key`, which was just pushed to the store at
+  /// `start`
+  pub(crate) fn extend(&mut self, key: &[u8], start: usize) {
+    let mut last = ROOT;
+    for (i, &b) in key.iter().enumerate() {
+      last = self.extend_one(last, b, start + i + 1);
+    }
+  }
+
+  fn extend_one(&mut self, last: usize, b: u8, end: usize) -> usize {
+    let len = self.states[last].len + 1;
+
+    // this prefix has been seen before, so at most the state needs splitting
+    if let Some(q) = self.states[last].next(b) {
+      if self.states[q].len == len {
+        return q;
+      }
+
+      return self.split(last, b, q);
+    }
+
+    let cur = self.states.len();
+    self.states.push(State {
+      len,
+      link: None,
+      end,
+      next: Vec::new(),
+    });
+
+    let mut p = Some(last);
+    while let Some(pp) = p {
+      if self.states[pp].next(b).is_some() {
+        break;
+      }
+
+      self.states[pp].set_next(b, cur);
+      p = self.states[pp].link;
+    }
+
+    self.states[cur].link = Some(match p {
+      None => ROOT,
+      Some(pp) => {
+        let q = self.states[pp].next(b).unwrap();
+        if self.states[pp].len + 1 == self.states[q].len {
+          q
+        } else {
+          self.split(pp, b, q)
+        }
+      },
+    });
+
+    cur
+  }
+
+  /// Splits the strings of `q` that are at most one longer than the strings
+  /// of `p` off into a new state, and returns it
+  fn split(&mut self, p: usize, b: u8, q: usize) -> usize {
+    let clone = self.states.len();
+    self.states.push(State {
+      len: self.states[p].len + 1,
+      ..self.states[q].clone()
+    });
+    self.states[q].link = Some(clone);
+
+    let mut p = Some(p);
+    while let Some(pp) = p {
+      if self.states[pp].next(b) != Some(q) {
+        break;
+      }
+
+      self.states[pp].set_next(b, clone);
+      p = self.states[pp].link;
+    }
+
+    clone
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn suffix_automaton() {
+    let mut sam = SuffixAutomaton::new();
+    sam.extend(b"hello", 0);
+
+    assert_eq!(Some(1..4), sam.find(b"ell"));
+    assert_eq!(Some(3..5), sam.find(b"lo"));
+    assert_eq!(Some(2..3), sam.find(b"l"));
+    assert_eq!(Some(0..0), sam.find(b""));
+    assert_eq!(None, sam.find(b"hi"));
+    assert_eq!(None, sam.find(b"hellohello"));
+
+    sam.extend(b"yellow", 5);
+
+    assert_eq!(Some(7..11), sam.find(b"llow"));
+    assert_eq!(Some(1..5), sam.find(b"ello"));
+    assert_eq!(Some(5..9), sam.find(b"yell"));
+    assert_eq!(None, sam.find(b"oy"));
+
+    // interning the same text again mustn't disturb existing occurrences
+    sam.extend(b"hello", 11);
+    assert_eq!(Some(0..5), sam.find(b"hello"));
+  }
+
+  #[test]
+  fn suffix_automaton_is_linear() {
+    let mut sam = SuffixAutomaton::new();
+    let text = "abcdefghij".repeat(100);
+    sam.extend(text.as_bytes(), 0);
+
+    assert!(sam.states() <= 2 * text.len());
+    assert_eq!(Some(3..13), sam.find(&text.as_bytes()[503..513]));
+  }
+}
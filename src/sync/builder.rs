@@ -0,0 +1,454 @@
+use crate::sync::{
+  dictionary, CaseFolding, IndexBackend, Interner, InternerObserver,
+  Normalization, ShardedInterner,
+};
+#[cfg(feature = "diagnostics")]
+use std::time::Duration;
+use std::{io, sync::Arc};
+
+/// Configures and constructs an [Interner]. [Interner::new] is the same as
+/// building with every option left at its default.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::{CaseFolding, IndexBackend, InternerBuilder};
+///
+/// let interner = InternerBuilder::new()
+///   .index(IndexBackend::Hash)
+///   .case_folding(CaseFolding::Ascii)
+///   .capacity(1 << 16, 1000)
+///   .max_store_bytes(1 << 20)
+///   .build();
+///
+/// let hello = interner.intern("Hello");
+///
+/// assert_eq!(hello, interner.intern("hello"));
+/// assert_eq!(&*hello.get_ref(), "hello");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct InternerBuilder {
+  pub(crate) index: IndexBackend,
+  pub(crate) substring_sharing: Option<bool>,
+  pub(crate) capacity: (usize, usize),
+  pub(crate) case_folding: CaseFolding,
+  pub(crate) normalization: Normalization,
+  pub(crate) max_store_bytes: Option<usize>,
+  pub(crate) store_full: StoreFullPolicy,
+  pub(crate) refs_held: RefsHeldPolicy,
+  pub(crate) preserve_case: bool,
+  pub(crate) record_spellings: bool,
+  pub(crate) observer: Option<Arc<dyn InternerObserver>>,
+  pub(crate) keywords: Vec<String>,
+  pub(crate) dictionary: Vec<String>,
+  pub(crate) count_interns: bool,
+  pub(crate) max_outstanding_refs: Option<usize>,
+  pub(crate) detect_leaked_refs: bool,
+  #[cfg(feature = "dashmap")]
+  pub(crate) lock_free_hits: bool,
+  #[cfg(feature = "diagnostics")]
+  pub(crate) diagnostics_threshold: Option<Duration>,
+}
+
+impl InternerBuilder {
+  /// Starts a builder with every option at its default
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Selects the data structure used to find existing strings. Defaults to
+  /// [IndexBackend::Trie].
+  ///
+  /// On typical identifiers, [IndexBackend::Hash] interns new strings about
+  /// three times as fast, and strings that are already there about twice as
+  /// fast, but the trie stores less, since it shares substrings. Reading
+  /// strings back costs the same either way. The `backends` bench measures
+  /// this for each backend, with `cargo bench --bench backends`.
+  pub fn index(mut self, backend: IndexBackend) -> Self {
+    self.index = backend;
+    self
+  }
+
+  /// Chooses whether new strings may reuse data from anywhere in the store,
+  /// such as the middle of a longer string, instead of only exact matches.
+  /// A new string may also start inside the end of the store, if the store
+  /// ends with the start of the string. This saves memory when strings
+  /// overlap a lot, at the cost of indexing every substring of the store.
+  /// Defaults to on for [IndexBackend::Trie] and off for [IndexBackend::Hash].
+  pub fn substring_sharing(mut self, enabled: bool) -> Self {
+    self.substring_sharing = Some(enabled);
+    self
+  }
+
+  /// Makes room up front for roughly `bytes` bytes of string data and
+  /// `entries` distinct strings, like [Interner::with_capacity].
+  pub fn capacity(mut self, bytes: usize, entries: usize) -> Self {
+    self.capacity = (bytes, entries);
+    self
+  }
+
+  /// Selects whether strings that differ only in case are interned as the
+  /// same string. Defaults to [CaseFolding::Off].
+  pub fn case_folding(mut self, folding: CaseFolding) -> Self {
+    self.case_folding = folding;
+    self
+  }
+
+  /// Selects which Unicode normalization form strings are put in before
+  /// they are interned, after any case folding. Defaults to
+  /// [Normalization::Off].
+  pub fn normalization(mut self, normalization: Normalization) -> Self {
+    self.normalization = normalization;
+    self
+  }
+
+  /// Chooses whether strings remember the casing they were first interned
+  /// with, when [InternerBuilder::case_folding] is on. Reading an
+  /// [Intern](crate::sync::Intern) then gives that first spelling rather than
+  /// the folded string, though every spelling still produces the same
+  /// [Intern](crate::sync::Intern). First spellings that
+  /// differ from the folded string are stored alongside it. They are not
+  /// kept by [Interner::freeze] or [Interner::save_to]. Off by default.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{CaseFolding, InternerBuilder};
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .case_folding(CaseFolding::Ascii)
+  ///   .preserve_case(true)
+  ///   .build();
+  ///
+  /// let header = interner.intern("Content-Type");
+  ///
+  /// assert_eq!(header, interner.intern("content-type"));
+  /// assert_eq!(&*header.get_ref(), "Content-Type");
+  /// ```
+  pub fn preserve_case(mut self, enabled: bool) -> Self {
+    self.preserve_case = enabled;
+    self
+  }
+
+  /// Chooses whether to keep every distinct spelling that each string has
+  /// been interned as, when [InternerBuilder::case_folding] or
+  /// [InternerBuilder::normalization] is on, for [Interner::spellings] and
+  /// [Intern::first_spelling](crate::sync::Intern::first_spelling).
+  /// Spellings are kept apart from the store, and forgotten when their
+  /// string is collected. Noticing a new spelling means taking the lock, so
+  /// this turns off lock-free hits, if they were asked for. Off by default.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{CaseFolding, InternerBuilder};
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .case_folding(CaseFolding::Ascii)
+  ///   .record_spellings(true)
+  ///   .build();
+  ///
+  /// let header = interner.intern("Content-Type");
+  /// interner.intern("content-type");
+  /// interner.intern("CONTENT-TYPE");
+  ///
+  /// assert_eq!(header.first_spelling(), "Content-Type");
+  /// assert_eq!(
+  ///   interner.spellings(header.symbol()),
+  ///   ["Content-Type", "content-type", "CONTENT-TYPE"]
+  /// );
+  /// ```
+  pub fn record_spellings(mut self, enabled: bool) -> Self {
+    self.record_spellings = enabled;
+    self
+  }
+
+  /// Chooses whether to keep a concurrent map of every string that has been
+  /// interned with [Interner::intern] or [Interner::try_intern], so that
+  /// interning a string that is already there never takes the lock, and
+  /// never waits for other threads that are interning new strings. This is
+  /// worth it when most strings are interned many times over, at the cost of
+  /// a second copy of every string. Off by default.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::InternerBuilder;
+  ///
+  /// let interner = InternerBuilder::new().lock_free_hits(true).build();
+  ///
+  /// let hello = interner.intern("hello");
+  ///
+  /// assert_eq!(hello, interner.intern("hello"));
+  /// assert_eq!(interner.stats().hits, 1);
+  /// ```
+  #[cfg(feature = "dashmap")]
+  pub fn lock_free_hits(mut self, enabled: bool) -> Self {
+    self.lock_free_hits = enabled;
+    self
+  }
+
+  /// Sets how long interning waits for the lock before logging a warning
+  /// with the [Interner::diagnostics] report, which names the thread
+  /// holding the lock and every thread that is reading. It keeps waiting
+  /// afterwards. Defaults to 1 second.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::InternerBuilder;
+  /// use std::time::Duration;
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .diagnostics_threshold(Duration::from_millis(100))
+  ///   .build();
+  ///
+  /// assert_eq!(&*interner.intern("hello").get_ref(), "hello");
+  /// ```
+  #[cfg(feature = "diagnostics")]
+  pub fn diagnostics_threshold(mut self, threshold: Duration) -> Self {
+    self.diagnostics_threshold = Some(threshold);
+    self
+  }
+
+  /// Caps how many bytes of string data the store may hold. Once storing a
+  /// new string would go over the limit, [Interner::try_intern] returns
+  /// [InternError::StoreFull](crate::sync::InternError::StoreFull), though
+  /// strings that are already in the store can still be interned. Unlimited
+  /// by default. See [InternerBuilder::on_store_full] to make room instead.
+  pub fn max_store_bytes(mut self, limit: usize) -> Self {
+    self.max_store_bytes = Some(limit);
+    self
+  }
+
+  /// Selects what interning does when a new string would grow the store
+  /// past [InternerBuilder::max_store_bytes]. Defaults to
+  /// [StoreFullPolicy::Fail].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{InternerBuilder, StoreFullPolicy};
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .max_store_bytes(10)
+  ///   .on_store_full(StoreFullPolicy::Evict)
+  ///   .build();
+  ///
+  /// let hello = interner.intern("hello");
+  /// drop(interner.intern("world"));
+  /// let again = interner.intern("again");
+  ///
+  /// assert_eq!(interner.get("world"), None);
+  /// assert_eq!(&*hello.get_ref(), "hello");
+  /// assert_eq!(interner.stats().stored_bytes, 10);
+  /// ```
+  pub fn on_store_full(mut self, policy: StoreFullPolicy) -> Self {
+    self.store_full = policy;
+    self
+  }
+
+  /// Selects what interning does when [StoreFullPolicy::Evict] needs to
+  /// evict, but readers such as [InternRef](crate::sync::InternRef)s keep
+  /// strings from being moved. Defaults to [RefsHeldPolicy::Fail].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{
+  ///   InternError, InternerBuilder, RefsHeldPolicy, StoreFullPolicy,
+  /// };
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .max_store_bytes(10)
+  ///   .on_store_full(StoreFullPolicy::Evict)
+  ///   .when_refs_held(RefsHeldPolicy::Queue)
+  ///   .build();
+  /// let hello = interner.intern("hello");
+  /// drop(interner.intern("world"));
+  ///
+  /// let hello_ref = hello.get_ref();
+  /// let Err(InternError::Queued(pending)) = interner.intern_or_queue("again")
+  /// else {
+  ///   panic!("nothing could be evicted while hello_ref was alive");
+  /// };
+  /// assert!(!pending.is_ready());
+  ///
+  /// drop(hello_ref);
+  /// assert!(pending.is_ready());
+  /// assert_eq!(pending.wait()?, interner.intern("again"));
+  /// # Ok::<(), InternError>(())
+  /// ```
+  pub fn when_refs_held(mut self, policy: RefsHeldPolicy) -> Self {
+    self.refs_held = policy;
+    self
+  }
+
+  /// Interns the strings of a dictionary made by
+  /// [Interner::export_dictionary] up front, after any
+  /// [InternerBuilder::with_keywords], so that the interner starts out warm.
+  /// They are pinned, so they are never collected. Strings that are already
+  /// there, such as ones that fold to a keyword, are skipped.
+  ///
+  /// Fails with [io::ErrorKind::InvalidData] if `dictionary` isn't a
+  /// well-formed dictionary.
+  pub fn with_dictionary(mut self, dictionary: &[u8]) -> io::Result<Self> {
+    self.dictionary = dictionary::decode(dictionary)?;
+    Ok(self)
+  }
+
+  /// Chooses whether to count how many times each string is interned, for
+  /// [Interner::counts], such as for building a dictionary or deciding
+  /// which strings to pass to [InternerBuilder::with_keywords]. Counting
+  /// adds an atomic increment to every intern. Off by default.
+  pub fn count_interns(mut self, enabled: bool) -> Self {
+    self.count_interns = enabled;
+    self
+  }
+
+  /// Limits how many readers, such as
+  /// [InternRef](crate::sync::InternRef)s, may be alive at once before
+  /// [Intern::try_get_ref](crate::sync::Intern::try_get_ref) refuses to
+  /// add another, to keep long-lived readers from holding off
+  /// [Interner::collect] for good.
+  /// [Intern::get_ref](crate::sync::Intern::get_ref) isn't limited.
+  /// Unlimited by default.
+  pub fn max_outstanding_refs(mut self, limit: usize) -> Self {
+    self.max_outstanding_refs = Some(limit);
+    self
+  }
+
+  /// Chooses whether to keep track of which thread started each reader,
+  /// such as an [InternRef](crate::sync::InternRef), so that readers which
+  /// were never dropped, because they were passed to [std::mem::forget] or
+  /// a destructor never ran, can be found once their thread has exited.
+  /// [Interner::leaked_refs] counts them, and interning reports
+  /// [InternError::Poisoned](crate::sync::InternError::Poisoned) instead of
+  /// [InternError::StoreFull](crate::sync::InternError::StoreFull) when they
+  /// keep it from evicting. This takes a second lock for every reader. Off
+  /// by default.
+  ///
+  /// Readers are tracked this way regardless under
+  /// [RefsHeldPolicy::Block] and [RefsHeldPolicy::Queue], which need to
+  /// tell whether the thread that is interning holds one.
+  ///
+  /// Leaked readers are never taken back. A reader can be moved to another
+  /// thread, like an [InternStr](crate::sync::InternStr) can, and outlive
+  /// the thread that started it, so moving strings under it wouldn't be
+  /// safe.
+  pub fn detect_leaked_refs(mut self, enabled: bool) -> Self {
+    self.detect_leaked_refs = enabled;
+    self
+  }
+
+  /// Registers `observer` to be told about hits, misses, store growth and
+  /// time spent waiting for the lock, such as for exporting metrics. See
+  /// [InternerObserver] for an example.
+  pub fn observer(mut self, observer: Arc<dyn InternerObserver>) -> Self {
+    self.observer = Some(observer);
+    self
+  }
+
+  /// Interns `keywords` up front, in order, as soon as the interner is
+  /// built, so that they get the [SymbolId](crate::sync::SymbolId)s 0 to
+  /// `keywords.len() - 1`. They are pinned like [Interner::intern_symbol]
+  /// pins strings, so they keep their ids for the life of the interner.
+  /// Each shard of [InternerBuilder::build_sharded] gets its own copy. Pairs
+  /// with [keywords!](crate::keywords), which generates the list along with
+  /// a constant for each id, so that checking for a keyword is a plain
+  /// integer comparison.
+  ///
+  /// [InternerBuilder::build] panics if a keyword appears twice, including
+  /// after case folding and normalization, or if the keywords don't fit
+  /// under [InternerBuilder::max_store_bytes].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{InternerBuilder, SymbolId};
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .with_keywords(&["fn", "let"])
+  ///   .build();
+  ///
+  /// assert_eq!(interner.intern("let").id(), SymbolId(1));
+  /// assert_eq!(interner.intern_symbol("fn").id(), SymbolId(0));
+  /// assert_eq!(interner.intern("main").id(), SymbolId(2));
+  /// ```
+  pub fn with_keywords(mut self, keywords: &[&str]) -> Self {
+    self.keywords = keywords.iter().map(|&keyword| keyword.into()).collect();
+    self
+  }
+
+  /// Constructs the configured [Interner]
+  pub fn build(&self) -> Arc<Interner> {
+    Interner::from_builder(self)
+  }
+
+  /// Constructs a [ShardedInterner] with `shards` shards, each configured
+  /// like [InternerBuilder::build] would. Picking a shard costs an extra
+  /// hash, so sharding is slower than a single interner until several
+  /// threads are interning new strings at once; the `mixed_threads`
+  /// workload of the `backends` bench shows where that is on a given
+  /// machine.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `shards` is 0.
+  pub fn build_sharded(&self, shards: usize) -> ShardedInterner {
+    ShardedInterner::from_builder(self, shards)
+  }
+}
+
+/// Selects what an [Interner] does when a new string doesn't fit under
+/// [InternerBuilder::max_store_bytes]. Chosen with
+/// [InternerBuilder::on_store_full].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StoreFullPolicy {
+  /// Interning fails with
+  /// [InternError::StoreFull](crate::sync::InternError::StoreFull)
+  #[default]
+  Fail,
+  /// Strings that no [Intern](crate::sync::Intern) or
+  /// [Symbol](crate::sync::Symbol) holds are evicted to make room, starting
+  /// with those that were interned or looked up the longest time ago, like
+  /// a partial [Interner::collect]. Interning still fails with
+  /// [InternError::StoreFull](crate::sync::InternError::StoreFull) if
+  /// evicting can't make enough room, or if an
+  /// [InternRef](crate::sync::InternRef) is alive on any thread, since
+  /// evicting moves strings around, unless [InternerBuilder::when_refs_held]
+  /// says otherwise.
+  Evict,
+}
+
+/// Selects what an [Interner] does when it needs to evict strings to make
+/// room under [StoreFullPolicy::Evict], but readers such as
+/// [InternRef](crate::sync::InternRef)s are alive, so strings can't be
+/// moved. Chosen with [InternerBuilder::when_refs_held].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RefsHeldPolicy {
+  /// Interning fails with
+  /// [InternError::StoreFull](crate::sync::InternError::StoreFull)
+  #[default]
+  Fail,
+  /// Interning waits for every reader on every thread to be dropped, then
+  /// evicts. The lock isn't held while waiting, so threads that hold
+  /// readers can go on using the interner, and the waiting thread is parked
+  /// rather than spinning. If the thread that is interning started a reader
+  /// that is still alive, waiting would never end, so interning fails with
+  /// [InternError::StoreFull](crate::sync::InternError::StoreFull) instead.
+  ///
+  /// [Interner::try_intern_nonblocking] fails with
+  /// [InternError::WouldBlock](crate::sync::InternError::WouldBlock) rather
+  /// than wait, and [Interner::intern_timeout] gives up once its timeout
+  /// runs out. Ways of interning that hold the lock across many strings,
+  /// such as [Interner::intern_all], fail as with [RefsHeldPolicy::Fail].
+  Block,
+  /// The string is queued, and interned once the last reader is dropped.
+  /// [Interner::intern_or_queue] hands back a
+  /// [PendingIntern](crate::sync::PendingIntern) for it inside
+  /// [InternError::Queued](crate::sync::InternError::Queued). Everything
+  /// else waits for the queued string like [RefsHeldPolicy::Block] waits
+  /// for readers, but strings are interned in the order they were queued.
+  Queue,
+}
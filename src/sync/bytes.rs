@@ -0,0 +1,168 @@
+use crate::sync::{
+  index::{Index, IndexBackend},
+  lock::Mutex,
+  store::Store,
+  trie::Span,
+};
+use std::{
+  cmp::Ordering,
+  fmt,
+  hash::{Hash, Hasher},
+  ops::Deref,
+  sync::Arc,
+};
+
+/// An interner for arbitrary byte strings, such as file paths or network
+/// tokens that aren't guaranteed to be valid UTF-8. Behaves exactly like
+/// [Interner](crate::sync::Interner), but hands out [InternBytes].
+pub struct ByteInterner {
+  index: Mutex<Index>,
+  store: Store,
+}
+
+impl ByteInterner {
+  /// Constructs a new ByteInterner
+  pub fn new() -> Arc<Self> {
+    Arc::new(Self {
+      index: Mutex::new(Index::new(IndexBackend::Trie, true)),
+      store: Store::new(),
+    })
+  }
+
+  /// Creates an interned byte string
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::ByteInterner;
+  ///
+  /// let interner = ByteInterner::new();
+  ///
+  /// let bytes = interner.intern(b"\xff\xfe");
+  /// let bytes2 = interner.intern([0xff, 0xfe]);
+  ///
+  /// assert_eq!(bytes, bytes2);
+  /// ```
+  pub fn intern<B: AsRef<[u8]>>(self: &Arc<Self>, b: B) -> InternBytes {
+    let mut index = self.index.lock();
+
+    let b = b.as_ref();
+    let span = index.get_or_promote(b).unwrap_or_else(|| {
+      // SAFETY: we hold the lock, so no other thread is pushing
+      let span = unsafe { self.store.push_overlapping(b) };
+      index.insert(b, span)
+    });
+
+    InternBytes {
+      span,
+      interner: Arc::clone(self),
+    }
+  }
+}
+
+/// Represents a single interned byte string. This is the byte-oriented
+/// counterpart to [Intern](crate::sync::Intern).
+#[derive(Clone)]
+pub struct InternBytes {
+  span: Span,
+  interner: Arc<ByteInterner>,
+}
+
+impl InternBytes {
+  /// Produces an [InternBytesRef], which borrows the underlying bytes that
+  /// this [InternBytes] represents. This never takes a lock.
+  pub fn get_ref(&self) -> InternBytesRef<'_> {
+    InternBytesRef {
+      bytes: self.as_bytes(),
+    }
+  }
+
+  pub(crate) fn as_bytes(&self) -> &[u8] {
+    // SAFETY: our span was handed out by this interner's store
+    unsafe { self.interner.store.get_bytes(self.span.clone()) }
+  }
+
+  /// Copies the underlying bytes out into an owned [Vec]
+  pub fn resolve(&self) -> Vec<u8> {
+    self.get_ref().to_vec()
+  }
+}
+
+impl fmt::Debug for InternBytes {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("InternBytes")
+      .field("span", &self.span)
+      .field("bytes", &&*self.get_ref())
+      .finish()
+  }
+}
+
+impl PartialEq for InternBytes {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.interner, &other.interner) && self.span == other.span
+  }
+}
+impl Eq for InternBytes {}
+
+/// Orders by interner, then by position in the store, like
+/// [Intern](crate::sync::Intern)
+impl Ord for InternBytes {
+  fn cmp(&self, other: &Self) -> Ordering {
+    Arc::as_ptr(&self.interner)
+      .cmp(&Arc::as_ptr(&other.interner))
+      .then(self.span.start.cmp(&other.span.start))
+      .then(self.span.end.cmp(&other.span.end))
+  }
+}
+
+impl PartialOrd for InternBytes {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Hash for InternBytes {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    Arc::as_ptr(&self.interner).hash(state);
+    self.span.hash(state);
+  }
+}
+
+/// Allows access to the bytes that an [InternBytes] represents
+pub struct InternBytesRef<'a> {
+  bytes: &'a [u8],
+}
+
+impl fmt::Debug for InternBytesRef<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("InternBytesRef").field(&self.bytes).finish()
+  }
+}
+
+impl Deref for InternBytesRef<'_> {
+  type Target = [u8];
+
+  fn deref(&self) -> &Self::Target {
+    self.bytes
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::ptr;
+
+  #[test]
+  fn byte_interner() {
+    let interner = ByteInterner::new();
+    let invalid = interner.intern(b"\xc3\x28 tail");
+    let tail = interner.intern(b"tail");
+    let invalid2 = interner.intern(b"\xc3\x28 tail");
+
+    assert_eq!(&*invalid.get_ref(), b"\xc3\x28 tail");
+    assert_eq!(&*tail.get_ref(), b"tail");
+    assert_eq!(invalid, invalid2);
+    assert_ne!(invalid, tail);
+    assert!(ptr::addr_eq(&*invalid.get_ref(), &*invalid2.get_ref()));
+  }
+}
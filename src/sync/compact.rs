@@ -0,0 +1,260 @@
+use crate::sync::{Intern, Interner};
+use std::{
+  fmt,
+  hash::{Hash, Hasher},
+  str,
+  sync::Arc,
+};
+
+/// The longest string, in bytes, that a [CompactIntern] keeps inline
+pub const INLINE_CAPACITY: usize = 22;
+
+/// An interned string that is kept inside the handle itself when it is
+/// short, produced by [Interner::intern_compact]. Most identifiers are only
+/// a few bytes long, and interning those this way skips the store and the
+/// lock entirely. Longer strings are interned as usual, and held as an
+/// [Intern].
+///
+/// Short strings are kept in canonical form, so they read back case folded
+/// or normalized even if the interner preserves case. They are never given
+/// a [SymbolId](crate::sync::SymbolId), since they aren't in the interner's
+/// index; [CompactIntern::into_intern] interns one for real when it's
+/// needed.
+///
+/// Two compact interns are equal when they come from the same interner and
+/// stand for the same string, whichever way each is held, since whether a
+/// string is held inline only depends on its canonical form.
+#[derive(Clone)]
+pub struct CompactIntern {
+  repr: Repr,
+}
+
+#[derive(Clone)]
+enum Repr {
+  Inline {
+    interner: Arc<Interner>,
+    len: u8,
+    bytes: [u8; INLINE_CAPACITY],
+  },
+  Stored(Intern),
+}
+
+impl CompactIntern {
+  /// Holds `s`, which is in canonical form, inline if it fits, or else the
+  /// [Intern] that `stored` makes
+  pub(crate) fn new(
+    interner: &Arc<Interner>,
+    s: &str,
+    stored: impl FnOnce() -> Intern,
+  ) -> Self {
+    if s.len() > INLINE_CAPACITY {
+      return Self {
+        repr: Repr::Stored(stored()),
+      };
+    }
+
+    let mut bytes = [0; INLINE_CAPACITY];
+    bytes[..s.len()].copy_from_slice(s.as_bytes());
+    Self {
+      repr: Repr::Inline {
+        interner: Arc::clone(interner),
+        len: s.len() as u8,
+        bytes,
+      },
+    }
+  }
+
+  /// Whether the string is kept inside the handle, rather than in the store
+  pub fn is_inline(&self) -> bool {
+    matches!(self.repr, Repr::Inline { .. })
+  }
+
+  /// Calls `f` with the string. Inline strings are read straight out of the
+  /// handle, and the rest like [Intern::with_str].
+  pub fn with_str<R>(&self, f: impl FnOnce(&str) -> R) -> R {
+    match &self.repr {
+      Repr::Inline { len, bytes, .. } => f(inline_str(bytes, *len)),
+      Repr::Stored(intern) => intern.with_str(f),
+    }
+  }
+
+  /// Copies the string out into an owned [String]
+  pub fn resolve(&self) -> String {
+    self.with_str(str::to_owned)
+  }
+
+  /// Length of the string in bytes
+  pub fn len(&self) -> usize {
+    match &self.repr {
+      Repr::Inline { len, .. } => usize::from(*len),
+      Repr::Stored(intern) => intern.len(),
+    }
+  }
+
+  /// Whether the string is empty
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// The interner that the string belongs to
+  pub fn interner(&self) -> &Arc<Interner> {
+    match &self.repr {
+      Repr::Inline { interner, .. } => interner,
+      Repr::Stored(intern) => intern.interner(),
+    }
+  }
+
+  /// Turns this into an [Intern], interning the string in the store if it
+  /// was kept inline
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  pub fn into_intern(self) -> Intern {
+    match self.repr {
+      Repr::Inline {
+        interner,
+        len,
+        bytes,
+      } => interner.intern(inline_str(&bytes, len)),
+      Repr::Stored(intern) => intern,
+    }
+  }
+}
+
+fn inline_str(bytes: &[u8; INLINE_CAPACITY], len: u8) -> &str {
+  // SAFETY: the bytes were copied from a str, which is only ever cut where
+  //         it ends
+  unsafe { str::from_utf8_unchecked(&bytes[..usize::from(len)]) }
+}
+
+/// Keeps the string inline if [Interner::intern_compact] would have
+impl From<Intern> for CompactIntern {
+  fn from(intern: Intern) -> Self {
+    let interner = Arc::clone(intern.interner());
+    let s = intern.with_str(|s| interner.canonical(s).into_owned());
+    Self::new(&interner, &s, || intern)
+  }
+}
+
+impl fmt::Debug for CompactIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.repr {
+      Repr::Inline { len, bytes, .. } => f
+        .debug_tuple("CompactIntern")
+        .field(&inline_str(bytes, *len))
+        .finish(),
+      Repr::Stored(intern) => {
+        f.debug_tuple("CompactIntern").field(intern).finish()
+      },
+    }
+  }
+}
+
+impl fmt::Display for CompactIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.repr {
+      Repr::Inline { len, bytes, .. } => f.pad(inline_str(bytes, *len)),
+      Repr::Stored(intern) => fmt::Display::fmt(intern, f),
+    }
+  }
+}
+
+impl PartialEq for CompactIntern {
+  fn eq(&self, other: &Self) -> bool {
+    match (&self.repr, &other.repr) {
+      (
+        Repr::Inline {
+          interner,
+          len,
+          bytes,
+        },
+        Repr::Inline {
+          interner: other_interner,
+          len: other_len,
+          bytes: other_bytes,
+        },
+      ) => {
+        Arc::ptr_eq(interner, other_interner)
+          && inline_str(bytes, *len) == inline_str(other_bytes, *other_len)
+      },
+      (Repr::Stored(intern), Repr::Stored(other)) => intern == other,
+      _ => false,
+    }
+  }
+}
+impl Eq for CompactIntern {}
+
+impl PartialEq<str> for CompactIntern {
+  fn eq(&self, other: &str) -> bool {
+    self.len() == other.len() && self.with_str(|s| s == other)
+  }
+}
+
+impl PartialEq<&str> for CompactIntern {
+  fn eq(&self, other: &&str) -> bool {
+    *self == **other
+  }
+}
+
+impl Hash for CompactIntern {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    match &self.repr {
+      Repr::Inline {
+        interner,
+        len,
+        bytes,
+      } => {
+        Arc::as_ptr(interner).hash(state);
+        inline_str(bytes, *len).hash(state);
+      },
+      Repr::Stored(intern) => intern.hash(state),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::sync::{CaseFolding, InternerBuilder};
+  use std::collections::HashSet;
+
+  #[test]
+  fn compact_interns() {
+    let interner = Interner::new();
+    let words = ["", "x", "exactly_twenty_two_chr", "twenty_three_characters"]
+      .map(|word| interner.intern_compact(word));
+
+    assert_eq!(
+      words.each_ref().map(CompactIntern::is_inline),
+      [true, true, true, false]
+    );
+    assert_eq!(interner.stats().stored_bytes, 23);
+    for word in &words {
+      assert_eq!(*word, interner.intern_compact(word.resolve()));
+      assert_ne!(*word, Interner::new().intern_compact(word.resolve()));
+      assert_eq!(*word, *word.to_string());
+      assert_eq!(word.len(), word.resolve().len());
+    }
+    assert_eq!(words.iter().collect::<HashSet<_>>().len(), 4);
+
+    let x = words[1].clone().into_intern();
+    assert_eq!(x, "x");
+    assert_eq!(CompactIntern::from(x), words[1]);
+    assert!(words[0].is_empty());
+  }
+
+  #[test]
+  fn compact_interns_are_canonical() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+
+    let short = interner.intern_compact("Hello");
+
+    assert_eq!(short, interner.intern_compact("HELLO"));
+    assert_eq!(short.resolve(), "hello");
+    assert_eq!(CompactIntern::from(interner.intern("HeLLo")), short);
+  }
+}
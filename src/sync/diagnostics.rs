@@ -0,0 +1,204 @@
+use crate::sync::lock::{Mutex, RwLock, RwLockWriteGuard};
+use std::{
+  collections::HashMap,
+  fmt,
+  ops::{Deref, DerefMut},
+  panic::Location,
+  sync::atomic::{AtomicUsize, Ordering},
+  thread::{self, ThreadId},
+  time::{Duration, Instant},
+};
+
+/// A thread that is holding on to part of an
+/// [Interner](crate::sync::Interner), as recorded in a [DiagnosticsReport]
+#[derive(Clone, Debug)]
+pub struct Holder {
+  /// The thread that took hold
+  pub thread: ThreadId,
+  /// The name of that thread, if it has one
+  pub thread_name: Option<String>,
+  /// Where it took hold. For readers, this is usually where an
+  /// [InternRef](crate::sync::InternRef) was made.
+  pub location: &'static Location<'static>,
+  /// When it took hold
+  pub since: Instant,
+}
+
+impl Holder {
+  #[track_caller]
+  fn here() -> Self {
+    let thread = thread::current();
+
+    Self {
+      thread: thread.id(),
+      thread_name: thread.name().map(str::to_owned),
+      location: Location::caller(),
+      since: Instant::now(),
+    }
+  }
+}
+
+impl fmt::Display for Holder {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{:?}", self.thread)?;
+    if let Some(name) = &self.thread_name {
+      write!(f, " ({name})")?;
+    }
+    write!(f, " at {} for {:?}", self.location, self.since.elapsed())
+  }
+}
+
+/// A snapshot of every thread holding on to an
+/// [Interner](crate::sync::Interner), produced by
+/// [Interner::diagnostics](crate::sync::Interner::diagnostics). The same
+/// report is logged as a warning whenever interning waits for the lock for
+/// longer than
+/// [InternerBuilder::diagnostics_threshold](crate::sync::InternerBuilder::diagnostics_threshold).
+#[derive(Clone, Debug, Default)]
+pub struct DiagnosticsReport {
+  /// The thread holding the lock for interning or collecting, if any
+  pub writer: Option<Holder>,
+  /// Every reader that is keeping strings from being moved, oldest first
+  pub readers: Vec<Holder>,
+}
+
+impl fmt::Display for DiagnosticsReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.writer {
+      Some(writer) => write!(f, "lock held by {writer}")?,
+      None => f.write_str("lock not held")?,
+    }
+
+    write!(f, "; {} readers", self.readers.len())?;
+    for reader in &self.readers {
+      write!(f, "\n  {reader}")?;
+    }
+    Ok(())
+  }
+}
+
+/// Keeps track of who holds on to an interner
+pub(crate) struct Diagnostics {
+  pub(crate) threshold: Duration,
+  next_reader: AtomicUsize,
+  readers: Mutex<HashMap<usize, Holder>>,
+  writer: Mutex<Option<Holder>>,
+}
+
+impl Diagnostics {
+  pub(crate) fn new(threshold: Duration) -> Self {
+    Self {
+      threshold,
+      next_reader: AtomicUsize::new(0),
+      readers: Mutex::new(HashMap::new()),
+      writer: Mutex::new(None),
+    }
+  }
+
+  /// Records a new reader, and returns the id to forget it by
+  #[track_caller]
+  pub(crate) fn enter(&self) -> usize {
+    let id = self.next_reader.fetch_add(1, Ordering::Relaxed);
+    self.readers.lock().insert(id, Holder::here());
+    id
+  }
+
+  pub(crate) fn leave(&self, id: usize) {
+    self.readers.lock().remove(&id);
+  }
+
+  /// Waits to lock `lock` for writing, logging a report of who is in the way
+  /// if that takes longer than the threshold
+  pub(crate) fn write<'a, T>(
+    &self,
+    lock: &'a RwLock<T>,
+  ) -> RwLockWriteGuard<'a, T> {
+    lock.try_write_for(self.threshold).unwrap_or_else(|| {
+      log::warn!(
+        "waited over {:?} to lock an interner: {}",
+        self.threshold,
+        self.report()
+      );
+      lock.write()
+    })
+  }
+
+  /// Records that the current thread holds `guard`
+  #[track_caller]
+  pub(crate) fn hold<'a, T>(
+    &'a self,
+    guard: RwLockWriteGuard<'a, T>,
+  ) -> Written<'a, T> {
+    *self.writer.lock() = Some(Holder::here());
+    Written {
+      guard,
+      diagnostics: self,
+    }
+  }
+
+  pub(crate) fn report(&self) -> DiagnosticsReport {
+    let mut readers = self.readers.lock().values().cloned().collect::<Vec<_>>();
+    readers.sort_by_key(|reader| reader.since);
+
+    DiagnosticsReport {
+      writer: self.writer.lock().clone(),
+      readers,
+    }
+  }
+}
+
+/// A write guard that is recorded as held until it is dropped
+pub(crate) struct Written<'a, T> {
+  guard: RwLockWriteGuard<'a, T>,
+  diagnostics: &'a Diagnostics,
+}
+
+impl<T> Deref for Written<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &Self::Target {
+    &self.guard
+  }
+}
+
+impl<T> DerefMut for Written<'_, T> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.guard
+  }
+}
+
+impl<T> Drop for Written<'_, T> {
+  fn drop(&mut self) {
+    *self.diagnostics.writer.lock() = None;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn diagnostics() {
+    let diagnostics = Diagnostics::new(Duration::from_millis(10));
+    let lock = RwLock::new(0);
+
+    let first = diagnostics.enter();
+    let second = diagnostics.enter();
+    diagnostics.leave(first);
+    let mut written = diagnostics.hold(diagnostics.write(&lock));
+    *written += 1;
+
+    let report = diagnostics.report();
+    assert_eq!(report.readers.len(), 1);
+    assert_eq!(report.readers[0].thread, thread::current().id());
+    assert_eq!(report.readers[0].location.file(), file!());
+    assert!(report.writer.is_some());
+    assert!(report.to_string().starts_with("lock held by"));
+
+    drop(written);
+    diagnostics.leave(second);
+    let report = diagnostics.report();
+    assert!(report.writer.is_none() && report.readers.is_empty());
+    assert_eq!(*lock.read(), 1);
+  }
+}
@@ -0,0 +1,80 @@
+use std::io;
+
+const MAGIC: &[u8; 8] = b"STRNDICT";
+const VERSION: u32 = 1;
+
+/// Lays out `strings` as a dictionary, for
+/// [Interner::export_dictionary](crate::sync::Interner::export_dictionary).
+/// Dictionaries are laid out as follows, with every integer little-endian:
+///
+/// - the magic bytes `STRNDICT` and a `u32` format version
+/// - a `u64` number of strings
+/// - each string as a `u32` length in bytes followed by its text, most
+///   important first
+pub(crate) fn encode(strings: &[String]) -> Vec<u8> {
+  let len = strings.iter().map(|s| 4 + s.len()).sum::<usize>();
+  let mut bytes = Vec::with_capacity(MAGIC.len() + 12 + len);
+  bytes.extend_from_slice(MAGIC);
+  bytes.extend_from_slice(&VERSION.to_le_bytes());
+  bytes.extend_from_slice(&(strings.len() as u64).to_le_bytes());
+
+  for s in strings {
+    let len = u32::try_from(s.len()).expect("string is too long to export");
+    bytes.extend_from_slice(&len.to_le_bytes());
+    bytes.extend_from_slice(s.as_bytes());
+  }
+
+  bytes
+}
+
+/// Reads the strings back out of a dictionary, in order
+pub(crate) fn decode(mut bytes: &[u8]) -> io::Result<Vec<String>> {
+  let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+  let mut take = |len: usize| {
+    if bytes.len() < len {
+      return Err(invalid("dictionary is truncated"));
+    }
+    let (taken, rest) = bytes.split_at(len);
+    bytes = rest;
+    Ok(taken)
+  };
+
+  if take(MAGIC.len())? != MAGIC {
+    return Err(invalid("not a dictionary"));
+  }
+  let version = u32::from_le_bytes(take(4)?.try_into().unwrap());
+  if version != VERSION {
+    return Err(invalid("unsupported dictionary version"));
+  }
+
+  let count = u64::from_le_bytes(take(8)?.try_into().unwrap());
+  let mut strings = Vec::new();
+  for _ in 0..count {
+    let len = u32::from_le_bytes(take(4)?.try_into().unwrap());
+    let text = std::str::from_utf8(take(len as usize)?)
+      .map_err(|_| invalid("dictionary holds a string that isn't UTF-8"))?;
+    strings.push(text.to_owned());
+  }
+
+  if !bytes.is_empty() {
+    return Err(invalid("dictionary has trailing bytes"));
+  }
+  Ok(strings)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn dictionaries_round_trip() {
+    let strings = ["the", "", "quick"].map(String::from);
+    let bytes = encode(&strings);
+
+    assert_eq!(bytes.len(), 20 + 3 * 4 + 8);
+    assert_eq!(decode(&bytes).unwrap(), strings);
+    assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+    assert!(decode(&[bytes.as_slice(), b"!"].concat()).is_err());
+    assert!(decode(b"STRINTRN").is_err());
+  }
+}
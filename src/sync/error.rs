@@ -1,10 +1,120 @@
+use crate::sync::{PendingIntern, SymbolId};
+use std::{ops::Range, time::Duration};
 use thiserror::Error;
 
-/// Represents the only error that an interner can encounter while
-/// interning. A thread can't wait on itself to drop an InternRef
-/// of course.
+/// Represents the errors that an interner can encounter while interning.
 #[derive(Debug, Error)]
 pub enum InternError {
-  #[error("Cannot intern while this thread holds an InternRef")]
-  OutstandingLocalRef(&'static std::panic::Location<'static>),
+  /// Storing the string would have grown the store past the limit set with
+  /// [InternerBuilder::max_store_bytes](crate::sync::InternerBuilder::max_store_bytes),
+  /// and no room could be made for it
+  #[error("interning would grow the store past its limit of {limit} bytes")]
+  StoreFull { limit: usize },
+  /// Another thread was using the interner, and
+  /// [Interner::try_intern_nonblocking](crate::sync::Interner::try_intern_nonblocking)
+  /// was asked not to wait for it
+  #[error("the interner is busy with another thread")]
+  WouldBlock,
+  /// Other threads kept the interner busy for longer than
+  /// [Interner::intern_timeout](crate::sync::Interner::intern_timeout) was
+  /// willing to wait
+  #[error("timed out after {timeout:?} waiting for the interner")]
+  TimedOut { timeout: Duration },
+  /// The store is full, and no room can be made, because threads that have
+  /// exited left readers behind that keep strings from ever being moved
+  /// again. Only found with
+  /// [InternerBuilder::detect_leaked_refs](crate::sync::InternerBuilder::detect_leaked_refs).
+  #[error("{leaked} refs were leaked by threads that have exited")]
+  Poisoned { leaked: usize },
+  /// The store is full, and readers keep strings from being evicted, so
+  /// [RefsHeldPolicy::Queue](crate::sync::RefsHeldPolicy::Queue) put the
+  /// string off until they are dropped. Only returned by
+  /// [Interner::intern_or_queue](crate::sync::Interner::intern_or_queue).
+  #[error("the store is full while refs are held, so interning was queued")]
+  Queued(PendingIntern),
+}
+
+/// Represents the ways that slicing an [Intern](crate::sync::Intern) with
+/// [Intern::slice](crate::sync::Intern::slice) can fail.
+#[derive(Debug, Error)]
+pub enum SliceError {
+  /// The range starts after it ends, or ends past the end of the string
+  #[error(
+    "range {start}..{end} is out of bounds for a string of length {len}"
+  )]
+  OutOfBounds {
+    start: usize,
+    end: usize,
+    len: usize,
+  },
+  /// The range starts or ends in the middle of a character
+  #[error("byte index {index} is not a char boundary")]
+  NotCharBoundary { index: usize },
+}
+
+/// Represents the ways that putting an interner back together with
+/// [FrozenInterner::from_parts](crate::sync::FrozenInterner::from_parts) can
+/// fail.
+#[derive(Debug, Error)]
+pub enum PartsError {
+  /// A span doesn't pick out a valid slice of the text
+  #[error(transparent)]
+  Slice(#[from] SliceError),
+  /// The same id is given to more than one span
+  #[error("symbol id {} is given more than once", id.0)]
+  DuplicateId { id: SymbolId },
+}
+
+/// Represents the ways that decoding a string literal with
+/// [Interner::intern_unescaped](crate::sync::Interner::intern_unescaped) can
+/// fail. Each carries the byte index of the backslash that starts the
+/// offending escape.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum UnescapeError {
+  /// The backslash is followed by a character that the
+  /// [EscapeStyle](crate::sync::EscapeStyle) has no escape for
+  #[error("unknown escape at byte index {index}")]
+  UnknownEscape { index: usize },
+  /// The escape is cut short, has bad digits, or stands for something that
+  /// isn't a valid character
+  #[error("malformed escape at byte index {index}")]
+  InvalidEscape { index: usize },
+}
+
+/// Represents the ways that reading an [Intern](crate::sync::Intern) with
+/// [Intern::try_get_ref](crate::sync::Intern::try_get_ref) can fail, rather
+/// than wait or add to the readers holding off collections.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum RefError {
+  /// As many readers as
+  /// [InternerBuilder::max_outstanding_refs](crate::sync::InternerBuilder::max_outstanding_refs)
+  /// allows are already alive
+  #[error("the interner already has {limit} outstanding refs")]
+  TooManyRefs { limit: usize },
+  /// [Interner::collect](crate::sync::Interner::collect) or
+  /// [Interner::shrink_to_fit](crate::sync::Interner::shrink_to_fit) is
+  /// moving strings, and reading would have to wait for it
+  #[error("the interner is moving strings")]
+  Moving,
+}
+
+impl SliceError {
+  /// Makes sure that `range` picks out a valid slice of `text`
+  pub(crate) fn check(text: &str, range: &Range<usize>) -> Result<(), Self> {
+    if range.start > range.end || range.end > text.len() {
+      return Err(Self::OutOfBounds {
+        start: range.start,
+        end: range.end,
+        len: text.len(),
+      });
+    }
+
+    match [range.start, range.end]
+      .into_iter()
+      .find(|&index| !text.is_char_boundary(index))
+    {
+      Some(index) => Err(Self::NotCharBoundary { index }),
+      None => Ok(()),
+    }
+  }
 }
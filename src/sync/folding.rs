@@ -0,0 +1,60 @@
+use std::borrow::Cow;
+
+/// Selects how an interner treats letter case. Chosen with
+/// [InternerBuilder::case_folding](crate::sync::InternerBuilder::case_folding).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CaseFolding {
+  /// Strings that differ only in case are different strings
+  #[default]
+  Off,
+  /// ASCII letters are lowercased before interning, so `"Foo"` and `"foo"`
+  /// produce the same [Intern](crate::sync::Intern). Everything else is left
+  /// as is.
+  Ascii,
+  /// Full Unicode case folding, so that for example `"Straße"` and
+  /// `"STRASSE"` produce the same [Intern](crate::sync::Intern). Requires
+  /// the `caseless` feature.
+  #[cfg(feature = "caseless")]
+  Unicode,
+}
+
+impl CaseFolding {
+  /// Folds `s`, only allocating if that actually changes anything
+  pub(crate) fn fold(self, s: &str) -> Cow<'_, str> {
+    match self {
+      CaseFolding::Ascii if s.bytes().any(|b| b.is_ascii_uppercase()) => {
+        Cow::Owned(s.to_ascii_lowercase())
+      },
+      #[cfg(feature = "caseless")]
+      CaseFolding::Unicode if s.is_ascii() => CaseFolding::Ascii.fold(s),
+      #[cfg(feature = "caseless")]
+      CaseFolding::Unicode => Cow::Owned(caseless::default_case_fold_str(s)),
+      _ => Cow::Borrowed(s),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn case_folding() {
+    assert_eq!(CaseFolding::Off.fold("HeLLo"), "HeLLo");
+    assert_eq!(CaseFolding::Ascii.fold("HeLLo"), "hello");
+    assert_eq!(CaseFolding::Ascii.fold("ÀB"), "Àb");
+    assert!(matches!(CaseFolding::Ascii.fold("hello"), Cow::Borrowed(_)));
+  }
+
+  #[cfg(feature = "caseless")]
+  #[test]
+  fn unicode_case_folding() {
+    assert_eq!(CaseFolding::Unicode.fold("HeLLo"), "hello");
+    assert_eq!(CaseFolding::Unicode.fold("ÀB"), "àb");
+    assert_eq!(CaseFolding::Unicode.fold("Straße"), "strasse");
+    assert!(matches!(
+      CaseFolding::Unicode.fold("hello"),
+      Cow::Borrowed(_)
+    ));
+  }
+}
@@ -0,0 +1,257 @@
+use crate::sync::{
+  error::{PartsError, SliceError},
+  folding::CaseFolding,
+  normalization::Normalization,
+  symbol::SymbolId,
+  trie::Span,
+};
+use std::ops::Range;
+
+/// A read-only snapshot of an [Interner](crate::sync::Interner), produced by
+/// [Interner::freeze](crate::sync::Interner::freeze). Every string lives in
+/// one contiguous buffer, and lookups are a binary search over a sorted
+/// table, so reading never takes a lock or touches a reference count. It
+/// can be shared between threads freely, but nothing new can be interned.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::Interner;
+///
+/// let interner = Interner::new();
+/// interner.intern("hello");
+/// interner.intern("world");
+///
+/// let frozen = interner.freeze();
+/// let hello = frozen.get("hello").unwrap();
+///
+/// assert_eq!(frozen.resolve(hello), "hello");
+/// assert_eq!(frozen.get("goodbye"), None);
+/// let world = frozen.id_of("world").unwrap();
+/// assert_eq!(frozen.resolve_id(world), Some("world"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrozenInterner {
+  text: Box<str>,
+  /// Where each string lives in `text`, sorted by the string
+  spans: Box<[Span]>,
+  /// The lowest [SymbolId] of each string in `spans`, in the same order
+  symbols: Box<[SymbolId]>,
+  /// Which string each [SymbolId] stands for, sorted by id
+  ids: Box<[(SymbolId, FrozenIntern)]>,
+  case_folding: CaseFolding,
+  normalization: Normalization,
+}
+
+/// Represents a single string in a [FrozenInterner]. This is just an index,
+/// so it is [Copy], and is ordered the same way as the strings themselves.
+/// Handles from different [FrozenInterner]s must not be mixed up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FrozenIntern(usize);
+
+impl FrozenInterner {
+  /// Builds a FrozenInterner out of strings and their ids. Each id must only
+  /// appear once, but a string may have more than one id.
+  pub(crate) fn new<'a>(
+    strings: impl IntoIterator<Item = (SymbolId, &'a str)>,
+    case_folding: CaseFolding,
+    normalization: Normalization,
+  ) -> Self {
+    let mut strings = strings.into_iter().collect::<Vec<_>>();
+    strings.sort_unstable_by(|(a_id, a), (b_id, b)| (a, a_id).cmp(&(b, b_id)));
+
+    let mut text = String::new();
+    let mut spans = Vec::<Span>::new();
+    let mut symbols = Vec::new();
+    let mut ids = Vec::with_capacity(strings.len());
+    for (id, s) in strings {
+      // ids for the same string come together, lowest first
+      if spans.last().is_none_or(|span| text[span.clone()] != *s) {
+        let start = text.len();
+        text.push_str(s);
+        spans.push(start..text.len());
+        symbols.push(id);
+      }
+      ids.push((id, FrozenIntern(spans.len() - 1)));
+    }
+    ids.sort_unstable();
+
+    Self {
+      text: text.into_boxed_str(),
+      spans: spans.into_boxed_slice(),
+      symbols: symbols.into_boxed_slice(),
+      ids: ids.into_boxed_slice(),
+      case_folding,
+      normalization,
+    }
+  }
+
+  /// Rebuilds a FrozenInterner from the parts that
+  /// [Interner::into_parts](crate::sync::Interner::into_parts) took an
+  /// interner apart into, or any other text and spans into it. Every
+  /// [SymbolId] resolves to its string again with
+  /// [FrozenInterner::resolve_id]. Strings are looked up exactly as they
+  /// are, without case folding or normalization. Fails if any span doesn't
+  /// pick out a valid slice of `text`, or if an id is given more than once.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{FrozenInterner, Interner};
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern("hello");
+  ///
+  /// let hello = interner.intern("hello");
+  ///
+  /// let (text, spans) = interner.into_parts();
+  /// let frozen = FrozenInterner::from_parts(&text, &spans)?;
+  ///
+  /// assert_eq!(frozen.resolve(frozen.get("hello").unwrap()), "hello");
+  /// assert_eq!(frozen.resolve_id(hello.id()), Some("hello"));
+  /// # Ok::<(), str_interning::sync::PartsError>(())
+  /// ```
+  pub fn from_parts(
+    text: &str,
+    spans: &[(SymbolId, Range<usize>)],
+  ) -> Result<Self, PartsError> {
+    let mut strings = Vec::with_capacity(spans.len());
+    for (id, span) in spans {
+      SliceError::check(text, span)?;
+      strings.push((*id, &text[span.clone()]));
+    }
+
+    let mut ids = strings.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+    ids.sort_unstable();
+    if let Some(pair) = ids.windows(2).find(|pair| pair[0] == pair[1]) {
+      return Err(PartsError::DuplicateId { id: pair[0] });
+    }
+
+    Ok(Self::new(strings, CaseFolding::Off, Normalization::Off))
+  }
+
+  /// Number of distinct strings
+  pub fn len(&self) -> usize {
+    self.spans.len()
+  }
+
+  /// Whether there are no strings at all
+  pub fn is_empty(&self) -> bool {
+    self.spans.is_empty()
+  }
+
+  /// Finds a string, applying the same case folding and normalization as the
+  /// interner that was frozen
+  pub fn get<S: AsRef<str>>(&self, s: S) -> Option<FrozenIntern> {
+    let s = self.normalization.canonical(self.case_folding, s.as_ref());
+
+    self
+      .spans
+      .binary_search_by(|span| self.text[span.clone()].cmp(&s))
+      .ok()
+      .map(FrozenIntern)
+  }
+
+  /// Reads back the string that a [FrozenIntern] represents
+  ///
+  /// # Panics
+  ///
+  /// May panic if `intern` came from a different [FrozenInterner].
+  pub fn resolve(&self, intern: FrozenIntern) -> &str {
+    &self.text[self.spans[intern.0].clone()]
+  }
+
+  /// Reads back the string with the given [SymbolId], if the interner that
+  /// was frozen or taken apart had it
+  pub fn resolve_id(&self, id: SymbolId) -> Option<&str> {
+    let found = self.ids.binary_search_by_key(&id, |&(id, _)| id).ok()?;
+    Some(self.resolve(self.ids[found].1))
+  }
+
+  /// Finds the [SymbolId] of a string, the same way as
+  /// [FrozenInterner::get]. If the string has more than one id, this is the
+  /// lowest one.
+  pub fn id_of<S: AsRef<str>>(&self, s: S) -> Option<SymbolId> {
+    self.get(s).map(|intern| self.symbols[intern.0])
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::sync::Interner;
+
+  #[test]
+  fn frozen_interner() {
+    let frozen = FrozenInterner::new(
+      [
+        (SymbolId(0), "b"),
+        (SymbolId(1), "a"),
+        (SymbolId(2), "ab"),
+        (SymbolId(3), ""),
+      ],
+      CaseFolding::Off,
+      Normalization::Off,
+    );
+    let a = frozen.get("a").unwrap();
+    let ab = frozen.get("ab").unwrap();
+    let b = frozen.get("b").unwrap();
+
+    assert_eq!(frozen.len(), 4);
+    assert_eq!(frozen.resolve(a), "a");
+    assert_eq!(frozen.resolve(ab), "ab");
+    assert!(a < ab && ab < b);
+    assert_eq!(frozen.resolve(frozen.get("").unwrap()), "");
+    assert_eq!(frozen.get("ba"), None);
+    assert_eq!(frozen.resolve_id(SymbolId(0)), Some("b"));
+    assert_eq!(frozen.resolve_id(SymbolId(4)), None);
+    assert_eq!(frozen.id_of("ab"), Some(SymbolId(2)));
+    assert_eq!(frozen.id_of("ba"), None);
+  }
+
+  #[test]
+  fn frozen_interner_from_parts() {
+    let text = "hello🦀";
+    let spans = [
+      (SymbolId(0), 0..5),
+      (SymbolId(3), 1..4),
+      (SymbolId(4), 1..4),
+    ];
+    let frozen = FrozenInterner::from_parts(text, &spans).unwrap();
+
+    assert_eq!(frozen.len(), 2);
+    assert_eq!(frozen.resolve(frozen.get("ell").unwrap()), "ell");
+    assert_eq!(frozen.get("HELLO"), None);
+    assert_eq!(frozen.resolve_id(SymbolId(4)), Some("ell"));
+    assert_eq!(frozen.resolve_id(SymbolId(1)), None);
+    assert_eq!(frozen.id_of("ell"), Some(SymbolId(3)));
+    assert!(matches!(
+      FrozenInterner::from_parts(text, &[(SymbolId(0), 0..6)]),
+      Err(PartsError::Slice(SliceError::NotCharBoundary { index: 6 }))
+    ));
+    assert!(matches!(
+      FrozenInterner::from_parts(text, &[(SymbolId(0), 5..20)]),
+      Err(PartsError::Slice(SliceError::OutOfBounds { len: 9, .. }))
+    ));
+    assert!(matches!(
+      FrozenInterner::from_parts(
+        text,
+        &[(SymbolId(2), 0..5), (SymbolId(2), 1..4)]
+      ),
+      Err(PartsError::DuplicateId { id: SymbolId(2) })
+    ));
+
+    // every id that comes out of an interner resolves to its string again
+    let interner = Interner::new();
+    let words = ["hello", "ell", "world", "", "hello🦀"];
+    let interns = words.map(|word| interner.intern(word));
+    let (text, spans) = interner.into_parts();
+    let frozen = FrozenInterner::from_parts(&text, &spans).unwrap();
+
+    assert_eq!(spans.len(), words.len());
+    for (word, intern) in words.iter().zip(&interns) {
+      assert_eq!(frozen.resolve_id(intern.id()), Some(*word));
+      assert_eq!(frozen.id_of(word), Some(intern.id()));
+    }
+  }
+}
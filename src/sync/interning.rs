@@ -1,355 +1,7071 @@
+#[cfg(feature = "diagnostics")]
+use crate::sync::diagnostics::{Diagnostics, DiagnosticsReport, Written};
+#[cfg(feature = "aho-corasick")]
+use crate::sync::matcher::InternMatcher;
 use crate::sync::{
-  error::InternError,
-  trie::{Span, Trie},
+  builder::{InternerBuilder, RefsHeldPolicy, StoreFullPolicy},
+  compact::CompactIntern,
+  dictionary,
+  error::{InternError, RefError, SliceError, UnescapeError},
+  escape::{self, EscapeStyle},
+  folding::CaseFolding,
+  frozen::FrozenInterner,
+  index::{Index, IndexBackend},
+  leaks::LeakDetector,
+  lock::{
+    atomic::{
+      self, AtomicBool, AtomicU64, AtomicUsize, Ordering as AtomicOrdering,
+    },
+    Mutex, RwLock, RwLockWriteGuard,
+  },
+  merge::MergeMap,
+  normalization::Normalization,
+  observer::InternerObserver,
+  parked::Parked,
+  path::{InternPath, PathTable},
+  persistence::Snapshot,
+  source::{LineColumn, Lines, SourceId},
+  stats::InternerStats,
+  store::{Stager, Store},
+  symbol::{Symbol, SymbolId},
+  trie::Span,
 };
-use parking_lot::{Condvar, Mutex};
+#[cfg(feature = "dashmap")]
+use dashmap::DashMap;
+#[cfg(feature = "regex")]
+use regex::Regex;
 use std::{
+  borrow::{Borrow, Cow},
+  cmp::{Ordering, Reverse},
   collections::HashMap,
-  fmt,
-  ops::Deref,
-  panic::Location,
-  sync::Arc,
-  thread::{self, ThreadId},
+  fmt::{self, Write as _},
+  hash::{Hash, Hasher},
+  io::{self, BufRead, Read, Write},
+  iter,
+  ops::{Bound, Deref, Range, RangeBounds},
+  path::Path,
+  ptr,
+  sync::{Arc, OnceLock, Weak},
+  thread::ThreadId,
+  time::{Duration, Instant},
+  vec,
 };
 
 /// The interner, which is where the underlying data store and index lives.
 /// [Intern]s keep a reference to this struct, so it can be tossed aside when
 /// no longer needed.
 pub struct Interner {
-  cond: Condvar,
-  internal: Mutex<InternerInternal>,
+  /// Written to by interning, and read by lookups such as
+  /// [Interner::resolve], which can run side by side. Reading the string of
+  /// an [Intern] doesn't need it at all; see `Interner::enter`.
+  internal: RwLock<InternerInternal>,
+  store: Store,
+  refs: AtomicUsize,
+  moving: AtomicBool,
+  case_folding: CaseFolding,
+  normalization: Normalization,
+  /// Whether each entry counts how many times it has been interned
+  counting: bool,
+  /// See [InternerBuilder::max_outstanding_refs]
+  max_refs: Option<usize>,
+  /// See [InternerBuilder::detect_leaked_refs]
+  leaks: Option<LeakDetector>,
+  /// See [InternerBuilder::when_refs_held]
+  refs_held: RefsHeldPolicy,
+  /// Strings put off by [RefsHeldPolicy::Queue], first queued first
+  queued: Mutex<Vec<Queued>>,
+  /// Threads waiting for the last reader to be dropped, under
+  /// [RefsHeldPolicy::Block] or [RefsHeldPolicy::Queue]
+  readers_gone: Parked,
+  /// Every interned string that has been looked up through
+  /// [Interner::try_intern], so that strings which are already interned can
+  /// be found again without taking the lock. See
+  /// [InternerBuilder::lock_free_hits].
+  #[cfg(feature = "dashmap")]
+  hit_cache: Option<DashMap<Box<str>, Weak<Entry>>>,
+  /// Hits served by `hit_cache`, which are counted here rather than under
+  /// the lock
+  #[cfg(feature = "dashmap")]
+  cache_hits: AtomicU64,
+  /// Who holds the lock and who is reading, for [Interner::diagnostics]
+  #[cfg(feature = "diagnostics")]
+  diagnostics: Diagnostics,
+  /// Paths interned with [Interner::intern_path], which are kept apart from
+  /// the strings. Made the first time a path is interned.
+  paths: OnceLock<PathTable>,
+  /// Files interned with [Interner::intern_source], indexed by [SourceId]
+  sources: RwLock<Vec<Source>>,
+  /// Told about hits served by `hit_cache` and time spent waiting for the
+  /// lock. The same observer as the one in [InternerInternal].
+  observer: Option<Arc<dyn InternerObserver>>,
+  /// Where strings that aren't here are looked for; see
+  /// [Interner::new_child]
+  parent: Option<Arc<Interner>>,
 }
 
-pub struct InternerInternal {
-  index: Mutex<Trie<char>>,
-  store: Mutex<String>,
-  refs: usize,
-  local_refs: HashMap<ThreadId, usize>,
-  last_ref: Option<&'static Location<'static>>,
+/// The lock, held for writing. With diagnostics on, it is recorded as held
+/// by the thread that took it.
+#[cfg(feature = "diagnostics")]
+type Locked<'a> = Written<'a, InternerInternal>;
+#[cfg(not(feature = "diagnostics"))]
+type Locked<'a> = RwLockWriteGuard<'a, InternerInternal>;
+
+/// A file interned with [Interner::intern_source]
+struct Source {
+  name: Arc<str>,
+  /// Keeps the contents from being collected, without keeping the interner
+  /// alive like an [Intern] would
+  contents: Arc<Entry>,
+  lines: Lines,
+}
+
+/// Where a string put off by [RefsHeldPolicy::Queue] ends up once it has
+/// been interned, or has failed to be
+type Slot = Arc<Mutex<Option<Result<Arc<Entry>, InternError>>>>;
+
+/// A string put off by [RefsHeldPolicy::Queue] until no readers are left
+struct Queued {
+  spelling: String,
+  /// The canonical form of `spelling`
+  s: String,
+  slot: Slot,
+}
+
+/// How long interning waits, both for the lock and for readers to be
+/// dropped under [RefsHeldPolicy::Block] or [RefsHeldPolicy::Queue]
+#[derive(Clone, Copy)]
+enum Patience {
+  Forever,
+  /// Fails with [InternError::WouldBlock] rather than wait at all
+  Never,
+  /// Fails with [InternError::TimedOut] once `deadline` passes, reporting
+  /// the `timeout` that the caller asked for
+  Until {
+    deadline: Instant,
+    timeout: Duration,
+  },
+}
+
+/// How interning went under [StoreFullPolicy::Evict], which readers can get
+/// in the way of
+enum Evicted {
+  /// Interned, or failed for some other reason than readers
+  Done(Result<Span, InternError>),
+  /// The store is full, and readers kept anything from being evicted
+  Blocked { limit: usize },
+}
+
+/// A reader counted by `Interner::enter`, which must be handed back to
+/// `Interner::leave` when it's done
+#[derive(Debug)]
+struct Reader {
+  #[cfg(feature = "diagnostics")]
+  id: usize,
+  /// The thread that started it, when leaks are being looked for
+  thread: Option<ThreadId>,
+}
+
+struct InternerInternal {
+  index: Index,
+  /// The id of the entry for each span
+  ids: HashMap<Span, usize>,
+  /// Every entry, indexed by id. Ids are never reused, so entries that have
+  /// been collected leave a gap.
+  symbols: Vec<Option<Arc<Entry>>>,
+  max_store_bytes: Option<usize>,
+  store_full: StoreFullPolicy,
+  preserve_case: bool,
+  /// Every spelling that each string has been interned as, by id, first
+  /// seen first; see [InternerBuilder::record_spellings]
+  spellings: Option<HashMap<usize, Vec<Box<str>>>>,
+  hits: u64,
+  misses: u64,
+  /// Ticks whenever an entry is used, to tell which entries were used least
+  /// recently. It is atomic so that lookups can tick it while only reading.
+  clock: AtomicU64,
+  /// See [InternerBuilder::observer]
+  observer: Option<Arc<dyn InternerObserver>>,
+  /// How many bytes the store had allocated when the observer was last
+  /// told, so it is only told when the store grows
+  allocated: usize,
+}
+
+/// The shared identity of one distinct interned string. Every [Intern] of the
+/// string points at the same entry, so its reference count tells whether the
+/// string is still in use, and [Interner::collect] can move the string by
+/// rewriting its span in one place.
+struct Entry {
+  id: usize,
+  /// Where the string is in the store, in the form it is indexed by
+  start: AtomicUsize,
+  end: AtomicUsize,
+  /// Whether the string was first interned with different casing, which is
+  /// stored separately to be shown to readers
+  spelled: bool,
+  /// Where the string that readers see is in the store. Unless the entry is
+  /// spelled, this is the same as the span.
+  shown_start: AtomicUsize,
+  shown_end: AtomicUsize,
+  /// Length of the string that readers see in bytes and in chars, which
+  /// never change even though the span does
+  len: usize,
+  chars: usize,
+  /// Set once a [Symbol] has been handed out for the string, since symbols
+  /// don't keep it alive
+  pinned: AtomicBool,
+  /// When the string was last interned or looked up, by the interner's
+  /// clock
+  used: AtomicU64,
+  /// How many times the string has been interned, if the interner counts;
+  /// see [InternerBuilder::count_interns]
+  interned: AtomicU64,
+}
+
+impl Entry {
+  /// Creates an entry for the string at `span`. `spelling` is where the
+  /// string it was first interned as is, if that differs, and `text` is
+  /// whichever of the two is shown.
+  fn new(id: usize, span: Span, spelling: Option<Span>, text: &str) -> Self {
+    let shown = spelling.clone().unwrap_or(span.clone());
+    Self {
+      id,
+      start: AtomicUsize::new(span.start),
+      end: AtomicUsize::new(span.end),
+      spelled: spelling.is_some(),
+      shown_start: AtomicUsize::new(shown.start),
+      shown_end: AtomicUsize::new(shown.end),
+      len: text.len(),
+      chars: text.chars().count(),
+      pinned: AtomicBool::new(false),
+      used: AtomicU64::new(0),
+      interned: AtomicU64::new(0),
+    }
+  }
+
+  fn symbol_id(&self) -> SymbolId {
+    SymbolId(u32::try_from(self.id).expect("ran out of symbol ids"))
+  }
+
+  /// Hands out a [Symbol] for the string, which keeps it from ever being
+  /// collected
+  fn pin(&self) -> Symbol {
+    self.pinned.store(true, AtomicOrdering::Relaxed);
+    Symbol(self.symbol_id())
+  }
+
+  fn span(&self) -> Span {
+    let start = self.start.load(AtomicOrdering::Relaxed);
+    start..self.end.load(AtomicOrdering::Relaxed)
+  }
+
+  fn set_span(&self, span: Span) {
+    if !self.spelled {
+      self.set_shown(span.clone());
+    }
+    self.start.store(span.start, AtomicOrdering::Relaxed);
+    self.end.store(span.end, AtomicOrdering::Relaxed);
+  }
+
+  fn shown(&self) -> Span {
+    let start = self.shown_start.load(AtomicOrdering::Relaxed);
+    start..self.shown_end.load(AtomicOrdering::Relaxed)
+  }
+
+  fn set_shown(&self, span: Span) {
+    self.shown_start.store(span.start, AtomicOrdering::Relaxed);
+    self.shown_end.store(span.end, AtomicOrdering::Relaxed);
+  }
+
+  /// Whether anything still needs the string, which is the case while an
+  /// [Intern] holds it, or once it has been pinned. `entry` is the reference
+  /// held by the symbol table.
+  fn in_use(entry: &Arc<Self>) -> bool {
+    entry.pinned.load(AtomicOrdering::Relaxed) || Arc::strong_count(entry) > 1
+  }
+
+  /// How many bytes the string and its spelling take up in the store at
+  /// most, once it has been compacted
+  fn stored_len(&self) -> usize {
+    let span = self.span();
+    let len = if Store::is_static(&span) {
+      0
+    } else {
+      span.len()
+    };
+    len + if self.spelled { self.shown().len() } else { 0 }
+  }
 }
 
 impl InternerInternal {
-  fn intern_uncontested<S: AsRef<str>>(&mut self, s: S) -> Span {
-    let index = unsafe { &mut *self.index.data_ptr() };
-    let store = unsafe { &mut *self.store.data_ptr() };
-    let span = index.get(s.as_ref().chars()).unwrap_or_else(|| {
-      let start = store.len();
-      store.push_str(s.as_ref());
-      index.insert(s.as_ref().chars(), start)
+  /// Gets the entry for the string at `span`, creating it if needed.
+  /// `spelling` is how the string was written before it was folded.
+  fn entry(&mut self, store: &Store, span: Span, spelling: &str) -> Arc<Entry> {
+    let symbols = &mut self.symbols;
+    let preserve_case = self.preserve_case;
+    let id = *self.ids.entry(span.clone()).or_insert_with(|| {
+      let id = symbols.len();
+      // SAFETY: the span was handed out by the store
+      let text = unsafe { store.get(span.clone()) };
+      let entry = if preserve_case && spelling != text {
+        // SAFETY: we hold the lock, so no other thread is pushing
+        let spelled = unsafe { store.push(spelling.as_bytes()) };
+        Entry::new(id, span, Some(spelled), spelling)
+      } else {
+        Entry::new(id, span, None, text)
+      };
+      symbols.push(Some(Arc::new(entry)));
+      id
     });
 
-    span
+    if let Some(spellings) = &mut self.spellings {
+      let seen = spellings.entry(id).or_default();
+      if !seen.iter().any(|seen| **seen == *spelling) {
+        seen.push(spelling.into());
+      }
+    }
+
+    self.observe_growth(store);
+    let entry = self.symbols[id].as_ref().unwrap();
+    self.touch(entry);
+    Arc::clone(entry)
   }
-}
 
-impl Interner {
-  /// Constructs a new Interner
-  pub fn new() -> Arc<Self> {
-    Arc::new(Self {
-      cond: Condvar::new(),
-      internal: Mutex::new(InternerInternal {
-        index: Mutex::new(Trie::new()),
-        store: Mutex::new(String::new()),
-        refs: 0,
-        local_refs: HashMap::new(),
-        last_ref: None,
-      }),
-    })
+  /// Gets the entry for the string at `span` if it already has one, and
+  /// `spelling` has been recorded for it, so that only reading is needed
+  fn existing(&self, span: &Span, spelling: &str) -> Option<Arc<Entry>> {
+    let &id = self.ids.get(span)?;
+    if let Some(spellings) = &self.spellings {
+      let seen = spellings.get(&id)?;
+      if !seen.iter().any(|seen| **seen == *spelling) {
+        return None;
+      }
+    }
+
+    let entry = self.symbols[id].as_ref()?;
+    self.touch(entry);
+    Some(Arc::clone(entry))
   }
 
-  /// Creates an interned string
+  /// Marks an entry as just used
+  fn touch(&self, entry: &Entry) {
+    let now = self.clock.fetch_add(1, AtomicOrdering::Relaxed);
+    entry.used.store(now, AtomicOrdering::Relaxed);
+  }
+
+  /// Counts a string that was already interned
+  fn hit(&mut self) {
+    self.hits += 1;
+    if let Some(observer) = &self.observer {
+      observer.on_intern_hit();
+    }
+  }
+
+  /// Counts a string of `len` bytes that wasn't interned yet
+  fn miss(&mut self, len: usize) {
+    self.misses += 1;
+    if let Some(observer) = &self.observer {
+      observer.on_intern_miss(len);
+    }
+  }
+
+  /// Tells the observer if the store has allocated more since it was last
+  /// told
+  fn observe_growth(&mut self, store: &Store) {
+    let Some(observer) = &self.observer else {
+      return;
+    };
+
+    let allocated = store.allocated_bytes();
+    if allocated > self.allocated {
+      observer.on_store_grow(allocated);
+    }
+    self.allocated = allocated;
+  }
+
+  fn entries(&self) -> impl Iterator<Item = &Arc<Entry>> {
+    self.symbols.iter().flatten()
+  }
+
+  fn intern_existing(&mut self, s: &str) -> Option<Span> {
+    let span = self.index.get_or_promote(s.as_bytes())?;
+    self.hit();
+    Some(span)
+  }
+
+  fn intern_uncontested(
+    &mut self,
+    store: &Store,
+    s: &str,
+  ) -> Result<Span, InternError> {
+    if let Some(span) = self.intern_existing(s) {
+      return Ok(span);
+    }
+
+    if let Some(limit) = self.max_store_bytes {
+      if store.stored_bytes() + s.len() > limit {
+        return Err(InternError::StoreFull { limit });
+      }
+    }
+
+    self.miss(s.len());
+    // SAFETY: we hold the lock, so no other thread is pushing
+    let span = unsafe { self.push(store, s.as_bytes()) };
+    Ok(self.index.insert(s.as_bytes(), span))
+  }
+
+  /// Copies a string that isn't in the index yet into the store. With
+  /// substring sharing on, it may start inside the end of the store, if the
+  /// store ends with the start of the string.
   ///
   /// # Safety
   ///
-  /// If there are any existing InternRefs in other threads, this method will
-  /// wait for them to be dropped. If this thread has any InternRefs, then
-  /// this method will panic and try to tell you where the last one was
-  /// created.
-  ///
-  /// # Examples
-  ///
-  /// ```
-  /// use str_interning::sync::Interner;
-  ///
-  /// let interner = Interner::new();
-  ///
-  /// let hello = interner.intern("hello");
-  /// let hello2 = interner.intern("hello");
-  ///
-  /// assert_eq!(hello, hello2);
-  /// ```
-  pub fn intern<S: AsRef<str>>(self: &Arc<Self>, s: S) -> Intern {
-    let mut lock = self.internal.lock();
-
-    // check local refs
-    let key = thread::current().id();
-    if let Some(local_refs) = lock.local_refs.get(&key) {
-      if *local_refs > 0 {
-        let lr = lock.last_ref.unwrap();
-        panic!(
-          concat!(
-            "Cannot intern while holding an InternRef\n",
-            " --> last InternRef created at {}:{}:{}",
-          ),
-          lr.file(),
-          lr.line(),
-          lr.column(),
-        );
-      }
+  /// Same as [Store::push].
+  unsafe fn push(&self, store: &Store, s: &[u8]) -> Span {
+    if self.index.shares_substrings() {
+      // SAFETY: passed on from the caller
+      unsafe { store.push_overlapping(s) }
     } else {
-      lock.local_refs.insert(key, 0);
+      // SAFETY: passed on from the caller
+      unsafe { store.push(s) }
     }
+  }
 
-    // check nonlocal refs
-    if lock.refs > 0 {
-      self.cond.wait(&mut lock);
+  /// Interns the text at `span`, which already lies in the store, without
+  /// copying it
+  fn intern_in_place(&mut self, store: &Store, span: Span) -> Span {
+    // SAFETY: the span lies within a span handed out by the store
+    let s = unsafe { store.get(span.clone()) };
+    if let Some(existing) = self.intern_existing(s) {
+      return existing;
     }
 
-    Intern {
-      span: lock.intern_uncontested(s),
-      interner: Arc::clone(self),
+    self.miss(s.len());
+    self.index.insert_in_place(s.as_bytes(), span)
+  }
+
+  /// Interns the `&'static str` at `span`, which was made by
+  /// [Store::static_span], without copying it into the store
+  fn intern_static(&mut self, store: &Store, span: Span) -> Span {
+    // SAFETY: the span points at a &'static str
+    let s = unsafe { store.get(span.clone()) };
+    if let Some(existing) = self.intern_existing(s) {
+      return existing;
     }
+
+    self.miss(s.len());
+    self.index.insert(s.as_bytes(), span)
   }
 
-  /// Creates an interned string
+  /// Interns the text staged at `staged`, which is only committed to the
+  /// store if it isn't there already.
   ///
   /// # Safety
   ///
-  /// If there are any existing InternRefs in other threads, this method will
-  /// wait for them to be dropped. If this thread has any InternRefs, then
-  /// this method will return an appropriate error.
-  ///
-  /// # Examples
+  /// `staged` must be the last span staged on `store`, with nothing written
+  /// since.
+  unsafe fn intern_staged(
+    &mut self,
+    store: &Store,
+    staged: Span,
+  ) -> Result<Span, InternError> {
+    // SAFETY: the staged bytes were copied out of strs
+    let s = unsafe { store.get(staged.clone()) };
+    if let Some(span) = self.intern_existing(s) {
+      return Ok(span);
+    }
+
+    if let Some(limit) = self.max_store_bytes {
+      if store.stored_bytes() + s.len() > limit {
+        return Err(InternError::StoreFull { limit });
+      }
+    }
+
+    self.miss(s.len());
+    // SAFETY: see above
+    unsafe { store.commit(staged.clone()) };
+    Ok(self.index.insert(s.as_bytes(), staged))
+  }
+
+  /// Rebuilds the store and index out of only the strings that are still in
+  /// use, and returns how many bytes were freed.
   ///
-  /// ```
-  /// use str_interning::sync::Interner;
+  /// # Safety
   ///
-  /// let interner = Interner::new();
+  /// Nothing may be reading from the store.
+  unsafe fn compact(&mut self, store: &Store) -> usize {
+    for slot in &mut self.symbols {
+      if slot.as_ref().is_some_and(|entry| !Entry::in_use(entry)) {
+        *slot = None;
+      }
+    }
+
+    // SAFETY: passed on from the caller
+    unsafe { self.repack(store) }
+  }
+
+  /// Drops strings that aren't in use, least recently used first, until the
+  /// rest would take up no more than `room` bytes once compacted, then
+  /// compacts the store like [InternerInternal::compact]. Returns how many
+  /// bytes were freed.
   ///
-  /// let hello = interner.try_intern("hello").unwrap();
-  /// let hello2 = interner.try_intern("hello").unwrap();
+  /// # Safety
   ///
-  /// assert_eq!(hello, hello2);
-  /// ```
-  pub fn try_intern<S: AsRef<str>>(
-    self: &Arc<Self>,
-    s: S,
-  ) -> Result<Intern, InternError> {
-    let mut lock = self.internal.lock();
+  /// Nothing may be reading from the store.
+  unsafe fn evict(&mut self, store: &Store, room: usize) -> usize {
+    let mut kept = self
+      .entries()
+      .map(|entry| entry.stored_len())
+      .sum::<usize>();
+    let mut unused = self
+      .entries()
+      .filter(|entry| !Entry::in_use(entry))
+      .map(|entry| (entry.used.load(AtomicOrdering::Relaxed), entry.id))
+      .collect::<Vec<_>>();
+    unused.sort_unstable();
 
-    // check local refs
-    let key = thread::current().id();
-    if let Some(local_refs) = lock.local_refs.get(&key) {
-      if *local_refs > 0 {
-        return Err(InternError::OutstandingLocalRef(lock.last_ref.unwrap()));
+    for (_, id) in unused {
+      if kept <= room {
+        break;
       }
-    } else {
-      lock.local_refs.insert(key, 0);
-    }
 
-    // check nonlocal refs
-    if lock.refs > 0 {
-      self.cond.wait(&mut lock);
+      let entry = self.symbols[id].take().unwrap();
+      kept -= entry.stored_len();
     }
 
-    Ok(Intern {
-      span: lock.intern_uncontested(s),
-      interner: Arc::clone(self),
-    })
+    // SAFETY: passed on from the caller
+    unsafe { self.repack(store) }
   }
-}
 
-/// Represents a single interned string. This struct may be passed around
-/// and cloned cheaply, and without regard for lifetimes. Created using
-/// [Interner::intern] or [Interner::try_intern].
-///
-/// The string data that this [Intern] represents is only accessible through
-/// the short-lived [InternRef] type, which can be produced by
-/// [Intern::get_ref].
-///
-/// # Examples
-///
-/// ```
-/// use str_interning::sync::Interner;
-///
-/// let interner = Interner::new();
-///
-/// let hello = interner.intern("hello");
-/// let hello_ref = hello.get_ref();
-///
-/// assert_eq!(&*hello_ref, "hello");
-/// ```
-///
-/// The following example will panic because the intern method was called
-/// while an [InternRef] still exists.
-///
-/// ```should_panic
-/// use str_interning::sync::Interner;
-///
-/// let interner = Interner::new();
-/// let hello = interner.intern("hello");
-/// let _hello_ref = hello.get_ref();
-///
-/// interner.intern("goodbye"); // this will cause a panic
-/// ```
-#[derive(Clone)]
-pub struct Intern {
-  span: Span,
-  interner: Arc<Interner>,
-}
-
-impl Intern {
-  /// Produces an [InternRef]. This is the only way to access the underlying
-  /// string data that this [Intern] represents.
+  /// Rebuilds the store and index out of the entries left in the symbol
+  /// table, and returns how many bytes were freed.
   ///
   /// # Safety
   ///
-  /// While any [InternRef]s exist, no new [Intern]s can be produced.
-  #[track_caller]
-  pub fn get_ref(&self) -> InternRef<'_> {
-    let mut lock = self.interner.internal.lock();
+  /// Nothing may be reading from the store.
+  unsafe fn repack(&mut self, store: &Store) -> usize {
+    let live = self
+      .entries()
+      .map(Arc::clone)
+      .map(|entry| {
+        // SAFETY: every entry's spans were handed out by the store, and the
+        //         text is copied out before the store is cleared
+        let text = unsafe { store.get(entry.span()) }.to_owned();
+        let spelling = entry
+          .spelled
+          // SAFETY: see above
+          .then(|| unsafe { store.get(entry.shown()) }.to_owned());
+        (entry, text, spelling)
+      })
+      .collect::<Vec<_>>();
 
-    // register last caller location for helpful panics
-    lock.last_ref = Some(Location::caller());
-
-    // increment refcounts
-    lock.refs += 1;
-    let key = thread::current().id();
-    if let Some(refs) = lock.local_refs.get_mut(&key) {
-      *refs += 1;
-    } else {
-      lock.local_refs.insert(key, 1);
+    let before = store.stored_bytes();
+    // SAFETY: the caller promises nothing is reading, and every span that
+    //         was handed out is forgotten or rewritten below
+    unsafe { store.clear() };
+    self.index = self.index.emptied();
+    self.ids.clear();
+    // SAFETY: the store was just emptied, so nothing in it is indexed
+    unsafe { self.rebuild(store, live) };
+    if let Some(spellings) = &mut self.spellings {
+      let symbols = &self.symbols;
+      spellings.retain(|&id, _| symbols.get(id).is_some_and(Option::is_some));
     }
 
-    let store = unsafe { &*lock.store.data_ptr() };
+    // strings that overlapped the end of the store may not line up the same
+    // way again, so the store can come out a little larger
+    before.saturating_sub(store.stored_bytes())
+  }
 
-    InternRef {
-      text: &store[self.span.clone()],
-      interner: Arc::clone(&self.interner),
+  /// Stores each entry's string, which must not already be in the index, and
+  /// points the entry at it. Spelled entries also get their spelling stored.
+  /// Entries that point at a `&'static str` keep pointing at it.
+  ///
+  /// # Safety
+  ///
+  /// Same as [Store::push].
+  unsafe fn rebuild(
+    &mut self,
+    store: &Store,
+    mut live: Vec<(Arc<Entry>, String, Option<String>)>,
+  ) {
+    // index static strings first, and store longer strings before shorter
+    // ones, so that shorter strings can share their data
+    live.sort_by_key(|(entry, text, _)| {
+      (!Store::is_static(&entry.span()), Reverse(text.len()))
+    });
+
+    for (entry, text, spelling) in live {
+      let text = text.as_bytes();
+      let span = self.index.get_or_promote(text).unwrap_or_else(|| {
+        let span = match entry.span() {
+          span if Store::is_static(&span) => span,
+          // SAFETY: passed on from the caller
+          _ => unsafe { self.push(store, text) },
+        };
+        self.index.insert(text, span)
+      });
+
+      if self.symbols.len() <= entry.id {
+        self.symbols.resize(entry.id + 1, None);
+      }
+      entry.set_span(span.clone());
+      if let Some(spelling) = spelling {
+        // SAFETY: passed on from the caller
+        entry.set_shown(unsafe { store.push(spelling.as_bytes()) });
+      }
+      self.ids.insert(span, entry.id);
+      let id = entry.id;
+      self.symbols[id] = Some(entry);
     }
   }
 }
 
-impl fmt::Debug for Intern {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.debug_struct("Intern")
-      .field("span", &self.span)
-      .field("text", &&*self.get_ref())
-      .finish()
+impl Interner {
+  /// Constructs a new Interner with the default configuration. Use
+  /// [InternerBuilder] to configure it instead.
+  pub fn new() -> Arc<Self> {
+    InternerBuilder::new().build()
   }
-}
 
-impl fmt::Display for Intern {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.write_str(&*self.get_ref())
+  pub(crate) fn from_builder(builder: &InternerBuilder) -> Arc<Self> {
+    Self::with_parent(builder, None)
   }
-}
 
-impl PartialEq for Intern {
-  fn eq(&self, other: &Self) -> bool {
-    Arc::ptr_eq(&self.interner, &other.interner) && self.span == other.span
-  }
-}
-impl Eq for Intern {}
+  fn with_parent(
+    builder: &InternerBuilder,
+    parent: Option<Arc<Interner>>,
+  ) -> Arc<Self> {
+    let substring_sharing = builder
+      .substring_sharing
+      .unwrap_or(builder.index == IndexBackend::Trie);
 
-/// Allows access to the string that an [Intern] represents. This type is
-/// not meant to be long-lived; you should only create them when needed and
-/// drop them as soon as possible. This type is so powerful that its very
-/// existence blocks all threads from creating new [Intern]s.
-///
-/// # Examples
-///
-/// ```
-/// use str_interning::sync::Interner;
-///
-/// let interner = Interner::new();
-/// let hello = interner.intern("hello");
-///
-/// let hello_ref = hello.get_ref();
-///
-/// assert_eq!(&*hello_ref, "hello");
-/// ```
-pub struct InternRef<'a> {
-  text: &'a str,
-  interner: Arc<Interner>,
-}
+    let interner = Arc::new(Self {
+      internal: RwLock::new(InternerInternal {
+        index: Index::new(builder.index, substring_sharing),
+        ids: HashMap::new(),
+        symbols: Vec::new(),
+        max_store_bytes: builder.max_store_bytes,
+        store_full: builder.store_full,
+        preserve_case: builder.preserve_case,
+        spellings: builder.record_spellings.then(HashMap::new),
+        hits: 0,
+        misses: 0,
+        clock: AtomicU64::new(0),
+        observer: builder.observer.clone(),
+        allocated: 0,
+      }),
+      store: Store::new(),
+      refs: AtomicUsize::new(0),
+      moving: AtomicBool::new(false),
+      case_folding: builder.case_folding,
+      normalization: builder.normalization,
+      counting: builder.count_interns,
+      max_refs: builder.max_outstanding_refs,
+      // waiting for readers can't end if they are on the waiting thread,
+      // so it has to be able to tell
+      leaks: (builder.detect_leaked_refs
+        || builder.refs_held != RefsHeldPolicy::Fail)
+        .then(LeakDetector::new),
+      refs_held: builder.refs_held,
+      queued: Mutex::new(Vec::new()),
+      readers_gone: Parked::new(),
+      #[cfg(feature = "dashmap")]
+      hit_cache: (builder.lock_free_hits && !builder.record_spellings)
+        .then(DashMap::new),
+      #[cfg(feature = "dashmap")]
+      cache_hits: AtomicU64::new(0),
+      #[cfg(feature = "diagnostics")]
+      diagnostics: Diagnostics::new(
+        builder
+          .diagnostics_threshold
+          .unwrap_or(Duration::from_secs(1)),
+      ),
+      paths: OnceLock::new(),
+      sources: RwLock::new(Vec::new()),
+      observer: builder.observer.clone(),
+      parent,
+    });
 
-impl Drop for InternRef<'_> {
-  fn drop(&mut self) {
-    let mut lock = self.interner.internal.lock();
+    let (bytes, entries) = builder.capacity;
+    interner.reserve(bytes, entries);
+    interner.seed(builder);
+    interner
+  }
 
-    let key = thread::current().id();
-    if let Some(local_refs) = lock.local_refs.get_mut(&key) {
-      if *local_refs == 0 {
-        panic!("Interner nonlocal reference count overflow");
-      } else {
-        *local_refs -= 1;
-      }
-    } else {
-      unreachable!();
-    }
+  /// Interns the keywords and dictionary of `builder` into a new interner,
+  /// pinned, checking that the keywords got the ids 0 to
+  /// `keywords.len() - 1`. They aren't counted as interned, since nobody has
+  /// asked for them yet.
+  fn seed(&self, builder: &InternerBuilder) {
+    let mut lock = self.lock();
+    let mut seed = |s: &str| {
+      let span = match self.intern_making_room(&mut lock, &self.canonical(s)) {
+        Ok(span) => span,
+        Err(e) => panic!("{e}"),
+      };
+      lock.entry(&self.store, span, s).pin().id()
+    };
 
-    if lock.refs == 0 {
-      panic!("Interner nonlocal reference count overflow");
-    } else {
-      lock.refs -= 1;
+    for (i, keyword) in builder.keywords.iter().enumerate() {
+      let id = seed(keyword);
+      assert!(id.0 as usize == i, "keyword {keyword:?} appears twice");
     }
-
-    if lock.refs == 0 {
-      self.interner.cond.notify_all();
+    for s in &builder.dictionary {
+      seed(s);
     }
   }
-}
 
-impl fmt::Debug for InternRef<'_> {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.debug_tuple("InternRef").field(&self.text).finish()
+  /// Constructs a new Interner with room for roughly `bytes` bytes of string
+  /// data and `entries` distinct strings before it needs to allocate.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::with_capacity(1 << 20, 10_000);
+  ///
+  /// let hello = interner.intern("hello");
+  ///
+  /// assert_eq!(&*hello.get_ref(), "hello");
+  /// ```
+  pub fn with_capacity(bytes: usize, entries: usize) -> Arc<Self> {
+    InternerBuilder::new().capacity(bytes, entries).build()
   }
-}
 
-impl fmt::Display for InternRef<'_> {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.write_str(self.text)
+  /// Constructs an Interner layered over `parent`, such as one for a single
+  /// file over a table of keywords that every file shares. Strings that are
+  /// in the parent, or in its own parents, are found there, and only new
+  /// strings are stored in the child, so the child can be thrown away as a
+  /// whole once it's no longer needed. The parent is never written to.
+  ///
+  /// The child folds case and normalizes like the parent, and its other
+  /// options are left at their defaults.
+  ///
+  /// [Interner::intern], its `try_` variants, [Interner::intern_all] and its
+  /// parallel variant, [Interner::intern_static] and [Interner::get] fall
+  /// back to the parent. Strings found there are handed out as the parent's
+  /// own [Intern]s, so they are equal to the parent's interns of them, but
+  /// they belong to the parent, and [Interner::scope] on the child won't
+  /// read them. Everything else, such as [Interner::intern_symbol],
+  /// [Interner::iter] and [Interner::collect], only sees the strings in the
+  /// child.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let keywords = Interner::new();
+  /// let fn_keyword = keywords.intern("fn");
+  ///
+  /// let file = Interner::new_child(&keywords);
+  /// let main = file.intern("main");
+  ///
+  /// assert_eq!(file.intern("fn"), fn_keyword);
+  /// assert_eq!(file.get("main"), Some(main));
+  /// assert_eq!(keywords.get("main"), None);
+  ///
+  /// drop(file);
+  /// assert_eq!(keywords.stats().stored_bytes, 2);
+  /// ```
+  pub fn new_child(parent: &Arc<Interner>) -> Arc<Self> {
+    let builder = InternerBuilder::new()
+      .case_folding(parent.case_folding)
+      .normalization(parent.normalization)
+      .preserve_case(parent.internal.read().preserve_case);
+    Self::with_parent(&builder, Some(Arc::clone(parent)))
   }
-}
 
-impl Deref for InternRef<'_> {
-  type Target = str;
+  /// The interner this one is layered over, if it was made with
+  /// [Interner::new_child]
+  pub fn parent(&self) -> Option<&Arc<Interner>> {
+    self.parent.as_ref()
+  }
 
-  fn deref(&self) -> &Self::Target {
-    self.text
+  /// Finds `s`, which has already been put in canonical form, in the
+  /// parents of this interner, unless this interner has it itself
+  fn in_parent(
+    &self,
+    lock: &InternerInternal,
+    spelling: &str,
+    s: &str,
+  ) -> Option<Intern> {
+    let parent = self.parent.as_ref()?;
+    if lock.index.get(s.as_bytes()).is_some() {
+      return None;
+    }
+
+    parent.get_canonical(spelling, s)
   }
-}
 
-#[cfg(test)]
+  /// Constructs a new Interner holding every string in `corpus`, and returns
+  /// an [Intern] for each one in the same order. This is faster than
+  /// interning the strings one by one: the store is allocated once, and the
+  /// longest strings are stored first so that shorter strings inside them
+  /// can share their data no matter what order they come in. Strings still
+  /// get their [SymbolId]s in the order they appear in `corpus`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let (interner, words) = Interner::from_corpus(["ell", "hello", "ell"]);
+  ///
+  /// assert_eq!(words[0], words[2]);
+  /// assert_eq!(&*words[1].get_ref(), "hello");
+  /// // "ell" is served from inside "hello"
+  /// assert_eq!(interner.stats().stored_bytes, 5);
+  /// ```
+  pub fn from_corpus<I>(corpus: I) -> (Arc<Self>, Vec<Intern>)
+  where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+  {
+    let strings = corpus.into_iter().collect::<Vec<_>>();
+    let bytes = strings.iter().map(|s| s.as_ref().len()).sum();
+    let interner = Self::with_capacity(bytes, strings.len());
+
+    let mut order = (0..strings.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| Reverse(strings[i].as_ref().len()));
+
+    let mut lock = interner.lock();
+    let mut spans = vec![0..0; strings.len()];
+    for i in order {
+      match lock.intern_uncontested(&interner.store, strings[i].as_ref()) {
+        Ok(span) => spans[i] = span,
+        Err(e) => panic!("{e}"),
+      }
+    }
+
+    let interns = spans
+      .into_iter()
+      .zip(&strings)
+      .map(|(span, s)| Intern {
+        entry: lock.entry(&interner.store, span, s.as_ref()),
+        interner: Arc::clone(&interner),
+      })
+      .collect();
+    drop(lock);
+
+    (interner, interns)
+  }
+
+  /// Makes room for roughly `bytes` more bytes of string data and `entries`
+  /// more distinct strings, so that a hot interning loop doesn't have to
+  /// stop and allocate.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the store would grow past `usize::MAX` bytes.
+  pub fn reserve(&self, bytes: usize, entries: usize) {
+    let mut lock = self.lock();
+    lock.index.reserve(entries);
+    // SAFETY: we hold the lock, so no other thread is pushing
+    unsafe { self.store.reserve(bytes) };
+    lock.observe_growth(&self.store);
+  }
+
+  /// Number of readers currently looking at the store, which are mostly
+  /// [InternRef]s, but also [InternStr]s and [InternerIter]s.
+  /// [Interner::collect] and [Interner::shrink_to_fit] only move strings
+  /// while this is 0. This is a snapshot, which other threads may change
+  /// straight away.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  ///
+  /// let reading = hello.get_ref();
+  /// assert_eq!(interner.outstanding_refs(), 1);
+  ///
+  /// drop(reading);
+  /// assert_eq!(interner.outstanding_refs(), 0);
+  /// ```
+  pub fn outstanding_refs(&self) -> usize {
+    self.refs.load(AtomicOrdering::Relaxed)
+  }
+
+  /// Reports which thread holds the lock, and which threads are reading and
+  /// where they started, such as to find out what an [Interner::intern]
+  /// that won't return is waiting for. Like [Interner::outstanding_refs],
+  /// this is a snapshot.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  /// use std::thread;
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  ///
+  /// let reading = hello.get_ref();
+  /// let report = interner.diagnostics();
+  ///
+  /// assert!(report.writer.is_none());
+  /// assert_eq!(report.readers.len(), 1);
+  /// assert_eq!(report.readers[0].thread, thread::current().id());
+  /// ```
+  #[cfg(feature = "diagnostics")]
+  pub fn diagnostics(&self) -> DiagnosticsReport {
+    self.diagnostics.report()
+  }
+
+  /// Takes a snapshot of how much memory this interner is using and how
+  /// effective it has been at deduplicating.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern("hello");
+  /// interner.intern("hello");
+  ///
+  /// let stats = interner.stats();
+  ///
+  /// assert_eq!(stats.stored_bytes, 5);
+  /// assert_eq!(stats.entries, 1);
+  /// assert_eq!((stats.hits, stats.misses), (1, 1));
+  /// ```
+  pub fn stats(&self) -> InternerStats {
+    let lock = self.internal.read();
+
+    InternerStats {
+      stored_bytes: self.store.stored_bytes(),
+      allocated_bytes: self.store.allocated_bytes(),
+      entries: lock.index.entries(),
+      trie_nodes: lock.index.trie_nodes(),
+      automaton_states: lock.index.automaton_states(),
+      outstanding_refs: self.outstanding_refs(),
+      hits: lock.hits + self.cached_hits(),
+      misses: lock.misses,
+    }
+  }
+
+  /// Lists every string along with how many times it has been interned,
+  /// most often first, with ties in the order the strings were first
+  /// interned. Strings are only counted if the interner was built with
+  /// [InternerBuilder::count_interns]; otherwise this is empty. Looking
+  /// strings up, such as with [Interner::get], doesn't count.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::InternerBuilder;
+  ///
+  /// let interner = InternerBuilder::new().count_interns(true).build();
+  /// let words = interner.intern_all("to be or not to be".split(' '));
+  ///
+  /// let counts = interner.counts();
+  ///
+  /// assert_eq!(counts[0], (words[0].clone(), 2));
+  /// assert_eq!(counts[1], (words[1].clone(), 2));
+  /// assert_eq!(counts[3], (interner.intern("not"), 1));
+  /// ```
+  pub fn counts(self: &Arc<Self>) -> Vec<(Intern, u64)> {
+    if !self.counting {
+      return Vec::new();
+    }
+
+    self.ranked()
+  }
+
+  /// Lays out the `k` most often interned strings, by [Interner::counts],
+  /// as a dictionary for [InternerBuilder::with_dictionary], so that a new
+  /// interner can start out with them. The most frequent strings come
+  /// first, and get the lowest ids in the new interner. Without
+  /// [InternerBuilder::count_interns], the first `k` strings to be interned
+  /// are exported instead.
+  ///
+  /// Dictionaries hold strings as they are shown, and are the same for
+  /// [unsync](crate::unsync) interners.
+  ///
+  /// # Panics
+  ///
+  /// Panics if a string is 4 GiB or longer.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{InternerBuilder, SymbolId};
+  ///
+  /// let learned = InternerBuilder::new().count_interns(true).build();
+  /// learned.intern_all("a rose is a rose is a rose".split(' '));
+  ///
+  /// let dictionary = learned.export_dictionary(2);
+  /// let interner = InternerBuilder::new()
+  ///   .with_dictionary(&dictionary)?
+  ///   .build();
+  ///
+  /// assert_eq!(interner.stats().entries, 2);
+  /// assert_eq!(interner.intern("a").id(), SymbolId(0));
+  /// assert_eq!(interner.intern("rose").id(), SymbolId(1));
+  /// # Ok::<(), std::io::Error>(())
+  /// ```
+  pub fn export_dictionary(self: &Arc<Self>, k: usize) -> Vec<u8> {
+    let strings = self
+      .ranked()
+      .into_iter()
+      .take(k)
+      .map(|(intern, _)| intern.resolve())
+      .collect::<Vec<_>>();
+    dictionary::encode(&strings)
+  }
+
+  /// Every string with how many times it has been interned, most often
+  /// first, then in order of id
+  fn ranked(self: &Arc<Self>) -> Vec<(Intern, u64)> {
+    let lock = self.internal.read();
+    let mut counts = lock
+      .entries()
+      .map(|entry| {
+        let intern = Intern {
+          entry: Arc::clone(entry),
+          interner: Arc::clone(self),
+        };
+        (intern, entry.interned.load(AtomicOrdering::Relaxed))
+      })
+      .collect::<Vec<_>>();
+    drop(lock);
+
+    counts.sort_by_key(|(intern, count)| (Reverse(*count), intern.entry.id));
+    counts
+  }
+
+  /// Creates an interned string. If the string already appears anywhere in
+  /// the store, even as part of a longer string, that data is reused and
+  /// nothing new is stored.
+  ///
+  /// # Safety
+  ///
+  /// Only one thread may intern at a time, so this method will wait for any
+  /// other thread that is currently interning. Existing [InternRef]s never
+  /// block interning, since interned data is never moved.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the string would grow the store past the limit set with
+  /// [InternerBuilder::max_store_bytes]. Use [Interner::try_intern] to handle
+  /// that case instead.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let hello = interner.intern("hello");
+  /// let hello2 = interner.intern("hello");
+  ///
+  /// assert_eq!(hello, hello2);
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn intern<S: AsRef<str>>(self: &Arc<Self>, s: S) -> Intern {
+    match self.try_intern(s) {
+      Ok(intern) => intern,
+      Err(e) => panic!("{e}"),
+    }
+  }
+
+  /// Creates an interned string
+  ///
+  /// # Safety
+  ///
+  /// Only one thread may intern at a time, so this method will wait for any
+  /// other thread that is currently interning. Fails with
+  /// [InternError::StoreFull] if the string is not already in the store and
+  /// storing it would go over [InternerBuilder::max_store_bytes].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let hello = interner.try_intern("hello").unwrap();
+  /// let hello2 = interner.try_intern("hello").unwrap();
+  ///
+  /// assert_eq!(hello, hello2);
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn try_intern<S: AsRef<str>>(
+    self: &Arc<Self>,
+    s: S,
+  ) -> Result<Intern, InternError> {
+    let spelling = s.as_ref();
+    self.try_intern_canonical(spelling, &self.canonical(spelling))
+  }
+
+  /// Creates an interned string like [Interner::try_intern], but fails with
+  /// [InternError::WouldBlock] instead of waiting if another thread is
+  /// interning or collecting at the same time. With the `dashmap` feature's
+  /// `InternerBuilder::lock_free_hits`, strings that are already interned
+  /// are still found without waiting.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let hello = interner.try_intern_nonblocking("hello").unwrap();
+  ///
+  /// assert_eq!(&*hello.get_ref(), "hello");
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn try_intern_nonblocking<S: AsRef<str>>(
+    self: &Arc<Self>,
+    s: S,
+  ) -> Result<Intern, InternError> {
+    let spelling = s.as_ref();
+    let s = self.canonical(spelling);
+    self.intern_within(spelling, &s, Patience::Never)
+  }
+
+  /// Creates an interned string like [Interner::try_intern], but gives up
+  /// with [InternError::TimedOut] if other threads keep the interner busy
+  /// for longer than `timeout`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  /// use std::time::Duration;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let hello = interner.intern_timeout("hello", Duration::from_millis(10));
+  ///
+  /// assert_eq!(&*hello.unwrap().get_ref(), "hello");
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn intern_timeout<S: AsRef<str>>(
+    self: &Arc<Self>,
+    s: S,
+    timeout: Duration,
+  ) -> Result<Intern, InternError> {
+    let spelling = s.as_ref();
+    let s = self.canonical(spelling);
+    let deadline = Instant::now() + timeout;
+    self.intern_within(spelling, &s, Patience::Until { deadline, timeout })
+  }
+
+  /// Interns `s`, which has already been put in canonical form, and was
+  /// written as `spelling` before that
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub(crate) fn try_intern_canonical(
+    self: &Arc<Self>,
+    spelling: &str,
+    s: &str,
+  ) -> Result<Intern, InternError> {
+    self.intern_within(spelling, s, Patience::Forever)
+  }
+
+  /// Creates an interned string like [Interner::try_intern], except that
+  /// under [RefsHeldPolicy::Queue], if the store is full and readers keep
+  /// anything from being evicted, it doesn't wait for them. It fails with
+  /// [InternError::Queued] instead, which holds a [PendingIntern] for the
+  /// string, to be interned once the last reader is dropped.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{
+  ///   InternError, InternerBuilder, RefsHeldPolicy, StoreFullPolicy,
+  /// };
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .max_store_bytes(10)
+  ///   .on_store_full(StoreFullPolicy::Evict)
+  ///   .when_refs_held(RefsHeldPolicy::Queue)
+  ///   .build();
+  /// let hello = interner.intern("hello");
+  /// drop(interner.intern("world"));
+  ///
+  /// let hello_ref = hello.get_ref();
+  /// let Err(InternError::Queued(pending)) = interner.intern_or_queue("again")
+  /// else {
+  ///   panic!("nothing could be evicted while hello_ref was alive");
+  /// };
+  /// assert!(!pending.is_ready());
+  ///
+  /// drop(hello_ref);
+  /// assert!(pending.is_ready());
+  /// assert_eq!(pending.wait()?, interner.intern("again"));
+  /// # Ok::<(), InternError>(())
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn intern_or_queue<S: AsRef<str>>(
+    self: &Arc<Self>,
+    s: S,
+  ) -> Result<Intern, InternError> {
+    let spelling = s.as_ref();
+    let s = self.canonical(spelling);
+    self.intern_when_locked(spelling, &s, Patience::Forever)
+  }
+
+  /// Interns `s` like [Interner::try_intern_canonical], waiting on the lock
+  /// and on readers for as long as `patience` allows, including for a string
+  /// that [RefsHeldPolicy::Queue] put off
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn intern_within(
+    self: &Arc<Self>,
+    spelling: &str,
+    s: &str,
+    patience: Patience,
+  ) -> Result<Intern, InternError> {
+    match self.intern_when_locked(spelling, s, patience) {
+      Err(InternError::Queued(pending)) => pending.wait_within(patience),
+      result => result,
+    }
+  }
+
+  /// Interns `s` like [Interner::try_intern_canonical], waiting on the lock
+  /// and on readers for as long as `patience` allows. Under
+  /// [RefsHeldPolicy::Queue], a string that doesn't fit while readers are
+  /// alive is queued instead, and handed back in [InternError::Queued].
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn intern_when_locked(
+    self: &Arc<Self>,
+    spelling: &str,
+    s: &str,
+    patience: Patience,
+  ) -> Result<Intern, InternError> {
+    #[cfg(feature = "dashmap")]
+    if let Some(intern) = self.intern_cached(s) {
+      return Ok(intern);
+    }
+
+    let (mut lock, span) = loop {
+      let mut lock = self.held(self.lock_within(patience)?);
+      if let Some(intern) = self.in_parent(&lock, spelling, s) {
+        return Ok(intern);
+      }
+
+      let limit = match self.intern_evicting(&mut lock, s) {
+        Evicted::Done(result) => break (lock, result?),
+        Evicted::Blocked { limit } => limit,
+      };
+      // readers can't be dropped on threads that are waiting for the lock
+      drop(lock);
+      match self.refs_held {
+        RefsHeldPolicy::Fail => return Err(InternError::StoreFull { limit }),
+        // the last reader was dropped after evicting was tried
+        _ if self.refs.load(AtomicOrdering::SeqCst) == 0 => {},
+        RefsHeldPolicy::Block => {
+          self.await_readers(patience, limit, || false)?;
+        },
+        RefsHeldPolicy::Queue => {
+          let pending = self.queue(spelling, s, limit);
+          // the last reader may have been dropped before the string was
+          // queued, and found nothing to intern
+          if self.refs.load(AtomicOrdering::SeqCst) == 0 {
+            self.apply_queued();
+          }
+          return Err(InternError::Queued(pending));
+        },
+      }
+    };
+    let entry = self.interned(&mut lock, span, spelling);
+
+    #[cfg(feature = "dashmap")]
+    if let Some(cache) = &self.hit_cache {
+      if !cache.contains_key(s) {
+        cache.insert(s.into(), Arc::downgrade(&entry));
+      }
+    }
+
+    Ok(Intern {
+      entry,
+      interner: Arc::clone(self),
+    })
+  }
+
+  /// Waits to lock the interner for writing, for as long as `patience`
+  /// allows
+  fn lock_within(
+    &self,
+    patience: Patience,
+  ) -> Result<RwLockWriteGuard<'_, InternerInternal>, InternError> {
+    match patience {
+      Patience::Forever => Ok(self.wait()),
+      Patience::Never => {
+        self.internal.try_write().ok_or(InternError::WouldBlock)
+      },
+      Patience::Until { deadline, timeout } => self
+        .internal
+        .try_write_for(deadline.saturating_duration_since(Instant::now()))
+        .ok_or(InternError::TimedOut { timeout }),
+    }
+  }
+
+  /// Waits, without holding the lock, until every reader on every thread
+  /// has been dropped or `done` returns true, for as long as `patience`
+  /// allows. If the current thread holds a reader itself, waiting would
+  /// never end, so this fails with [InternError::StoreFull] instead.
+  fn await_readers(
+    &self,
+    patience: Patience,
+    limit: usize,
+    mut done: impl FnMut() -> bool,
+  ) -> Result<(), InternError> {
+    if self.leaks.as_ref().is_some_and(LeakDetector::here) {
+      return Err(InternError::StoreFull { limit });
+    }
+
+    let deadline = match patience {
+      Patience::Forever => None,
+      Patience::Never => return Err(InternError::WouldBlock),
+      Patience::Until { deadline, .. } => Some(deadline),
+    };
+    let gone = self.readers_gone.until(
+      || self.refs.load(AtomicOrdering::SeqCst) == 0 || done(),
+      deadline,
+    );
+    match patience {
+      Patience::Until { timeout, .. } if !gone => {
+        Err(InternError::TimedOut { timeout })
+      },
+      _ => Ok(()),
+    }
+  }
+
+  /// Puts off interning `s`, written as `spelling`, until no readers are
+  /// left. `limit` is the size of the store, for when waiting for it fails.
+  fn queue(
+    self: &Arc<Self>,
+    spelling: &str,
+    s: &str,
+    limit: usize,
+  ) -> PendingIntern {
+    let slot = Arc::new(Mutex::new(None));
+    self.queued.lock().push(Queued {
+      spelling: spelling.to_owned(),
+      s: s.to_owned(),
+      slot: Arc::clone(&slot),
+    });
+
+    PendingIntern {
+      interner: Arc::clone(self),
+      slot,
+      limit,
+    }
+  }
+
+  /// Interns what [RefsHeldPolicy::Queue] put off, like
+  /// [Interner::apply_queued_to], unless another thread holds the lock
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn apply_queued(&self) {
+    if self.queued.lock().is_empty() {
+      return;
+    }
+    if let Some(lock) = self.internal.try_write() {
+      self.apply_queued_to(&mut self.held(lock));
+    }
+  }
+
+  /// Interns what [RefsHeldPolicy::Queue] put off, under `lock`, and wakes
+  /// whoever is waiting on it. Strings stay queued while readers still keep
+  /// them from fitting, and are forgotten once their [PendingIntern] is
+  /// dropped.
+  fn apply_queued_to(&self, lock: &mut InternerInternal) {
+    self.queued.lock().retain(|queued| {
+      if Arc::strong_count(&queued.slot) == 1 {
+        return false;
+      }
+
+      let result = loop {
+        match self.intern_evicting(lock, &queued.s) {
+          Evicted::Done(result) => break result,
+          Evicted::Blocked { .. }
+            if self.refs.load(AtomicOrdering::SeqCst) != 0 =>
+          {
+            return true;
+          },
+          // the last reader was dropped after evicting was tried
+          Evicted::Blocked { .. } => {},
+        }
+      };
+      let result =
+        result.map(|span| self.interned(lock, span, &queued.spelling));
+      *queued.slot.lock() = Some(result);
+      false
+    });
+    self.readers_gone.wake();
+  }
+
+  /// Finds a string that is already interned in the hit cache, without
+  /// taking the lock
+  #[cfg(feature = "dashmap")]
+  fn intern_cached(self: &Arc<Self>, s: &str) -> Option<Intern> {
+    let cache = self.hit_cache.as_ref()?;
+
+    // counting as a reader keeps a collection from deciding that the entry
+    // is dead while we're reviving it
+    let reader = self.enter();
+    let entry = cache.get(s).and_then(|entry| entry.upgrade());
+    self.leave(&reader);
+
+    let entry = entry?;
+    self.count(&entry);
+    self.cache_hits.fetch_add(1, AtomicOrdering::Relaxed);
+    if let Some(observer) = &self.observer {
+      observer.on_intern_hit();
+    }
+    Some(Intern {
+      entry,
+      interner: Arc::clone(self),
+    })
+  }
+
+  /// How many hits were served by the hit cache
+  fn cached_hits(&self) -> u64 {
+    #[cfg(feature = "dashmap")]
+    return self.cache_hits.load(AtomicOrdering::Relaxed);
+    #[cfg(not(feature = "dashmap"))]
+    0
+  }
+
+  /// Creates an interned string out of a `&'static str`, such as an entry in
+  /// a keyword table, without copying it into the store. The interner reads
+  /// the string where it already is, and [Interner::collect] leaves it
+  /// there, so it takes up no room in the store. Other strings can still
+  /// reuse it if they lie inside it.
+  ///
+  /// If the string is already interned, the existing copy is used instead.
+  /// Strings that case folding or normalization would change are interned
+  /// like [Interner::intern], since their canonical form isn't `'static`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let keywords = ["fn", "let", "match"].map(|keyword| {
+  ///   interner.intern_static(keyword)
+  /// });
+  /// let at = interner.intern("at");
+  ///
+  /// assert_eq!(keywords[1], interner.intern("let"));
+  /// assert_eq!(&*at.get_ref(), "at");
+  /// assert_eq!(interner.stats().stored_bytes, 0);
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn intern_static(self: &Arc<Self>, s: &'static str) -> Intern {
+    let span = match (self.canonical(s), Store::static_span(s)) {
+      (Cow::Borrowed(_), Some(span)) => span,
+      _ => return self.intern(s),
+    };
+
+    let mut lock = self.lock();
+    if let Some(intern) = self.in_parent(&lock, s, s) {
+      return intern;
+    }
+
+    let span = lock.intern_static(&self.store, span);
+    Intern {
+      entry: self.interned(&mut lock, span, s),
+      interner: Arc::clone(self),
+    }
+  }
+
+  /// Interns the text of an [Intern] from another interner, so it can be
+  /// compared with, and used alongside, this interner's strings. An [Intern]
+  /// that is already from this interner is just cloned.
+  ///
+  /// The text is interned the way this interner sees it, so if the two
+  /// interners fold case or normalize differently, the adopted string may be
+  /// equal to more, or fewer, strings than it was before.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let lexer = Interner::new();
+  /// let parser = Interner::new();
+  /// let token = lexer.intern("ident");
+  ///
+  /// let adopted = parser.adopt(&token);
+  ///
+  /// assert_ne!(adopted, token);
+  /// assert_eq!(adopted, parser.intern("ident"));
+  /// assert_eq!(parser.adopt(&adopted), adopted);
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn adopt(self: &Arc<Self>, intern: &Intern) -> Intern {
+    if intern.is_from(self) {
+      return intern.clone();
+    }
+
+    // copy the text out first, since holding a reader on the other interner
+    // while waiting on this one could deadlock against a collection there
+    self.intern(intern.resolve())
+  }
+
+  /// Interns every string in `other` into this interner, and returns a
+  /// [MergeMap] from their ids in `other` to their ids here. Parallel workers
+  /// can each fill a private interner, and merge them into a shared one when
+  /// they are done.
+  ///
+  /// Strings keep the spelling that readers of `other` saw, and are interned
+  /// the way [Interner::adopt] interns them. Strings that had been handed
+  /// out as [Symbol]s in `other` are kept for good here too.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let shared = Interner::new();
+  /// shared.intern("main");
+  ///
+  /// let worker = Interner::new();
+  /// let helper = worker.intern("helper");
+  /// let main = worker.intern("main");
+  ///
+  /// let map = shared.merge(worker);
+  ///
+  /// assert_eq!(map.intern(helper.id()), Some(&shared.intern("helper")));
+  /// assert_eq!(map.id(main.id()), Some(shared.intern("main").id()));
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn merge(self: &Arc<Self>, other: Arc<Interner>) -> MergeMap {
+    let old = other
+      .internal
+      .read()
+      .entries()
+      .map(|entry| Intern {
+        entry: Arc::clone(entry),
+        interner: Arc::clone(&other),
+      })
+      .collect::<Vec<_>>();
+
+    let new = if Arc::ptr_eq(self, &other) {
+      old.clone()
+    } else {
+      // copy the text out first, like `adopt` does
+      let texts: Vec<String> = other.scope(|scope| {
+        old
+          .iter()
+          .map(|intern| scope.get(intern).to_owned())
+          .collect()
+      });
+      self.intern_all(texts)
+    };
+
+    let mut interns = Vec::new();
+    for (old, new) in old.iter().zip(new) {
+      if old.entry.pinned.load(AtomicOrdering::Relaxed) {
+        new.symbol();
+      }
+      let id = old.entry.id;
+      interns.resize(interns.len().max(id + 1), None);
+      interns[id] = Some(new);
+    }
+
+    MergeMap::new(interns)
+  }
+
+  /// Copies this interner into a new, independent one with the same
+  /// configuration and strings, so that it can be handed to another thread
+  /// for analysis while this one keeps changing. Every string keeps its
+  /// [SymbolId], along with its spelling, pin, use count and any recorded
+  /// spellings, so [Symbol]s from this interner resolve in the copy too.
+  /// The returned [MergeMap] maps each id to its [Intern] in the copy.
+  ///
+  /// The copy shares the parent of a child interner, but not the observer,
+  /// and it starts without any of the paths interned with
+  /// [Interner::intern_path] or the files interned with
+  /// [Interner::intern_source].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let main = interner.intern_symbol("main");
+  ///
+  /// let (copy, map) = interner.deep_clone();
+  /// interner.intern("helper");
+  ///
+  /// assert_eq!(&*copy.resolve(main), "main");
+  /// assert_eq!(map.id(main.id()), Some(main.id()));
+  /// assert_eq!(copy.get("helper"), None);
+  /// ```
+  pub fn deep_clone(&self) -> (Arc<Interner>, MergeMap) {
+    let lock = self.internal.read();
+    let mut builder = InternerBuilder::new()
+      .index(lock.index.backend())
+      .substring_sharing(lock.index.shares_substrings())
+      .case_folding(self.case_folding)
+      .normalization(self.normalization)
+      .preserve_case(lock.preserve_case)
+      .record_spellings(lock.spellings.is_some())
+      .count_interns(self.counting)
+      .on_store_full(lock.store_full)
+      .capacity(self.store.stored_bytes(), lock.ids.len());
+    builder.max_store_bytes = lock.max_store_bytes;
+    builder.max_outstanding_refs = self.max_refs;
+    builder.detect_leaked_refs = self.leaks.is_some();
+    builder.refs_held = self.refs_held;
+    #[cfg(feature = "dashmap")]
+    {
+      builder.lock_free_hits = self.hit_cache.is_some();
+    }
+    #[cfg(feature = "diagnostics")]
+    {
+      builder.diagnostics_threshold = Some(self.diagnostics.threshold);
+    }
+    let copy = Self::with_parent(&builder, self.parent.clone());
+
+    let entries = lock
+      .entries()
+      .map(|entry| {
+        // SAFETY: the spans came from our store, and we hold the lock, so
+        //         nothing can move them
+        let text = unsafe { self.store.get(entry.span()) }.to_owned();
+        let spelling = entry
+          .spelled
+          // SAFETY: see above
+          .then(|| unsafe { self.store.get(entry.shown()) }.to_owned());
+        let shown = spelling.as_deref().unwrap_or(&text);
+        // static strings keep pointing at the same `&'static str`
+        let copied = Entry::new(
+          entry.id,
+          entry.span(),
+          entry.spelled.then_some(0..0),
+          shown,
+        );
+        for (from, to) in [
+          (&entry.used, &copied.used),
+          (&entry.interned, &copied.interned),
+        ] {
+          to.store(from.load(AtomicOrdering::Relaxed), AtomicOrdering::Relaxed);
+        }
+        let pinned = entry.pinned.load(AtomicOrdering::Relaxed);
+        copied.pinned.store(pinned, AtomicOrdering::Relaxed);
+        (Arc::new(copied), text, spelling)
+      })
+      .collect();
+
+    let mut copied = copy.lock();
+    copied.symbols.resize(lock.symbols.len(), None);
+    copied.spellings.clone_from(&lock.spellings);
+    copied.hits = lock.hits;
+    copied.misses = lock.misses;
+    copied.clock = AtomicU64::new(lock.clock.load(AtomicOrdering::Relaxed));
+    drop(lock);
+    // SAFETY: we hold the lock, so no other thread is pushing
+    unsafe { copied.rebuild(&copy.store, entries) };
+
+    let interns = copied
+      .symbols
+      .iter()
+      .map(|slot| {
+        Some(Intern {
+          entry: Arc::clone(slot.as_ref()?),
+          interner: Arc::clone(&copy),
+        })
+      })
+      .collect();
+    drop(copied);
+
+    (copy, MergeMap::new(interns))
+  }
+
+  /// Creates an interned string, and returns a [Symbol] for it instead of an
+  /// [Intern]. A [Symbol] is a plain integer that can be copied around
+  /// freely, but needs the interner to read it back with
+  /// [Interner::resolve]. Strings that have been handed out as symbols are
+  /// never freed by [Interner::collect].
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let hello = interner.intern_symbol("hello");
+  ///
+  /// assert_eq!(hello, interner.intern_symbol("hello"));
+  /// assert_eq!(&*interner.resolve(hello), "hello");
+  /// ```
+  pub fn intern_symbol<S: AsRef<str>>(&self, s: S) -> Symbol {
+    let spelling = s.as_ref();
+    let s = self.canonical(spelling);
+    let mut lock = self.lock();
+
+    match self.intern_making_room(&mut lock, &s) {
+      Ok(span) => self.interned(&mut lock, span, spelling).pin(),
+      Err(e) => panic!("{e}"),
+    }
+  }
+
+  /// Creates an interned string, which is kept inside the returned
+  /// [CompactIntern] if its canonical form is no longer than
+  /// [INLINE_CAPACITY](crate::sync::INLINE_CAPACITY) bytes, and interned like
+  /// [Interner::intern] otherwise.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern], for strings
+  /// that aren't kept inline.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let x = interner.intern_compact("x");
+  /// let long = interner.intern_compact("a_rather_long_identifier_name");
+  ///
+  /// assert!(x.is_inline() && !long.is_inline());
+  /// assert_eq!(x, interner.intern_compact("x"));
+  /// assert_eq!(x.resolve(), "x");
+  /// assert_eq!(interner.stats().stored_bytes, 29);
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn intern_compact<S: AsRef<str>>(
+    self: &Arc<Self>,
+    s: S,
+  ) -> CompactIntern {
+    let spelling = s.as_ref();
+    let s = self.canonical(spelling);
+    CompactIntern::new(self, &s, || {
+      match self.try_intern_canonical(spelling, &s) {
+        Ok(intern) => intern,
+        Err(e) => panic!("{e}"),
+      }
+    })
+  }
+
+  /// Creates an interned string out of `pieces` joined end to end. The
+  /// pieces are written straight into the store, so there's no temporary
+  /// [String] to build first. If case folding or normalization would change
+  /// the joined string though, it is copied out and interned as usual.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let module = "collections";
+  ///
+  /// let path = interner.intern_concat(&["std::", module, "::HashMap"]);
+  ///
+  /// assert_eq!(path, interner.intern("std::collections::HashMap"));
+  /// ```
+  pub fn intern_concat<S: AsRef<str>>(
+    self: &Arc<Self>,
+    pieces: &[S],
+  ) -> Intern {
+    self.intern_written(
+      |stager| {
+        pieces
+          .iter()
+          .try_for_each(|piece| stager.write_str(piece.as_ref()))
+      },
+      || pieces.iter().map(AsRef::as_ref).collect(),
+    )
+  }
+
+  /// Creates an interned string out of the strings of `parts`, with
+  /// `separator` between each one, like a module path out of its segments.
+  /// It's written straight into the store under one lock, like
+  /// [Interner::intern_concat]. Parts from other interners are copied out
+  /// first.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let segments = ["std", "collections", "HashMap"]
+  ///   .map(|segment| interner.intern(segment));
+  ///
+  /// let path = interner.join(&segments, "::");
+  ///
+  /// assert_eq!(path, interner.intern("std::collections::HashMap"));
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn join(self: &Arc<Self>, parts: &[Intern], separator: &str) -> Intern {
+    self.intern_written(
+      |stager| {
+        parts.iter().enumerate().try_for_each(|(i, part)| {
+          if i > 0 {
+            stager.write_str(separator)?;
+          }
+          // reading from another interner while holding this one's lock
+          // could deadlock, like in `Interner::adopt`
+          if !part.is_from(self) {
+            return Err(fmt::Error);
+          }
+          part.with_str(|s| stager.write_str(s))
+        })
+      },
+      || {
+        let parts = parts.iter().map(Intern::resolve).collect::<Vec<_>>();
+        parts.join(separator)
+      },
+    )
+  }
+
+  /// Splits the string of `intern` around each `separator`, like
+  /// [str::split], and interns the pieces. The pieces already lie in the
+  /// store, so like [Intern::slice], nothing is copied unless case folding
+  /// or normalization change them. Every piece is interned under one lock.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Intern::slice].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let path = interner.intern("std::collections::HashMap");
+  ///
+  /// let segments = interner.split_intern(&path, "::");
+  ///
+  /// assert_eq!(segments.len(), 3);
+  /// assert_eq!(segments[1], interner.intern("collections"));
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn split_intern(
+    self: &Arc<Self>,
+    intern: &Intern,
+    separator: &str,
+  ) -> Vec<Intern> {
+    let intern = self.adopt(intern);
+    let mut lock = self.lock();
+    // SAFETY: the span was handed out by the store, and holding the lock
+    //         keeps the string from moving
+    let text = unsafe { self.store.get(intern.entry.shown()) };
+    let ranges = text
+      .split(separator)
+      .map(|piece| {
+        let start = piece.as_ptr() as usize - text.as_ptr() as usize;
+        start..(start + piece.len())
+      })
+      .collect::<Vec<_>>();
+
+    ranges
+      .into_iter()
+      .map(|range| intern.slice_locked(&mut lock, range))
+      .collect()
+  }
+
+  /// Creates an interned string out of formatted text, as made by
+  /// [format_args!]. The text is formatted straight into the store, so
+  /// there's no temporary [String] to build first. [intern_format!] is a
+  /// shorthand for this.
+  ///
+  /// The formatted values must not intern anything into this same interner
+  /// while they are being formatted, or this will deadlock.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern], or if one of
+  /// the formatted values returns an error.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let temp = interner.intern_fmt(format_args!("tmp{}", 3));
+  ///
+  /// assert_eq!(temp, interner.intern("tmp3"));
+  /// ```
+  ///
+  /// [intern_format!]: crate::intern_format
+  pub fn intern_fmt(self: &Arc<Self>, args: fmt::Arguments<'_>) -> Intern {
+    self.intern_written(|stager| stager.write_fmt(args), || args.to_string())
+  }
+
+  /// Creates an interned string out of the body of a string literal, with
+  /// its escapes decoded as `style` says. The decoded text is written
+  /// straight into the store, so there's no temporary [String] to build
+  /// first.
+  ///
+  /// # Errors
+  ///
+  /// Fails without interning anything if `raw` holds an escape that `style`
+  /// doesn't know, or one that is malformed.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{EscapeStyle, Interner, UnescapeError};
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let line = interner.intern_unescaped(r"a\tb\n", EscapeStyle::Rust);
+  /// let json = interner.intern_unescaped(r"\u00e9", EscapeStyle::Json);
+  ///
+  /// assert_eq!(line.unwrap(), interner.intern("a\tb\n"));
+  /// assert_eq!(json.unwrap(), interner.intern("é"));
+  /// assert!(matches!(
+  ///   interner.intern_unescaped(r"\q", EscapeStyle::Rust),
+  ///   Err(UnescapeError::UnknownEscape { index: 0 })
+  /// ));
+  /// ```
+  pub fn intern_unescaped(
+    self: &Arc<Self>,
+    raw: &str,
+    style: EscapeStyle,
+  ) -> Result<Intern, UnescapeError> {
+    // checked up front, so that nothing is staged for a bad literal
+    escape::unescape(raw, style, |_| {})?;
+
+    Ok(self.intern_written(
+      |stager| {
+        let mut written = Ok(());
+        let _ = escape::unescape(raw, style, |piece| {
+          written = written.and_then(|()| stager.write_str(piece));
+        });
+        written
+      },
+      || escape::unescape_to_string(raw, style).unwrap(),
+    ))
+  }
+
+  /// Interns whatever `write` stages in the store. Falls back to interning
+  /// what `fallback` makes as usual if writing fails, or if case folding or
+  /// normalization would change the staged text.
+  fn intern_written(
+    self: &Arc<Self>,
+    write: impl FnOnce(&mut Stager<'_>) -> fmt::Result,
+    fallback: impl FnOnce() -> String,
+  ) -> Intern {
+    let mut lock = self.lock();
+    // SAFETY: we hold the lock, so no other thread is pushing
+    let mut stager = unsafe { self.store.stager() };
+    if write(&mut stager).is_err() {
+      drop(lock);
+      return self.intern(fallback());
+    }
+
+    let staged = stager.finish();
+    // SAFETY: only strs were written, so the staged text is a str too
+    let text = unsafe { self.store.get(staged.clone()) };
+    if let Cow::Owned(_) = self.canonical(text) {
+      let text = text.to_owned();
+      drop(lock);
+      return self.intern(text);
+    }
+
+    // SAFETY: nothing has been written since the text was staged
+    let span = match unsafe { lock.intern_staged(&self.store, staged) } {
+      Ok(span) => span,
+      // evicting would clear the staged text along with the store
+      Err(InternError::StoreFull { .. })
+        if lock.store_full == StoreFullPolicy::Evict =>
+      {
+        let text = text.to_owned();
+        drop(lock);
+        return self.intern(text);
+      },
+      Err(e) => panic!("{e}"),
+    };
+    // SAFETY: the span was handed out by the store
+    let text = unsafe { self.store.get(span.clone()) };
+
+    Intern {
+      entry: self.interned(&mut lock, span, text),
+      interner: Arc::clone(self),
+    }
+  }
+
+  /// Produces an [InternRef] for the string that a [Symbol] represents.
+  ///
+  /// Unlike [Intern::get_ref], this briefly takes the lock to look up the
+  /// symbol, so it waits for any thread that is currently interning. Threads
+  /// that are only resolving don't wait for each other.
+  ///
+  /// # Panics
+  ///
+  /// May panic if `symbol` came from a different interner.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let words = ["a", "b", "a"].map(|word| interner.intern_symbol(word));
+  ///
+  /// assert_eq!(words[0], words[2]);
+  /// assert_eq!(&*interner.resolve(words[1]), "b");
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn resolve(&self, symbol: Symbol) -> InternRef<'_> {
+    self
+      .try_resolve(symbol)
+      .expect("symbol does not belong to this interner")
+  }
+
+  /// Reads back the string that a [Symbol] stands for, like
+  /// [Interner::resolve], or returns [None] if the symbol came from a
+  /// different interner.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let other = Interner::new();
+  /// other.intern_symbol("hello");
+  /// let world = other.intern_symbol("world");
+  ///
+  /// assert!(interner.try_resolve(world).is_none());
+  /// assert_eq!(&*other.try_resolve(world).unwrap(), "world");
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn try_resolve(&self, symbol: Symbol) -> Option<InternRef<'_>> {
+    let lock = self.internal.read();
+    let entry = usize::try_from(symbol.id().0)
+      .ok()
+      .and_then(|id| lock.symbols.get(id)?.as_ref())
+      .filter(|entry| entry.pinned.load(AtomicOrdering::Relaxed))?;
+
+    // nothing can start moving strings while we hold the lock
+    Some(self.read(entry))
+  }
+
+  /// Lists every distinct spelling that the string `symbol` stands for has
+  /// been interned or looked up as, first seen first, if the interner was
+  /// built with [InternerBuilder::record_spellings]. Otherwise, spellings
+  /// aren't kept, and this only gives the string as it reads.
+  ///
+  /// # Panics
+  ///
+  /// May panic if `symbol` came from a different interner.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{CaseFolding, InternerBuilder};
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .case_folding(CaseFolding::Ascii)
+  ///   .record_spellings(true)
+  ///   .build();
+  /// let html = interner.intern_symbol("<DIV>");
+  /// interner.intern("<div>");
+  /// interner.intern("<Div>");
+  /// interner.intern("<DIV>");
+  ///
+  /// assert_eq!(interner.spellings(html), ["<DIV>", "<div>", "<Div>"]);
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn spellings(&self, symbol: Symbol) -> Vec<String> {
+    let shown = self.resolve(symbol).to_owned();
+    let lock = self.internal.read();
+    let id = symbol.id().0 as usize;
+    match lock
+      .spellings
+      .as_ref()
+      .and_then(|spellings| spellings.get(&id))
+    {
+      Some(spellings) => spellings.iter().map(ToString::to_string).collect(),
+      None => vec![shown],
+    }
+  }
+
+  /// Puts `s` in the form that strings are indexed by
+  pub(crate) fn canonical<'a>(&self, s: &'a str) -> Cow<'a, str> {
+    self.normalization.canonical(self.case_folding, s)
+  }
+
+  /// Finds how many bytes from the start of `input` are put in the form
+  /// `canonical`, which [Interner::canonical] may have made longer or shorter
+  fn canonical_prefix_len(
+    &self,
+    input: &str,
+    canonical: &str,
+  ) -> Option<usize> {
+    input
+      .char_indices()
+      .map(|(i, _)| i)
+      .chain([input.len()])
+      .map(|end| (end, self.canonical(&input[..end])))
+      .take_while(|(_, prefix)| prefix.len() <= canonical.len())
+      .find(|(_, prefix)| prefix == canonical)
+      .map(|(end, _)| end)
+  }
+
+  /// Counts a new reader of `entry`'s string, waiting for any collection to
+  /// finish first
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn read(&self, entry: &Entry) -> InternRef<'_> {
+    self.read_span(|| entry.shown())
+  }
+
+  /// Counts a new reader like [Interner::read], but of the string at the
+  /// span that `span` gives once it's safe to ask for
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn read_span(&self, span: impl FnOnce() -> Span) -> InternRef<'_> {
+    let reader = self.enter();
+
+    // SAFETY: the span was handed out by this interner's store, and it can't
+    //         move while we're counted as a reader
+    let text = unsafe { self.store.get(span()) };
+    InternRef {
+      text,
+      interner: self,
+      reader,
+    }
+  }
+
+  /// Counts a new reader, which keeps strings from moving until it leaves.
+  ///
+  /// Readers announce themselves in `refs` before checking `moving`, and
+  /// movers set `moving` before checking `refs`, so at least one of them
+  /// sees the other. A reader that sees a move in progress takes itself back
+  /// out and waits on the lock, which the mover holds until it's done, then
+  /// tries again. It only ever gets through while nothing is moving.
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn enter(&self) -> Reader {
+    self.wait_to_read();
+    self.reader()
+  }
+
+  /// Records a reader that has just been counted
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn reader(&self) -> Reader {
+    Reader {
+      #[cfg(feature = "diagnostics")]
+      id: self.diagnostics.enter(),
+      thread: self.leaks.as_ref().map(LeakDetector::enter),
+    }
+  }
+
+  /// Counts a new reader like `Interner::enter`, unless strings are being
+  /// moved, or there are already as many readers as
+  /// [InternerBuilder::max_outstanding_refs] allows
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn try_enter(&self) -> Result<Reader, RefError> {
+    let refs = self.refs.fetch_add(1, AtomicOrdering::SeqCst);
+    // pairs with the fence in `Interner::without_readers`, like in
+    // `Interner::wait_to_read`
+    atomic::fence(AtomicOrdering::SeqCst);
+    let refused = if self.moving.load(AtomicOrdering::SeqCst) {
+      Some(RefError::Moving)
+    } else {
+      self
+        .max_refs
+        .filter(|&limit| refs >= limit)
+        .map(|limit| RefError::TooManyRefs { limit })
+    };
+    if let Some(e) = refused {
+      self.release();
+      return Err(e);
+    }
+
+    Ok(self.reader())
+  }
+
+  /// Counts a reader, without keeping track of it, once nothing is moving
+  fn wait_to_read(&self) {
+    loop {
+      self.refs.fetch_add(1, AtomicOrdering::SeqCst);
+      // pairs with the fence in `Interner::without_readers`, so that either
+      // the mover sees this reader, or this reader sees the mover
+      atomic::fence(AtomicOrdering::SeqCst);
+      if !self.moving.load(AtomicOrdering::SeqCst) {
+        break;
+      }
+
+      // back off so the strings can be moved, and wait until they have been
+      self.release();
+      drop(self.internal.read());
+    }
+  }
+
+  /// Runs `f` on `entry`'s string, counting as a reader only while it runs.
+  /// This is how [Intern]s are formatted: there's no [InternRef] to hold
+  /// onto, and unlike `Interner::enter`, nothing is recorded for
+  /// diagnostics, so it is safe to call from anywhere, even while panicking.
+  fn peek<R>(&self, entry: &Entry, f: impl FnOnce(&str) -> R) -> R {
+    /// Stops counting as a reader even if `f` panics
+    struct Peeking<'a>(&'a Interner);
+
+    impl Drop for Peeking<'_> {
+      fn drop(&mut self) {
+        self.0.release();
+      }
+    }
+
+    self.wait_to_read();
+    let _peeking = Peeking(self);
+    // SAFETY: the span was handed out by this interner's store, and it can't
+    //         move while we're counted as a reader
+    f(unsafe { self.store.get(entry.shown()) })
+  }
+
+  /// Counts another reader, when the caller is already counted as one, so
+  /// strings can't be moving
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn enter_again(&self) -> Reader {
+    self.refs.fetch_add(1, AtomicOrdering::SeqCst);
+    self.reader()
+  }
+
+  /// Takes a reader back out, once it's done reading
+  fn leave(&self, reader: &Reader) {
+    #[cfg(feature = "diagnostics")]
+    self.diagnostics.leave(reader.id);
+    if let (Some(leaks), Some(thread)) = (&self.leaks, reader.thread) {
+      leaks.leave(thread);
+    }
+    if self.release() && self.refs_held == RefsHeldPolicy::Queue {
+      self.apply_queued();
+    }
+  }
+
+  /// Uncounts a reader, waking whoever waits for the last one to be
+  /// dropped. Returns whether it was the last.
+  fn release(&self) -> bool {
+    let last = self.refs.fetch_sub(1, AtomicOrdering::SeqCst) == 1;
+    if last && self.refs_held != RefsHeldPolicy::Fail {
+      self.readers_gone.wake();
+    }
+    last
+  }
+
+  /// Locks the interner for writing
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn lock(&self) -> Locked<'_> {
+    self.held(self.wait())
+  }
+
+  /// Waits to lock the interner for writing. With diagnostics on, waiting
+  /// for longer than [InternerBuilder::diagnostics_threshold] logs who is
+  /// in the way. With an observer, it is told how long the wait took, if
+  /// the lock was held at all.
+  fn wait(&self) -> RwLockWriteGuard<'_, InternerInternal> {
+    let Some(observer) = &self.observer else {
+      return self.block();
+    };
+    if let Some(guard) = self.internal.try_write() {
+      return guard;
+    }
+
+    let started = Instant::now();
+    let guard = self.block();
+    observer.on_blocked(started.elapsed());
+    guard
+  }
+
+  /// Blocks until the interner is locked for writing
+  fn block(&self) -> RwLockWriteGuard<'_, InternerInternal> {
+    #[cfg(feature = "diagnostics")]
+    return self.diagnostics.write(&self.internal);
+    #[cfg(not(feature = "diagnostics"))]
+    self.internal.write()
+  }
+
+  /// Records that the current thread holds `guard`
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn held<'a>(
+    &'a self,
+    guard: RwLockWriteGuard<'a, InternerInternal>,
+  ) -> Locked<'a> {
+    #[cfg(feature = "diagnostics")]
+    return self.diagnostics.hold(guard);
+    #[cfg(not(feature = "diagnostics"))]
+    guard
+  }
+
+  /// Creates an interned path. Paths can be any [OsStr](std::ffi::OsStr),
+  /// not just valid UTF-8, so they are kept apart from the strings, and
+  /// split into components that are each interned once. Paths that share a
+  /// directory share the components that name it, so interning many paths
+  /// with long common prefixes, as a build system does, stores little more
+  /// than their file names.
+  ///
+  /// Paths are never collected, and aren't counted in [Interner::stats].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  /// use std::path::Path;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let lib = interner.intern_path("src/lib.rs");
+  ///
+  /// assert_eq!(lib, interner.intern_path(Path::new("src/./lib.rs")));
+  /// assert!(lib.starts_with(&interner.intern_path("src")));
+  /// assert_eq!(lib.to_path_buf(), Path::new("src/lib.rs"));
+  /// ```
+  pub fn intern_path<P: AsRef<Path>>(&self, path: P) -> InternPath {
+    self.paths.get_or_init(PathTable::new).intern(path.as_ref())
+  }
+
+  /// Interns the contents of a source file as one string, and records where
+  /// its lines start, so that tokens can be [Intern::slice]d out of
+  /// [Interner::source] without copying, and byte offsets into it can be
+  /// turned into lines and columns with [Interner::line_column]. Files are
+  /// kept until the interner is dropped.
+  ///
+  /// Offsets refer to the contents as stored, which differ from `contents`
+  /// if case folding or normalization change them.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern], or if more
+  /// than [u32::MAX] files are interned.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{Interner, LineColumn};
+  ///
+  /// let interner = Interner::new();
+  /// let main = interner.intern_source("main.rs", "fn main() {\n  exit()\n}");
+  ///
+  /// let exit = interner.source(main).unwrap().slice(14..18)?;
+  ///
+  /// assert_eq!(exit, interner.intern("exit"));
+  /// assert_eq!(
+  ///   interner.line_column(main, 14),
+  ///   Some(LineColumn { line: 2, column: 3 })
+  /// );
+  /// assert_eq!(&*interner.source_name(main).unwrap(), "main.rs");
+  /// # Ok::<(), str_interning::sync::SliceError>(())
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn intern_source(
+    self: &Arc<Self>,
+    name: &str,
+    contents: &str,
+  ) -> SourceId {
+    let contents = self.intern(contents);
+    let lines = contents.with_str(Lines::new);
+
+    let mut sources = self.sources.write();
+    let id = u32::try_from(sources.len())
+      .map(SourceId)
+      .expect("ran out of source ids");
+    sources.push(Source {
+      name: name.into(),
+      contents: contents.entry,
+      lines,
+    });
+    id
+  }
+
+  /// The contents of a file interned with [Interner::intern_source], or
+  /// [None] if `id` came from a different interner
+  pub fn source(self: &Arc<Self>, id: SourceId) -> Option<Intern> {
+    let sources = self.sources.read();
+    let source = sources.get(usize::try_from(id.0).ok()?)?;
+
+    Some(Intern {
+      entry: Arc::clone(&source.contents),
+      interner: Arc::clone(self),
+    })
+  }
+
+  /// The name a file was interned with by [Interner::intern_source]
+  pub fn source_name(&self, id: SourceId) -> Option<Arc<str>> {
+    let sources = self.sources.read();
+    let source = sources.get(usize::try_from(id.0).ok()?)?;
+    Some(Arc::clone(&source.name))
+  }
+
+  /// Finds the line and column of the byte at `offset` in a file interned
+  /// with [Interner::intern_source]. Returns [None] if the offset is past
+  /// the end of the file or inside a char, or if `id` came from a different
+  /// interner.
+  pub fn line_column(&self, id: SourceId, offset: usize) -> Option<LineColumn> {
+    let sources = self.sources.read();
+    let source = sources.get(usize::try_from(id.0).ok()?)?;
+    self.peek(&source.contents, |text| source.lines.locate(text, offset))
+  }
+
+  /// Produces an [Intern] for line number `line` of a file interned with
+  /// [Interner::intern_source], counting from 1 and leaving out the line
+  /// ending, such as to quote it in an error message
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let main = interner.intern_source("main.rs", "fn main() {\r\n}\r\n");
+  ///
+  /// let first = interner.source_line(main, 1).unwrap();
+  ///
+  /// assert_eq!(&*first.get_ref(), "fn main() {");
+  /// assert_eq!(interner.source_line(main, 3), Some(interner.intern("")));
+  /// assert_eq!(interner.source_line(main, 4), None);
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn source_line(
+    self: &Arc<Self>,
+    id: SourceId,
+    line: usize,
+  ) -> Option<Intern> {
+    let contents = self.source(id)?;
+    let range = {
+      let sources = self.sources.read();
+      let lines = &sources[usize::try_from(id.0).ok()?].lines;
+      contents.with_str(|text| lines.line(text, line))?
+    };
+    Some(contents.slice(range).unwrap())
+  }
+
+  /// Interns a string only if it can be served entirely from data that is
+  /// already in the store, such as a substring of a previously interned
+  /// string. No new bytes are ever stored by this method.
+  ///
+  /// [Interner::intern] reuses existing data in the same way, but falls back
+  /// to storing the string when it isn't found.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern("hello");
+  ///
+  /// let ell = interner.intern_substring("ell").unwrap();
+  ///
+  /// assert_eq!(&*ell.get_ref(), "ell");
+  /// assert_eq!(interner.intern_substring("yellow"), None);
+  /// assert_eq!(interner.stats().stored_bytes, 5);
+  /// ```
+  pub fn intern_substring<S: AsRef<str>>(
+    self: &Arc<Self>,
+    s: S,
+  ) -> Option<Intern> {
+    let spelling = s.as_ref();
+    let s = self.canonical(spelling);
+    let mut lock = self.lock();
+
+    let span = lock.intern_existing(&s)?;
+
+    Some(Intern {
+      entry: self.interned(&mut lock, span, spelling),
+      interner: Arc::clone(self),
+    })
+  }
+
+  /// Finds an existing interned string without interning anything new.
+  /// Strings that only appear inside other interned strings don't count.
+  /// Lookups only read the interner, so they run side by side, unless a
+  /// [new spelling](InternerBuilder::record_spellings) has to be recorded.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  ///
+  /// assert_eq!(interner.get("hello"), Some(hello));
+  /// assert_eq!(interner.get("hell"), None);
+  /// assert_eq!(interner.get("goodbye"), None);
+  /// ```
+  pub fn get<S: AsRef<str>>(self: &Arc<Self>, s: S) -> Option<Intern> {
+    let spelling = s.as_ref();
+    self.get_canonical(spelling, &self.canonical(spelling))
+  }
+
+  /// Finds the [Symbol] of a previously interned string. Like
+  /// [Interner::intern_symbol], this keeps the string from ever being
+  /// collected.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  ///
+  /// assert_eq!(interner.get_symbol("hello"), Some(hello.symbol()));
+  /// assert_eq!(interner.get_symbol("goodbye"), None);
+  /// ```
+  pub fn get_symbol<S: AsRef<str>>(&self, s: S) -> Option<Symbol> {
+    let spelling = s.as_ref();
+    let s = self.canonical(spelling);
+    let mut lock = self.lock();
+    let span = lock.index.get(s.as_bytes())?;
+
+    Some(lock.entry(&self.store, span, spelling).pin())
+  }
+
+  /// Finds `s`, which has already been put in canonical form, and was
+  /// written as `spelling` before that
+  pub(crate) fn get_canonical(
+    self: &Arc<Self>,
+    spelling: &str,
+    s: &str,
+  ) -> Option<Intern> {
+    let lock = self.internal.read();
+    let Some(span) = lock.index.get(s.as_bytes()) else {
+      drop(lock);
+      return self.parent.as_ref()?.get_canonical(spelling, s);
+    };
+    if let Some(entry) = lock.existing(&span, spelling) {
+      return Some(Intern {
+        entry,
+        interner: Arc::clone(self),
+      });
+    }
+    drop(lock);
+
+    // creating the entry takes the lock for writing, and the string may have
+    // been collected while it wasn't held
+    let mut lock = self.lock();
+    let Some(span) = lock.index.get(s.as_bytes()) else {
+      drop(lock);
+      return self.parent.as_ref()?.get_canonical(spelling, s);
+    };
+
+    Some(Intern {
+      entry: lock.entry(&self.store, span, spelling),
+      interner: Arc::clone(self),
+    })
+  }
+
+  /// Finds the string with the given [SymbolId], if it is still interned.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{Interner, SymbolId};
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  ///
+  /// assert_eq!(interner.from_id(hello.id()), Some(hello));
+  /// assert_eq!(interner.from_id(SymbolId(1)), None);
+  /// ```
+  pub fn from_id(self: &Arc<Self>, id: SymbolId) -> Option<Intern> {
+    let lock = self.internal.read();
+    let entry = lock.symbols.get(usize::try_from(id.0).ok()?)?.as_ref()?;
+
+    Some(Intern {
+      entry: Arc::clone(entry),
+      interner: Arc::clone(self),
+    })
+  }
+
+  /// Iterates over every distinct string in the interner along with its
+  /// [SymbolId], in the order they were first interned. Strings interned
+  /// after this is called are left out.
+  ///
+  /// The strings are read through [InternRef]s. Like them, the iterator
+  /// counts as a reader until it is dropped, so strings can't be collected
+  /// in the meantime.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{Interner, SymbolId};
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern_all(["hello", "world", "hello"]);
+  ///
+  /// let strings = interner
+  ///   .iter()
+  ///   .map(|(id, s)| (id, s.to_owned()))
+  ///   .collect::<Vec<_>>();
+  ///
+  /// assert_eq!(strings[0], (SymbolId(0), "hello".to_owned()));
+  /// assert_eq!(strings[1], (SymbolId(1), "world".to_owned()));
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn iter(&self) -> InternerIter<'_> {
+    let lock = self.internal.read();
+    let entries = lock
+      .entries()
+      .map(|entry| (entry.symbol_id(), entry.shown()))
+      .collect::<Vec<_>>();
+
+    // nothing can start moving strings while we hold the lock
+    let reader = self.enter();
+    drop(lock);
+
+    InternerIter {
+      entries: entries.into_iter(),
+      interner: self,
+      reader,
+    }
+  }
+
+  /// Runs `f` with an [InternScope], which reads any number of [Intern]s as
+  /// plain `&str`s. The whole scope counts as one reader, so it costs less
+  /// than calling [Intern::get_ref] for each string, but like an [InternRef]
+  /// it keeps [Interner::collect] and [Interner::shrink_to_fit] from moving
+  /// strings until it ends.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let words = ["hello", "world"].map(|word| interner.intern(word));
+  ///
+  /// let joined = interner.scope(|scope| {
+  ///   words.iter().map(|word| scope.get(word)).collect::<Vec<_>>().join(" ")
+  /// });
+  ///
+  /// assert_eq!(joined, "hello world");
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn scope<R>(&self, f: impl FnOnce(&InternScope<'_>) -> R) -> R {
+    let scope = InternScope {
+      interner: self,
+      reader: self.enter(),
+    };
+    f(&scope)
+  }
+
+  /// Reads every one of `interns` at once, and runs `f` on their strings in
+  /// the same order, counting as a single reader like [Interner::scope].
+  ///
+  /// # Panics
+  ///
+  /// Panics if any of `interns` came from a different interner.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let path = ["std", "sync", "Arc"].map(|part| interner.intern(part));
+  ///
+  /// let joined = interner.resolve_many(&path, |parts| parts.join("::"));
+  ///
+  /// assert_eq!(joined, "std::sync::Arc");
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn resolve_many<R>(
+    &self,
+    interns: &[Intern],
+    f: impl FnOnce(&[&str]) -> R,
+  ) -> R {
+    self.scope(|scope| {
+      let strs = interns
+        .iter()
+        .map(|intern| scope.get(intern))
+        .collect::<Vec<_>>();
+      f(&strs)
+    })
+  }
+
+  /// Finds every interned string that starts with `prefix`, sorted by their
+  /// text, such as to offer completions for a partially typed name. With
+  /// [IndexBackend::Trie], only the strings that match are visited. With
+  /// [IndexBackend::Hash], every string is checked.
+  ///
+  /// The prefix is case folded and normalized like any other string first.
+  /// With substring sharing on, strings that were only looked up as part of
+  /// a longer one may turn up as well.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern_all(["print", "println", "parse", "printf"]);
+  ///
+  /// let found = interner.find_with_prefix("print");
+  ///
+  /// assert_eq!(found.len(), 3);
+  /// assert_eq!(found[0], interner.intern("print"));
+  /// assert_eq!(found[2], interner.intern("println"));
+  /// ```
+  pub fn find_with_prefix<S: AsRef<str>>(
+    self: &Arc<Self>,
+    prefix: S,
+  ) -> Vec<Intern> {
+    let prefix = self.canonical(prefix.as_ref());
+    let mut lock = self.lock();
+    let mut spans = lock.index.with_prefix(prefix.as_bytes());
+    // SAFETY: the spans were handed out by the store
+    spans.sort_unstable_by_key(|span| unsafe { self.store.get(span.clone()) });
+
+    spans
+      .into_iter()
+      .map(|span| {
+        // SAFETY: see above
+        let text = unsafe { self.store.get(span.clone()) };
+        Intern {
+          entry: lock.entry(&self.store, span, text),
+          interner: Arc::clone(self),
+        }
+      })
+      .collect()
+  }
+
+  /// Iterates over every interned string in sorted order, such as to dump
+  /// a symbol table. With [IndexBackend::Trie], this walks the trie, which
+  /// already keeps the strings in order. With [IndexBackend::Hash], they
+  /// have to be sorted first.
+  ///
+  /// Strings are compared after case folding and normalization, byte by
+  /// byte, which is also how [str]s compare. The interns are all gathered
+  /// up front, so strings interned in the meantime are left out. With
+  /// substring sharing on, strings that were only looked up as part of a
+  /// longer one may turn up as well.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern_all(["pear", "apple", "fig"]);
+  ///
+  /// let sorted = interner.iter_sorted().collect::<Vec<_>>();
+  ///
+  /// assert_eq!(sorted, interner.intern_all(["apple", "fig", "pear"]));
+  /// ```
+  pub fn iter_sorted(self: &Arc<Self>) -> vec::IntoIter<Intern> {
+    self.interns_in((Bound::Unbounded, Bound::Unbounded))
+  }
+
+  /// Iterates over every interned string within `range`, in sorted order,
+  /// like [Interner::iter_sorted]. With [IndexBackend::Trie], only the
+  /// parts of the trie that overlap the range are visited.
+  ///
+  /// The ends of the range are case folded and normalized like any other
+  /// string first.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern_all(["bat", "ant", "bee", "cat", "boar"]);
+  ///
+  /// let b = interner.range("b".."c").collect::<Vec<_>>();
+  ///
+  /// assert_eq!(b, interner.intern_all(["bat", "bee", "boar"]));
+  /// assert_eq!(interner.range("bee"..).count(), 3);
+  /// ```
+  pub fn range<S: AsRef<str>>(
+    self: &Arc<Self>,
+    range: impl RangeBounds<S>,
+  ) -> vec::IntoIter<Intern> {
+    let start = range.start_bound().map(|s| self.canonical(s.as_ref()));
+    let end = range.end_bound().map(|s| self.canonical(s.as_ref()));
+    self.interns_in((
+      start.as_ref().map(|s| s.as_bytes()),
+      end.as_ref().map(|s| s.as_bytes()),
+    ))
+  }
+
+  fn interns_in(
+    self: &Arc<Self>,
+    range: (Bound<&[u8]>, Bound<&[u8]>),
+  ) -> vec::IntoIter<Intern> {
+    let mut lock = self.lock();
+    let spans = lock.index.range(range);
+    spans
+      .into_iter()
+      .map(|span| {
+        // SAFETY: the spans were handed out by the store
+        let text = unsafe { self.store.get(span.clone()) };
+        Intern {
+          entry: lock.entry(&self.store, span, text),
+          interner: Arc::clone(self),
+        }
+      })
+      .collect::<Vec<_>>()
+      .into_iter()
+  }
+
+  /// Finds every interned string within `max_dist` edits of `query`, along
+  /// with how many edits away each one is, such as to suggest a name when
+  /// the one typed isn't known. An edit inserts, deletes or replaces one
+  /// char. Results are sorted by distance, then by their text.
+  ///
+  /// With [IndexBackend::Trie], strings that share a prefix are measured
+  /// together, and whole branches are skipped once they are too far off.
+  /// With [IndexBackend::Hash], every string is measured.
+  ///
+  /// The query is case folded and normalized like any other string first,
+  /// and distances are between the folded forms. With substring sharing on,
+  /// strings that were only looked up as part of a longer one may turn up
+  /// as well.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern_all(["length", "width", "depth"]);
+  ///
+  /// let found = interner.fuzzy_search("lenght", 2);
+  ///
+  /// assert_eq!(found, [(interner.intern("length"), 2)]);
+  /// assert!(interner.fuzzy_search("breadth", 1).is_empty());
+  /// ```
+  pub fn fuzzy_search<S: AsRef<str>>(
+    self: &Arc<Self>,
+    query: S,
+    max_dist: u32,
+  ) -> Vec<(Intern, u32)> {
+    let query = self.canonical(query.as_ref());
+    let mut lock = self.lock();
+    let mut found = lock.index.fuzzy(&query, max_dist as usize);
+    found.sort_unstable_by_key(|(span, dist)| {
+      // SAFETY: the spans were handed out by the store
+      (*dist, unsafe { self.store.get(span.clone()) })
+    });
+
+    found
+      .into_iter()
+      .map(|(span, dist)| {
+        // SAFETY: see above
+        let text = unsafe { self.store.get(span.clone()) };
+        let intern = Intern {
+          entry: lock.entry(&self.store, span, text),
+          interner: Arc::clone(self),
+        };
+        (intern, dist as u32)
+      })
+      .collect()
+  }
+
+  /// Finds every interned string that `re` matches somewhere in, in the
+  /// order they were first interned, such as to search a symbol table.
+  /// Strings are matched one at a time, straight out of the store, so
+  /// anchors like `^` and `$` match at the start and end of each string,
+  /// and matches never run from one string into the next.
+  ///
+  /// Strings are matched as readers see them, before case folding. Only
+  /// the lock for reading is held, so threads that resolve strings don't
+  /// wait on the search.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use regex::Regex;
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern_all(["get_name", "set_name", "name", "get_id"]);
+  ///
+  /// let getters = interner.grep(&Regex::new("^get_")?);
+  ///
+  /// assert_eq!(getters, interner.intern_all(["get_name", "get_id"]));
+  /// # Ok::<(), regex::Error>(())
+  /// ```
+  #[cfg(feature = "regex")]
+  pub fn grep(self: &Arc<Self>, re: &Regex) -> Vec<Intern> {
+    let lock = self.internal.read();
+    lock
+      .entries()
+      // SAFETY: the span was handed out by the store, and nothing can move
+      //         strings while we hold the lock
+      .filter(|entry| re.is_match(unsafe { self.store.get(entry.shown()) }))
+      .map(|entry| Intern {
+        entry: Arc::clone(entry),
+        interner: Arc::clone(self),
+      })
+      .collect()
+  }
+
+  /// Builds an [InternMatcher], which finds every string interned so far
+  /// wherever it occurs in other text, all in one pass over the text.
+  ///
+  /// Strings are matched in the form they are indexed by. With case folding
+  /// on, ASCII letters match regardless of case, but nothing else is folded
+  /// or normalized in the text that is searched.
+  ///
+  /// # Panics
+  ///
+  /// Panics if there are so many strings that the matcher would be too
+  /// large to build.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{CaseFolding, InternerBuilder};
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .case_folding(CaseFolding::Ascii)
+  ///   .build();
+  /// let select = interner.intern("select");
+  ///
+  /// let matcher = interner.build_matcher();
+  /// let (range, hit) = matcher.find_iter("SELECT 1").next().unwrap();
+  ///
+  /// assert_eq!((range, hit), (0..6, select));
+  /// ```
+  #[cfg(feature = "aho-corasick")]
+  pub fn build_matcher(self: &Arc<Self>) -> InternMatcher {
+    let lock = self.internal.read();
+    let patterns = lock.entries().map(|entry| {
+      // SAFETY: the span was handed out by the store, and nothing can move
+      //         strings while we hold the lock
+      let text = unsafe { self.store.get(entry.span()) };
+      let intern = Intern {
+        entry: Arc::clone(entry),
+        interner: Arc::clone(self),
+      };
+      (text, intern)
+    });
+
+    InternMatcher::new(patterns, self.case_folding != CaseFolding::Off)
+  }
+
+  /// Finds the longest interned string that `input` starts with, along with
+  /// how many bytes of `input` it covers. Repeatedly matching and then
+  /// skipping past the match splits text into known tokens, such as
+  /// keywords and operators. With [IndexBackend::Trie], this only walks as
+  /// far into `input` as the longest match could go.
+  ///
+  /// Matching happens after case folding and normalization, so the length
+  /// covered may differ from the length of the interned string.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern_all(["=", "==", "=>"]);
+  ///
+  /// let (op, len) = interner.longest_prefix("== b").unwrap();
+  ///
+  /// assert_eq!(op, interner.intern("=="));
+  /// assert_eq!(len, 2);
+  /// assert_eq!(interner.longest_prefix("b"), None);
+  /// ```
+  pub fn longest_prefix<S: AsRef<str>>(
+    self: &Arc<Self>,
+    input: S,
+  ) -> Option<(Intern, usize)> {
+    let input = input.as_ref();
+    let canonical = self.canonical(input);
+    let mut lock = self.lock();
+    let span = lock.index.longest_prefix(canonical.as_bytes())?;
+    let len = match canonical {
+      Cow::Borrowed(_) => span.len(),
+      Cow::Owned(_) => {
+        // SAFETY: the span was handed out by the store
+        let text = unsafe { self.store.get(span.clone()) };
+        self.canonical_prefix_len(input, text)?
+      },
+    };
+
+    let intern = Intern {
+      entry: lock.entry(&self.store, span, &input[..len]),
+      interner: Arc::clone(self),
+    };
+    Some((intern, len))
+  }
+
+  /// Creates interned strings for a whole batch at once. The interner is only
+  /// locked once for the entire batch, which is much cheaper than calling
+  /// [Interner::intern] in a loop when interning many strings.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let words = interner.intern_all(["a", "b", "a"]);
+  ///
+  /// assert_eq!(words[0], words[2]);
+  /// assert_ne!(words[0], words[1]);
+  /// ```
+  pub fn intern_all<I>(self: &Arc<Self>, iter: I) -> Vec<Intern>
+  where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+  {
+    let mut lock = self.lock();
+
+    iter
+      .into_iter()
+      .map(|s| {
+        let spelling = s.as_ref();
+        let s = self.canonical(spelling);
+        if let Some(intern) = self.in_parent(&lock, spelling, &s) {
+          return intern;
+        }
+
+        match self.intern_making_room(&mut lock, &s) {
+          Ok(span) => Intern {
+            entry: self.interned(&mut lock, span, spelling),
+            interner: Arc::clone(self),
+          },
+          Err(e) => panic!("{e}"),
+        }
+      })
+      .collect()
+  }
+
+  /// Creates interned strings for a whole batch at once, like
+  /// [Interner::intern_all], but puts the strings in canonical form on
+  /// every thread of rayon's thread pool first. Case folding and
+  /// normalization are usually the slow part of interning a large corpus,
+  /// while the interning itself still takes the lock only once. Use
+  /// [ShardedInterner::par_intern_all](crate::sync::ShardedInterner::par_intern_all)
+  /// to spread that out as well.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{CaseFolding, InternerBuilder};
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .case_folding(CaseFolding::Ascii)
+  ///   .build();
+  ///
+  /// let words = interner.par_intern_all(&["A", "b", "a"]);
+  ///
+  /// assert_eq!(words[0], words[2]);
+  /// assert_eq!(&*words[0].get_ref(), "a");
+  /// ```
+  #[cfg(feature = "rayon")]
+  pub fn par_intern_all<S: AsRef<str> + Sync>(
+    self: &Arc<Self>,
+    strings: &[S],
+  ) -> Vec<Intern> {
+    use rayon::prelude::*;
+
+    let canonical = strings
+      .par_iter()
+      .map(|s| self.canonical(s.as_ref()))
+      .collect::<Vec<_>>();
+    self.intern_all_canonical(
+      strings
+        .iter()
+        .map(AsRef::as_ref)
+        .zip(canonical.iter().cloned()),
+    )
+  }
+
+  /// Interns a batch of strings that have already been put in canonical
+  /// form, each paired with how it was spelled before that, taking the lock
+  /// once
+  #[cfg(feature = "rayon")]
+  pub(crate) fn intern_all_canonical<'a>(
+    self: &Arc<Self>,
+    batch: impl IntoIterator<Item = (&'a str, Cow<'a, str>)>,
+  ) -> Vec<Intern> {
+    let mut lock = self.lock();
+
+    batch
+      .into_iter()
+      .map(|(spelling, s)| {
+        if let Some(intern) = self.in_parent(&lock, spelling, &s) {
+          return intern;
+        }
+
+        match self.intern_making_room(&mut lock, &s) {
+          Ok(span) => Intern {
+            entry: self.interned(&mut lock, span, spelling),
+            interner: Arc::clone(self),
+          },
+          Err(e) => panic!("{e}"),
+        }
+      })
+      .collect()
+  }
+
+  /// Interns each line of `reader` as it is read, without the line ending,
+  /// like [BufRead::lines]. Lines are read into one buffer that is reused
+  /// for the whole stream, so nothing is allocated per line other than new
+  /// strings in the store. Lines that aren't UTF-8 come out as
+  /// [io::ErrorKind::InvalidData] errors, and reading carries on after
+  /// errors.
+  ///
+  /// # Panics
+  ///
+  /// The iterator panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let log = b"GET /\r\nGET /about\r\nGET /\r\n";
+  ///
+  /// let lines = interner
+  ///   .intern_lines(&log[..])
+  ///   .collect::<Result<Vec<_>, _>>()?;
+  ///
+  /// assert_eq!(lines[0], lines[2]);
+  /// assert_eq!(lines[1], interner.intern("GET /about"));
+  /// assert_eq!(interner.stats().entries, 2);
+  /// # Ok::<(), std::io::Error>(())
+  /// ```
+  pub fn intern_lines<R: BufRead>(
+    self: &Arc<Self>,
+    reader: R,
+  ) -> impl Iterator<Item = io::Result<Intern>> {
+    self.intern_records(reader, b'\n', true)
+  }
+
+  /// Interns each piece of `reader` between occurrences of `delimiter`, as
+  /// it is read, like [BufRead::split]. Works like [Interner::intern_lines]
+  /// otherwise.
+  ///
+  /// # Panics
+  ///
+  /// The iterator panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  ///
+  /// let fields = interner
+  ///   .intern_split(&b"a,b,,a"[..], b',')
+  ///   .collect::<Result<Vec<_>, _>>()?;
+  ///
+  /// assert_eq!(fields, interner.intern_all(["a", "b", "", "a"]));
+  /// # Ok::<(), std::io::Error>(())
+  /// ```
+  pub fn intern_split<R: BufRead>(
+    self: &Arc<Self>,
+    reader: R,
+    delimiter: u8,
+  ) -> impl Iterator<Item = io::Result<Intern>> {
+    self.intern_records(reader, delimiter, false)
+  }
+
+  /// Interns the records of `reader` ending in `delimiter`, and drops a
+  /// carriage return before the delimiter as well for `lines`
+  fn intern_records<R: BufRead>(
+    self: &Arc<Self>,
+    mut reader: R,
+    delimiter: u8,
+    lines: bool,
+  ) -> impl Iterator<Item = io::Result<Intern>> {
+    let interner = Arc::clone(self);
+    let mut buffer = Vec::new();
+
+    iter::from_fn(move || {
+      buffer.clear();
+      match reader.read_until(delimiter, &mut buffer) {
+        Ok(0) => return None,
+        Ok(_) => {},
+        Err(e) => return Some(Err(e)),
+      }
+
+      let mut record = &buffer[..];
+      if let Some(rest) = record.strip_suffix(&[delimiter]) {
+        record = rest;
+        if lines {
+          record = record.strip_suffix(b"\r").unwrap_or(record);
+        }
+      }
+      Some(match std::str::from_utf8(record) {
+        Ok(s) => Ok(interner.intern(s)),
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+      })
+    })
+  }
+
+  /// Interns column `column` of every row of delimited input, such as CSV
+  /// with a `delimiter` of `b','` or TSV with `b'\t'`, counting columns from
+  /// 0. Quoted fields are unquoted first. Every row is interned, so skip the
+  /// first one if `reader` has a header. Rows may have any number of
+  /// columns, but one that doesn't reach `column` is an
+  /// [io::ErrorKind::InvalidData] error, as is text that isn't UTF-8.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let csv = "1,GET,/\n2,POST,\"/login, then /\"\n3,GET,/about\n";
+  ///
+  /// let methods = interner.intern_column(csv.as_bytes(), 1, b',')?;
+  /// let paths = interner.intern_column(csv.as_bytes(), 2, b',')?;
+  ///
+  /// assert_eq!(methods, interner.intern_all(["GET", "POST", "GET"]));
+  /// assert_eq!(paths[1], "/login, then /");
+  /// assert_eq!(interner.stats().entries, 5);
+  /// # Ok::<(), std::io::Error>(())
+  /// ```
+  #[cfg(feature = "csv")]
+  pub fn intern_column<R: Read>(
+    self: &Arc<Self>,
+    reader: R,
+    column: usize,
+    delimiter: u8,
+  ) -> io::Result<Vec<Intern>> {
+    let mut reader = csv::ReaderBuilder::new()
+      .delimiter(delimiter)
+      .has_headers(false)
+      .flexible(true)
+      .from_reader(reader);
+    let mut record = csv::StringRecord::new();
+    let mut interns = Vec::new();
+
+    let read = |e: csv::Error| {
+      if e.is_io_error() {
+        io::Error::from(e)
+      } else {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+      }
+    };
+
+    while reader.read_record(&mut record).map_err(read)? {
+      let Some(field) = record.get(column) else {
+        return Err(io::Error::new(
+          io::ErrorKind::InvalidData,
+          format!("row {} has no column {column}", interns.len()),
+        ));
+      };
+      interns.push(self.intern(field));
+    }
+
+    Ok(interns)
+  }
+
+  /// Frees every string that no [Intern] refers to any more, then compacts
+  /// the store so the survivors sit next to each other again. Returns how
+  /// many bytes of string data were freed. [Intern]s stay valid across a
+  /// collection, and compare, hash and order exactly as they did before it.
+  ///
+  /// Compacting moves strings around, so nothing is collected while any
+  /// [InternRef] is alive on any thread; in that case this returns 0.
+  /// Threads that call [Intern::get_ref] while a collection is running wait
+  /// for it to finish.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  /// drop(interner.intern("goodbye"));
+  ///
+  /// assert_eq!(interner.collect(), 7);
+  /// assert_eq!(&*hello.get_ref(), "hello");
+  /// assert_eq!(interner.stats().stored_bytes, 5);
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn collect(&self) -> usize {
+    let mut lock = self.lock();
+
+    // SAFETY: there are no readers, and we hold the lock, so no pushers
+    let freed = self.without_readers(|| unsafe { lock.compact(&self.store) });
+    self.forget_freed(freed)
+  }
+
+  /// Rebuilds the store out of the strings already in it, longest first,
+  /// so that shorter strings share the data of longer ones wherever they
+  /// can. Returns how many bytes of string data were saved. This catches
+  /// strings interned before a longer string that contains them, which
+  /// substring sharing can't reuse at the time.
+  ///
+  /// Unlike [Interner::collect], no strings are freed. Every [Intern] points
+  /// at its string through an entry that is updated as the string moves, so
+  /// [Intern]s stay valid, and [Symbol]s and [SymbolId]s are unchanged.
+  /// Nothing is moved while any [InternRef] is alive on any thread; in that
+  /// case this returns 0.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let cat = interner.intern("cat");
+  /// let _word = interner.intern("concatenate");
+  /// assert_eq!(interner.stats().stored_bytes, 14);
+  ///
+  /// assert_eq!(interner.defragment(), 3);
+  /// assert_eq!(interner.stats().stored_bytes, 11);
+  /// assert_eq!(&*cat.get_ref(), "cat");
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn defragment(&self) -> usize {
+    let mut lock = self.lock();
+
+    // SAFETY: there are no readers, and we hold the lock, so no pushers
+    let saved = self.without_readers(|| unsafe { lock.repack(&self.store) });
+    saved.unwrap_or(0)
+  }
+
+  /// Number of readers, such as [InternRef]s, that were started by threads
+  /// which have since exited, and were never dropped. They keep
+  /// [Interner::collect] and [Interner::shrink_to_fit] from ever moving
+  /// strings again. A thread only counts as exited once it has been joined.
+  /// Always 0 unless the interner was built with
+  /// [InternerBuilder::detect_leaked_refs], or with a [RefsHeldPolicy]
+  /// other than [RefsHeldPolicy::Fail].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::InternerBuilder;
+  /// use std::{mem, thread};
+  ///
+  /// let interner = InternerBuilder::new().detect_leaked_refs(true).build();
+  /// let hello = interner.intern("hello");
+  ///
+  /// thread::scope(|scope| {
+  ///   scope.spawn(|| mem::forget(hello.get_ref())).join().unwrap();
+  /// });
+  ///
+  /// assert_eq!(interner.leaked_refs(), 1);
+  /// ```
+  pub fn leaked_refs(&self) -> usize {
+    self.leaks.as_ref().map_or(0, LeakDetector::leaked)
+  }
+
+  /// Takes what [InternerInternal::compact] or [InternerInternal::evict]
+  /// freed, if it ran, and forgets the freed strings in the hit cache
+  fn forget_freed(&self, freed: Option<usize>) -> usize {
+    #[cfg(feature = "dashmap")]
+    if let (Some(cache), Some(_)) = (&self.hit_cache, freed) {
+      cache.retain(|_, entry| entry.strong_count() > 0);
+    }
+
+    freed.unwrap_or(0)
+  }
+
+  /// Interns `s` like [InternerInternal::intern_uncontested], but if the
+  /// store is full and the interner was built with [StoreFullPolicy::Evict],
+  /// evicts unused strings to make room for it first
+  fn intern_making_room(
+    &self,
+    lock: &mut InternerInternal,
+    s: &str,
+  ) -> Result<Span, InternError> {
+    match self.intern_evicting(lock, s) {
+      Evicted::Done(result) => result,
+      Evicted::Blocked { limit } => Err(InternError::StoreFull { limit }),
+    }
+  }
+
+  /// Interns `s` like [Interner::intern_making_room], but tells apart
+  /// failing because readers kept anything from being evicted, so that the
+  /// caller can wait for them under [RefsHeldPolicy::Block] or
+  /// [RefsHeldPolicy::Queue]
+  fn intern_evicting(&self, lock: &mut InternerInternal, s: &str) -> Evicted {
+    match lock.intern_uncontested(&self.store, s) {
+      Err(InternError::StoreFull { limit })
+        if lock.store_full == StoreFullPolicy::Evict =>
+      {
+        let room = limit.saturating_sub(s.len());
+        // SAFETY: there are no readers, and we hold the lock, so no pushers
+        let freed =
+          self.without_readers(|| unsafe { lock.evict(&self.store, room) });
+        self.forget_freed(freed);
+        let result = lock.intern_uncontested(&self.store, s);
+        match (freed, self.leaked_refs(), result) {
+          // nothing can ever be evicted again
+          (None, leaked, Err(_)) if leaked > 0 => {
+            Evicted::Done(Err(InternError::Poisoned { leaked }))
+          },
+          (None, _, Err(InternError::StoreFull { limit })) => {
+            Evicted::Blocked { limit }
+          },
+          (_, _, result) => Evicted::Done(result),
+        }
+      },
+      result => Evicted::Done(result),
+    }
+  }
+
+  /// Gets the entry for the string at `span`, like
+  /// [InternerInternal::entry], and counts it as interned once more
+  fn interned(
+    &self,
+    lock: &mut InternerInternal,
+    span: Span,
+    spelling: &str,
+  ) -> Arc<Entry> {
+    let entry = lock.entry(&self.store, span, spelling);
+    self.count(&entry);
+    entry
+  }
+
+  /// Counts `entry` as interned once more, if the interner counts
+  fn count(&self, entry: &Entry) {
+    if self.counting {
+      entry.interned.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+  }
+
+  /// Releases memory that the interner has allocated but isn't using, such
+  /// as the unused end of the store after a large batch, or capacity set
+  /// aside with [Interner::reserve]. Returns how many bytes of store
+  /// capacity were released. Interning afterwards allocates again as usual.
+  ///
+  /// Shrinking the store may move strings around, so the store is left
+  /// alone while any [InternRef] is alive on any thread; in that case only
+  /// the index is shrunk and this returns 0.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::with_capacity(1 << 20, 1000);
+  /// let hello = interner.intern("hello");
+  ///
+  /// assert!(interner.shrink_to_fit() > 0);
+  /// assert_eq!(interner.stats().allocated_bytes, 5);
+  /// assert_eq!(&*hello.get_ref(), "hello");
+  /// ```
+  pub fn shrink_to_fit(&self) -> usize {
+    let mut lock = self.lock();
+    lock.index.shrink_to_fit();
+    lock.ids.shrink_to_fit();
+    lock.symbols.shrink_to_fit();
+
+    // SAFETY: there are no readers, and we hold the lock, so no pushers
+    self
+      .without_readers(|| unsafe { self.store.shrink_to_fit() })
+      .unwrap_or(0)
+  }
+
+  /// Ends the loading phase by copying every interned string into a
+  /// [FrozenInterner], which can be read from any number of threads without
+  /// ever locking. Existing [Intern]s keep working, but they belong to this
+  /// interner rather than the snapshot.
+  pub fn freeze(self: Arc<Self>) -> FrozenInterner {
+    let lock = self.internal.read();
+
+    let strings = lock.entries().map(|entry| {
+      let id = u32::try_from(entry.id).expect("ran out of symbol ids");
+      // SAFETY: the spans came from our store, and we hold the lock, so
+      //         nothing can move them
+      (SymbolId(id), unsafe { self.store.get(entry.span()) })
+    });
+
+    FrozenInterner::new(strings, self.case_folding, self.normalization)
+  }
+
+  /// Writes every interned string to `writer` in a compact binary format, so
+  /// that the interner can be rebuilt with [Interner::load_from] on a later
+  /// run instead of interning everything again. Strings keep their position
+  /// in the order they were interned, so [Intern]s made after reloading
+  /// order the same way as before. The index backend, substring sharing and
+  /// case folding settings are saved along with them.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern("hello");
+  ///
+  /// let mut saved = Vec::new();
+  /// interner.save_to(&mut saved).unwrap();
+  /// let reloaded = Interner::load_from(&saved[..]).unwrap();
+  ///
+  /// assert_eq!(&*reloaded.get("hello").unwrap().get_ref(), "hello");
+  /// ```
+  pub fn save_to<W: Write>(&self, writer: W) -> io::Result<()> {
+    self.snapshot().write_to(writer)
+  }
+
+  /// Takes the interner apart into one [String] holding every interned
+  /// string, and the [SymbolId] of each string along with where it is in
+  /// that [String], sorted by id. Strings that lie inside another string
+  /// point into it rather than being copied again. The parts can be put
+  /// back together as a read-only [FrozenInterner] with
+  /// [FrozenInterner::from_parts].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{Interner, SymbolId};
+  ///
+  /// let interner = Interner::new();
+  /// interner.intern("hello");
+  /// interner.intern("ell");
+  ///
+  /// let (text, spans) = interner.into_parts();
+  ///
+  /// assert_eq!(text, "hello");
+  /// assert_eq!(spans, [(SymbolId(0), 0..5), (SymbolId(1), 1..4)]);
+  /// ```
+  pub fn into_parts(
+    self: Arc<Self>,
+  ) -> (String, Vec<(SymbolId, Range<usize>)>) {
+    let snapshot = self.snapshot();
+    let spans = snapshot
+      .entries
+      .into_iter()
+      .map(|(id, span)| {
+        (
+          SymbolId(u32::try_from(id).expect("ran out of symbol ids")),
+          span,
+        )
+      })
+      .collect();
+
+    (snapshot.text, spans)
+  }
+
+  /// Copies every interned string out, along with how to rebuild the
+  /// interner
+  fn snapshot(&self) -> Snapshot {
+    let lock = self.internal.read();
+    let entries = lock
+      .entries()
+      .map(|entry| (entry.id, entry.span()))
+      .collect();
+
+    // SAFETY: the spans came from our store, and we hold the lock, so
+    //         nothing can move them
+    Snapshot::new(
+      lock.index.backend(),
+      lock.index.shares_substrings(),
+      self.case_folding,
+      self.normalization,
+      entries,
+      |span| unsafe { self.store.get(span) },
+    )
+  }
+
+  /// Rebuilds an interner that was written with [Interner::save_to]. Fails
+  /// with [io::ErrorKind::InvalidData] if `reader` doesn't hold a saved
+  /// interner.
+  pub fn load_from<R: Read>(reader: R) -> io::Result<Arc<Self>> {
+    let snapshot = Snapshot::read_from(reader)?;
+    let interner = InternerBuilder::new()
+      .index(snapshot.backend)
+      .substring_sharing(snapshot.substring_sharing)
+      .case_folding(snapshot.case_folding)
+      .normalization(snapshot.normalization)
+      .capacity(snapshot.text.len(), snapshot.entries.len())
+      .build();
+
+    let entries = snapshot
+      .entries
+      .iter()
+      .map(|(id, span)| {
+        let text = snapshot.text[span.clone()].to_owned();
+        (Arc::new(Entry::new(*id, 0..0, None, &text)), text, None)
+      })
+      .collect();
+
+    let mut lock = interner.lock();
+    lock.symbols.resize(snapshot.next_id, None);
+    // SAFETY: we hold the lock, so no other thread is pushing
+    unsafe { lock.rebuild(&interner.store, entries) };
+    if lock.ids.len() != snapshot.entries.len() {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "saved interner has duplicate strings",
+      ));
+    }
+    drop(lock);
+
+    Ok(interner)
+  }
+
+  /// Runs `f` while no thread can read from the store, or returns [None]
+  /// without running it if any [InternRef]s are alive. The caller must hold
+  /// the lock for writing, which is what readers wait on until `f` is done.
+  fn without_readers<R>(&self, f: impl FnOnce() -> R) -> Option<R> {
+    // readers announce themselves before checking this flag, so if none has
+    // been seen once it is set, none can start until it is cleared
+    self.moving.store(true, AtomicOrdering::SeqCst);
+    atomic::fence(AtomicOrdering::SeqCst);
+    if self.refs.load(AtomicOrdering::SeqCst) != 0 {
+      self.moving.store(false, AtomicOrdering::SeqCst);
+      return None;
+    }
+
+    let result = f();
+    self.moving.store(false, AtomicOrdering::SeqCst);
+    Some(result)
+  }
+}
+
+/// Represents a single interned string. This struct may be passed around
+/// and cloned cheaply, and without regard for lifetimes. Created using
+/// [Interner::intern] or [Interner::try_intern].
+///
+/// The string data that this [Intern] represents is only accessible through
+/// the short-lived [InternRef] type, which can be produced by
+/// [Intern::get_ref].
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::Interner;
+///
+/// let interner = Interner::new();
+///
+/// let hello = interner.intern("hello");
+/// let hello_ref = hello.get_ref();
+///
+/// assert_eq!(&*hello_ref, "hello");
+/// ```
+///
+/// [InternRef]s may be held while interning, even on the same thread.
+///
+/// ```
+/// use str_interning::sync::Interner;
+///
+/// let interner = Interner::new();
+/// let hello = interner.intern("hello");
+/// let hello_ref = hello.get_ref();
+///
+/// let goodbye = interner.intern("goodbye");
+///
+/// assert_eq!(&*hello_ref, "hello");
+/// assert_eq!(&*goodbye.get_ref(), "goodbye");
+/// ```
+///
+/// [Intern] is [Send] and [Sync], so it can be moved or shared between
+/// threads freely. It holds no pointer into the store, only the entry for
+/// its string, and the text is looked up through the interner each time it
+/// is read, which keeps it from moving until the read is over.
+#[derive(Clone)]
+pub struct Intern {
+  entry: Arc<Entry>,
+  interner: Arc<Interner>,
+}
+
+impl Intern {
+  /// Whether this came from `interner`
+  pub(crate) fn is_from(&self, interner: &Arc<Interner>) -> bool {
+    Arc::ptr_eq(&self.interner, interner)
+  }
+
+  pub(crate) fn interner(&self) -> &Arc<Interner> {
+    &self.interner
+  }
+
+  /// Produces an [InternRef], which borrows the underlying string data that
+  /// this [Intern] represents.
+  ///
+  /// This never takes a lock, so it is safe to call from any thread at any
+  /// time, including while another thread is interning. The only time it
+  /// waits is while [Interner::collect] or [Interner::shrink_to_fit] is
+  /// moving strings around.
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn get_ref(&self) -> InternRef<'_> {
+    self.interner.read(&self.entry)
+  }
+
+  /// Produces an [InternRef] like [Intern::get_ref], but fails instead of
+  /// waiting while [Interner::collect] or [Interner::shrink_to_fit] is
+  /// moving strings, or if there are already as many readers as
+  /// [InternerBuilder::max_outstanding_refs] allows.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{InternerBuilder, RefError};
+  ///
+  /// let interner = InternerBuilder::new().max_outstanding_refs(1).build();
+  /// let hello = interner.intern("hello");
+  ///
+  /// let hello_ref = hello.try_get_ref()?;
+  ///
+  /// assert_eq!(&*hello_ref, "hello");
+  /// assert_eq!(
+  ///   hello.try_get_ref().unwrap_err(),
+  ///   RefError::TooManyRefs { limit: 1 }
+  /// );
+  ///
+  /// drop(hello_ref);
+  /// assert!(hello.try_get_ref().is_ok());
+  /// # Ok::<(), RefError>(())
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn try_get_ref(&self) -> Result<InternRef<'_>, RefError> {
+    let reader = self.interner.try_enter()?;
+
+    // SAFETY: the span was handed out by this interner's store, and it can't
+    //         move while we're counted as a reader
+    let text = unsafe { self.interner.store.get(self.entry.shown()) };
+    Ok(InternRef {
+      text,
+      interner: &self.interner,
+      reader,
+    })
+  }
+
+  /// Reads the string in the form it is indexed by, after case folding and
+  /// normalization, even if [InternerBuilder::preserve_case] has
+  /// [Intern::get_ref] read it as first spelled.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{CaseFolding, InternerBuilder};
+  ///
+  /// let interner = InternerBuilder::new()
+  ///   .case_folding(CaseFolding::Ascii)
+  ///   .preserve_case(true)
+  ///   .build();
+  /// let header = interner.intern("Content-Type");
+  ///
+  /// assert_eq!(&*header.get_ref(), "Content-Type");
+  /// assert_eq!(&*header.folded(), "content-type");
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn folded(&self) -> InternRef<'_> {
+    self.interner.read_span(|| self.entry.span())
+  }
+
+  /// The spelling the string was first interned as. [Intern::get_ref] reads
+  /// that too with [InternerBuilder::preserve_case], and it's remembered
+  /// either way with [InternerBuilder::record_spellings]. Otherwise, the
+  /// first spelling isn't kept, and this gives the string as it reads.
+  pub fn first_spelling(&self) -> String {
+    let lock = self.interner.internal.read();
+    let first = lock
+      .spellings
+      .as_ref()
+      .and_then(|spellings| spellings.get(&self.entry.id)?.first().cloned());
+    drop(lock);
+    first.map_or_else(|| self.resolve(), String::from)
+  }
+
+  /// Runs `f` on the underlying string data, and stops reading as soon as it
+  /// returns. Unlike holding onto an [InternRef], this can't accidentally
+  /// keep [Interner::collect] or [Interner::shrink_to_fit] from running.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  ///
+  /// assert_eq!(hello.with_str(|s| s.to_uppercase()), "HELLO");
+  /// ```
+  pub fn with_str<R>(&self, f: impl FnOnce(&str) -> R) -> R {
+    f(&self.get_ref())
+  }
+
+  /// Whether the underlying string starts with `prefix`. Like the other
+  /// string queries here, this only reads the string for as long as it
+  /// takes to check, so there's no [InternRef] to hold onto.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let path = interner.intern("src/main.rs");
+  ///
+  /// assert!(path.starts_with("src/"));
+  /// assert!(path.ends_with(".rs"));
+  /// assert!(path.contains("main"));
+  /// ```
+  pub fn starts_with(&self, prefix: &str) -> bool {
+    self.with_str(|s| s.starts_with(prefix))
+  }
+
+  /// Whether the underlying string ends with `suffix`
+  pub fn ends_with(&self, suffix: &str) -> bool {
+    self.with_str(|s| s.ends_with(suffix))
+  }
+
+  /// Whether `needle` appears anywhere in the underlying string
+  pub fn contains(&self, needle: &str) -> bool {
+    self.with_str(|s| s.contains(needle))
+  }
+
+  /// Finds the byte offset of the first place `needle` appears in the
+  /// underlying string, which can be passed on to [Intern::slice].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let pair = interner.intern("key=value");
+  ///
+  /// let equals = pair.find("=").unwrap();
+  ///
+  /// assert_eq!(equals, 3);
+  /// assert_eq!(pair.slice(0..equals)?, interner.intern("key"));
+  /// assert_eq!(pair.find("?"), None);
+  /// # Ok::<(), str_interning::sync::SliceError>(())
+  /// ```
+  pub fn find(&self, needle: &str) -> Option<usize> {
+    self.with_str(|s| s.find(needle))
+  }
+
+  /// Copies the underlying string data out into an owned [String].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  ///
+  /// assert_eq!(hello.resolve(), "hello");
+  /// ```
+  pub fn resolve(&self) -> String {
+    self.get_ref().to_owned()
+  }
+
+  /// Copies the underlying string data out into a shared [`Arc<str>`].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  ///
+  /// assert_eq!(&*hello.resolve_arc(), "hello");
+  /// ```
+  pub fn resolve_arc(&self) -> Arc<str> {
+    Arc::from(&*self.get_ref())
+  }
+
+  /// Length of the string in bytes. This is read from the [Intern] itself,
+  /// so it never waits, even while strings are being collected.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let crab = interner.intern("🦀");
+  ///
+  /// assert_eq!(crab.len(), 4);
+  /// assert_eq!(crab.char_len(), 1);
+  /// assert!(!crab.is_empty());
+  /// assert!(interner.intern("").is_empty());
+  /// ```
+  pub fn len(&self) -> usize {
+    self.entry.len
+  }
+
+  /// Whether the string is empty. Never waits, like [Intern::len].
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Number of [char]s in the string. This is counted once when the string
+  /// is first interned, so it never waits, like [Intern::len].
+  pub fn char_len(&self) -> usize {
+    self.entry.chars
+  }
+
+  /// Checks whether two [Intern]s, which may come from different interners,
+  /// have the same text. Interns from the same interner are compared by
+  /// pointer just like [PartialEq], since an interner never stores the same
+  /// string twice; otherwise their lengths are compared before their text.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let hello = Interner::new().intern("hello");
+  /// let other_hello = Interner::new().intern("hello");
+  ///
+  /// assert_ne!(hello, other_hello);
+  /// assert!(hello.text_eq(&other_hello));
+  /// ```
+  pub fn text_eq(&self, other: &Self) -> bool {
+    if Arc::ptr_eq(&self.interner, &other.interner) {
+      return self == other;
+    }
+
+    self.len() == other.len() && *self == *other.get_ref()
+  }
+
+  /// Compares the underlying text of two [Intern]s, which may come from
+  /// different interners. Use this for user-facing sorting; the [Ord] impl is
+  /// cheaper but only orders by position in the store.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let mut words = vec![interner.intern("b"), interner.intern("a")];
+  ///
+  /// words.sort_by(|a, b| a.cmp_text(b));
+  ///
+  /// assert_eq!(&*words[0].get_ref(), "a");
+  /// ```
+  pub fn cmp_text(&self, other: &Self) -> Ordering {
+    if self == other {
+      return Ordering::Equal;
+    }
+
+    str::cmp(&self.get_ref(), &other.get_ref())
+  }
+
+  /// Length in bytes of the longest prefix that the text of two [Intern]s
+  /// share, which may come from different interners. It always ends on a
+  /// char boundary, so it can be used to [Intern::slice] either one. Handy
+  /// as a cheap similarity hint for lines being diffed.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let old = interner.intern("let x = 1;");
+  /// let new = interner.intern("let x = 2;");
+  ///
+  /// assert_eq!(old.common_prefix_len(&new), 8);
+  /// assert_eq!(old.common_suffix_len(&new), 1);
+  /// ```
+  pub fn common_prefix_len(&self, other: &Self) -> usize {
+    if self == other {
+      return self.len();
+    }
+
+    let (a, b) = (self.get_ref(), other.get_ref());
+    a.chars()
+      .zip(b.chars())
+      .take_while(|(a, b)| a == b)
+      .map(|(c, _)| c.len_utf8())
+      .sum()
+  }
+
+  /// Length in bytes of the longest suffix that the text of two [Intern]s
+  /// share, like [Intern::common_prefix_len]
+  pub fn common_suffix_len(&self, other: &Self) -> usize {
+    if self == other {
+      return self.len();
+    }
+
+    let (a, b) = (self.get_ref(), other.get_ref());
+    a.chars()
+      .rev()
+      .zip(b.chars().rev())
+      .take_while(|(a, b)| a == b)
+      .map(|(c, _)| c.len_utf8())
+      .sum()
+  }
+
+  /// Produces an [Intern] for part of this string, picked out by a range of
+  /// byte offsets like when indexing a [str]. The part already lies in the
+  /// store, so this doesn't copy anything, unless case folding or
+  /// normalization change it. Handy for interning a whole line and then
+  /// picking tokens out of it.
+  ///
+  /// Fails if the range is out of bounds, or doesn't lie on char
+  /// boundaries.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern], if the part
+  /// has to be stored because case folding or normalization change it.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let line = interner.intern("let x = 1;");
+  ///
+  /// let x = line.slice(4..5)?;
+  ///
+  /// assert_eq!(x, interner.intern("x"));
+  /// assert!(line.slice(4..20).is_err());
+  /// # Ok::<(), str_interning::sync::SliceError>(())
+  /// ```
+  pub fn slice(&self, range: Range<usize>) -> Result<Intern, SliceError> {
+    let mut lock = self.interner.lock();
+    // SAFETY: the span was handed out by the store, and holding the lock
+    //         keeps the string from moving
+    let text = unsafe { self.interner.store.get(self.entry.shown()) };
+    SliceError::check(text, &range)?;
+
+    Ok(self.slice_locked(&mut lock, range))
+  }
+
+  /// Interns the part of this string in `range` like [Intern::slice], once
+  /// the range has been checked, while the caller holds the lock
+  fn slice_locked(
+    &self,
+    lock: &mut InternerInternal,
+    range: Range<usize>,
+  ) -> Intern {
+    let interner = &self.interner;
+    let shown = self.entry.shown();
+    // SAFETY: the span was handed out by the store, and the caller holds the
+    //         lock
+    let text = unsafe { interner.store.get(shown.clone()) };
+
+    let part = &text[range.clone()];
+    let (span, part) = match interner.canonical(part) {
+      Cow::Borrowed(_) => {
+        let span = (shown.start + range.start)..(shown.start + range.end);
+        (
+          lock.intern_in_place(&interner.store, span),
+          Cow::Borrowed(part),
+        )
+      },
+      Cow::Owned(s) => {
+        // making room for `s` may move the text that `part` lies in
+        let part = part.to_owned();
+        match interner.intern_making_room(lock, &s) {
+          Ok(span) => (span, Cow::Owned(part)),
+          Err(e) => panic!("{e}"),
+        }
+      },
+    };
+
+    Intern {
+      entry: interner.interned(lock, span, &part),
+      interner: Arc::clone(interner),
+    }
+  }
+
+  /// Interns the string that `f` computes from this one, such as a
+  /// lowercased or prefixed copy. The text is copied out before `f` runs,
+  /// so no lock or reader is held while it does, and `f` is free to use the
+  /// interner itself.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let name = interner.intern("Point");
+  ///
+  /// let lower = name.map(|s| s.to_lowercase());
+  /// let getter = name.map(|s| format!("get{s}"));
+  ///
+  /// assert_eq!(lower, interner.intern("point"));
+  /// assert_eq!(&*getter.get_ref(), "getPoint");
+  /// ```
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn map(&self, f: impl FnOnce(&str) -> String) -> Intern {
+    let mapped = f(&self.resolve());
+    self.interner.intern(mapped)
+  }
+
+  /// Turns this into an [InternStr], which can be borrowed as a plain
+  /// [str].
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn into_str(self) -> InternStr {
+    InternStr::from(self)
+  }
+
+  /// Copies the string out into a [DetachedIntern], which doesn't need the
+  /// interner anymore. The string itself can be collected once nothing else
+  /// holds it.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let detached = interner.intern("hello").detach();
+  ///
+  /// assert_eq!(interner.collect(), 5);
+  /// assert_eq!(detached, "hello");
+  /// ```
+  pub fn detach(self) -> DetachedIntern {
+    DetachedIntern {
+      text: self.resolve_arc(),
+    }
+  }
+
+  /// Borrows this as an [InternDisplay], which formats the text itself with
+  /// both `{}` and `{:?}`, reading it only when it's formatted. Padding,
+  /// alignment, precision and the alternate flag all apply to the text, as
+  /// they would to a [str].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  ///
+  /// assert_eq!(format!("[{:>7}]", hello.display()), "[  hello]");
+  /// assert_eq!(format!("[{:.3}]", hello.display()), "[hel]");
+  /// assert_eq!(format!("{:?}", hello.display()), r#""hello""#);
+  /// ```
+  pub fn display(&self) -> InternDisplay<'_> {
+    InternDisplay { intern: self }
+  }
+
+  /// Creates a [WeakIntern] for this string, which doesn't keep the string
+  /// or its interner alive.
+  pub fn downgrade(&self) -> WeakIntern {
+    WeakIntern {
+      entry: Arc::downgrade(&self.entry),
+      interner: Arc::downgrade(&self.interner),
+    }
+  }
+
+  /// Gets the [SymbolId] of this string, which can be turned back into an
+  /// [Intern] with [Interner::from_id].
+  ///
+  /// # Panics
+  ///
+  /// Panics if the interner has handed out more than [u32::MAX] ids.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{Interner, SymbolId};
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  /// let world = interner.intern("world");
+  ///
+  /// assert_eq!(hello.id(), SymbolId(0));
+  /// assert_eq!(world.id(), SymbolId(1));
+  /// assert_eq!(interner.intern("hello").id(), hello.id());
+  /// ```
+  pub fn id(&self) -> SymbolId {
+    self.entry.symbol_id()
+  }
+
+  /// Gets a [Symbol] for this string, which can be read back with
+  /// [Interner::resolve]. Like [Interner::intern_symbol], this keeps the
+  /// string from ever being collected.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Intern::id].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::Interner;
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern("hello");
+  /// let symbol = hello.symbol();
+  ///
+  /// assert_eq!(symbol, interner.intern_symbol("hello"));
+  /// assert_eq!(symbol.id(), hello.id());
+  /// assert_eq!(interner.from_id(symbol.id()), Some(hello));
+  /// ```
+  pub fn symbol(&self) -> Symbol {
+    self.entry.pin()
+  }
+}
+
+/// Formatting reads the string without making an [InternRef], so it never
+/// panics, and only waits while another thread is moving strings.
+impl fmt::Debug for Intern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.interner.peek(&self.entry, |text| {
+      f.debug_struct("Intern")
+        .field("id", &self.entry.id)
+        .field("text", &text)
+        .finish()
+    })
+  }
+}
+
+impl fmt::Display for Intern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.interner.peek(&self.entry, |text| f.pad(text))
+  }
+}
+
+/// Two [Intern]s are equal when they are the same string from the same
+/// interner, which only takes comparing pointers. The same text interned in
+/// different interners is not equal; use [Intern::text_eq] to compare that.
+impl PartialEq for Intern {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.entry, &other.entry)
+  }
+}
+impl Eq for Intern {}
+
+/// Compares the underlying text, which lets an [Intern] be checked against
+/// string literals directly. Strings of different lengths are told apart
+/// without reading either one.
+///
+/// ```
+/// use str_interning::sync::Interner;
+///
+/// let interner = Interner::new();
+/// let keyword = interner.intern("return");
+///
+/// assert!(keyword == "return");
+/// assert!("while" != keyword);
+/// assert!(keyword < "while");
+/// ```
+impl PartialEq<str> for Intern {
+  fn eq(&self, other: &str) -> bool {
+    self.len() == other.len() && self.with_str(|s| s == other)
+  }
+}
+
+impl PartialEq<&str> for Intern {
+  fn eq(&self, other: &&str) -> bool {
+    *self == **other
+  }
+}
+
+impl PartialEq<Intern> for str {
+  fn eq(&self, other: &Intern) -> bool {
+    *other == *self
+  }
+}
+
+impl PartialEq<Intern> for &str {
+  fn eq(&self, other: &Intern) -> bool {
+    *other == **self
+  }
+}
+
+/// Compares the text, so interns can be checked against strings from
+/// std-based APIs without resolving them first
+impl PartialEq<String> for Intern {
+  fn eq(&self, other: &String) -> bool {
+    *self == **other
+  }
+}
+
+impl PartialEq<Intern> for String {
+  fn eq(&self, other: &Intern) -> bool {
+    *other == **self
+  }
+}
+
+impl PartialEq<Cow<'_, str>> for Intern {
+  fn eq(&self, other: &Cow<'_, str>) -> bool {
+    *self == **other
+  }
+}
+
+impl PartialEq<Intern> for Cow<'_, str> {
+  fn eq(&self, other: &Intern) -> bool {
+    *other == **self
+  }
+}
+
+/// Copies the text out, like [Intern::resolve]
+impl From<Intern> for String {
+  fn from(intern: Intern) -> Self {
+    intern.resolve()
+  }
+}
+
+impl From<&Intern> for String {
+  fn from(intern: &Intern) -> Self {
+    intern.resolve()
+  }
+}
+
+/// Compares the underlying text alphabetically, like [Intern::cmp_text]
+impl PartialOrd<str> for Intern {
+  fn partial_cmp(&self, other: &str) -> Option<Ordering> {
+    Some(self.with_str(|s| s.cmp(other)))
+  }
+}
+
+impl PartialOrd<&str> for Intern {
+  fn partial_cmp(&self, other: &&str) -> Option<Ordering> {
+    self.partial_cmp(*other)
+  }
+}
+
+impl PartialOrd<Intern> for str {
+  fn partial_cmp(&self, other: &Intern) -> Option<Ordering> {
+    other.partial_cmp(self).map(Ordering::reverse)
+  }
+}
+
+impl PartialOrd<Intern> for &str {
+  fn partial_cmp(&self, other: &Intern) -> Option<Ordering> {
+    other.partial_cmp(*self).map(Ordering::reverse)
+  }
+}
+
+/// Orders by interner, then by when each string was first interned. This is
+/// cheap and consistent with [Eq], which makes it suitable for ordered
+/// collections, but it is not alphabetical; use [Intern::cmp_text] for that.
+impl Ord for Intern {
+  fn cmp(&self, other: &Self) -> Ordering {
+    Arc::as_ptr(&self.interner)
+      .cmp(&Arc::as_ptr(&other.interner))
+      .then(self.entry.id.cmp(&other.entry.id))
+  }
+}
+
+impl PartialOrd for Intern {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Hash for Intern {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    Arc::as_ptr(&self.entry).hash(state);
+  }
+}
+
+/// A handle to an interned string that, unlike [Intern], doesn't keep the
+/// string alive. Once every [Intern] of the string is gone and
+/// [Interner::collect] has freed it, or the whole [Interner] has been dropped,
+/// it can no longer be upgraded. Created using [Intern::downgrade].
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::Interner;
+///
+/// let interner = Interner::new();
+/// let hello = interner.intern("hello");
+/// let weak = hello.downgrade();
+///
+/// assert_eq!(weak.upgrade(), Some(hello.clone()));
+///
+/// drop(hello);
+/// interner.collect();
+///
+/// assert_eq!(weak.upgrade(), None);
+/// ```
+#[derive(Clone)]
+pub struct WeakIntern {
+  entry: Weak<Entry>,
+  interner: Weak<Interner>,
+}
+
+impl WeakIntern {
+  /// Produces an [Intern] for the string, if it hasn't been freed
+  pub fn upgrade(&self) -> Option<Intern> {
+    let interner = self.interner.upgrade()?;
+
+    // hold the lock so that a collection can't free the entry between here
+    // and it being counted as in use
+    let lock = interner.internal.read();
+    let entry = self.entry.upgrade();
+    drop(lock);
+
+    Some(Intern {
+      entry: entry?,
+      interner,
+    })
+  }
+}
+
+impl fmt::Debug for WeakIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("(WeakIntern)")
+  }
+}
+
+/// A string that [RefsHeldPolicy::Queue] put off interning, because the
+/// store was full and readers kept anything from being evicted. It is
+/// interned once the last [InternRef] on any thread is dropped, or, if the
+/// lock was busy then, the next time it is checked on. Handed out inside
+/// [InternError::Queued] by [Interner::intern_or_queue].
+///
+/// Dropping it takes the string back out of the queue, unless it has
+/// already been interned.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::{
+///   InternError, InternerBuilder, RefsHeldPolicy, StoreFullPolicy,
+/// };
+/// use std::thread;
+///
+/// let interner = InternerBuilder::new()
+///   .max_store_bytes(8)
+///   .on_store_full(StoreFullPolicy::Evict)
+///   .when_refs_held(RefsHeldPolicy::Queue)
+///   .build();
+/// let a = interner.intern("a");
+/// drop(interner.intern("stale"));
+///
+/// let a_ref = a.get_ref();
+/// let Err(InternError::Queued(pending)) = interner.intern_or_queue("fresh")
+/// else {
+///   panic!("a_ref kept stale from being evicted");
+/// };
+///
+/// // another thread waits for the last reader to be dropped
+/// let fresh = thread::scope(|scope| {
+///   let waiter = scope.spawn(|| pending.wait());
+///   drop(a_ref);
+///   waiter.join().unwrap()
+/// })?;
+/// assert_eq!(&*fresh.get_ref(), "fresh");
+/// # Ok::<(), InternError>(())
+/// ```
+pub struct PendingIntern {
+  interner: Arc<Interner>,
+  slot: Slot,
+  /// The size of the store, for when waiting fails
+  limit: usize,
+}
+
+impl PendingIntern {
+  /// Whether the string has been interned, or interning it has failed.
+  /// Tries to intern it first, if no readers are left.
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn is_ready(&self) -> bool {
+    self.interner.apply_queued();
+    self.slot.lock().is_some()
+  }
+
+  /// Parks the current thread until no readers are left and the string has
+  /// been interned, or interning it has failed. If the current thread holds
+  /// a reader itself, waiting would never end, so this fails with
+  /// [InternError::StoreFull] instead.
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn wait(self) -> Result<Intern, InternError> {
+    self.wait_within(Patience::Forever)
+  }
+
+  /// Waits like [PendingIntern::wait], for as long as `patience` allows
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn wait_within(self, patience: Patience) -> Result<Intern, InternError> {
+    let interner = &self.interner;
+    loop {
+      if let Some(result) = self.slot.lock().take() {
+        return result.map(|entry| Intern {
+          entry,
+          interner: Arc::clone(interner),
+        });
+      }
+
+      // the last reader may have been dropped while another thread held
+      // the lock, so that nothing was interned then
+      if interner.refs.load(AtomicOrdering::SeqCst) == 0 {
+        let lock = interner.lock_within(patience)?;
+        interner.apply_queued_to(&mut interner.held(lock));
+        continue;
+      }
+
+      interner
+        .await_readers(patience, self.limit, || self.slot.lock().is_some())?;
+    }
+  }
+}
+
+impl fmt::Debug for PendingIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("PendingIntern")
+      .field("ready", &self.slot.lock().is_some())
+      .finish_non_exhaustive()
+  }
+}
+
+/// The text of an [Intern], copied out so that it no longer depends on its
+/// [Interner]. It can outlive the interner, or be sent somewhere the
+/// interner can't go, such as inside an error that is returned after a
+/// parse's interner has been torn down. Created using [Intern::detach].
+///
+/// Equality, ordering and hashing go by the text, and cloning only bumps a
+/// reference count. Interning it again, into any interner, gives back an
+/// ordinary [Intern].
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::Interner;
+///
+/// let interner = Interner::new();
+/// let name = interner.intern("main").detach();
+/// drop(interner);
+///
+/// assert_eq!(name, "main");
+///
+/// let other = Interner::new();
+/// assert_eq!(other.intern(&name), other.intern("main"));
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DetachedIntern {
+  text: Arc<str>,
+}
+
+impl DetachedIntern {
+  /// Borrows the text
+  pub fn as_str(&self) -> &str {
+    &self.text
+  }
+}
+
+impl Deref for DetachedIntern {
+  type Target = str;
+
+  fn deref(&self) -> &Self::Target {
+    &self.text
+  }
+}
+
+impl AsRef<str> for DetachedIntern {
+  fn as_ref(&self) -> &str {
+    &self.text
+  }
+}
+
+impl Borrow<str> for DetachedIntern {
+  fn borrow(&self) -> &str {
+    &self.text
+  }
+}
+
+impl From<DetachedIntern> for Arc<str> {
+  fn from(detached: DetachedIntern) -> Self {
+    detached.text
+  }
+}
+
+impl fmt::Debug for DetachedIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("DetachedIntern").field(&&*self.text).finish()
+  }
+}
+
+impl fmt::Display for DetachedIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad(&self.text)
+  }
+}
+
+impl PartialEq<str> for DetachedIntern {
+  fn eq(&self, other: &str) -> bool {
+    *self.text == *other
+  }
+}
+
+impl PartialEq<&str> for DetachedIntern {
+  fn eq(&self, other: &&str) -> bool {
+    *self.text == **other
+  }
+}
+
+/// Formats the text of an [Intern], produced by [Intern::display]. Like
+/// formatting the [Intern] itself, this never makes an [InternRef], so it
+/// never panics, and it only waits while another thread is moving strings.
+#[derive(Clone, Copy)]
+pub struct InternDisplay<'a> {
+  intern: &'a Intern,
+}
+
+impl fmt::Debug for InternDisplay<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let Intern { interner, entry } = self.intern;
+    interner.peek(entry, |text| fmt::Debug::fmt(text, f))
+  }
+}
+
+impl fmt::Display for InternDisplay<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Display::fmt(self.intern, f)
+  }
+}
+
+/// Allows access to the string that an [Intern] represents. Reading
+/// through this type never takes a lock, and it does not prevent other
+/// threads from interning.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::Interner;
+///
+/// let interner = Interner::new();
+/// let hello = interner.intern("hello");
+///
+/// let hello_ref = hello.get_ref();
+///
+/// assert_eq!(&*hello_ref, "hello");
+/// ```
+pub struct InternRef<'a> {
+  text: &'a str,
+  interner: &'a Interner,
+  reader: Reader,
+}
+
+impl Drop for InternRef<'_> {
+  fn drop(&mut self) {
+    self.interner.leave(&self.reader);
+  }
+}
+
+impl fmt::Debug for InternRef<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("InternRef").field(&self.text).finish()
+  }
+}
+
+impl fmt::Display for InternRef<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad(self.text)
+  }
+}
+
+impl Deref for InternRef<'_> {
+  type Target = str;
+
+  fn deref(&self) -> &Self::Target {
+    self.text
+  }
+}
+
+/// An [Intern] that reads its string up front, so that it can implement
+/// [AsRef], [Borrow] and [Deref] for [str]. Equality, ordering and hashing
+/// all go by the text, so it can be used as a map key and looked up by
+/// `&str`.
+///
+/// Each [InternStr] counts as a reader for as long as it lives, just like an
+/// [InternRef], so [Interner::collect] and [Interner::shrink_to_fit] can't
+/// move strings while any exist. Prefer [Intern] for anything long-lived
+/// that doesn't need borrowed lookups.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use str_interning::sync::Interner;
+///
+/// let interner = Interner::new();
+/// let mut counts = HashMap::new();
+/// counts.insert(interner.intern("hello").into_str(), 1);
+///
+/// assert_eq!(counts.get("hello"), Some(&1));
+/// ```
+pub struct InternStr {
+  intern: Intern,
+  span: Span,
+  reader: Reader,
+}
+
+impl InternStr {
+  /// Gets the [Intern] this was made from
+  pub fn intern(&self) -> &Intern {
+    &self.intern
+  }
+
+  /// Stops reading the string, so that it no longer holds off
+  /// [Interner::collect], and gives back the [Intern] it was made from
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::{InternStr, Interner};
+  ///
+  /// let interner = Interner::new();
+  /// let hello = InternStr::from(interner.intern("hello"));
+  /// assert_eq!(interner.stats().outstanding_refs, 1);
+  ///
+  /// let hello = hello.downgrade();
+  ///
+  /// assert_eq!(interner.stats().outstanding_refs, 0);
+  /// assert_eq!(hello, interner.intern("hello"));
+  /// ```
+  pub fn downgrade(self) -> Intern {
+    self.intern.clone()
+  }
+}
+
+impl From<Intern> for InternStr {
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn from(intern: Intern) -> Self {
+    let reader = intern.interner.enter();
+    // the span can't change now that we're counted as a reader
+    let span = intern.entry.shown();
+
+    Self {
+      intern,
+      span,
+      reader,
+    }
+  }
+}
+
+impl Clone for InternStr {
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn clone(&self) -> Self {
+    Self::from(self.intern.clone())
+  }
+}
+
+impl Drop for InternStr {
+  fn drop(&mut self) {
+    self.intern.interner.leave(&self.reader);
+  }
+}
+
+impl Deref for InternStr {
+  type Target = str;
+
+  fn deref(&self) -> &Self::Target {
+    // SAFETY: the span came from the interner's store, and we have been
+    //         counted as a reader ever since
+    unsafe { self.intern.interner.store.get(self.span.clone()) }
+  }
+}
+
+impl AsRef<str> for InternStr {
+  fn as_ref(&self) -> &str {
+    self
+  }
+}
+
+impl Borrow<str> for InternStr {
+  fn borrow(&self) -> &str {
+    self
+  }
+}
+
+/// Borrows the text. An [Intern] can't be borrowed as a [Cow] itself, since
+/// the borrow has to keep its string from being moved, so turn it into an
+/// [InternStr] first with [Intern::into_str].
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+/// use str_interning::sync::Interner;
+///
+/// let interner = Interner::new();
+/// let hello = interner.intern("hello").into_str();
+///
+/// assert_eq!(Cow::from(&hello), Cow::Borrowed("hello"));
+/// ```
+impl<'a> From<&'a InternStr> for Cow<'a, str> {
+  fn from(s: &'a InternStr) -> Self {
+    Cow::Borrowed(s)
+  }
+}
+
+impl fmt::Debug for InternStr {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("InternStr").field(&&**self).finish()
+  }
+}
+
+impl fmt::Display for InternStr {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad(self)
+  }
+}
+
+impl PartialEq for InternStr {
+  fn eq(&self, other: &Self) -> bool {
+    self.intern.text_eq(&other.intern)
+  }
+}
+impl Eq for InternStr {}
+
+impl Ord for InternStr {
+  fn cmp(&self, other: &Self) -> Ordering {
+    str::cmp(self, other)
+  }
+}
+
+impl PartialOrd for InternStr {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+/// Hashes the same as the text, as [Borrow] requires
+impl Hash for InternStr {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    str::hash(self, state);
+  }
+}
+
+/// Iterates over the strings in an [Interner], produced by [Interner::iter]
+pub struct InternerIter<'a> {
+  entries: vec::IntoIter<(SymbolId, Span)>,
+  interner: &'a Interner,
+  reader: Reader,
+}
+
+impl<'a> Iterator for InternerIter<'a> {
+  type Item = (SymbolId, InternRef<'a>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (id, span) = self.entries.next()?;
+    // the iterator is already counted as a reader, so strings can't be moving
+    let interner = self.interner;
+    let reader = interner.enter_again();
+
+    // SAFETY: the span was handed out by the interner's store, and it can't
+    //         move while we're counted as a reader
+    let text = unsafe { interner.store.get(span) };
+    Some((
+      id,
+      InternRef {
+        text,
+        interner,
+        reader,
+      },
+    ))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.entries.size_hint()
+  }
+}
+
+impl ExactSizeIterator for InternerIter<'_> {}
+
+impl Drop for InternerIter<'_> {
+  fn drop(&mut self) {
+    self.interner.leave(&self.reader);
+  }
+}
+
+impl fmt::Debug for InternerIter<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("InternerIter")
+      .field("remaining", &self.entries.len())
+      .finish_non_exhaustive()
+  }
+}
+
+/// Reads strings out of an [Interner] for as long as it lives, produced by
+/// [Interner::scope]
+pub struct InternScope<'a> {
+  interner: &'a Interner,
+  reader: Reader,
+}
+
+impl InternScope<'_> {
+  /// Borrows the string that `intern` represents until the scope ends
+  ///
+  /// # Panics
+  ///
+  /// Panics if `intern` came from a different interner.
+  pub fn get(&self, intern: &Intern) -> &str {
+    assert!(
+      ptr::eq(&*intern.interner, self.interner),
+      "intern does not belong to this interner"
+    );
+
+    // SAFETY: the span was handed out by the interner's store, and it can't
+    //         move while the scope is counted as a reader
+    unsafe { self.interner.store.get(intern.entry.shown()) }
+  }
+}
+
+impl Drop for InternScope<'_> {
+  fn drop(&mut self) {
+    self.interner.leave(&self.reader);
+  }
+}
+
+impl fmt::Debug for InternScope<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("InternScope").finish_non_exhaustive()
+  }
+}
+
+#[cfg(test)]
 mod tests {
   use super::*;
-  use std::ptr;
+  use std::{
+    collections::{BTreeSet, HashMap},
+    panic, ptr, thread,
+  };
+
+  #[test]
+  fn interner() {
+    let interner = Interner::new();
+    let hello1 = interner.intern("hello");
+    let goodbye = interner.intern("goodbye");
+    let hello2 = interner.intern("hello");
+
+    assert_eq!(&*hello1.get_ref(), "hello");
+    assert_eq!(&*hello2.get_ref(), "hello");
+    assert_eq!(&*goodbye.get_ref(), "goodbye");
+
+    assert_eq!(*hello1.get_ref(), *hello2.get_ref());
+    assert_ne!(*hello1.get_ref(), *goodbye.get_ref());
+
+    assert!(ptr::addr_eq(&*hello1.get_ref(), &*hello2.get_ref()));
+    assert!(!ptr::addr_eq(&*hello1.get_ref(), &*goodbye.get_ref()));
+  }
+
+  /// Fails to compile if a change to the internals stops any public type
+  /// from crossing threads
+  #[test]
+  fn public_types_are_send_and_sync() {
+    use crate::sync::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Interner>();
+    assert_send_sync::<InternerBuilder>();
+    assert_send_sync::<Intern>();
+    assert_send_sync::<InternRef<'_>>();
+    assert_send_sync::<InternStr>();
+    assert_send_sync::<DetachedIntern>();
+    assert_send_sync::<WeakIntern>();
+    assert_send_sync::<PendingIntern>();
+    assert_send_sync::<InternDisplay<'_>>();
+    assert_send_sync::<InternScope<'_>>();
+    assert_send_sync::<InternerIter<'_>>();
+    assert_send_sync::<CompactIntern>();
+    assert_send_sync::<Symbol>();
+    assert_send_sync::<MergeMap>();
+    assert_send_sync::<InternerStats>();
+    assert_send_sync::<InternError>();
+    assert_send_sync::<SliceError>();
+    assert_send_sync::<PartsError>();
+    assert_send_sync::<UnescapeError>();
+    assert_send_sync::<ByteInterner>();
+    assert_send_sync::<InternBytes>();
+    assert_send_sync::<InternBytesRef<'_>>();
+    assert_send_sync::<SequenceInterner<u32>>();
+    assert_send_sync::<InternSequence<u32>>();
+    assert_send_sync::<InternPath>();
+    assert_send_sync::<FrozenInterner>();
+    assert_send_sync::<FrozenIntern>();
+    assert_send_sync::<ShardedInterner>();
+    assert_send_sync::<LocalScribe>();
+    assert_send_sync::<NamespacedInterner<()>>();
+    assert_send_sync::<TaggedIntern<()>>();
+    assert_send_sync::<SourceId>();
+    assert_send_sync::<LineColumn>();
+    #[cfg(feature = "tokio")]
+    assert_send_sync::<AsyncInterner>();
+    #[cfg(feature = "memmap2")]
+    assert_send_sync::<MmapInterner>();
+    #[cfg(feature = "aho-corasick")]
+    assert_send_sync::<InternMatcher>();
+    #[cfg(feature = "serde")]
+    assert_send_sync::<InternSeed<'_>>();
+    #[cfg(feature = "diagnostics")]
+    assert_send_sync::<DiagnosticsReport>();
+  }
+
+  #[test]
+  fn intern_resolves_owned_copies() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let owned = hello.resolve();
+    let shared = hello.resolve_arc();
+
+    assert_eq!(owned, "hello");
+    assert_eq!(&*shared, "hello");
+    assert!(!ptr::addr_eq(owned.as_str(), &*hello.get_ref()));
+  }
+
+  #[test]
+  fn interns_work_as_map_keys() {
+    let interner = Interner::new();
+    let other = Interner::new();
+    let mut counts = HashMap::new();
+
+    for word in ["a", "b", "a", "c", "a"] {
+      *counts.entry(interner.intern(word)).or_insert(0) += 1;
+    }
+    counts.insert(other.intern("a"), 10);
+
+    assert_eq!(counts[&interner.intern("a")], 3);
+    assert_eq!(counts[&interner.intern("b")], 1);
+    assert_eq!(counts[&other.intern("a")], 10);
+    assert_eq!(counts.len(), 4);
+  }
+
+  #[test]
+  fn interns_order_by_age_or_text() {
+    let interner = Interner::new();
+    let b = interner.intern("b");
+    let a = interner.intern("a");
+    let mut set = BTreeSet::new();
+    set.insert(a.clone());
+    set.insert(b.clone());
+
+    assert!(b < a);
+    assert_eq!(set.into_iter().collect::<Vec<_>>(), [b.clone(), a.clone()]);
+    assert_eq!(b.cmp_text(&a), Ordering::Greater);
+    assert_eq!(a.cmp_text(&interner.intern("a")), Ordering::Equal);
+  }
+
+  #[test]
+  fn interner_interns_batches() {
+    let interner = Interner::new();
+    let single = interner.intern("b");
+    let batch = interner.intern_all(vec![String::from("a"), "b".into()]);
+
+    assert_eq!(batch.len(), 2);
+    assert_eq!(&*batch[0].get_ref(), "a");
+    assert_eq!(batch[1], single);
+  }
+
+  #[test]
+  fn interner_reuses_substrings() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let ell = interner.intern_substring("ell").unwrap();
+
+    assert!(ptr::addr_eq(&hello.get_ref()[1..4], &*ell.get_ref()));
+    assert_eq!(interner.get("ell"), Some(ell.clone()));
+    assert_eq!(interner.intern("ell"), ell);
+    assert_eq!(interner.intern_substring("help"), None);
+    assert_eq!(interner.stats().stored_bytes, 5);
+  }
+
+  #[test]
+  fn interner_overlaps_strings_with_the_end_of_the_store() {
+    let interner = Interner::new();
+    let config = interner.intern("config");
+    let figure = interner.intern("figure");
+
+    assert_eq!(&*figure.get_ref(), "figure");
+    assert!(ptr::addr_eq(&config.get_ref()[3..], &figure.get_ref()[..3]));
+    assert_eq!(interner.stats().stored_bytes, 9);
+
+    drop(config);
+    interner.collect();
+    assert_eq!(&*figure.get_ref(), "figure");
+    assert_eq!(interner.stats().stored_bytes, 6);
+
+    // collecting may not find the same overlaps again
+    let (ab, bcd) = (interner.intern("ab"), interner.intern("bcd"));
+    assert_eq!(interner.collect(), 0);
+    assert_eq!((&*ab.get_ref(), &*bcd.get_ref()), ("ab", "bcd"));
+
+    let exact = InternerBuilder::new().substring_sharing(false).build();
+    exact.intern("config");
+    exact.intern("figure");
+    assert_eq!(exact.stats().stored_bytes, 12);
+  }
+
+  #[test]
+  fn interner_gets_without_inserting() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+
+    assert_eq!(interner.get("hello"), Some(hello.clone()));
+    assert_eq!(interner.get("ell"), None);
+
+    let ell = interner.intern("ell");
+    assert_eq!(interner.get("ell"), Some(ell));
+    assert_eq!(interner.get(""), None);
+
+    // lookups only read, so they don't wait for other readers
+    let reading = interner.internal.read();
+    assert_eq!(interner.get("hello"), Some(hello));
+    assert_eq!(interner.get("goodbye"), None);
+    drop(reading);
+  }
+
+  #[test]
+  fn interner_stats() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    interner.intern("ell");
+    interner.intern("hello");
+    let hello_ref = hello.get_ref();
+
+    let stats = interner.stats();
+    assert_eq!(stats.stored_bytes, 5);
+    assert!(stats.allocated_bytes >= 5);
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.trie_nodes, 2);
+    assert_eq!(stats.automaton_states, 7);
+    assert_eq!(stats.outstanding_refs, 1);
+    assert_eq!((stats.hits, stats.misses), (2, 1));
+
+    drop(hello_ref);
+    assert_eq!(interner.stats().outstanding_refs, 0);
+  }
+
+  #[test]
+  fn interner_with_hash_index() {
+    let interner = InternerBuilder::new().index(IndexBackend::Hash).build();
+    let hello = interner.intern("hello");
+    let ell = interner.intern("ell");
+
+    assert_eq!(interner.intern("hello"), hello);
+    assert_eq!(&*ell.get_ref(), "ell");
+    assert_eq!(interner.intern_substring("hel"), None);
+    assert_eq!(interner.stats().stored_bytes, 8);
+  }
+
+  #[test]
+  fn interner_toggles_substring_sharing() {
+    let exact = InternerBuilder::new().substring_sharing(false).build();
+    let shared = InternerBuilder::new()
+      .index(IndexBackend::Hash)
+      .substring_sharing(true)
+      .build();
+    exact.intern("hello");
+    shared.intern("hello");
+
+    assert_eq!(exact.intern_substring("ell"), None);
+    assert_eq!(exact.stats().automaton_states, 0);
+    assert_eq!(&*shared.intern_substring("ell").unwrap().get_ref(), "ell");
+  }
+
+  #[test]
+  fn interner_folds_ascii_case() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let hello = interner.intern("Hello");
+
+    assert_eq!(interner.intern("HELLO"), hello);
+    assert_eq!(interner.get("hELLo"), Some(hello.clone()));
+    assert_eq!(&*hello.get_ref(), "hello");
+    assert_eq!(&*interner.intern("ÀB").get_ref(), "Àb");
+  }
+
+  #[cfg(feature = "caseless")]
+  #[test]
+  fn interner_folds_unicode_case() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Unicode)
+      .build();
+    let street = interner.intern("Straße");
+
+    assert_eq!(interner.intern("STRASSE"), street);
+    assert_eq!(&*street.get_ref(), "strasse");
+    assert_eq!(interner.get("ÀB"), None);
+    assert_eq!(interner.intern("ÀB"), interner.intern("àb"));
+  }
+
+  #[cfg(feature = "unicode-normalization")]
+  #[test]
+  fn interner_normalizes_unicode() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .normalization(Normalization::Nfkc)
+      .build();
+    let cafe = interner.intern("cafe\u{301}");
+
+    assert_eq!(interner.intern("café"), cafe);
+    assert_eq!(interner.intern("CAFE\u{301}"), cafe);
+    assert_eq!(&*cafe.get_ref(), "café");
+    assert_eq!(interner.intern("ﬁle"), interner.intern("file"));
+
+    let mut saved = Vec::new();
+    interner.save_to(&mut saved).unwrap();
+    let reloaded = Interner::load_from(&saved[..]).unwrap();
+    assert!(reloaded.get("Cafe\u{301}").is_some());
+    assert!(reloaded.clone().freeze().get("Cafe\u{301}").is_some());
+  }
+
+  #[test]
+  fn interner_preserves_first_casing() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let header = interner.intern("Content-Type");
+    let lower = interner.intern("accept");
+
+    assert_eq!(interner.intern("CONTENT-TYPE"), header);
+    assert_eq!(interner.get("content-type"), Some(header.clone()));
+    assert_eq!(&*header.get_ref(), "Content-Type");
+    assert_eq!(header.resolve(), "Content-Type");
+    assert_eq!(&*interner.intern("ACCEPT").get_ref(), "accept");
+    // only the differing spelling takes up extra room
+    assert_eq!(interner.stats().stored_bytes, 30);
+
+    drop(interner.intern("garbage"));
+    interner.collect();
+    assert_eq!(&*header.get_ref(), "Content-Type");
+    assert_eq!(&*lower.get_ref(), "accept");
+    assert_eq!(interner.stats().stored_bytes, 30);
+  }
+
+  #[test]
+  fn interner_records_spellings() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .record_spellings(true)
+      .build();
+    let header = interner.intern_symbol("Content-Type");
+    let accept = interner.intern("accept");
+    interner.intern("content-type");
+    interner.intern("CONTENT-TYPE");
+    interner.intern("Content-Type");
+    interner.intern("ACCEPT");
+    // looking up a new spelling has to lock for writing to record it
+    interner.get("Accept");
+    interner.get("accept");
+
+    assert_eq!(
+      interner.spellings(header),
+      ["Content-Type", "content-type", "CONTENT-TYPE"]
+    );
+    assert_eq!(
+      interner.spellings(accept.symbol()),
+      ["accept", "ACCEPT", "Accept"]
+    );
+    assert_eq!(accept.first_spelling(), "accept");
+    assert_eq!(&*accept.get_ref(), "accept");
+
+    let shout = interner.intern("SHOUT");
+    assert_eq!(shout.first_spelling(), "SHOUT");
+    assert_eq!(&*shout.folded(), "shout");
+    let id = shout.id().0 as usize;
+    drop(shout);
+    interner.collect();
+    let spellings = interner.internal.read().spellings.clone().unwrap();
+    assert!(!spellings.contains_key(&id));
+
+    let preserving = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let html = preserving.intern("<DIV>");
+    assert_eq!(html.first_spelling(), "<DIV>");
+    assert_eq!(&*html.folded(), "<div>");
+    let div = preserving.intern_symbol("<div>");
+    assert_eq!(preserving.spellings(div), ["<DIV>"]);
+  }
+
+  #[test]
+  fn interner_interns_static_strs_in_place() {
+    static KEYWORD: &str = "keyword";
+    let interner = Interner::new();
+    let existing = interner.intern("fn");
+    let keyword = interner.intern_static(KEYWORD);
+    let key = interner.intern("key");
+
+    assert_eq!(keyword, interner.intern("keyword"));
+    assert_eq!(existing, interner.intern_static("fn"));
+    assert_eq!(interner.stats().stored_bytes, 2);
+    assert!(ptr::eq(keyword.get_ref().as_ptr(), KEYWORD.as_ptr()));
+
+    drop(existing);
+    drop(interner.intern("garbage"));
+    interner.collect();
+    assert_eq!(interner.stats().stored_bytes, 0);
+    assert!(ptr::eq(keyword.get_ref().as_ptr(), KEYWORD.as_ptr()));
+    assert_eq!(&*key.get_ref(), "key");
+    assert_eq!(keyword.slice(3..7).unwrap(), interner.intern("word"));
+    assert_eq!(interner.stats().stored_bytes, 0);
+
+    let mut saved = Vec::new();
+    interner.save_to(&mut saved).unwrap();
+    let reloaded = Interner::load_from(&saved[..]).unwrap();
+    assert_eq!(&*reloaded.get("keyword").unwrap().get_ref(), "keyword");
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    assert_eq!(&*folded.intern_static("Keyword").get_ref(), "keyword");
+    assert_eq!(folded.stats().stored_bytes, 7);
+  }
+
+  #[test]
+  fn interner_limits_store_size() {
+    let interner = InternerBuilder::new().max_store_bytes(8).build();
+    interner.intern("hello");
+
+    assert!(matches!(
+      interner.try_intern("world"),
+      Err(InternError::StoreFull { limit: 8 })
+    ));
+    assert!(interner.try_intern("ell").is_ok());
+    assert!(interner.try_intern("abc").is_ok());
+    assert_eq!(interner.stats().stored_bytes, 8);
+  }
+
+  #[test]
+  #[should_panic(expected = "limit of 0 bytes")]
+  fn intern_panics_when_store_is_full() {
+    InternerBuilder::new()
+      .max_store_bytes(0)
+      .build()
+      .intern("a");
+  }
+
+  #[test]
+  fn interner_evicts_least_recently_used_strings() {
+    let interner = InternerBuilder::new()
+      .max_store_bytes(15)
+      .on_store_full(StoreFullPolicy::Evict)
+      .build();
+    let kept = interner.intern("kept!");
+    drop(interner.intern("older"));
+    drop(interner.intern("newer"));
+    // looking a string up counts as using it
+    assert!(interner.get("older").is_some());
+
+    let extra = interner.intern("extra");
+    assert_eq!(interner.get("newer"), None);
+    assert!(interner.get("older").is_some());
+    assert_eq!(&*kept.get_ref(), "kept!");
+    assert_eq!(&*extra.get_ref(), "extra");
+    assert_eq!(interner.stats().stored_bytes, 15);
+
+    // strings can't be moved while they're being read
+    let reading = kept.get_ref();
+    assert!(matches!(
+      interner.try_intern("more"),
+      Err(InternError::StoreFull { limit: 15 })
+    ));
+    drop(reading);
+    assert_eq!(interner.intern("more"), "more");
+    assert_eq!(interner.get("older"), None);
+    assert!(matches!(
+      interner.try_intern("too long!"),
+      Err(InternError::StoreFull { .. })
+    ));
+  }
+
+  #[test]
+  fn interner_from_corpus() {
+    let (interner, words) =
+      Interner::from_corpus(["ell", "b", "hello", "ell", "a b"]);
+
+    assert_eq!(words.len(), 5);
+    assert_eq!(words[0], words[3]);
+    assert_eq!(words[2], interner.intern("hello"));
+    assert_eq!(&*words[1].get_ref(), "b");
+    assert_eq!(interner.stats().stored_bytes, 8);
+    assert_eq!(
+      words.iter().map(Intern::id).collect::<Vec<_>>(),
+      [0, 1, 2, 0, 3].map(SymbolId)
+    );
+  }
+
+  #[test]
+  fn interner_iterates_over_strings() {
+    let interner = Interner::new();
+    let _words = interner.intern_all(["hello", "ell", "world", "hello"]);
+    drop(interner.intern("garbage"));
+    interner.collect();
+
+    let strings = interner.iter();
+    assert_eq!(strings.len(), 3);
+    assert_eq!(interner.collect(), 0);
+    let strings = strings.collect::<Vec<_>>();
+    assert_eq!(interner.stats().outstanding_refs, 3);
+    assert_eq!(
+      strings
+        .iter()
+        .map(|(id, s)| (id.0, &**s))
+        .collect::<Vec<_>>(),
+      [(0, "hello"), (1, "ell"), (2, "world")]
+    );
+
+    drop(strings);
+    assert_eq!(interner.stats().outstanding_refs, 0);
+  }
+
+  #[test]
+  fn interner_concatenates_pieces() {
+    let interner = Interner::new();
+    let hello = interner.intern_concat(&["hel", "lo ", "world"]);
+    let stored = interner.stats().stored_bytes;
+    let again =
+      interner.intern_concat(&[String::from("hello"), " world".into()]);
+    let world = interner.intern_concat(&["wor", "", "ld"]);
+
+    assert_eq!(&*hello.get_ref(), "hello world");
+    assert_eq!(hello, again);
+    assert_eq!(hello, interner.intern("hello world"));
+    assert_eq!(&*world.get_ref(), "world");
+    assert_eq!(interner.stats().stored_bytes, stored);
+    assert_eq!(&*interner.intern_concat::<&str>(&[]).get_ref(), "");
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let header = folded.intern_concat(&["Content", "-Type"]);
+    assert_eq!(header, folded.intern("content-type"));
+    assert_eq!(&*header.get_ref(), "content-type");
+  }
+
+  #[test]
+  fn interner_joins_and_splits() {
+    let interner = Interner::new();
+    let path = interner.intern("a::bc::d");
+    let stored = interner.stats().stored_bytes;
+
+    let segments = interner.split_intern(&path, "::");
+    assert_eq!(segments, ["a", "bc", "d"].map(|s| interner.intern(s)));
+    assert_eq!(interner.stats().stored_bytes, stored);
+    assert_eq!(interner.join(&segments, "::"), path);
+    assert_eq!(interner.join(&segments[1..], ""), interner.intern("bcd"));
+    assert_eq!(interner.join(&[], "::"), interner.intern(""));
+    assert_eq!(
+      interner.split_intern(&interner.intern("::a::"), "::"),
+      ["", "a", ""].map(|s| interner.intern(s))
+    );
+
+    let other = Interner::new();
+    let foreign = other.intern("std");
+    let joined = interner.join(&[foreign.clone(), segments[0].clone()], "/");
+    assert_eq!(joined, interner.intern("std/a"));
+    assert_eq!(
+      interner.split_intern(&foreign, "t")[1],
+      interner.intern("d")
+    );
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let header = folded.intern("Content-Type");
+    let parts = folded.split_intern(&header, "-");
+    assert_eq!(parts[1], folded.intern("type"));
+    assert_eq!(&*parts[1].get_ref(), "Type");
+    assert_eq!(folded.join(&parts, "-"), header);
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn interner_interns_formatted_text() {
+    let interner = Interner::new();
+    let temp = interner.intern_fmt(format_args!("tmp{}", 3));
+    let stored = interner.stats().stored_bytes;
+    let again = crate::intern_format!(interner, "tmp{}", 1 + 2);
+    assert_eq!(interner.stats().stored_bytes, stored);
+    let long = crate::intern_format!(interner, "{:x<5000}", "long");
+
+    assert_eq!(&*temp.get_ref(), "tmp3");
+    assert_eq!(temp, again);
+    assert_eq!(long.len(), 5000);
+    assert!(long.get_ref().starts_with("longxxx"));
+  }
+
+  #[test]
+  fn interner_interns_unescaped_literals() {
+    let interner = Interner::new();
+    let line = interner
+      .intern_unescaped(r#"say \"hi\"\n"#, EscapeStyle::Rust)
+      .unwrap();
+    let stored = interner.stats().stored_bytes;
+    let json = interner
+      .intern_unescaped(r#"say \"hi\"\u000a"#, EscapeStyle::Json)
+      .unwrap();
+
+    assert_eq!(&*line.get_ref(), "say \"hi\"\n");
+    assert_eq!(line, json);
+    assert_eq!(interner.stats().stored_bytes, stored);
+
+    let entries = interner.stats().entries;
+    assert_eq!(
+      interner.intern_unescaped(r"tab\ta\z", EscapeStyle::Rust),
+      Err(UnescapeError::UnknownEscape { index: 6 })
+    );
+    assert_eq!(
+      interner.intern_unescaped(r"\ud800", EscapeStyle::Json),
+      Err(UnescapeError::InvalidEscape { index: 0 })
+    );
+    assert_eq!(interner.stats().entries, entries);
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let shout = folded.intern_unescaped(r"\x48I", EscapeStyle::Rust);
+    assert_eq!(shout.unwrap(), folded.intern("hi"));
+  }
+
+  #[test]
+  fn interner_interns_sources() {
+    let interner = InternerBuilder::new()
+      .max_store_bytes(64)
+      .on_store_full(StoreFullPolicy::Evict)
+      .build();
+    let text = "let x = 1;\nlet y = x;\n";
+    let lib = interner.intern_source("lib.rs", text);
+    let copy = interner.intern_source("copy.rs", text);
+
+    assert_eq!((lib, copy), (SourceId(0), SourceId(1)));
+    assert_eq!(interner.source(lib), interner.source(copy));
+    assert_eq!(&*interner.source_name(copy).unwrap(), "copy.rs");
+    assert_eq!(interner.source(SourceId(2)), None);
+    assert_eq!(interner.line_column(SourceId(2), 0), None);
+    assert_eq!(
+      interner.line_column(lib, 19),
+      Some(LineColumn { line: 2, column: 9 })
+    );
+    assert_eq!(interner.line_column(lib, 23), None);
+    let y = interner.source_line(lib, 2).unwrap().slice(4..5).unwrap();
+    assert_eq!(y, interner.intern("y"));
+    drop(y);
+
+    // sources are never evicted, and offsets survive strings moving
+    for i in 0..100 {
+      drop(interner.intern(format!("filler{i}")));
+    }
+    assert_eq!(&*interner.source(lib).unwrap().get_ref(), text);
+    assert_eq!(
+      interner.line_column(lib, 11),
+      Some(LineColumn { line: 2, column: 1 })
+    );
+  }
+
+  #[test]
+  fn interns_slice_without_copying() {
+    let interner = Interner::new();
+    let line = interner.intern("let crab = \"🦀\";");
+    let stored = interner.stats().stored_bytes;
+    let crab = line.slice(4..8).unwrap();
+    let emoji = line.slice(12..16).unwrap();
+
+    assert_eq!(&*crab.get_ref(), "crab");
+    assert_eq!(crab, interner.intern("crab"));
+    assert_eq!(emoji, interner.intern("🦀"));
+    assert_eq!(line.slice(0..0).unwrap(), interner.intern(""));
+    assert_eq!(interner.stats().stored_bytes, stored);
+    assert!(matches!(
+      line.slice(4..40),
+      Err(SliceError::OutOfBounds {
+        start: 4,
+        end: 40,
+        len: 18
+      })
+    ));
+    assert!(matches!(
+      line.slice(13..16),
+      Err(SliceError::NotCharBoundary { index: 13 })
+    ));
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let header = folded.intern("Content-Type");
+    let content = header.slice(0..7).unwrap();
+    assert_eq!(content, folded.intern("content"));
+    assert_eq!(&*content.get_ref(), "Content");
+  }
+
+  #[test]
+  fn interns_map_to_computed_strings() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let name = interner.intern("Point");
+    let held = name.get_ref();
+
+    let getter = name.map(|s| format!("get{s}"));
+    assert_eq!(&*getter.get_ref(), "getPoint");
+    assert_eq!(getter, interner.intern("GETPOINT"));
+    assert_eq!(name.map(str::to_owned), name);
+    assert_eq!(&*held, "Point");
+
+    // the closure can use the interner without waiting on itself
+    let nested =
+      name.map(|s| interner.intern(s).map(|s| format!("{s}s")).resolve());
+    assert_eq!(&*nested.get_ref(), "Points");
+  }
+
+  #[test]
+  fn interns_answer_string_queries() {
+    let interner = Interner::new();
+    let path = interner.intern("src/main.rs");
+
+    assert!(path.starts_with("src/"));
+    assert!(!path.starts_with("main"));
+    assert!(path.ends_with(".rs"));
+    assert!(!path.ends_with("src"));
+    assert!(path.contains("main"));
+    assert!(!path.contains("lib"));
+    assert_eq!(path.find("/"), Some(3));
+    assert_eq!(path.find("lib"), None);
+    assert_eq!(interner.stats().outstanding_refs, 0);
+  }
+
+  #[cfg(feature = "aho-corasick")]
+  #[test]
+  fn interner_builds_matchers() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let words = interner.intern_all(["Let", "letter", "", "X"]);
+    let matcher = interner.build_matcher();
+    let late = interner.intern("late");
+
+    assert_eq!(matcher.len(), 3);
+    let hits = matcher
+      .find_iter("let x = LETTERS; late")
+      .collect::<Vec<_>>();
+    assert_eq!(
+      hits,
+      [
+        (0..3, words[0].clone()),
+        (4..5, words[3].clone()),
+        (8..14, words[1].clone()),
+      ]
+    );
+    drop((matcher, hits, words, late));
+    interner.collect();
+    assert!(interner.build_matcher().is_empty());
+  }
+
+  #[cfg(feature = "regex")]
+  #[test]
+  fn interner_greps_strings() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let words = interner.intern_all(["getName", "name", "SetName", "getter"]);
+    drop(interner.intern("getGone"));
+    interner.collect();
+
+    let grep = |re| interner.grep(&Regex::new(re).unwrap());
+    assert_eq!(grep("^get"), [words[0].clone(), words[3].clone()]);
+    assert_eq!(grep("Name$"), [words[0].clone(), words[2].clone()]);
+    assert_eq!(grep("^name$"), [words[1].clone()]);
+    // strings aren't run together
+    assert!(grep("megetter").is_empty());
+    assert!(grep("Gone").is_empty());
+  }
+
+  #[test]
+  fn interner_finds_strings_with_prefix() {
+    for backend in [IndexBackend::Trie, IndexBackend::Hash] {
+      let interner = InternerBuilder::new()
+        .index(backend)
+        .case_folding(CaseFolding::Ascii)
+        .build();
+      let _words = interner.intern_all(["println", "print", "parse", "printf"]);
+
+      let found = interner.find_with_prefix("PRINT");
+      assert_eq!(
+        found.iter().map(Intern::resolve).collect::<Vec<_>>(),
+        ["print", "printf", "println"]
+      );
+      assert_eq!(interner.find_with_prefix("p").len(), 4);
+      assert_eq!(interner.find_with_prefix("").len(), 4);
+      assert!(interner.find_with_prefix("printer").is_empty());
+    }
+  }
+
+  #[test]
+  fn interner_finds_strings_within_edit_distance() {
+    for backend in [IndexBackend::Trie, IndexBackend::Hash] {
+      let interner = InternerBuilder::new()
+        .index(backend)
+        .case_folding(CaseFolding::Ascii)
+        .build();
+      let _words = interner.intern_all(["length", "lengths", "lent", "height"]);
+
+      let found = interner.fuzzy_search("LENGHT", 2);
+      assert_eq!(
+        found
+          .iter()
+          .map(|(intern, dist)| (intern.resolve(), *dist))
+          .collect::<Vec<_>>(),
+        [
+          ("height".to_string(), 2),
+          ("length".to_string(), 2),
+          ("lengths".to_string(), 2),
+          ("lent".to_string(), 2)
+        ]
+      );
+      let found = interner.fuzzy_search("length", 1);
+      assert_eq!(found.len(), 2);
+      assert_eq!(found[0], (interner.intern("length"), 0));
+      assert_eq!(found[1], (interner.intern("lengths"), 1));
+      assert!(interner.fuzzy_search("width", 1).is_empty());
+    }
+  }
+
+  #[test]
+  fn interner_iterates_in_sorted_order() {
+    for backend in [IndexBackend::Trie, IndexBackend::Hash] {
+      let interner = InternerBuilder::new()
+        .index(backend)
+        .case_folding(CaseFolding::Ascii)
+        .build();
+      let _words = interner.intern_all(["pear", "apple", "fig", "peach", "a"]);
+
+      let resolved = |interns: vec::IntoIter<Intern>| {
+        interns.map(|intern| intern.resolve()).collect::<Vec<_>>()
+      };
+      assert_eq!(
+        resolved(interner.iter_sorted()),
+        ["a", "apple", "fig", "peach", "pear"]
+      );
+      assert_eq!(resolved(interner.range("b".."PEAR")), ["fig", "peach"]);
+      assert_eq!(
+        resolved(interner.range("b"..="PEAR")),
+        ["fig", "peach", "pear"]
+      );
+      assert_eq!(resolved(interner.range(.."apple")), ["a"]);
+      assert_eq!(resolved(interner.range("peaches"..)), ["pear"]);
+      assert!(resolved(interner.range("q"..)).is_empty());
+    }
+  }
+
+  #[test]
+  fn interner_finds_longest_prefix() {
+    for backend in [IndexBackend::Trie, IndexBackend::Hash] {
+      let interner = InternerBuilder::new().index(backend).build();
+      let _ops = interner.intern_all(["=", "==", "====", "=>"]);
+
+      let (op, len) = interner.longest_prefix("=== x").unwrap();
+      assert_eq!(&*op.get_ref(), "==");
+      assert_eq!(len, 2);
+      assert_eq!(interner.longest_prefix("=>").unwrap().1, 2);
+      assert_eq!(interner.longest_prefix("=").unwrap().1, 1);
+      assert_eq!(interner.longest_prefix("x"), None);
+    }
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let _keywords = folded.intern_all(["let", "letter"]);
+    let (keyword, len) = folded.longest_prefix("LET x").unwrap();
+    assert_eq!(keyword, folded.intern("let"));
+    assert_eq!(len, 3);
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn interner_with_capacity() {
+    let interner = Interner::with_capacity(100_000, 1000);
+    let long = "x".repeat(50_000);
+    let interned = interner.intern(&long);
+    interner.reserve(0, 0);
+
+    assert_eq!(&*interned.get_ref(), long);
+  }
+
+  #[test]
+  fn interner_allows_interning_while_holding_refs() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let hello_ref = hello.get_ref();
+    let world = interner.intern("world");
+
+    assert_eq!(&*hello_ref, "hello");
+    assert_eq!(&*world.get_ref(), "world");
+  }
+
+  #[test]
+  fn interner_collects_unused_strings() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let ell = interner.intern("ell");
+    let world = interner.intern("world");
+    let mut set = HashMap::new();
+    set.insert(world.clone(), ());
+    drop(interner.intern("goodbye"));
+
+    assert_eq!(interner.collect(), 7);
+    assert_eq!(&*world.get_ref(), "world");
+    assert!(set.contains_key(&interner.intern("world")));
+    assert_eq!(interner.stats().stored_bytes, 10);
+
+    drop(hello);
+    assert_eq!(interner.collect(), 2);
+    assert_eq!(&*ell.get_ref(), "ell");
+    assert_eq!(interner.intern("hello").cmp(&ell), Ordering::Greater);
+  }
+
+  #[test]
+  fn interner_defragments_without_freeing() {
+    let interner = Interner::new();
+    let cat = interner.intern("cat");
+    let symbol = interner.intern_symbol("nate");
+    let word = interner.intern("concatenate");
+    drop(interner.intern("dog"));
+    assert_eq!(interner.stats().stored_bytes, 21);
+
+    let cat_ref = cat.get_ref();
+    assert_eq!(interner.defragment(), 0);
+    drop(cat_ref);
+
+    assert_eq!(interner.defragment(), 7);
+    assert_eq!(interner.stats().stored_bytes, 14);
+    assert_eq!(interner.stats().entries, 4);
+    assert_eq!(&*cat.get_ref(), "cat");
+    assert_eq!(&*interner.resolve(symbol), "nate");
+    assert_eq!(interner.intern("concatenate"), word);
+    assert_eq!(interner.defragment(), 0);
+  }
+
+  #[test]
+  fn interner_does_not_collect_while_refs_are_held() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    drop(interner.intern("goodbye"));
+    let hello_ref = hello.get_ref();
+
+    assert_eq!(interner.collect(), 0);
+    assert_eq!(&*hello_ref, "hello");
+
+    drop(hello_ref);
+    assert_eq!(interner.collect(), 7);
+  }
+
+  // contends for the lock, which single-threaded builds panic on instead
+  #[cfg(not(feature = "single-threaded"))]
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn interner_resolves_while_interning_across_threads() {
+    let interner = Interner::new();
+    let symbols = (0..100)
+      .map(|i| interner.intern_symbol(format!("symbol{i}")))
+      .collect::<Vec<_>>();
+
+    thread::scope(|scope| {
+      for _ in 0..4 {
+        scope.spawn(|| {
+          for _ in 0..100 {
+            for (i, &symbol) in symbols.iter().enumerate() {
+              assert_eq!(*interner.resolve(symbol), format!("symbol{i}"));
+            }
+          }
+        });
+      }
+
+      for i in 0..100 {
+        drop(interner.intern(format!("other{i}")));
+        interner.collect();
+      }
+    });
+
+    assert_eq!(interner.stats().outstanding_refs, 0);
+  }
+
+  #[cfg(feature = "dashmap")]
+  #[test]
+  fn interner_serves_hits_without_locking() {
+    let interner = InternerBuilder::new().lock_free_hits(true).build();
+    let hello = interner.intern("hello");
+
+    let lock = interner.internal.write();
+    assert_eq!(hello, interner.intern("hello"));
+    drop(lock);
+    assert_eq!(interner.stats().hits, 1);
+
+    drop(hello);
+    assert_eq!(interner.collect(), 5);
+    assert!(interner.hit_cache.as_ref().unwrap().is_empty());
+    assert_eq!(&*interner.intern("hello").get_ref(), "hello");
+    assert_eq!(interner.stats().misses, 2);
+  }
+
+  #[cfg(all(feature = "dashmap", not(feature = "single-threaded")))]
+  #[test]
+  fn interner_serves_hits_while_collecting_across_threads() {
+    let interner = InternerBuilder::new().lock_free_hits(true).build();
+
+    thread::scope(|scope| {
+      for _ in 0..4 {
+        scope.spawn(|| {
+          for _ in 0..100 {
+            for i in 0..20 {
+              let word = interner.intern(format!("word{i}"));
+              assert_eq!(*word.get_ref(), format!("word{i}"));
+            }
+          }
+        });
+      }
+
+      for _ in 0..100 {
+        interner.collect();
+      }
+    });
+
+    interner.collect();
+    assert_eq!(interner.stats().stored_bytes, 0);
+  }
+
+  #[test]
+  fn interner_reads_many_strings_in_one_scope() {
+    let interner = Interner::new();
+    let words = (0..10)
+      .map(|i| interner.intern(format!("word{i}")))
+      .collect::<Vec<_>>();
+    drop(interner.intern("garbage"));
+
+    let (texts, collected) = interner.scope(|scope| {
+      assert_eq!(interner.outstanding_refs(), 1);
+      let texts = words.iter().map(|word| scope.get(word)).collect::<Vec<_>>();
+      // strings can't move while the scope is open
+      (texts.join(","), interner.collect())
+    });
+
+    assert_eq!(
+      texts,
+      (0..10)
+        .map(|i| format!("word{i}"))
+        .collect::<Vec<_>>()
+        .join(",")
+    );
+    assert_eq!(collected, 0);
+    assert_eq!(interner.outstanding_refs(), 0);
+    assert_eq!(interner.collect(), 7);
+  }
+
+  #[test]
+  fn interner_resolves_many_strings_at_once() {
+    let interner = Interner::new();
+    let words = ["a", "b", "a", "c"].map(|word| interner.intern(word));
+
+    let (joined, refs) = interner.resolve_many(&words, |strs| {
+      (strs.concat(), interner.outstanding_refs())
+    });
+
+    assert_eq!(joined, "abac");
+    assert_eq!(refs, 1);
+    assert_eq!(interner.resolve_many(&[], |strs| strs.len()), 0);
+  }
+
+  #[test]
+  #[should_panic = "intern does not belong to this interner"]
+  fn interner_scope_rejects_foreign_interns() {
+    let interner = Interner::new();
+    let other = Interner::new().intern("hello");
+
+    interner.scope(|scope| scope.get(&other).len());
+  }
+
+  #[test]
+  fn interns_format_while_the_interner_is_busy() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let held = hello.get_ref();
+
+    // the lock is held while the arguments are formatted
+    let greeting = interner.intern_fmt(format_args!("{hello} world"));
+    let debug = format!("{hello:?}");
+
+    assert_eq!(greeting, interner.intern("hello world"));
+    assert_eq!(debug, r#"Intern { id: 0, text: "hello" }"#);
+    assert_eq!(interner.outstanding_refs(), 1);
+    #[cfg(feature = "diagnostics")]
+    assert_eq!(interner.diagnostics().readers.len(), 1);
+    drop(held);
+
+    let panicked = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+      interner.scope(|_| panic!("{hello} went wrong"))
+    }));
+    let message = panicked.unwrap_err();
+    assert_eq!(
+      message.downcast_ref::<String>().unwrap(),
+      "hello went wrong"
+    );
+    assert_eq!(interner.outstanding_refs(), 0);
+  }
+
+  #[test]
+  fn interner_gives_up_instead_of_waiting() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+
+    let lock = interner.internal.write();
+    assert!(matches!(
+      interner.try_intern_nonblocking("hello"),
+      Err(InternError::WouldBlock)
+    ));
+    let timeout = Duration::from_millis(10);
+    assert!(matches!(
+      interner.intern_timeout("world", timeout),
+      Err(InternError::TimedOut { timeout: t }) if t == timeout
+    ));
+    drop(lock);
+
+    assert_eq!(interner.try_intern_nonblocking("hello").unwrap(), hello);
+    assert_eq!(interner.intern_timeout("hello", timeout).unwrap(), hello);
+  }
+
+  #[test]
+  #[cfg(all(feature = "diagnostics", not(feature = "single-threaded")))]
+  fn interner_reports_who_is_blocking() {
+    let interner = InternerBuilder::new()
+      .diagnostics_threshold(Duration::from_millis(10))
+      .build();
+    let hello = interner.intern("hello");
+
+    let reading = hello.get_ref();
+    let line = line!() - 1;
+    let locked = std::sync::Barrier::new(2);
+    let unlock = std::sync::Barrier::new(2);
+    thread::scope(|scope| {
+      let writer = scope.spawn(|| {
+        let lock = interner.lock();
+        locked.wait();
+        unlock.wait();
+        drop(lock);
+      });
+      locked.wait();
+
+      let report = interner.diagnostics();
+      assert_eq!(report.writer.unwrap().thread, writer.thread().id());
+      assert_eq!(report.readers.len(), 1);
+      assert_eq!(report.readers[0].thread, thread::current().id());
+      assert_eq!(report.readers[0].location.line(), line);
+      unlock.wait();
+    });
+
+    // waits past the threshold, logging the report, and carries on
+    let lock = interner.lock();
+    let interning = thread::spawn({
+      let interner = Arc::clone(&interner);
+      move || interner.intern("world")
+    });
+    thread::sleep(Duration::from_millis(50));
+    drop(lock);
+    assert_eq!(interning.join().unwrap(), interner.intern("world"));
+
+    drop(reading);
+    let report = interner.diagnostics();
+    assert!(report.writer.is_none() && report.readers.is_empty());
+  }
 
+  // contends for the lock, which single-threaded builds panic on instead
+  #[cfg(not(feature = "single-threaded"))]
   #[test]
-  fn interner() {
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn interner_keeps_readers_and_collections_apart() {
     let interner = Interner::new();
-    let hello1 = interner.intern("hello");
-    let goodbye = interner.intern("goodbye");
-    let hello2 = interner.intern("hello");
+    let words = (0..50)
+      .map(|i| interner.intern(format!("word{i}")))
+      .collect::<Vec<_>>();
+    let symbols = (0..50)
+      .map(|i| interner.intern_symbol(format!("symbol{i}")))
+      .collect::<Vec<_>>();
 
-    assert_eq!(&*hello1.get_ref(), "hello");
-    assert_eq!(&*hello2.get_ref(), "hello");
-    assert_eq!(&*goodbye.get_ref(), "goodbye");
+    thread::scope(|scope| {
+      for _ in 0..4 {
+        scope.spawn(|| {
+          for round in 0..50 {
+            let held = words
+              .iter()
+              .map(|word| word.clone().into_str())
+              .collect::<Vec<_>>();
+            for (i, word) in words.iter().enumerate() {
+              assert!(word.with_str(|s| s == format!("word{i}")));
+              assert_eq!(&*held[i], format!("word{i}"));
+            }
+            for (i, &symbol) in symbols.iter().enumerate() {
+              assert_eq!(*interner.resolve(symbol), format!("symbol{i}"));
+            }
+            assert!(interner.iter().count() >= 100);
+            drop(interner.intern(format!("garbage{round}")));
+          }
+        });
+      }
 
-    assert_eq!(*hello1.get_ref(), *hello2.get_ref());
-    assert_ne!(*hello1.get_ref(), *goodbye.get_ref());
+      for _ in 0..200 {
+        interner.collect();
+        interner.shrink_to_fit();
+      }
+    });
 
-    assert!(ptr::addr_eq(&*hello1.get_ref(), &*hello2.get_ref()));
-    assert!(!ptr::addr_eq(&*hello1.get_ref(), &*goodbye.get_ref()));
+    assert_eq!(interner.outstanding_refs(), 0);
+    interner.collect();
+    assert_eq!(interner.get("garbage0"), None);
+    for (i, word) in words.iter().enumerate() {
+      assert_eq!(*word.get_ref(), format!("word{i}"));
+    }
+  }
+
+  // contends for the lock, which single-threaded builds panic on instead
+  #[cfg(not(feature = "single-threaded"))]
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn interner_collects_while_reading_across_threads() {
+    let interner = Interner::new();
+    let words = (0..100)
+      .map(|i| interner.intern(format!("word{i}")))
+      .collect::<Vec<_>>();
+
+    thread::scope(|scope| {
+      for _ in 0..4 {
+        scope.spawn(|| {
+          for _ in 0..100 {
+            for (i, word) in words.iter().enumerate() {
+              assert_eq!(*word.get_ref(), format!("word{i}"));
+            }
+          }
+        });
+      }
+
+      for i in 0..100 {
+        drop(interner.intern(format!("garbage{i}")));
+        interner.collect();
+      }
+    });
+
+    interner.collect();
+    assert_eq!(interner.collect(), 0);
+    assert_eq!(interner.get("garbage99"), None);
+  }
+
+  #[test]
+  fn interner_shrinks_to_fit() {
+    let interner = Interner::with_capacity(1 << 16, 100);
+    let hello = interner.intern("hello");
+    let hello_ref = hello.get_ref();
+    let allocated = interner.stats().allocated_bytes;
+
+    assert_eq!(interner.shrink_to_fit(), 0);
+
+    drop(hello_ref);
+    assert_eq!(interner.shrink_to_fit(), allocated - 5);
+    assert_eq!(interner.stats().allocated_bytes, 5);
+    assert_eq!(&*hello.get_ref(), "hello");
+
+    let world = interner.intern("world");
+    assert_eq!(&*world.get_ref(), "world");
+    assert_eq!(interner.intern("hello"), hello);
+  }
+
+  #[test]
+  fn interner_freezes() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let hello = interner.intern("Hello");
+    interner.intern("ell");
+    interner.intern("world");
+
+    let frozen = Arc::clone(&interner).freeze();
+    let frozen_hello = frozen.get("HELLO").unwrap();
+
+    assert_eq!(frozen.len(), 3);
+    assert_eq!(frozen.resolve(frozen_hello), "hello");
+    assert_eq!(frozen.get(&*hello.get_ref()), Some(frozen_hello));
+    assert_eq!(frozen.get("hell"), None);
+
+    thread::scope(|scope| {
+      scope.spawn(|| assert_eq!(frozen.resolve(frozen_hello), "hello"));
+    });
+  }
+
+  #[test]
+  fn interner_saves_and_loads() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let hello = interner.intern("hello");
+    let world = interner.intern("World");
+    let _ell = interner.intern("ell");
+    drop(interner.intern("goodbye"));
+    interner.collect();
+
+    let mut saved = Vec::new();
+    interner.save_to(&mut saved).unwrap();
+    let reloaded = Interner::load_from(&saved[..]).unwrap();
+
+    let stats = reloaded.stats();
+    assert_eq!((stats.entries, stats.stored_bytes), (3, 10));
+
+    let reloaded_hello = reloaded.get("HELLO").unwrap();
+    let reloaded_world = reloaded.get("world").unwrap();
+    assert_eq!(&*reloaded_hello.get_ref(), "hello");
+    assert_eq!(&*reloaded.get("ell").unwrap().get_ref(), "ell");
+    assert_eq!(reloaded.get("goodbye"), None);
+    assert_eq!(hello.cmp(&world), reloaded_hello.cmp(&reloaded_world));
+    assert!(reloaded.intern("new") > reloaded_world);
+
+    saved[0] = b'X';
+    assert!(Interner::load_from(&saved[..]).is_err());
+  }
+
+  #[test]
+  fn interner_looks_up_symbol_ids() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    drop(interner.intern("goodbye"));
+    let ell = interner.intern("ell");
+
+    assert_eq!((hello.id(), ell.id()), (SymbolId(0), SymbolId(2)));
+    assert_eq!(interner.from_id(SymbolId(1)).unwrap().resolve(), "goodbye");
+
+    interner.collect();
+    assert_eq!(interner.from_id(SymbolId(0)), Some(hello.clone()));
+    assert_eq!(interner.from_id(SymbolId(1)), None);
+    assert_eq!(interner.from_id(SymbolId(2)), Some(ell));
+    assert_eq!(interner.intern("goodbye").id(), SymbolId(3));
+
+    let mut saved = Vec::new();
+    interner.save_to(&mut saved).unwrap();
+    let reloaded = Interner::load_from(&saved[..]).unwrap();
+    assert_eq!(reloaded.from_id(SymbolId(0)).unwrap().resolve(), "hello");
+    assert_eq!(reloaded.from_id(SymbolId(1)), None);
+    assert_eq!(reloaded.intern("new").id(), SymbolId(4));
+  }
+
+  #[test]
+  fn interner_resolves_symbols() {
+    let interner = Interner::new();
+    let hello = interner.intern_symbol("hello");
+    let world = interner.intern("world");
+    let world_symbol = world.symbol();
+    drop(world);
+
+    assert_eq!(interner.intern_symbol("hello"), hello);
+    assert_eq!(interner.intern("hello").symbol(), hello);
+    assert_eq!(world_symbol.id(), SymbolId(1));
+
+    // symbols keep their strings from being collected
+    assert_eq!(interner.collect(), 0);
+    assert_eq!(&*interner.resolve(hello), "hello");
+    assert_eq!(&*interner.resolve(world_symbol), "world");
+    assert_eq!(interner.from_id(hello.id()), interner.get("hello"));
+  }
+
+  #[test]
+  #[should_panic = "symbol does not belong to this interner"]
+  fn interner_rejects_foreign_symbols() {
+    let interner = Interner::new();
+    let symbol = Interner::new().intern_symbol("hello");
+
+    interner.resolve(symbol);
+  }
+
+  #[test]
+  fn weak_interns_do_not_keep_strings_alive() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let weak = hello.downgrade();
+
+    assert_eq!(weak.upgrade(), Some(hello.clone()));
+    assert_eq!(interner.collect(), 0);
+
+    drop(hello);
+    let upgraded = weak.upgrade().unwrap();
+    assert_eq!(&*upgraded.get_ref(), "hello");
+
+    drop(upgraded);
+    assert_eq!(interner.collect(), 5);
+    assert_eq!(weak.upgrade(), None);
+
+    let world = interner.intern("world").downgrade();
+    drop(interner);
+    assert_eq!(world.upgrade(), None);
+  }
+
+  #[test]
+  fn interner_adopts_interns_from_other_interners() {
+    let first = Interner::new();
+    let second = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let hello = first.intern("Hello");
+    let adopted = second.adopt(&hello);
+
+    assert_eq!(adopted, second.intern("HELLO"));
+    assert_eq!(second.adopt(&adopted), adopted);
+    assert_eq!(first.adopt(&hello), hello);
+    assert_eq!(first.adopt(&adopted), first.intern("hello"));
+    assert_eq!(second.stats().entries, 1);
+  }
+
+  #[test]
+  fn child_interner_falls_back_to_parent() {
+    let parent = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let fn_keyword = parent.intern("fn");
+    let let_keyword = parent.intern("let");
+
+    let child = Interner::new_child(&parent);
+    let main = child.intern("main");
+    let late = child.intern("late");
+    parent.intern("late");
+
+    assert!(Arc::ptr_eq(child.parent().unwrap(), &parent));
+    assert_eq!(child.intern("FN"), fn_keyword);
+    assert_eq!(child.get("let"), Some(let_keyword.clone()));
+    assert_eq!(child.intern_static("fn"), fn_keyword);
+    assert_eq!(
+      child.intern_all(["LET", "main"]),
+      [let_keyword, main.clone()]
+    );
+    assert_eq!(child.intern("late"), late);
+    assert_eq!(parent.get("main"), None);
+
+    let grandchild = Interner::new_child(&child);
+    assert_eq!(grandchild.intern("fn"), fn_keyword);
+    assert_eq!(grandchild.get("Main"), Some(main));
+    assert_eq!(grandchild.stats().entries, 0);
+
+    assert_eq!(child.stats().entries, 2);
+    let stored = parent.stats().stored_bytes;
+    drop((child, grandchild, late));
+    assert_eq!(parent.stats().stored_bytes, stored);
+    assert_eq!(parent.collect(), 7);
+  }
+
+  crate::keywords! {
+    const KEYWORDS;
+    const KW_FN: SymbolId = "fn";
+    const KW_LET: SymbolId = "let";
+    const KW_MATCH: SymbolId = "match";
+  }
+
+  #[test]
+  fn keywords_get_dense_ids() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .with_keywords(KEYWORDS)
+      .build();
+
+    assert_eq!(interner.stats().entries, 3);
+    assert_eq!(interner.collect(), 0);
+    assert_eq!(interner.intern("Match").id(), KW_MATCH);
+    assert_eq!(interner.intern_symbol("fn").id(), KW_FN);
+    assert_eq!(interner.intern("main").id(), SymbolId(3));
+    let keyword =
+      |s| matches!(interner.intern(s).id(), KW_FN | KW_LET | KW_MATCH);
+    assert!(keyword("LET") && !keyword("main"));
+  }
+
+  #[test]
+  #[should_panic = "keyword \"FN\" appears twice"]
+  fn keywords_must_be_distinct() {
+    InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .with_keywords(&["fn", "let", "FN"])
+      .build();
+  }
+
+  mod symbols {
+    crate::symbols! {
+      Kw { Fn: "fn", Let: "let", Match: "match" },
+      Sym { Fn: "Fn", Len: "len" },
+      Empty {},
+    }
+  }
+
+  #[test]
+  fn symbols_are_grouped_in_order() {
+    use symbols::{Empty, Kw, Sym};
+
+    let interner = symbols::interner();
+    let kind = |s| match interner.intern(s).id() {
+      Kw::Fn | Kw::Let => "item",
+      id if Kw::contains(id) => "keyword",
+      id if Sym::contains(id) => "symbol",
+      _ => "identifier",
+    };
+
+    assert_eq!(symbols::SYMBOLS, ["fn", "let", "match", "Fn", "len"]);
+    assert_eq!(Sym::ALL, ["Fn", "len"]);
+    assert_eq!((Kw::Match, Sym::Fn), (SymbolId(2), SymbolId(3)));
+    assert_eq!(interner.intern_symbol("len").id(), Sym::Len);
+    assert_eq!(
+      ["let", "match", "Fn", "main"].map(kind),
+      ["item", "keyword", "symbol", "identifier"]
+    );
+    assert!(Empty::ALL.is_empty() && !Empty::contains(Sym::Len));
+  }
+
+  #[test]
+  fn interns_format_with_flags() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+
+    assert_eq!(format!("[{hello:>7}]"), "[  hello]");
+    assert_eq!(format!("[{hello:-<7.4}]"), "[hell---]");
+    assert_eq!(format!("[{:^9}]", hello.get_ref()), "[  hello  ]");
+    assert_eq!(format!("[{:>7}]", hello.clone().into_str()), "[  hello]");
+    assert_eq!(format!("[{:>7}]", hello.clone().detach()), "[  hello]");
+    assert_eq!(format!("[{:>3}]", interner.intern_compact("x")), "[  x]");
+
+    let display = hello.display();
+    assert_eq!(format!("{display}"), "hello");
+    assert_eq!(format!("{display:?}"), format!("{:?}", "hello"));
+    assert_eq!(format!("{display:#?}"), format!("{:#?}", "hello"));
+    assert_eq!(format!("[{display:>3.2}]"), "[ he]");
+    assert_eq!(format!("{:?}", [display, display]), r#"["hello", "hello"]"#);
+  }
+
+  #[test]
+  fn interns_convert_to_std_strings() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let (owned, cow) = (String::from("hello"), Cow::Borrowed("hello"));
+    let (other, other_cow) = (String::from("hell"), Cow::from("hello!"));
+
+    assert_eq!(hello, owned);
+    assert_eq!(owned, hello);
+    assert_eq!(hello, cow);
+    assert_eq!(cow, hello);
+    assert_ne!(hello, other);
+    assert_ne!(other_cow, hello);
+    assert_eq!(String::from(&hello), "hello");
+    assert_eq!(String::from(hello.clone()), "hello");
+
+    let hello = hello.into_str();
+    assert!(matches!(Cow::from(&hello), Cow::Borrowed("hello")));
+  }
+
+  #[test]
+  fn interner_interns_lines_from_readers() {
+    let interner = Interner::new();
+    let input = b"GET /\r\n\nGET /\n\xff\nGET /about";
+
+    let mut lines = interner.intern_lines(&input[..]);
+    let get = lines.next().unwrap().unwrap();
+    assert_eq!(get, "GET /");
+    assert_eq!(lines.next().unwrap().unwrap(), "");
+    assert_eq!(lines.next().unwrap().unwrap(), get);
+    let invalid = lines.next().unwrap().unwrap_err();
+    assert_eq!(invalid.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(lines.next().unwrap().unwrap(), "GET /about");
+    assert!(lines.next().is_none());
+    assert_eq!(interner.stats().entries, 3);
+
+    let fields = interner
+      .intern_split(&b"a\r,b,a,"[..], b',')
+      .collect::<io::Result<Vec<_>>>()
+      .unwrap();
+    assert_eq!(fields, interner.intern_all(["a\r", "b", "a"]));
   }
 
+  #[cfg(feature = "csv")]
   #[test]
-  #[should_panic]
-  fn interner_errors_while_holding_refs() {
+  fn interner_interns_csv_columns() {
+    let interner = Interner::new();
+    let tsv = "id\tname\n1\tada\n2\t\"grace\thopper\"\n3\tada\textra\n";
+
+    let names = interner.intern_column(tsv.as_bytes(), 1, b'\t').unwrap();
+    assert_eq!(names.len(), 4);
+    assert_eq!(names[1], names[3]);
+    assert_eq!(names[2], "grace\thopper");
+    assert_eq!(interner.stats().entries, 3);
+
+    let error = interner
+      .intern_column(tsv.as_bytes(), 2, b'\t')
+      .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(error.to_string(), "row 0 has no column 2");
+    let invalid = interner.intern_column(&b"a,\xff"[..], 1, b',');
+    assert_eq!(invalid.unwrap_err().kind(), io::ErrorKind::InvalidData);
+  }
+
+  #[test]
+  fn interner_counts_interns() {
+    let interner = InternerBuilder::new()
+      .count_interns(true)
+      .with_keywords(&["fn"])
+      .build();
+    let words = interner.intern_all("b a b c b a".split(' '));
+    let c = interner.intern_symbol("c");
+    interner.get("a");
+    interner.intern_static("fn");
+
+    let counts = interner
+      .counts()
+      .into_iter()
+      .map(|(intern, count)| (intern.resolve(), count))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      counts,
+      [
+        ("b".into(), 3),
+        ("a".into(), 2),
+        ("c".into(), 2),
+        ("fn".into(), 1)
+      ]
+    );
+    assert_eq!(c, words[3].symbol());
+    assert!(Interner::new().counts().is_empty());
+
+    #[cfg(feature = "dashmap")]
+    {
+      let interner = InternerBuilder::new()
+        .count_interns(true)
+        .lock_free_hits(true)
+        .build();
+      let hello = interner.intern("hello");
+      interner.intern("hello");
+      assert_eq!(interner.counts(), [(hello, 2)]);
+    }
+  }
+
+  #[test]
+  fn dictionaries_warm_start_interners() {
+    let learned = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .count_interns(true)
+      .build();
+    learned.intern_all("If it is to be it is up to me".split(' '));
+
+    let dictionary = learned.export_dictionary(3);
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .with_keywords(&["IS"])
+      .with_dictionary(&dictionary)
+      .unwrap()
+      .build();
+    assert_eq!(interner.stats().entries, 3);
+    assert_eq!(interner.intern("it").id(), SymbolId(1));
+    assert_eq!(interner.intern("to").id(), SymbolId(2));
+    assert_eq!(interner.collect(), 0);
+    assert!(interner.counts().is_empty());
+
+    let unsync = crate::unsync::InternerBuilder::new()
+      .with_dictionary(&Interner::new().export_dictionary(1))
+      .unwrap()
+      .build();
+    assert_eq!(unsync.stats().entries, 0);
+    let unranked = Interner::new();
+    unranked.intern_all(["b", "a", "a"]);
+    let dictionary = unranked.export_dictionary(usize::MAX);
+    let interner = InternerBuilder::new()
+      .with_dictionary(&dictionary)
+      .unwrap()
+      .build();
+    assert_eq!(interner.from_id(SymbolId(0)).unwrap(), "b");
+    assert!(InternerBuilder::new().with_dictionary(b"STRNDICT").is_err());
+  }
+
+  #[test]
+  fn interner_reports_refs_leaked_by_exited_threads() {
+    let interner = InternerBuilder::new()
+      .detect_leaked_refs(true)
+      .max_store_bytes(10)
+      .on_store_full(StoreFullPolicy::Evict)
+      .build();
+    let hello = interner.intern("hello");
+    drop(interner.intern("world"));
+
+    let held = hello.get_ref();
+    let moved = thread::scope(|scope| {
+      scope
+        .spawn(|| {
+          std::mem::forget(hello.get_ref());
+          InternStr::from(hello.clone())
+        })
+        .join()
+        .unwrap()
+    });
+    // a reader that outlives its thread counts as leaked until it is
+    // dropped, on whichever thread that happens
+    assert_eq!(interner.leaked_refs(), 2);
+    drop(moved);
+    assert_eq!(interner.leaked_refs(), 1);
+    drop(held);
+    assert_eq!(interner.leaked_refs(), 1);
+
+    assert_eq!(interner.collect(), 0);
+    assert!(matches!(
+      interner.try_intern("other"),
+      Err(InternError::Poisoned { leaked: 1 })
+    ));
+    assert_eq!(Interner::new().leaked_refs(), 0);
+  }
+
+  #[test]
+  fn interns_refuse_refs_past_limit() {
+    let interner = InternerBuilder::new().max_outstanding_refs(2).build();
+    let hello = interner.intern("hello");
+    let world = InternStr::from(interner.intern("world"));
+
+    let hello_ref = hello.try_get_ref().unwrap();
+    assert_eq!(&*hello_ref, "hello");
+    assert_eq!(
+      hello.try_get_ref().unwrap_err(),
+      RefError::TooManyRefs { limit: 2 }
+    );
+    // only try_get_ref is limited
+    assert_eq!(&*hello.get_ref(), "hello");
+
+    let world = world.downgrade();
+    assert_eq!(interner.stats().outstanding_refs, 1);
+    assert_eq!(&*world.try_get_ref().unwrap(), "world");
+    drop(hello_ref);
+
+    interner.moving.store(true, AtomicOrdering::SeqCst);
+    assert_eq!(hello.try_get_ref().unwrap_err(), RefError::Moving);
+    interner.moving.store(false, AtomicOrdering::SeqCst);
+    assert_eq!(interner.stats().outstanding_refs, 0);
+    assert!(Interner::new().intern("unlimited").try_get_ref().is_ok());
+  }
+
+  #[test]
+  fn interner_queues_interns_while_refs_are_held() {
+    let interner = InternerBuilder::new()
+      .max_store_bytes(10)
+      .on_store_full(StoreFullPolicy::Evict)
+      .when_refs_held(RefsHeldPolicy::Queue)
+      .build();
+    let hello = interner.intern("hello");
+    drop(interner.intern("world"));
+
+    let held = hello.get_ref();
+    let Err(InternError::Queued(again)) = interner.intern_or_queue("again")
+    else {
+      panic!("expected the intern to be queued");
+    };
+    let Err(InternError::Queued(other)) = interner.intern_or_queue("other")
+    else {
+      panic!("expected the intern to be queued");
+    };
+    drop(other);
+    assert!(!again.is_ready());
+    assert_eq!(interner.queued.lock().len(), 1);
+    // the ref is on this thread, so waiting for it would never end
+    assert!(matches!(
+      interner.try_intern("other"),
+      Err(InternError::StoreFull { limit: 10 })
+    ));
+
+    // the last ref is dropped on another thread, which interns the string
+    thread::scope(|scope| {
+      scope.spawn(move || drop(held)).join().unwrap();
+    });
+    assert!(interner.queued.lock().is_empty());
+    assert!(again.is_ready());
+    let again = again.wait().unwrap();
+    assert_eq!(&*again.get_ref(), "again");
+    assert_eq!(interner.get("world"), None);
+    assert_eq!(interner.get("other"), None);
+
+    // the store is still full of strings that are in use
+    let held = hello.get_ref();
+    assert!(matches!(
+      interner.intern_or_queue("other"),
+      Err(InternError::Queued(_))
+    ));
+    drop(held);
+    assert!(matches!(
+      interner.try_intern("other"),
+      Err(InternError::StoreFull { limit: 10 })
+    ));
+
+    // interning waits for the string it queued on threads without refs
+    drop(again);
+    let held = hello.get_ref();
+    thread::scope(|scope| {
+      let waiter = scope.spawn(|| interner.try_intern("other"));
+      while interner.readers_gone.waiting() == 0 {
+        thread::yield_now();
+      }
+      assert_eq!(interner.queued.lock().len(), 1);
+      drop(held);
+      assert_eq!(&*waiter.join().unwrap().unwrap().get_ref(), "other");
+    });
+    assert_eq!(interner.get("again"), None);
+  }
+
+  #[test]
+  fn interner_blocks_while_refs_are_held() {
+    let interner = InternerBuilder::new()
+      .max_store_bytes(10)
+      .on_store_full(StoreFullPolicy::Evict)
+      .when_refs_held(RefsHeldPolicy::Block)
+      .build();
+    let hello = interner.intern("hello");
+    drop(interner.intern("world"));
+
+    // the ref is on this thread, so waiting for it would never end
+    let held = hello.get_ref();
+    assert!(matches!(
+      interner.try_intern("again"),
+      Err(InternError::StoreFull { limit: 10 })
+    ));
+
+    thread::scope(|scope| {
+      let waiter = scope.spawn(|| interner.intern("again"));
+      while interner.readers_gone.waiting() == 0 {
+        thread::yield_now();
+      }
+
+      // waiting doesn't hold the lock, so threads with refs of their own
+      // can still intern, and fail rather than wait on themselves
+      let (found, other) = scope
+        .spawn(|| {
+          let _held = hello.get_ref();
+          (interner.intern("hello"), interner.try_intern("other"))
+        })
+        .join()
+        .unwrap();
+      assert_eq!(found, hello);
+      assert!(matches!(other, Err(InternError::StoreFull { limit: 10 })));
+
+      assert!(!waiter.is_finished());
+      drop(held);
+      assert_eq!(&*waiter.join().unwrap().get_ref(), "again");
+    });
+    assert_eq!(interner.get("world"), None);
+
+    let held = hello.get_ref();
+    thread::scope(|scope| {
+      scope.spawn(|| {
+        assert!(matches!(
+          interner.try_intern_nonblocking("other"),
+          Err(InternError::WouldBlock)
+        ));
+        assert!(matches!(
+          interner.intern_timeout("other", Duration::from_millis(10)),
+          Err(InternError::TimedOut { .. })
+        ));
+      });
+    });
+    drop(held);
+    assert_eq!(&*interner.intern("other").get_ref(), "other");
+
+    let interner = InternerBuilder::new()
+      .max_store_bytes(10)
+      .on_store_full(StoreFullPolicy::Evict)
+      .build();
+    let hello = interner.intern("hello");
+    drop(interner.intern("world"));
+    let _held = hello.get_ref();
+    assert!(matches!(
+      interner.try_intern("again"),
+      Err(InternError::StoreFull { limit: 10 })
+    ));
+  }
+
+  #[test]
+  fn interner_deep_clones() {
+    static STATIC: &str = "static";
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .count_interns(true)
+      .build();
+    drop(interner.intern("gone"));
+    let header = interner.intern("Content-Type");
+    let fn_symbol = interner.intern_symbol("fn");
+    let fixed = interner.intern_static(STATIC);
+    interner.intern("content-type");
+    interner.collect();
+
+    let (copy, map) = interner.deep_clone();
+    interner.intern("original");
+    copy.intern("copy");
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.id(SymbolId(0)), None);
+    assert_eq!(map.id(header.id()), Some(header.id()));
+    let copied = map.intern(header.id()).unwrap();
+    assert_eq!(&*copied.get_ref(), "Content-Type");
+    assert_eq!(copy.intern("CONTENT-TYPE"), *copied);
+    assert_eq!(&*copy.resolve(fn_symbol), "fn");
+    assert_eq!(copy.get("original"), None);
+    assert_eq!(interner.get("copy"), None);
+    assert_eq!(copy.counts()[0], (copied.clone(), 3));
+    assert_eq!(interner.counts()[0], (header.clone(), 2));
+    let text = map.intern(fixed.id()).unwrap().get_ref().as_ptr();
+    assert!(ptr::eq(text, STATIC.as_ptr()));
+
+    drop(map);
+    copy.collect();
+    assert_eq!(&*copy.resolve(fn_symbol), "fn");
+    assert_eq!(copy.get("static"), None);
+    assert_eq!(interner.get("static"), Some(fixed));
+  }
+
+  #[test]
+  fn detached_interns_outlive_interner() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let detached = hello.clone().detach();
+
+    assert_eq!(detached, "hello");
+    assert_eq!(detached, hello.clone().detach());
+    assert_eq!(format!("{detached:?}"), r#"DetachedIntern("hello")"#);
+
+    drop(hello);
+    assert_eq!(interner.collect(), 5);
+    drop(interner);
+
+    let sent = thread::spawn(move || detached).join().unwrap();
+    assert_eq!(sent.as_str(), "hello");
+    assert_eq!(Interner::new().intern(&sent).resolve(), "hello");
+  }
+
+  #[test]
+  fn interner_handles_multibyte_text() {
+    let interner = Interner::new();
+    let crab = interner.intern("crabs 🦀 ahoy");
+    let emoji = interner.intern("🦀");
+    let ahoy = interner.intern("ahoy");
+
+    assert_eq!(&*crab.get_ref(), "crabs 🦀 ahoy");
+    assert_eq!(&*emoji.get_ref(), "🦀");
+    assert_eq!(&*ahoy.get_ref(), "ahoy");
+  }
+
+  #[test]
+  fn interns_run_closures_on_their_text() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    drop(interner.intern("garbage"));
+
+    assert_eq!(hello.with_str(str::len), 5);
+    assert!(hello.with_str(|s| s.starts_with("he")));
+    // nothing is left reading, so the garbage can be collected
+    assert_eq!(interner.collect(), 7);
+  }
+
+  #[test]
+  fn interns_compare_text_across_interners() {
+    let interner = Interner::new();
+    let other = Interner::new();
+    let hello = interner.intern("hello");
+
+    assert!(hello.text_eq(&interner.intern("hello")));
+    assert!(!hello.text_eq(&interner.intern("world")));
+    assert_ne!(hello, other.intern("hello"));
+    assert!(hello.text_eq(&other.intern("hello")));
+    assert!(!hello.text_eq(&other.intern("jello")));
+    assert!(!hello.text_eq(&other.intern("hell")));
+  }
+
+  #[test]
+  fn interns_measure_common_affixes() {
+    let interner = Interner::new();
+    let other = Interner::new();
+    let old = interner.intern("let crab = 🦀;");
+    let new = interner.intern("let crab = 🦞;");
+
+    assert_eq!(old.common_prefix_len(&new), 11);
+    assert_eq!(old.common_suffix_len(&new), 1);
+    assert_eq!(old.common_prefix_len(&old), old.len());
+    assert_eq!(old.common_suffix_len(&other.intern("🦀;")), 5);
+    assert_eq!(old.common_prefix_len(&interner.intern("")), 0);
+    assert_eq!(old.common_suffix_len(&interner.intern("x")), 0);
+  }
+
+  #[test]
+  fn intern_strs_borrow_as_strs() {
+    let interner = Interner::new();
+    let mut counts = HashMap::new();
+    for word in ["a", "b", "a"] {
+      *counts.entry(interner.intern(word).into_str()).or_insert(0) += 1;
+    }
+    let hello = interner.intern("hello").into_str();
+    drop(interner.intern("garbage"));
+
+    assert_eq!(counts.get("a"), Some(&2));
+    assert_eq!(counts.get("b"), Some(&1));
+    assert_eq!(&*hello, "hello");
+    assert_eq!(hello.as_ref(), "hello");
+    assert_eq!(hello.intern(), &interner.intern("hello"));
+    assert_eq!(hello, Interner::new().intern("hello").into_str());
+    assert_eq!(interner.collect(), 0);
+
+    // nothing refers to any of the strings any more
+    drop((counts, hello.clone(), hello));
+    assert_eq!(interner.collect(), 14);
+  }
+
+  #[test]
+  fn interns_compare_with_strs() {
     let interner = Interner::new();
     let hello = interner.intern("hello");
-    let _hello_ref = hello.get_ref();
-    interner.intern("hello"); // this should panic
+
+    assert!(hello == "hello");
+    assert!(hello == *"hello");
+    assert!("hello" == hello);
+    assert!(hello != "hell");
+    assert!(hello != "jello");
+    assert!(hello < "help");
+    assert!(hello > "hell");
+    assert!("help" > hello);
+    assert_eq!(hello.partial_cmp("hello"), Some(Ordering::Equal));
+  }
+
+  #[test]
+  fn interns_know_their_length() {
+    let interner = Interner::new();
+    let crab = interner.intern("crabs 🦀");
+    let emoji = interner.intern("🦀");
+    let empty = interner.intern("");
+
+    assert_eq!((crab.len(), crab.char_len()), (10, 7));
+    assert_eq!((emoji.len(), emoji.char_len()), (4, 1));
+    assert!(!crab.is_empty());
+    assert!(empty.is_empty());
+    assert_eq!(empty.char_len(), 0);
+
+    // lengths survive strings moving around
+    drop(interner.intern("garbage"));
+    interner.collect();
+    assert_eq!((emoji.len(), emoji.char_len()), (4, 1));
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn interner_reads_across_threads() {
+    let interner = Interner::new();
+    let words = (0..1000).map(|i| format!("word{i}")).collect::<Vec<_>>();
+
+    let interns = thread::scope(|scope| {
+      scope
+        .spawn(|| words.iter().map(|w| interner.intern(w)).collect::<Vec<_>>())
+        .join()
+        .unwrap()
+    });
+
+    thread::scope(|scope| {
+      for _ in 0..4 {
+        scope.spawn(|| {
+          for (intern, word) in interns.iter().zip(&words) {
+            assert_eq!(&*intern.get_ref(), word);
+          }
+        });
+      }
+    });
+  }
+
+  /// Runs `f` on a loom thread with room for a collection, which needs
+  /// more stack than loom gives its threads by default
+  #[cfg(loom)]
+  fn loom_spawn<T: Send + 'static>(
+    f: impl FnOnce() -> T + Send + 'static,
+  ) -> loom::thread::JoinHandle<T> {
+    loom::thread::Builder::new()
+      .stack_size(1 << 22)
+      .spawn(f)
+      .unwrap()
+  }
+
+  /// Checks that [Intern::get_ref] never reads a string while a collection
+  /// moves it, under every interleaving that loom finds
+  #[cfg(loom)]
+  #[test]
+  fn loom_reads_while_collecting() {
+    loom::model(|| {
+      let interner = Interner::new();
+      let hello = interner.intern("hello");
+      drop(interner.intern("goodbye"));
+
+      let reader = {
+        let hello = hello.clone();
+        loom_spawn(move || assert_eq!(&*hello.get_ref(), "hello"))
+      };
+      let collector = {
+        let interner = Arc::clone(&interner);
+        loom_spawn(move || interner.collect())
+      };
+      reader.join().unwrap();
+      collector.join().unwrap();
+      assert_eq!(&*hello.get_ref(), "hello");
+    });
+  }
+
+  /// Checks that a string dropped and collected on one thread while another
+  /// interns it again is either kept or stored again, but never lost
+  #[cfg(loom)]
+  #[test]
+  fn loom_interns_while_dropping_and_collecting() {
+    loom::model(|| {
+      let interner = Interner::new();
+      let word = interner.intern("word");
+
+      let interning = {
+        let interner = Arc::clone(&interner);
+        loom_spawn(move || interner.intern("word"))
+      };
+      let collector = {
+        let interner = Arc::clone(&interner);
+        loom_spawn(move || {
+          drop(word);
+          interner.collect()
+        })
+      };
+      let word = interning.join().unwrap();
+      collector.join().unwrap();
+
+      assert_eq!(&*word.get_ref(), "word");
+      assert_eq!(interner.get("word"), Some(word));
+    });
   }
 }
@@ -0,0 +1,157 @@
+use crate::sync::lock::Mutex;
+use std::{
+  collections::HashMap,
+  sync::{Arc, Weak},
+  thread::{self, ThreadId},
+};
+
+thread_local! {
+  /// Dropped when the thread exits, which is how [LeakDetector] tells that
+  /// the thread is gone
+  static ALIVE: Arc<()> = Arc::new(());
+}
+
+/// Fewest threads to let pile up before sweeping out the ones that exited
+const MIN_SWEEP: usize = 16;
+
+/// Counts the readers of an interner by the thread that started them, to
+/// find readers that threads which have since exited never dropped
+pub(crate) struct LeakDetector {
+  threads: Mutex<Threads>,
+}
+
+struct Threads {
+  /// Only threads with readers are kept, and threads that have exited are
+  /// swept out now and then, so thread pools that come and go don't make
+  /// this grow for good
+  by_thread: HashMap<ThreadId, Readers>,
+  /// Readers of threads that have exited and been swept out. Thread ids are
+  /// never reused, so a reader whose thread isn't in `by_thread` is one of
+  /// these.
+  orphaned: usize,
+  /// How many threads were left after the last sweep
+  swept: usize,
+}
+
+/// The readers that one thread has started and not yet dropped
+struct Readers {
+  /// Can't be upgraded once the thread has exited
+  alive: Weak<()>,
+  count: usize,
+}
+
+impl LeakDetector {
+  pub(crate) fn new() -> Self {
+    Self {
+      threads: Mutex::new(Threads {
+        by_thread: HashMap::new(),
+        orphaned: 0,
+        swept: 0,
+      }),
+    }
+  }
+
+  /// Records a new reader on the current thread, and returns the thread to
+  /// forget it by
+  pub(crate) fn enter(&self) -> ThreadId {
+    let thread = thread::current().id();
+    let mut threads = self.threads.lock();
+    let readers = threads.by_thread.entry(thread).or_insert_with(|| Readers {
+      // a thread that is already being torn down counts as gone
+      alive: ALIVE.try_with(Arc::downgrade).unwrap_or_default(),
+      count: 0,
+    });
+    readers.count += 1;
+    if threads.by_thread.len() >= (threads.swept * 2).max(MIN_SWEEP) {
+      threads.sweep();
+    }
+    thread
+  }
+
+  /// Forgets a reader started on `thread`, which may be dropped on any
+  /// thread
+  pub(crate) fn leave(&self, thread: ThreadId) {
+    let mut threads = self.threads.lock();
+    match threads.by_thread.get_mut(&thread) {
+      Some(readers) if readers.count > 1 => readers.count -= 1,
+      Some(_) => drop(threads.by_thread.remove(&thread)),
+      None => threads.orphaned -= 1,
+    }
+  }
+
+  /// Whether the current thread has started readers that haven't been
+  /// dropped yet
+  pub(crate) fn here(&self) -> bool {
+    let thread = thread::current().id();
+    self.threads.lock().by_thread.contains_key(&thread)
+  }
+
+  /// Number of readers started by threads that have since exited
+  pub(crate) fn leaked(&self) -> usize {
+    let mut threads = self.threads.lock();
+    threads.sweep();
+    threads.orphaned
+  }
+}
+
+impl Threads {
+  /// Moves the readers of threads that have exited into `orphaned`
+  fn sweep(&mut self) {
+    let orphaned = &mut self.orphaned;
+    self.by_thread.retain(|_, readers| {
+      let alive = readers.alive.strong_count() > 0;
+      if !alive {
+        *orphaned += readers.count;
+      }
+      alive
+    });
+    self.swept = self.by_thread.len();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn leaks_are_found_once_threads_exit() {
+    let leaks = LeakDetector::new();
+
+    assert!(!leaks.here());
+    let here = leaks.enter();
+    assert!(leaks.here());
+    let (kept, _forgotten) = thread::scope(|scope| {
+      scope
+        .spawn(|| (leaks.enter(), leaks.enter()))
+        .join()
+        .unwrap()
+    });
+    assert_eq!(leaks.leaked(), 2);
+
+    // readers can be dropped on a different thread than they started on
+    leaks.leave(kept);
+    assert_eq!(leaks.leaked(), 1);
+    leaks.leave(here);
+    assert!(!leaks.here());
+    assert_eq!(leaks.leaked(), 1);
+    assert!(leaks.threads.lock().by_thread.is_empty());
+  }
+
+  #[test]
+  fn exited_threads_are_swept_out() {
+    let leaks = LeakDetector::new();
+
+    let leaked = (0..100)
+      .map(|_| {
+        thread::scope(|scope| scope.spawn(|| leaks.enter()).join().unwrap())
+      })
+      .collect::<Vec<_>>();
+
+    assert!(leaks.threads.lock().by_thread.len() < MIN_SWEEP);
+    assert_eq!(leaks.leaked(), 100);
+    for thread in leaked {
+      leaks.leave(thread);
+    }
+    assert_eq!(leaks.leaked(), 0);
+  }
+}
@@ -0,0 +1,270 @@
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic;
+#[cfg(loom)]
+pub(crate) use loom_locks::{Mutex, RwLock, RwLockWriteGuard};
+#[cfg(not(any(
+  loom,
+  feature = "single-threaded",
+  all(target_family = "wasm", not(target_feature = "atomics"))
+)))]
+pub(crate) use parking_lot::{Mutex, RwLock, RwLockWriteGuard};
+#[cfg(all(
+  not(loom),
+  any(
+    feature = "single-threaded",
+    all(target_family = "wasm", not(target_feature = "atomics"))
+  )
+))]
+pub(crate) use single_threaded::{Mutex, RwLock, RwLockWriteGuard};
+#[cfg(not(loom))]
+pub(crate) use std::sync::atomic;
+
+/// Locks for checking the interner with loom, built with `--cfg loom`. They
+/// wrap loom's own locks, which loom can pause and resume to try every
+/// order that threads could take them in, along with the atomics that are
+/// swapped in above. Run the checks with
+/// `RUSTFLAGS="--cfg loom" cargo test --release --lib loom`.
+#[cfg(loom)]
+mod loom_locks {
+  use std::time::Duration;
+
+  pub(crate) use loom::sync::RwLockWriteGuard;
+
+  /// A reader-writer lock with the same interface as parking_lot's. Loom
+  /// locks are only poisoned by a panic, which fails the check anyway.
+  pub(crate) struct RwLock<T>(loom::sync::RwLock<T>);
+
+  impl<T> RwLock<T> {
+    pub(crate) fn new(data: T) -> Self {
+      Self(loom::sync::RwLock::new(data))
+    }
+
+    pub(crate) fn read(&self) -> loom::sync::RwLockReadGuard<'_, T> {
+      self.0.read().unwrap()
+    }
+
+    pub(crate) fn write(&self) -> RwLockWriteGuard<'_, T> {
+      self.0.write().unwrap()
+    }
+
+    pub(crate) fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+      self.0.try_write().ok()
+    }
+
+    /// Loom has no clock, so this gives up straight away, which is one of
+    /// the things a real timeout can do
+    pub(crate) fn try_write_for(
+      &self,
+      _timeout: Duration,
+    ) -> Option<RwLockWriteGuard<'_, T>> {
+      self.try_write()
+    }
+  }
+
+  pub(crate) struct Mutex<T>(loom::sync::Mutex<T>);
+
+  impl<T> Mutex<T> {
+    pub(crate) fn new(data: T) -> Self {
+      Self(loom::sync::Mutex::new(data))
+    }
+
+    pub(crate) fn lock(&self) -> loom::sync::MutexGuard<'_, T> {
+      self.0.lock().unwrap()
+    }
+  }
+}
+
+/// Locks for targets with only one thread, such as `wasm32-unknown-unknown`,
+/// where there is nobody to wait for. Rather than parking, taking a lock
+/// that is already held panics, since waiting would never end. They are
+/// still sound if threads do exist, so they can be picked with the
+/// `single-threaded` feature anywhere, in which case a lock that another
+/// thread holds is retried for a little while before giving up.
+#[cfg(any(
+  test,
+  feature = "single-threaded",
+  all(target_family = "wasm", not(target_feature = "atomics"))
+))]
+mod single_threaded {
+  use std::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+  };
+
+  const WRITER: usize = usize::MAX;
+  const ATTEMPTS: usize = 10_000;
+
+  /// Takes a lock with `attempt`, or panics if it stays held
+  fn acquire<G>(mut attempt: impl FnMut() -> Option<G>) -> G {
+    for _ in 0..ATTEMPTS {
+      if let Some(guard) = attempt() {
+        return guard;
+      }
+      thread::yield_now();
+    }
+
+    panic!("interner lock is already held, and waiting for it would deadlock")
+  }
+
+  /// A reader-writer lock that counts its readers in `state`, or holds
+  /// [WRITER] while it is locked for writing
+  pub(crate) struct RwLock<T> {
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+  }
+
+  // SAFETY: `state` hands out either one writer or any number of readers,
+  //         so sharing the lock only shares `T` the way `&T` and `&mut T`
+  //         would, which is what these bounds allow
+  unsafe impl<T: Send> Send for RwLock<T> {}
+  // SAFETY: see above
+  unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+  impl<T> RwLock<T> {
+    pub(crate) fn new(data: T) -> Self {
+      Self {
+        state: AtomicUsize::new(0),
+        data: UnsafeCell::new(data),
+      }
+    }
+
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, T> {
+      acquire(|| self.try_read())
+    }
+
+    pub(crate) fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+      let mut state = self.state.load(Ordering::Relaxed);
+      loop {
+        if state >= WRITER - 1 {
+          return None;
+        }
+
+        match self.state.compare_exchange_weak(
+          state,
+          state + 1,
+          Ordering::Acquire,
+          Ordering::Relaxed,
+        ) {
+          Ok(_) => return Some(RwLockReadGuard { lock: self }),
+          Err(actual) => state = actual,
+        }
+      }
+    }
+
+    pub(crate) fn write(&self) -> RwLockWriteGuard<'_, T> {
+      acquire(|| self.try_write())
+    }
+
+    pub(crate) fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+      self
+        .state
+        .compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+        .ok()
+        .map(|_| RwLockWriteGuard { lock: self })
+    }
+
+    /// There is no other thread that could let go of the lock, so this
+    /// doesn't wait either
+    pub(crate) fn try_write_for(
+      &self,
+      _timeout: Duration,
+    ) -> Option<RwLockWriteGuard<'_, T>> {
+      self.try_write()
+    }
+  }
+
+  pub(crate) struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+  }
+
+  impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+      // SAFETY: there's no writer while we're counted as a reader
+      unsafe { &*self.lock.data.get() }
+    }
+  }
+
+  impl<T> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+      self.lock.state.fetch_sub(1, Ordering::Release);
+    }
+  }
+
+  pub(crate) struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+  }
+
+  impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+      // SAFETY: we're the only writer, and there are no readers
+      unsafe { &*self.lock.data.get() }
+    }
+  }
+
+  impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+      // SAFETY: we're the only writer, and there are no readers
+      unsafe { &mut *self.lock.data.get() }
+    }
+  }
+
+  impl<T> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+      self.lock.state.store(0, Ordering::Release);
+    }
+  }
+
+  /// A mutex, which is a lock that only ever has one writer
+  pub(crate) struct Mutex<T>(RwLock<T>);
+
+  impl<T> Mutex<T> {
+    pub(crate) fn new(data: T) -> Self {
+      Self(RwLock::new(data))
+    }
+
+    pub(crate) fn lock(&self) -> RwLockWriteGuard<'_, T> {
+      self.0.write()
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use super::*;
+
+    #[test]
+    fn single_threaded_locks() {
+      let lock = RwLock::new(1);
+
+      let (first, second) = (lock.read(), lock.read());
+      assert_eq!(*first + *second, 2);
+      assert!(lock.try_write().is_none());
+      drop((first, second));
+
+      let mut written = lock.write();
+      *written += 1;
+      assert!(lock.try_read().is_none());
+      assert!(lock.try_write_for(Duration::from_secs(60)).is_none());
+      drop(written);
+      assert_eq!(*lock.read(), 2);
+
+      let mutex = Mutex::new(vec![1]);
+      mutex.lock().push(2);
+      assert_eq!(*mutex.lock(), [1, 2]);
+    }
+
+    #[test]
+    #[should_panic = "waiting for it would deadlock"]
+    fn single_threaded_locks_panic_instead_of_deadlocking() {
+      let lock = RwLock::new(());
+      let _written = lock.write();
+      lock.read();
+    }
+  }
+}
@@ -0,0 +1,91 @@
+use crate::sync::Intern;
+use aho_corasick::{AhoCorasick, MatchKind};
+use std::{fmt, ops::Range};
+
+/// Finds every interned string in a text of any size in one pass, by way of
+/// an Aho-Corasick automaton built from them all, such as to highlight
+/// keywords or tag dictionary words. Created using
+/// [Interner::build_matcher](crate::sync::Interner::build_matcher).
+///
+/// It holds the strings that were interned when it was built, and keeps
+/// them from being collected. Strings interned later aren't found.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::Interner;
+///
+/// let interner = Interner::new();
+/// let keywords = interner.intern_all(["fn", "let", "letter"]);
+/// let matcher = interner.build_matcher();
+///
+/// let source = "fn f() { let letter; }";
+/// let hits = matcher.find_iter(source).collect::<Vec<_>>();
+///
+/// assert_eq!(hits[0], (0..2, keywords[0].clone()));
+/// assert_eq!(hits[1], (9..12, keywords[1].clone()));
+/// assert_eq!(hits[2], (13..19, keywords[2].clone()));
+/// ```
+pub struct InternMatcher {
+  automaton: AhoCorasick,
+  /// The [Intern] of each pattern, indexed by pattern id
+  interns: Vec<Intern>,
+}
+
+impl InternMatcher {
+  /// Builds a matcher for `patterns`, each being the text that the
+  /// [Intern] alongside it is indexed by. With `ascii_case_insensitive`,
+  /// ASCII letters match regardless of case.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the automaton would be too large to build.
+  pub(crate) fn new<'a>(
+    patterns: impl IntoIterator<Item = (&'a str, Intern)>,
+    ascii_case_insensitive: bool,
+  ) -> Self {
+    let (patterns, interns): (Vec<_>, Vec<_>) = patterns
+      .into_iter()
+      .filter(|(pattern, _)| !pattern.is_empty())
+      .unzip();
+    let automaton = AhoCorasick::builder()
+      .match_kind(MatchKind::LeftmostLongest)
+      .ascii_case_insensitive(ascii_case_insensitive)
+      .build(patterns)
+      .expect("too many strings to build a matcher");
+
+    Self { automaton, interns }
+  }
+
+  /// Iterates over where interned strings occur in `haystack`, from left
+  /// to right, along with their [Intern]s. Where several start at the same
+  /// place, the longest is picked, and matches never overlap. The empty
+  /// string is never matched.
+  pub fn find_iter<'a>(
+    &'a self,
+    haystack: &'a str,
+  ) -> impl Iterator<Item = (Range<usize>, Intern)> + 'a {
+    self
+      .automaton
+      .find_iter(haystack)
+      .map(|hit| (hit.range(), self.interns[hit.pattern().as_usize()].clone()))
+  }
+
+  /// Number of strings that can be found
+  pub fn len(&self) -> usize {
+    self.interns.len()
+  }
+
+  /// Whether there are no strings to find
+  pub fn is_empty(&self) -> bool {
+    self.interns.is_empty()
+  }
+}
+
+impl fmt::Debug for InternMatcher {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("InternMatcher")
+      .field("patterns", &self.interns.len())
+      .finish_non_exhaustive()
+  }
+}
@@ -0,0 +1,112 @@
+use crate::sync::{Intern, Symbol, SymbolId};
+
+/// Translates the [SymbolId]s of an interner that was merged into another
+/// one to their ids in the interner it was merged into, produced by
+/// [Interner::merge](crate::sync::Interner::merge).
+///
+/// The map holds an [Intern] for every merged string, so none of them can be
+/// collected while it is around. Strings that had been handed out as
+/// [Symbol]s are kept for good, like any other symbol.
+#[derive(Clone, Debug, Default)]
+pub struct MergeMap {
+  /// Indexed by old id; strings that had been collected leave a gap
+  interns: Vec<Option<Intern>>,
+}
+
+impl MergeMap {
+  pub(crate) fn new(interns: Vec<Option<Intern>>) -> Self {
+    Self { interns }
+  }
+
+  /// Gets the new id of the string that had the id `old`, if there was one
+  pub fn id(&self, old: SymbolId) -> Option<SymbolId> {
+    self.intern(old).map(Intern::id)
+  }
+
+  /// Gets the new [Intern] of the string that had the id `old`, if there was
+  /// one
+  pub fn intern(&self, old: SymbolId) -> Option<&Intern> {
+    self.interns.get(usize::try_from(old.0).ok()?)?.as_ref()
+  }
+
+  /// Translates a [Symbol] from the merged interner into one that can be
+  /// resolved by the interner it was merged into
+  pub fn symbol(&self, old: Symbol) -> Option<Symbol> {
+    self.intern(old.id()).map(Intern::symbol)
+  }
+
+  /// Iterates over the old id and new [Intern] of every merged string
+  pub fn iter(&self) -> impl Iterator<Item = (SymbolId, &Intern)> {
+    self.interns.iter().enumerate().filter_map(|(id, intern)| {
+      let id = u32::try_from(id).expect("ran out of symbol ids");
+      Some((SymbolId(id), intern.as_ref()?))
+    })
+  }
+
+  /// Number of strings that were merged
+  pub fn len(&self) -> usize {
+    self.interns.iter().flatten().count()
+  }
+
+  /// Whether no strings were merged
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::sync::{Interner, SymbolId};
+
+  #[test]
+  fn interner_merges_other_interner() {
+    let shared = Interner::new();
+    let main = shared.intern("main");
+
+    let worker = Interner::new();
+    let gone = worker.intern("gone");
+    let helper = worker.intern("Helper");
+    worker.intern_symbol("main");
+    let exit = worker.intern_symbol("exit");
+    drop(gone);
+    worker.collect();
+
+    let map = shared.merge(worker);
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.id(SymbolId(0)), None);
+    assert_eq!(map.intern(helper.id()), Some(&shared.intern("Helper")));
+    assert_eq!(map.id(SymbolId(2)), Some(main.id()));
+    assert_eq!(map.id(SymbolId(4)), None);
+    assert_eq!(
+      map
+        .iter()
+        .map(|(old, new)| (old, new.id()))
+        .collect::<Vec<_>>(),
+      [
+        (SymbolId(1), SymbolId(1)),
+        (SymbolId(2), SymbolId(0)),
+        (SymbolId(3), SymbolId(2)),
+      ]
+    );
+
+    // symbols stay valid without the map, but nothing else does
+    let new_exit = map.id(exit.id()).unwrap();
+    drop((map, main));
+    assert_eq!(shared.collect(), 6);
+    assert_eq!(shared.from_id(new_exit).unwrap().resolve(), "exit");
+    assert_eq!(shared.from_id(SymbolId(1)), None);
+  }
+
+  #[test]
+  fn interner_merges_itself() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+
+    let map = interner.merge(interner.clone());
+
+    assert_eq!(map.intern(hello.id()), Some(&hello));
+    assert_eq!(interner.stats().entries, 1);
+    assert!(Interner::new().merge(Interner::new()).is_empty());
+  }
+}
@@ -0,0 +1,145 @@
+use crate::sync::persistence::Layout;
+use memmap2::Mmap;
+use std::{cmp::Ordering, fmt, fs::File, io, path::Path};
+
+/// A read-only interner that serves lookups straight out of a memory-mapped
+/// file written by [Interner::save_to](crate::sync::Interner::save_to).
+/// Nothing is copied onto the heap, so any number of processes can share one
+/// symbol table through the page cache, and opening it is a single pass to
+/// check the file.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::{Interner, MmapInterner};
+///
+/// let path = std::env::temp_dir().join("mmap_interner_doc.interner");
+/// let interner = Interner::new();
+/// interner.intern("hello");
+/// interner.save_to(std::fs::File::create(&path)?)?;
+///
+/// let mapped = MmapInterner::open(&path)?;
+/// let hello = mapped.get("hello").unwrap();
+///
+/// assert_eq!(mapped.resolve(hello), "hello");
+/// assert_eq!(mapped.get("goodbye"), None);
+/// # std::fs::remove_file(&path)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct MmapInterner {
+  map: Mmap,
+  layout: Layout,
+}
+
+/// Represents a single string in an [MmapInterner]. This is just an index,
+/// so it is [Copy]. Handles from different [MmapInterner]s must not be mixed
+/// up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MmapIntern(usize);
+
+impl MmapInterner {
+  /// Maps a saved interner into memory. Fails with
+  /// [io::ErrorKind::InvalidData] if the file isn't a saved interner.
+  ///
+  /// The file must not be modified while it is mapped, since the check made
+  /// here can't see later changes. Saved interners are only ever written
+  /// whole, so replace the file rather than writing over it.
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    let file = File::open(path)?;
+    // SAFETY: see above; the contents are checked before anything reads them
+    let map = unsafe { Mmap::map(&file)? };
+    let layout = Layout::check(&map)?;
+
+    Ok(Self { map, layout })
+  }
+
+  /// Number of distinct strings
+  pub fn len(&self) -> usize {
+    self.layout.len
+  }
+
+  /// Whether there are no strings at all
+  pub fn is_empty(&self) -> bool {
+    self.layout.len == 0
+  }
+
+  /// Finds a string, applying the same case folding and normalization as the
+  /// interner that was saved
+  pub fn get<S: AsRef<str>>(&self, s: S) -> Option<MmapIntern> {
+    let layout = &self.layout;
+    let s = layout
+      .normalization
+      .canonical(layout.case_folding, s.as_ref());
+
+    let (mut low, mut high) = (0, self.layout.len);
+    while low < high {
+      let middle = low + (high - low) / 2;
+      let entry = self.layout.sorted(&self.map, middle);
+      match self.resolve(MmapIntern(entry)).cmp(&s) {
+        Ordering::Less => low = middle + 1,
+        Ordering::Greater => high = middle,
+        Ordering::Equal => return Some(MmapIntern(entry)),
+      }
+    }
+
+    None
+  }
+
+  /// Reads back the string that an [MmapIntern] represents
+  ///
+  /// # Panics
+  ///
+  /// May panic if `intern` came from a different [MmapInterner].
+  pub fn resolve(&self, intern: MmapIntern) -> &str {
+    assert!(intern.0 < self.layout.len, "intern is out of range");
+    let (_, span) = self.layout.entry(&self.map, intern.0);
+
+    // SAFETY: the map was checked when it was opened, which makes sure every
+    //         entry's span is on char boundaries
+    unsafe { self.layout.text(&self.map).get_unchecked(span) }
+  }
+}
+
+impl fmt::Debug for MmapInterner {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("MmapInterner")
+      .field("len", &self.layout.len)
+      .finish_non_exhaustive()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::sync::{CaseFolding, InternerBuilder};
+  use std::{env, fs, process};
+
+  #[test]
+  fn mmap_interner() {
+    let path =
+      env::temp_dir().join(format!("sync_mmap_{}.interner", process::id()));
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    for word in ["hello", "ell", "world", "crabs 🦀"] {
+      interner.intern(word);
+    }
+    interner.save_to(fs::File::create(&path).unwrap()).unwrap();
+
+    let garbage_path = path.with_extension("garbage");
+    fs::write(&garbage_path, b"garbage").unwrap();
+
+    let mapped = MmapInterner::open(&path).unwrap();
+    let garbage = MmapInterner::open(&garbage_path);
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&garbage_path).unwrap();
+
+    assert_eq!(mapped.len(), 4);
+    for word in ["hello", "ell", "world", "crabs 🦀"] {
+      assert_eq!(mapped.resolve(mapped.get(word).unwrap()), word);
+    }
+    assert_eq!(mapped.get("HELLO"), mapped.get("hello"));
+    assert_eq!(mapped.get("hell"), None);
+    assert_eq!(garbage.unwrap_err().kind(), io::ErrorKind::InvalidData);
+  }
+}
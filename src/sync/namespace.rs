@@ -0,0 +1,238 @@
+use crate::sync::{Intern, InternRef, Interner, Symbol};
+use std::{
+  any,
+  cmp::Ordering,
+  fmt,
+  hash::{Hash, Hasher},
+  marker::PhantomData,
+  sync::Arc,
+};
+
+/// A view of an [Interner] that tags every string it interns with the
+/// namespace `N`, so that identifiers, string literals and lifetimes can
+/// share one store, but come out as different types that can't be mixed up
+/// or compared by accident. `N` is only a marker, and is usually an empty
+/// enum.
+///
+/// The same text interned in two namespaces is stored once, and tagging
+/// costs nothing at runtime.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::{Interner, NamespacedInterner};
+///
+/// enum Ident {}
+/// enum Lifetime {}
+///
+/// let interner = Interner::new();
+/// let idents = NamespacedInterner::<Ident>::new(&interner);
+/// let lifetimes = NamespacedInterner::<Lifetime>::new(&interner);
+///
+/// let a = idents.intern("a");
+/// let lifetime = lifetimes.intern("a");
+///
+/// assert_eq!(a, idents.intern("a"));
+/// assert_eq!(a.intern(), lifetime.intern());
+/// assert_eq!(interner.stats().entries, 1);
+/// ```
+///
+/// Tagged interns from different namespaces don't compare:
+///
+/// ```compile_fail
+/// use str_interning::sync::{Interner, NamespacedInterner};
+///
+/// enum Ident {}
+/// enum Lifetime {}
+///
+/// let interner = Interner::new();
+/// let a = NamespacedInterner::<Ident>::new(&interner).intern("a");
+/// let lifetime = NamespacedInterner::<Lifetime>::new(&interner).intern("a");
+///
+/// assert_eq!(a, lifetime);
+/// ```
+pub struct NamespacedInterner<N> {
+  interner: Arc<Interner>,
+  namespace: PhantomData<fn() -> N>,
+}
+
+impl<N> NamespacedInterner<N> {
+  /// Starts tagging strings interned into `interner` with `N`
+  pub fn new(interner: &Arc<Interner>) -> Self {
+    Self {
+      interner: Arc::clone(interner),
+      namespace: PhantomData,
+    }
+  }
+
+  /// Creates an interned string in this namespace, like [Interner::intern]
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn intern<S: AsRef<str>>(&self, s: S) -> TaggedIntern<N> {
+    TaggedIntern::new(self.interner.intern(s))
+  }
+
+  /// Finds a previously interned string, like [Interner::get], whichever
+  /// namespace it was interned in
+  pub fn get<S: AsRef<str>>(&self, s: S) -> Option<TaggedIntern<N>> {
+    self.interner.get(s).map(TaggedIntern::new)
+  }
+
+  /// Tags an [Intern] from the shared interner as being in this namespace,
+  /// or from another interner, after adopting it like [Interner::adopt]
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn tag(&self, intern: &Intern) -> TaggedIntern<N> {
+    TaggedIntern::new(self.interner.adopt(intern))
+  }
+
+  /// The interner that every namespace shares
+  pub fn interner(&self) -> &Arc<Interner> {
+    &self.interner
+  }
+}
+
+impl<N> Clone for NamespacedInterner<N> {
+  fn clone(&self) -> Self {
+    Self::new(&self.interner)
+  }
+}
+
+impl<N> fmt::Debug for NamespacedInterner<N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("NamespacedInterner")
+      .field("namespace", &any::type_name::<N>())
+      .finish_non_exhaustive()
+  }
+}
+
+/// An [Intern] tagged with the namespace `N` by a [NamespacedInterner].
+/// Equality, ordering and hashing are those of the [Intern], but only
+/// between interns in the same namespace.
+pub struct TaggedIntern<N> {
+  intern: Intern,
+  namespace: PhantomData<fn() -> N>,
+}
+
+impl<N> TaggedIntern<N> {
+  fn new(intern: Intern) -> Self {
+    Self {
+      intern,
+      namespace: PhantomData,
+    }
+  }
+
+  /// Borrows the string, like [Intern::get_ref]
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn get_ref(&self) -> InternRef<'_> {
+    self.intern.get_ref()
+  }
+
+  /// Copies the string out, like [Intern::resolve]
+  pub fn resolve(&self) -> String {
+    self.intern.resolve()
+  }
+
+  /// The [Symbol] of the string, which is the same in every namespace
+  pub fn symbol(&self) -> Symbol {
+    self.intern.symbol()
+  }
+
+  /// The untagged [Intern]
+  pub fn intern(&self) -> &Intern {
+    &self.intern
+  }
+
+  /// Takes the tag off, giving back the [Intern]
+  pub fn into_intern(self) -> Intern {
+    self.intern
+  }
+}
+
+impl<N> Clone for TaggedIntern<N> {
+  fn clone(&self) -> Self {
+    Self::new(self.intern.clone())
+  }
+}
+
+impl<N> fmt::Debug for TaggedIntern<N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("TaggedIntern")
+      .field("namespace", &any::type_name::<N>())
+      .field("intern", &self.intern)
+      .finish()
+  }
+}
+
+impl<N> fmt::Display for TaggedIntern<N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Display::fmt(&self.intern, f)
+  }
+}
+
+impl<N> PartialEq for TaggedIntern<N> {
+  fn eq(&self, other: &Self) -> bool {
+    self.intern == other.intern
+  }
+}
+impl<N> Eq for TaggedIntern<N> {}
+
+impl<N> Ord for TaggedIntern<N> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.intern.cmp(&other.intern)
+  }
+}
+
+impl<N> PartialOrd for TaggedIntern<N> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl<N> Hash for TaggedIntern<N> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.intern.hash(state);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::sync::{CaseFolding, InternerBuilder};
+  use std::collections::HashSet;
+
+  enum Ident {}
+  enum Literal {}
+
+  #[test]
+  fn namespaces_share_one_store() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let idents = NamespacedInterner::<Ident>::new(&interner);
+    let literals = NamespacedInterner::<Literal>::new(&interner);
+
+    let x = idents.intern("x");
+    let literal = literals.intern("X");
+    assert_eq!(x, idents.intern("X"));
+    assert_eq!(x.intern(), literal.intern());
+    assert_eq!(x.symbol(), literal.symbol());
+    assert_eq!(&*literal.get_ref(), "x");
+    assert_eq!(interner.stats().entries, 1);
+
+    assert_eq!(literals.get("x"), Some(literal.clone()));
+    assert_eq!(idents.get("y"), None);
+    assert_eq!(idents.tag(literal.intern()), x);
+    let other = Interner::new().intern("y");
+    assert_eq!(idents.tag(&other).into_intern(), interner.intern("y"));
+
+    let set = [x.clone(), idents.intern("y"), x.clone()]
+      .into_iter()
+      .collect::<HashSet<_>>();
+    assert_eq!(set.len(), 2);
+    assert_eq!(x.to_string(), "x");
+    assert!(format!("{x:?}").contains("Ident"));
+  }
+}
@@ -0,0 +1,83 @@
+use crate::sync::folding::CaseFolding;
+use std::borrow::Cow;
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::{is_nfc, is_nfkc, UnicodeNormalization};
+
+/// Selects which Unicode normalization form strings are put in before they
+/// are interned, so that canonically equivalent strings produce the same
+/// [Intern](crate::sync::Intern). Chosen with
+/// [InternerBuilder::normalization](crate::sync::InternerBuilder::normalization).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Normalization {
+  /// Strings are interned exactly as they are
+  #[default]
+  Off,
+  /// Canonical composition, so that for example `"e\u{301}"` and `"é"` are
+  /// the same string. Requires the `unicode-normalization` feature.
+  #[cfg(feature = "unicode-normalization")]
+  Nfc,
+  /// Compatibility composition, which also merges characters that only
+  /// differ in presentation, such as `"ﬁ"` and `"fi"`. This is the form
+  /// recommended for identifiers. Requires the `unicode-normalization`
+  /// feature.
+  #[cfg(feature = "unicode-normalization")]
+  Nfkc,
+}
+
+impl Normalization {
+  /// Normalizes `s`, only allocating if that actually changes anything
+  pub(crate) fn normalize(self, s: &str) -> Cow<'_, str> {
+    match self {
+      #[cfg(feature = "unicode-normalization")]
+      Normalization::Nfc if !is_nfc(s) => Cow::Owned(s.nfc().collect()),
+      #[cfg(feature = "unicode-normalization")]
+      Normalization::Nfkc if !is_nfkc(s) => Cow::Owned(s.nfkc().collect()),
+      _ => Cow::Borrowed(s),
+    }
+  }
+
+  /// Puts `s` in the form that strings are indexed by: case folded, then
+  /// normalized
+  pub(crate) fn canonical(
+    self,
+    case_folding: CaseFolding,
+    s: &str,
+  ) -> Cow<'_, str> {
+    let folded = case_folding.fold(s);
+    match self.normalize(&folded) {
+      Cow::Borrowed(_) => folded,
+      Cow::Owned(normalized) => Cow::Owned(normalized),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn normalization_off() {
+    let decomposed = "e\u{301}";
+    assert_eq!(Normalization::Off.normalize(decomposed), decomposed);
+    assert_eq!(
+      Normalization::Off.canonical(CaseFolding::Ascii, "E\u{301}"),
+      decomposed
+    );
+  }
+
+  #[cfg(feature = "unicode-normalization")]
+  #[test]
+  fn normalization() {
+    assert_eq!(Normalization::Nfc.normalize("e\u{301}"), "é");
+    assert_eq!(Normalization::Nfc.normalize("ﬁ"), "ﬁ");
+    assert_eq!(Normalization::Nfkc.normalize("ﬁ"), "fi");
+    assert!(matches!(
+      Normalization::Nfc.normalize("é"),
+      Cow::Borrowed(_)
+    ));
+    assert_eq!(
+      Normalization::Nfc.canonical(CaseFolding::Ascii, "E\u{301}"),
+      "é"
+    );
+  }
+}
@@ -0,0 +1,154 @@
+use std::{fmt, time::Duration};
+
+/// Hooks that an [Interner](crate::sync::Interner) calls as it works, for
+/// feeding metrics such as Prometheus counters. Registered with
+/// [InternerBuilder::observer](crate::sync::InternerBuilder::observer).
+/// Every method does nothing by default, so only the interesting ones need
+/// to be written.
+///
+/// Most hooks are called while the interner is locked, so they should be
+/// quick, and must not intern anything into the same interner, or they will
+/// deadlock.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::{InternerBuilder, InternerObserver};
+/// use std::sync::{
+///   atomic::{AtomicU64, Ordering},
+///   Arc,
+/// };
+///
+/// #[derive(Debug, Default)]
+/// struct Counters {
+///   hits: AtomicU64,
+///   missed_bytes: AtomicU64,
+/// }
+///
+/// impl InternerObserver for Counters {
+///   fn on_intern_hit(&self) {
+///     self.hits.fetch_add(1, Ordering::Relaxed);
+///   }
+///
+///   fn on_intern_miss(&self, len: usize) {
+///     self.missed_bytes.fetch_add(len as u64, Ordering::Relaxed);
+///   }
+/// }
+///
+/// let counters = Arc::new(Counters::default());
+/// let interner = InternerBuilder::new().observer(counters.clone()).build();
+///
+/// interner.intern("hello");
+/// interner.intern("hello");
+///
+/// assert_eq!(counters.hits.load(Ordering::Relaxed), 1);
+/// assert_eq!(counters.missed_bytes.load(Ordering::Relaxed), 5);
+/// ```
+pub trait InternerObserver: fmt::Debug + Send + Sync {
+  /// Called when a string being interned was already in the store, whether
+  /// as a string of its own or as part of another one
+  fn on_intern_hit(&self) {}
+
+  /// Called when a string being interned wasn't in the store yet, with its
+  /// length in bytes
+  fn on_intern_miss(&self, len: usize) {
+    let _ = len;
+  }
+
+  /// Called when the store has allocated more memory, with how many bytes
+  /// it holds now, the same count as `allocated_bytes` in
+  /// [InternerStats](crate::sync::InternerStats)
+  fn on_store_grow(&self, allocated: usize) {
+    let _ = allocated;
+  }
+
+  /// Called when interning had to wait for another thread that held the
+  /// lock, with how long it waited. This is called once the lock is held.
+  fn on_blocked(&self, waited: Duration) {
+    let _ = waited;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::sync::InternerBuilder;
+  use std::{
+    sync::{
+      atomic::{AtomicBool, AtomicUsize, Ordering},
+      Arc, Mutex,
+    },
+    thread,
+  };
+
+  #[derive(Debug, Default)]
+  struct Recorder {
+    hits: AtomicUsize,
+    misses: Mutex<Vec<usize>>,
+    allocated: Mutex<Vec<usize>>,
+    blocked: AtomicUsize,
+    /// Set while a miss is holding up the lock
+    stalling: AtomicBool,
+  }
+
+  impl InternerObserver for Recorder {
+    fn on_intern_hit(&self) {
+      self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_intern_miss(&self, len: usize) {
+      self.misses.lock().unwrap().push(len);
+      if len == 5 {
+        self.stalling.store(true, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(100));
+      }
+    }
+
+    fn on_store_grow(&self, allocated: usize) {
+      self.allocated.lock().unwrap().push(allocated);
+    }
+
+    fn on_blocked(&self, _waited: Duration) {
+      self.blocked.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn interner_tells_observer() {
+    let recorder = Arc::new(Recorder::default());
+    let interner = InternerBuilder::new().observer(recorder.clone()).build();
+
+    interner.intern("hi");
+    interner.intern("hi");
+    interner.intern("h");
+    interner.intern("x".repeat(100_000));
+
+    assert_eq!(recorder.hits.load(Ordering::Relaxed), 2);
+    assert_eq!(*recorder.misses.lock().unwrap(), [2, 100_000]);
+    let allocated = recorder.allocated.lock().unwrap().clone();
+    assert!(allocated.len() >= 2);
+    assert!(allocated.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(*allocated.last().unwrap(), interner.stats().allocated_bytes);
+    assert_eq!(recorder.blocked.load(Ordering::Relaxed), 0);
+  }
+
+  #[cfg(not(feature = "single-threaded"))]
+  #[test]
+  fn interner_tells_observer_when_blocked() {
+    let recorder = Arc::new(Recorder::default());
+    let interner = InternerBuilder::new().observer(recorder.clone()).build();
+
+    let stalled = {
+      let interner = Arc::clone(&interner);
+      thread::spawn(move || drop(interner.intern("stall")))
+    };
+    while !recorder.stalling.load(Ordering::SeqCst) {
+      thread::yield_now();
+    }
+    interner.intern("other");
+    stalled.join().unwrap();
+
+    assert_eq!(recorder.blocked.load(Ordering::Relaxed), 1);
+  }
+}
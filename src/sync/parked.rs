@@ -0,0 +1,100 @@
+use crate::sync::lock::Mutex;
+use std::{
+  thread::{self, Thread},
+  time::Instant,
+};
+
+/// Threads parked until something they are waiting on happens, such as the
+/// last reader of an interner being dropped. Whoever makes it happen calls
+/// [Parked::wake], and each thread checks again whether it is done.
+pub(crate) struct Parked {
+  threads: Mutex<Vec<Thread>>,
+}
+
+impl Parked {
+  pub(crate) fn new() -> Self {
+    Self {
+      threads: Mutex::new(Vec::new()),
+    }
+  }
+
+  /// Parks the current thread until `done` returns true, or `deadline`
+  /// passes. Returns whether `done` did.
+  ///
+  /// The thread is listed before `done` is checked, so a wake that comes
+  /// between the check and parking isn't lost: it leaves the thread's park
+  /// token set, and parking returns straight away.
+  pub(crate) fn until(
+    &self,
+    mut done: impl FnMut() -> bool,
+    deadline: Option<Instant>,
+  ) -> bool {
+    let current = thread::current();
+    let finished = loop {
+      self.threads.lock().push(current.clone());
+      if done() {
+        break true;
+      }
+
+      match deadline {
+        None => thread::park(),
+        Some(deadline) => match deadline.checked_duration_since(Instant::now())
+        {
+          Some(left) => thread::park_timeout(left),
+          None => break false,
+        },
+      }
+    };
+
+    self
+      .threads
+      .lock()
+      .retain(|thread| thread.id() != current.id());
+    finished
+  }
+
+  /// Number of threads that are waiting
+  #[cfg(test)]
+  pub(crate) fn waiting(&self) -> usize {
+    self.threads.lock().len()
+  }
+
+  /// Unparks every thread that is waiting, so they check again
+  pub(crate) fn wake(&self) {
+    for thread in self.threads.lock().drain(..) {
+      thread.unpark();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+  };
+
+  #[test]
+  fn parked_threads_wake_once_done() {
+    let parked = Parked::new();
+    let done = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+      let waiter =
+        scope.spawn(|| parked.until(|| done.load(Ordering::SeqCst), None));
+      while parked.threads.lock().is_empty() {
+        thread::yield_now();
+      }
+      parked.wake();
+      done.store(true, Ordering::SeqCst);
+      parked.wake();
+      assert!(waiter.join().unwrap());
+    });
+    assert!(parked.threads.lock().is_empty());
+
+    let soon = Instant::now() + Duration::from_millis(10);
+    assert!(!parked.until(|| false, Some(soon)));
+    assert!(parked.until(|| true, Some(soon)));
+  }
+}
@@ -0,0 +1,143 @@
+use crate::sync::{
+  bytes::{ByteInterner, InternBytes},
+  sequence::{InternSequence, SequenceInterner},
+};
+use std::{
+  ffi::OsStr,
+  fmt,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+
+/// Where an [Interner](crate::sync::Interner) keeps the paths interned with
+/// [Interner::intern_path](crate::sync::Interner::intern_path). Every
+/// component is interned once as bytes, and paths are interned as sequences
+/// of components, so paths in the same directory share its components, and
+/// their common prefix is a single branch of the trie.
+pub(crate) struct PathTable {
+  components: Arc<ByteInterner>,
+  paths: Arc<SequenceInterner<InternBytes>>,
+}
+
+impl PathTable {
+  pub(crate) fn new() -> Self {
+    Self {
+      components: ByteInterner::new(),
+      paths: SequenceInterner::new(),
+    }
+  }
+
+  pub(crate) fn intern(&self, path: &Path) -> InternPath {
+    let components = path
+      .components()
+      .map(|component| {
+        self
+          .components
+          .intern(component.as_os_str().as_encoded_bytes())
+      })
+      .collect::<Vec<_>>();
+
+    InternPath {
+      components: self.paths.intern(components),
+    }
+  }
+}
+
+impl fmt::Debug for PathTable {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("PathTable")
+      .field("paths", &self.paths.len())
+      .finish_non_exhaustive()
+  }
+}
+
+/// Represents a single interned path, produced by
+/// [Interner::intern_path](crate::sync::Interner::intern_path). Paths are
+/// interned component by component, as [Path::components] splits them, so
+/// `a/./b` and `a//b/` are the same path as `a/b`, just like they compare
+/// equal as [Path]s. Components are stored as the platform encodes them, so
+/// paths that aren't valid UTF-8 come back unchanged.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InternPath {
+  components: InternSequence<InternBytes>,
+}
+
+impl InternPath {
+  /// Iterates over the components of the path
+  pub fn components(
+    &self,
+  ) -> impl DoubleEndedIterator<Item = &OsStr> + ExactSizeIterator {
+    self.components.get_ref().iter().map(|component| {
+      // SAFETY: the bytes came from `OsStr::as_encoded_bytes` in this process
+      unsafe { OsStr::from_encoded_bytes_unchecked(component.as_bytes()) }
+    })
+  }
+
+  /// Copies the path out into an owned [PathBuf]
+  pub fn to_path_buf(&self) -> PathBuf {
+    self.components().collect()
+  }
+
+  /// Number of components in the path
+  pub fn len(&self) -> usize {
+    self.components.len()
+  }
+
+  /// Whether the path has no components, like an empty path
+  pub fn is_empty(&self) -> bool {
+    self.components.is_empty()
+  }
+
+  /// Whether `base` is a prefix of this path, component by component. Both
+  /// paths have to come from the same interner.
+  pub fn starts_with(&self, base: &InternPath) -> bool {
+    self
+      .components
+      .get_ref()
+      .starts_with(base.components.get_ref())
+  }
+}
+
+impl fmt::Debug for InternPath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("InternPath")
+      .field(&self.to_path_buf())
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::sync::Interner;
+  use std::path::Path;
+
+  #[test]
+  fn interner_interns_paths() {
+    let interner = Interner::new();
+    let lib = interner.intern_path("/src/crate/lib.rs");
+    let main = interner.intern_path(Path::new("/src/crate/main.rs"));
+    let dir = interner.intern_path("/src/crate");
+
+    assert_eq!(lib, interner.intern_path("/src//crate/./lib.rs"));
+    assert_ne!(lib, main);
+    assert_eq!(lib.to_path_buf(), Path::new("/src/crate/lib.rs"));
+    assert_eq!(lib.len(), 4);
+    assert_eq!(lib.components().last().unwrap(), "lib.rs");
+    assert!(lib.starts_with(&dir) && main.starts_with(&dir));
+    assert!(!dir.starts_with(&lib));
+    assert!(interner.intern_path("").is_empty());
+    // paths don't take up room in the string store
+    assert_eq!(interner.stats().stored_bytes, 0);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn interner_interns_paths_that_are_not_utf8() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let interner = Interner::new();
+    let path = Path::new(OsStr::from_bytes(b"dir/\xff\xfe.txt"));
+
+    assert_eq!(interner.intern_path(path).to_path_buf(), path);
+  }
+}
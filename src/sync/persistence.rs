@@ -0,0 +1,397 @@
+use crate::sync::{
+  folding::CaseFolding, index::IndexBackend, normalization::Normalization,
+  trie::Span,
+};
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 8] = b"STRINTRN";
+const VERSION: u32 = 1;
+
+/// Everything needed to rebuild an interner. Saved interners are laid out as
+/// follows, with every integer little-endian:
+///
+/// - the magic bytes `STRINTRN` and a `u32` format version
+/// - one byte each for the index backend, substring sharing, case folding
+///   and normalization
+/// - `u64`s for the next id to hand out, which is one past the last entry's,
+///   the number of entries `n`, and the length of the text
+/// - `n` entries, each a `u64` id followed by the `u64` start and end of its
+///   string within the text, sorted by id
+/// - `n` `u64` positions in the entry table, sorted by each entry's string,
+///   so that a reader can binary search without building an index
+/// - the text itself, which entries may share
+pub(crate) struct Snapshot {
+  pub(crate) backend: IndexBackend,
+  pub(crate) substring_sharing: bool,
+  pub(crate) case_folding: CaseFolding,
+  pub(crate) normalization: Normalization,
+  pub(crate) next_id: usize,
+  /// Each entry's id and where its string is in `text`, sorted by id
+  pub(crate) entries: Vec<(usize, Span)>,
+  pub(crate) text: String,
+}
+
+fn invalid(message: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads through a saved interner, failing if it ends too soon
+struct Reader<'a> {
+  bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+    if self.bytes.len() < len {
+      return Err(invalid("saved interner is truncated"));
+    }
+
+    let (taken, rest) = self.bytes.split_at(len);
+    self.bytes = rest;
+    Ok(taken)
+  }
+
+  fn byte(&mut self) -> io::Result<u8> {
+    Ok(self.take(1)?[0])
+  }
+
+  fn u64(&mut self) -> io::Result<usize> {
+    let bytes = self.take(8)?.try_into().unwrap();
+    usize::try_from(u64::from_le_bytes(bytes))
+      .map_err(|_| invalid("saved interner is too large"))
+  }
+}
+
+impl Snapshot {
+  /// Takes a snapshot of entries whose strings are read with `text`. Strings
+  /// that lie inside another entry's string aren't written out twice.
+  pub(crate) fn new<'a>(
+    backend: IndexBackend,
+    substring_sharing: bool,
+    case_folding: CaseFolding,
+    normalization: Normalization,
+    mut entries: Vec<(usize, Span)>,
+    text: impl Fn(Span) -> &'a str,
+  ) -> Self {
+    // visit enclosing strings before the strings inside them
+    let mut order = (0..entries.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| (entries[i].1.start, usize::MAX - entries[i].1.end));
+
+    let mut out = String::new();
+    let mut enclosing: Option<(Span, usize)> = None;
+    for i in order {
+      let span = entries[i].1.clone();
+      let start = match &enclosing {
+        Some((outer, at))
+          if outer.start <= span.start && span.end <= outer.end =>
+        {
+          at + (span.start - outer.start)
+        },
+        _ => {
+          let at = out.len();
+          out.push_str(text(span.clone()));
+          enclosing = Some((span.clone(), at));
+          at
+        },
+      };
+
+      entries[i].1 = start..(start + span.len());
+    }
+
+    entries.sort_unstable_by_key(|&(id, _)| id);
+    let next_id = entries.last().map_or(0, |&(id, _)| id + 1);
+
+    Self {
+      backend,
+      substring_sharing,
+      case_folding,
+      normalization,
+      next_id,
+      entries,
+      text: out,
+    }
+  }
+
+  pub(crate) fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+    let flags = [
+      match self.backend {
+        IndexBackend::Trie => 0,
+        IndexBackend::Hash => 1,
+      },
+      self.substring_sharing.into(),
+      match self.case_folding {
+        CaseFolding::Off => 0,
+        CaseFolding::Ascii => 1,
+        #[cfg(feature = "caseless")]
+        CaseFolding::Unicode => 2,
+      },
+      match self.normalization {
+        Normalization::Off => 0,
+        #[cfg(feature = "unicode-normalization")]
+        Normalization::Nfc => 1,
+        #[cfg(feature = "unicode-normalization")]
+        Normalization::Nfkc => 2,
+      },
+    ];
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&flags)?;
+
+    let mut write_u64 = |n: usize| writer.write_all(&(n as u64).to_le_bytes());
+    write_u64(self.next_id)?;
+    write_u64(self.entries.len())?;
+    write_u64(self.text.len())?;
+
+    for (id, span) in &self.entries {
+      write_u64(*id)?;
+      write_u64(span.start)?;
+      write_u64(span.end)?;
+    }
+
+    let mut sorted = (0..self.entries.len()).collect::<Vec<_>>();
+    sorted.sort_unstable_by_key(|&i| &self.text[self.entries[i].1.clone()]);
+    for i in sorted {
+      write_u64(i)?;
+    }
+
+    writer.write_all(self.text.as_bytes())
+  }
+
+  pub(crate) fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let layout = Layout::check(&bytes)?;
+
+    Ok(Self {
+      backend: layout.backend,
+      substring_sharing: layout.substring_sharing,
+      case_folding: layout.case_folding,
+      normalization: layout.normalization,
+      next_id: layout.next_id,
+      entries: (0..layout.len).map(|i| layout.entry(&bytes, i)).collect(),
+      // SAFETY: the bytes were just checked
+      text: unsafe { layout.text(&bytes) }.to_owned(),
+    })
+  }
+}
+
+/// The header of a saved interner, which says where everything else in it
+/// is. Once a saved interner has been checked, it can be read in place.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Layout {
+  pub(crate) backend: IndexBackend,
+  pub(crate) substring_sharing: bool,
+  pub(crate) case_folding: CaseFolding,
+  pub(crate) normalization: Normalization,
+  pub(crate) next_id: usize,
+  /// Number of entries
+  pub(crate) len: usize,
+  text_len: usize,
+}
+
+const HEADER_LEN: usize = 40;
+const ENTRY_LEN: usize = 24;
+
+fn read_u64(bytes: &[u8], at: usize) -> usize {
+  u64::from_le_bytes(bytes[at..(at + 8)].try_into().unwrap()) as usize
+}
+
+impl Layout {
+  /// Makes sure that `bytes` hold a well-formed saved interner, and reads its
+  /// header
+  pub(crate) fn check(bytes: &[u8]) -> io::Result<Self> {
+    let mut reader = Reader { bytes };
+
+    if reader.take(MAGIC.len())? != MAGIC {
+      return Err(invalid("not a saved interner"));
+    }
+    let version = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+    if version != VERSION {
+      return Err(invalid("unsupported saved interner version"));
+    }
+
+    let backend = match reader.byte()? {
+      0 => IndexBackend::Trie,
+      1 => IndexBackend::Hash,
+      _ => return Err(invalid("unknown index backend")),
+    };
+    let substring_sharing = match reader.byte()? {
+      0 => false,
+      1 => true,
+      _ => return Err(invalid("invalid substring sharing flag")),
+    };
+    let case_folding = match reader.byte()? {
+      0 => CaseFolding::Off,
+      1 => CaseFolding::Ascii,
+      #[cfg(feature = "caseless")]
+      2 => CaseFolding::Unicode,
+      _ => return Err(invalid("unknown case folding")),
+    };
+    let normalization = match reader.byte()? {
+      0 => Normalization::Off,
+      #[cfg(feature = "unicode-normalization")]
+      1 => Normalization::Nfc,
+      #[cfg(feature = "unicode-normalization")]
+      2 => Normalization::Nfkc,
+      _ => return Err(invalid("unknown normalization")),
+    };
+
+    let layout = Self {
+      backend,
+      substring_sharing,
+      case_folding,
+      normalization,
+      next_id: reader.u64()?,
+      len: reader.u64()?,
+      text_len: reader.u64()?,
+    };
+
+    let expected_len = layout
+      .len
+      .checked_mul(ENTRY_LEN + 8)
+      .and_then(|tables| tables.checked_add(HEADER_LEN))
+      .and_then(|n| n.checked_add(layout.text_len));
+    match expected_len {
+      Some(expected) if expected == bytes.len() => {},
+      Some(expected) if expected < bytes.len() => {
+        return Err(invalid("saved interner has trailing data"));
+      },
+      // too long to fit in memory counts as truncated too
+      _ => return Err(invalid("saved interner is truncated")),
+    }
+
+    let text = std::str::from_utf8(&bytes[layout.text_start()..])
+      .map_err(|_| invalid("saved text is not UTF-8"))?;
+
+    let mut last_id = None;
+    for i in 0..layout.len {
+      let (id, span) = layout.entry(bytes, i);
+      if id >= layout.next_id || last_id.is_some_and(|last| last >= id) {
+        return Err(invalid("entry ids are out of order"));
+      }
+      if span.start > span.end || text.get(span).is_none() {
+        return Err(invalid("entry lies outside the saved text"));
+      }
+      last_id = Some(id);
+
+      if layout.sorted(bytes, i) >= layout.len {
+        return Err(invalid("sorted entry is out of range"));
+      }
+    }
+    if layout.next_id > last_id.map_or(0, |last| last + 1) {
+      return Err(invalid("next id is past the last entry"));
+    }
+
+    // lookups binary search the sorted table, so it has to really be sorted,
+    // which also makes sure it lists every entry once
+    let sorted_text = |i| &text[layout.entry(bytes, layout.sorted(bytes, i)).1];
+    for i in 1..layout.len {
+      if sorted_text(i - 1) >= sorted_text(i) {
+        return Err(invalid("sorted entries are out of order"));
+      }
+    }
+
+    Ok(layout)
+  }
+
+  fn text_start(&self) -> usize {
+    HEADER_LEN + self.len * (ENTRY_LEN + 8)
+  }
+
+  /// Reads the id and span of the `i`th entry
+  pub(crate) fn entry(&self, bytes: &[u8], i: usize) -> (usize, Span) {
+    let at = HEADER_LEN + i * ENTRY_LEN;
+    let span = read_u64(bytes, at + 8)..read_u64(bytes, at + 16);
+    (read_u64(bytes, at), span)
+  }
+
+  /// Reads which entry comes `i`th in order of their strings
+  pub(crate) fn sorted(&self, bytes: &[u8], i: usize) -> usize {
+    read_u64(bytes, HEADER_LEN + self.len * ENTRY_LEN + i * 8)
+  }
+
+  /// Reads the text that entries point into
+  ///
+  /// # Safety
+  ///
+  /// `bytes` must have passed [Layout::check].
+  pub(crate) unsafe fn text<'a>(&self, bytes: &'a [u8]) -> &'a str {
+    // SAFETY: checking the layout made sure the text is valid UTF-8
+    unsafe { std::str::from_utf8_unchecked(&bytes[self.text_start()..]) }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn snapshot_round_trip() {
+    let store = "helloworld";
+    let snapshot = Snapshot::new(
+      IndexBackend::Hash,
+      true,
+      CaseFolding::Ascii,
+      Normalization::Off,
+      vec![(4, 6..9), (0, 0..5), (8, 1..4), (2, 5..10)],
+      |span| &store[span],
+    );
+
+    // "ell" lives inside "hello" and "orl" inside "world"
+    assert_eq!(snapshot.text, "helloworld");
+    assert_eq!(
+      snapshot.entries,
+      [(0, 0..5), (2, 5..10), (4, 6..9), (8, 1..4)]
+    );
+
+    let mut bytes = Vec::new();
+    snapshot.write_to(&mut bytes).unwrap();
+    let read = Snapshot::read_from(&bytes[..]).unwrap();
+
+    assert_eq!(read.backend, IndexBackend::Hash);
+    assert!(read.substring_sharing);
+    assert_eq!(read.case_folding, CaseFolding::Ascii);
+    assert_eq!(read.normalization, Normalization::Off);
+    assert_eq!(read.next_id, 9);
+    assert_eq!(read.entries, snapshot.entries);
+    assert_eq!(read.text, snapshot.text);
+
+    for len in [0, 12, bytes.len() - 1] {
+      let error = Snapshot::read_from(&bytes[..len]).err().unwrap();
+      assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // swap the first two entries in the sorted table
+    let sorted = HEADER_LEN + 4 * ENTRY_LEN;
+    let mut unsorted = bytes.clone();
+    unsorted[sorted..(sorted + 16)].rotate_left(8);
+    let error = Snapshot::read_from(&unsorted[..]).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+  }
+
+  #[test]
+  fn rejects_headers_that_dont_add_up() {
+    let header = |next_id: u64, len: u64, text_len: u64| {
+      let mut bytes = MAGIC.to_vec();
+      bytes.extend(VERSION.to_le_bytes());
+      bytes.extend([0; 4]);
+      for n in [next_id, len, text_len] {
+        bytes.extend(n.to_le_bytes());
+      }
+      bytes
+    };
+
+    let empty = header(0, 0, 0);
+    assert!(Snapshot::read_from(&empty[..]).is_ok());
+    for bytes in [
+      empty[..(HEADER_LEN - 1)].to_vec(),
+      header(0, 0, u64::MAX),
+      header(0, u64::MAX, 0),
+      header(1 << 40, 0, 0),
+    ] {
+      let error = Snapshot::read_from(&bytes[..]).err().unwrap();
+      assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+  }
+}
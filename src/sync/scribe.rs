@@ -0,0 +1,190 @@
+use crate::sync::{Intern, Interner};
+use std::{collections::HashMap, fmt, sync::Arc};
+
+/// An id handed out by a [LocalScribe] for a string that hasn't reached the
+/// shared [Interner] yet. Ids count up from 0, and start over after every
+/// [LocalScribe::flush], which says what each one turned into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProvisionalId(pub u32);
+
+/// A private buffer that one thread interns into without touching the
+/// shared [Interner], then flushes into it all at once. Where interning each
+/// string takes the interner's lock, a flush takes it once for the whole
+/// batch, so producer threads that intern a lot spend far less time waiting
+/// on each other.
+///
+/// Strings are handed [ProvisionalId]s as they are written, with duplicates
+/// in the same batch, after case folding and normalization, getting the
+/// same id. [LocalScribe::flush] interns the batch and returns the final
+/// [Intern] of every provisional id.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::{Interner, LocalScribe};
+///
+/// let interner = Interner::new();
+/// let mut scribe = LocalScribe::new(&interner);
+///
+/// let ids = "let x = x"
+///   .split(' ')
+///   .map(|token| scribe.intern(token))
+///   .collect::<Vec<_>>();
+///
+/// assert_eq!(ids[1], ids[3]);
+/// assert_eq!(interner.stats().entries, 0);
+///
+/// let interns = scribe.flush();
+///
+/// assert_eq!(interns[ids[1].0 as usize], interner.intern("x"));
+/// assert_eq!(interner.stats().entries, 3);
+/// ```
+pub struct LocalScribe {
+  interner: Arc<Interner>,
+  /// Every string written since the last flush, as first spelled, indexed
+  /// by provisional id
+  pending: Vec<String>,
+  /// Provisional ids by canonical form
+  ids: HashMap<String, ProvisionalId>,
+}
+
+impl LocalScribe {
+  /// Starts an empty buffer that flushes into `interner`
+  pub fn new(interner: &Arc<Interner>) -> Self {
+    Self {
+      interner: Arc::clone(interner),
+      pending: Vec::new(),
+      ids: HashMap::new(),
+    }
+  }
+
+  /// Writes `s` into the buffer, without taking the interner's lock, and
+  /// gives back its provisional id
+  ///
+  /// # Panics
+  ///
+  /// Panics if more than [u32::MAX] distinct strings are written between
+  /// flushes.
+  pub fn intern<S: AsRef<str>>(&mut self, s: S) -> ProvisionalId {
+    let spelling = s.as_ref();
+    let canonical = self.interner.canonical(spelling);
+    if let Some(&id) = self.ids.get(&*canonical) {
+      return id;
+    }
+
+    let id = u32::try_from(self.pending.len())
+      .map(ProvisionalId)
+      .expect("ran out of provisional ids");
+    self.ids.insert(canonical.into_owned(), id);
+    self.pending.push(spelling.to_owned());
+    id
+  }
+
+  /// Interns everything written since the last flush into the shared
+  /// interner, taking its lock once, and empties the buffer. The returned
+  /// [Intern]s are indexed by provisional id.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  pub fn flush(&mut self) -> Vec<Intern> {
+    self.ids.clear();
+    if self.pending.is_empty() {
+      return Vec::new();
+    }
+
+    self.interner.intern_all(self.pending.drain(..))
+  }
+
+  /// Number of distinct strings waiting to be flushed
+  pub fn len(&self) -> usize {
+    self.pending.len()
+  }
+
+  /// Whether nothing is waiting to be flushed
+  pub fn is_empty(&self) -> bool {
+    self.pending.is_empty()
+  }
+
+  /// The interner this flushes into
+  pub fn interner(&self) -> &Arc<Interner> {
+    &self.interner
+  }
+}
+
+impl fmt::Debug for LocalScribe {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("LocalScribe")
+      .field("pending", &self.pending)
+      .finish_non_exhaustive()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::sync::{CaseFolding, InternerBuilder};
+
+  #[test]
+  fn scribe_batches_interning() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let mut scribe = LocalScribe::new(&interner);
+
+    let hello = scribe.intern("Hello");
+    let world = scribe.intern("world");
+
+    assert_eq!(hello, ProvisionalId(0));
+    assert_eq!(world, ProvisionalId(1));
+    assert_eq!(scribe.intern("HELLO"), hello);
+    assert_eq!(scribe.len(), 2);
+    assert_eq!(interner.stats().entries, 0);
+
+    let interns = scribe.flush();
+    assert!(scribe.is_empty());
+    assert_eq!(
+      interns,
+      [interner.intern("hello"), interner.intern("world")]
+    );
+
+    assert_eq!(scribe.intern("again"), ProvisionalId(0));
+    assert_eq!(scribe.flush()[0], interner.intern("again"));
+    assert!(scribe.flush().is_empty());
+  }
+
+  #[cfg(not(feature = "single-threaded"))]
+  #[test]
+  fn scribes_flush_from_many_threads() {
+    let interner = Interner::new();
+
+    let flushed = std::thread::scope(|scope| {
+      let workers = (0..4)
+        .map(|t| {
+          let mut scribe = LocalScribe::new(&interner);
+          scope.spawn(move || {
+            let mut interns = Vec::new();
+            for i in 0..1000 {
+              scribe.intern(format!("word{}", (i * (t + 1)) % 500));
+              if scribe.len() == 100 {
+                interns.extend(scribe.flush());
+              }
+            }
+            interns.extend(scribe.flush());
+            interns
+          })
+        })
+        .collect::<Vec<_>>();
+      workers
+        .into_iter()
+        .flat_map(|worker| worker.join().unwrap())
+        .collect::<Vec<_>>()
+    });
+
+    assert_eq!(interner.stats().entries, 500);
+    for intern in flushed {
+      assert_eq!(interner.get(intern.resolve()), Some(intern));
+    }
+  }
+}
@@ -0,0 +1,273 @@
+use crate::sync::{
+  lock::Mutex,
+  trie::{Span, Trie},
+};
+use std::{
+  cmp::Ordering,
+  fmt,
+  hash::{Hash, Hasher},
+  slice,
+  sync::Arc,
+};
+
+const MIN_CHUNK: usize = 256;
+
+/// An interner for sequences of any kind of element, such as token streams
+/// or paths split into components. Works like
+/// [ByteInterner](crate::sync::ByteInterner), with sequences indexed by the
+/// same radix trie, but the elements are kept in a store of `T`s, and it
+/// hands out [InternSequence]s.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::SequenceInterner;
+///
+/// let interner = SequenceInterner::new();
+///
+/// let tokens = interner.intern([1, 2, 3]);
+///
+/// assert_eq!(tokens, interner.intern(vec![1, 2, 3]));
+/// assert_eq!(tokens.get_ref(), [1, 2, 3]);
+/// assert_eq!(interner.get([1, 2]), None);
+/// ```
+pub struct SequenceInterner<T: Clone + Eq + Hash + Send + Sync> {
+  internal: Mutex<SequenceInternal<T>>,
+}
+
+struct SequenceInternal<T: Clone + Eq + Hash + Send + Sync> {
+  trie: Trie<T>,
+  /// Where the elements live. A chunk is never pushed to past its capacity,
+  /// so it never reallocates, and elements in it stay put until the
+  /// interner is dropped.
+  chunks: Vec<Chunk<T>>,
+}
+
+struct Chunk<T> {
+  /// Where the chunk starts in the spans handed out
+  start: usize,
+  elements: Vec<T>,
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync> SequenceInterner<T> {
+  /// Constructs a new SequenceInterner
+  pub fn new() -> Arc<Self> {
+    Arc::new(Self {
+      internal: Mutex::new(SequenceInternal {
+        trie: Trie::new(),
+        chunks: Vec::new(),
+      }),
+    })
+  }
+
+  /// Creates an interned sequence
+  pub fn intern<S: AsRef<[T]>>(self: &Arc<Self>, s: S) -> InternSequence<T> {
+    let s = s.as_ref();
+    let mut internal = self.internal.lock();
+
+    let span = match internal.trie.get(s) {
+      Some(span) => span,
+      None => {
+        let span = internal.push(s);
+        internal.trie.insert(s, span)
+      },
+    };
+
+    InternSequence {
+      span,
+      interner: Arc::clone(self),
+    }
+  }
+
+  /// Finds a previously interned sequence
+  pub fn get<S: AsRef<[T]>>(
+    self: &Arc<Self>,
+    s: S,
+  ) -> Option<InternSequence<T>> {
+    let span = self.internal.lock().trie.get(s.as_ref())?;
+
+    Some(InternSequence {
+      span,
+      interner: Arc::clone(self),
+    })
+  }
+
+  /// Number of distinct sequences
+  pub fn len(&self) -> usize {
+    self.internal.lock().trie.entries()
+  }
+
+  /// Whether there are no sequences at all
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync> SequenceInternal<T> {
+  /// Copies `s` into a chunk with room for it
+  fn push(&mut self, s: &[T]) -> Span {
+    let fits = self.chunks.last().is_some_and(|chunk| {
+      chunk.elements.capacity() - chunk.elements.len() >= s.len()
+    });
+    if !fits {
+      let (start, last) = self.chunks.last().map_or((0, 0), |chunk| {
+        (
+          chunk.start + chunk.elements.capacity(),
+          chunk.elements.capacity(),
+        )
+      });
+      let capacity = (last * 2).max(s.len()).max(MIN_CHUNK);
+      self.chunks.push(Chunk {
+        start,
+        elements: Vec::with_capacity(capacity),
+      });
+    }
+
+    let chunk = self.chunks.last_mut().unwrap();
+    let start = chunk.start + chunk.elements.len();
+    chunk.elements.extend_from_slice(s);
+    start..(start + s.len())
+  }
+
+  /// Finds where the elements of `span` live
+  fn get(&self, span: Span) -> *const T {
+    let chunk = self
+      .chunks
+      .partition_point(|chunk| chunk.start <= span.start);
+    let chunk = &self.chunks[chunk - 1];
+    chunk.elements[span.start - chunk.start..].as_ptr()
+  }
+}
+
+/// Represents a single interned sequence. This is the counterpart to
+/// [InternBytes](crate::sync::InternBytes) for a [SequenceInterner].
+pub struct InternSequence<T: Clone + Eq + Hash + Send + Sync> {
+  span: Span,
+  interner: Arc<SequenceInterner<T>>,
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync> InternSequence<T> {
+  /// Borrows the elements of the sequence. Elements are never moved, so
+  /// this only takes the lock long enough to find them.
+  pub fn get_ref(&self) -> &[T] {
+    if self.span.is_empty() {
+      return &[];
+    }
+
+    let elements = self.interner.internal.lock().get(self.span.clone());
+    // SAFETY: the span was handed out by this interner, and its elements
+    //         won't be touched again until the interner is dropped, which
+    //         can't happen while we hold on to it
+    unsafe { slice::from_raw_parts(elements, self.span.len()) }
+  }
+
+  /// Copies the elements out into an owned [Vec]
+  pub fn resolve(&self) -> Vec<T> {
+    self.get_ref().to_vec()
+  }
+
+  /// Number of elements in the sequence
+  pub fn len(&self) -> usize {
+    self.span.len()
+  }
+
+  /// Whether the sequence is empty
+  pub fn is_empty(&self) -> bool {
+    self.span.is_empty()
+  }
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync> Clone for InternSequence<T> {
+  fn clone(&self) -> Self {
+    Self {
+      span: self.span.clone(),
+      interner: Arc::clone(&self.interner),
+    }
+  }
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync + fmt::Debug> fmt::Debug
+  for InternSequence<T>
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("InternSequence")
+      .field("span", &self.span)
+      .field("elements", &self.get_ref())
+      .finish()
+  }
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync> PartialEq for InternSequence<T> {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.interner, &other.interner) && self.span == other.span
+  }
+}
+impl<T: Clone + Eq + Hash + Send + Sync> Eq for InternSequence<T> {}
+
+/// Orders by interner, then by position in the store, like
+/// [Intern](crate::sync::Intern)
+impl<T: Clone + Eq + Hash + Send + Sync> Ord for InternSequence<T> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    Arc::as_ptr(&self.interner)
+      .cmp(&Arc::as_ptr(&other.interner))
+      .then(self.span.start.cmp(&other.span.start))
+      .then(self.span.end.cmp(&other.span.end))
+  }
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync> PartialOrd for InternSequence<T> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync> Hash for InternSequence<T> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    Arc::as_ptr(&self.interner).hash(state);
+    self.span.hash(state);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::Path;
+
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn sequence_interner() {
+    let interner = SequenceInterner::new();
+    let sequences = (0..1000u32)
+      .map(|i| interner.intern((0..i % 300).collect::<Vec<_>>()))
+      .collect::<Vec<_>>();
+
+    assert_eq!(interner.len(), 300);
+    for (i, sequence) in sequences.iter().enumerate() {
+      let expected = (0..i as u32 % 300).collect::<Vec<_>>();
+      assert_eq!(sequence.get_ref(), expected);
+      assert_eq!(*sequence, sequences[i % 300]);
+      assert_eq!(interner.get(&expected).as_ref(), Some(sequence));
+    }
+    assert!(sequences[0].is_empty());
+    assert_eq!(interner.get([1]), None);
+  }
+
+  #[test]
+  fn sequence_interner_interns_paths() {
+    let interner = SequenceInterner::new();
+    let components = |path: &str| {
+      Path::new(path)
+        .iter()
+        .map(|component| component.to_owned())
+        .collect::<Vec<_>>()
+    };
+
+    let main = interner.intern(components("src/sync/main.rs"));
+    let lib = interner.intern(components("src/lib.rs"));
+
+    assert_eq!(main, interner.intern(components("src/sync/main.rs")));
+    assert_ne!(main, lib);
+    assert_eq!(lib.resolve(), components("src/lib.rs"));
+    assert_eq!(main.len(), 3);
+  }
+}
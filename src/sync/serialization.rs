@@ -0,0 +1,93 @@
+use crate::sync::{Intern, Interner};
+use serde::{
+  de::{self, DeserializeSeed, Deserializer, Visitor},
+  Serialize, Serializer,
+};
+use std::{fmt, sync::Arc};
+
+/// [Intern]s serialize as their underlying text.
+impl Serialize for Intern {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.get_ref())
+  }
+}
+
+/// Deserializes a string into an [Intern] by interning it into the provided
+/// [Interner]. Since an [Intern] can't exist without its interner, this is
+/// used in place of a [serde::Deserialize] impl.
+///
+/// # Examples
+///
+/// ```
+/// use serde::de::DeserializeSeed;
+/// use str_interning::sync::{InternSeed, Interner};
+///
+/// let interner = Interner::new();
+/// let hello = interner.intern("hello");
+///
+/// let json = serde_json::to_string(&hello).unwrap();
+/// let mut de = serde_json::Deserializer::from_str(&json);
+/// let hello2 = InternSeed::new(&interner).deserialize(&mut de).unwrap();
+///
+/// assert_eq!(hello, hello2);
+/// ```
+#[derive(Clone, Copy)]
+pub struct InternSeed<'a> {
+  interner: &'a Arc<Interner>,
+}
+
+impl<'a> InternSeed<'a> {
+  /// Creates a seed that interns into `interner`
+  pub fn new(interner: &'a Arc<Interner>) -> Self {
+    Self { interner }
+  }
+}
+
+impl<'de> DeserializeSeed<'de> for InternSeed<'_> {
+  type Value = Intern;
+
+  fn deserialize<D: Deserializer<'de>>(
+    self,
+    deserializer: D,
+  ) -> Result<Self::Value, D::Error> {
+    deserializer.deserialize_str(self)
+  }
+}
+
+impl Visitor<'_> for InternSeed<'_> {
+  type Value = Intern;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("a string")
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+    self.interner.try_intern(v).map_err(E::custom)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde::Deserialize;
+
+  #[test]
+  fn round_trip() {
+    let interner = Interner::new();
+    let words = ["hello", "world", "hello"].map(|w| interner.intern(w));
+
+    let json = serde_json::to_string(&words).unwrap();
+    assert_eq!(json, r#"["hello","world","hello"]"#);
+
+    let fresh = Interner::new();
+    let mut de = serde_json::Deserializer::from_str(&json);
+    let values = Vec::<serde_json::Value>::deserialize(&mut de).unwrap();
+    let interns = values
+      .into_iter()
+      .map(|v| InternSeed::new(&fresh).deserialize(v).unwrap())
+      .collect::<Vec<_>>();
+
+    assert_eq!(interns[0], interns[2]);
+    assert_eq!(&*interns[1].get_ref(), "world");
+  }
+}
@@ -0,0 +1,312 @@
+use crate::sync::{
+  Intern, InternError, Interner, InternerBuilder, InternerStats,
+};
+use std::{
+  fmt,
+  hash::{BuildHasher, RandomState},
+  sync::Arc,
+};
+
+/// An interner split into several independent [Interner]s, called shards,
+/// each with its own lock and store. Every string is assigned to a shard by
+/// its hash, so threads interning different strings mostly take different
+/// locks, instead of all waiting on one.
+///
+/// The [Intern]s handed out are ordinary ones, which point at the shard
+/// that their string lives in. A given string always lands in the same
+/// shard, so [Intern]s compare and hash just like those of a single
+/// [Interner]. Options such as [InternerBuilder::capacity] and
+/// [InternerBuilder::max_store_bytes] apply to each shard on its own.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::sync::ShardedInterner;
+/// use std::thread;
+///
+/// let interner = ShardedInterner::new(8);
+///
+/// let interns = thread::scope(|scope| {
+///   let handles = (0..4)
+///     .map(|_| scope.spawn(|| interner.intern("hello")))
+///     .collect::<Vec<_>>();
+///   handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+/// });
+///
+/// assert!(interns.iter().all(|hello| *hello == interns[0]));
+/// assert_eq!(&*interns[0].get_ref(), "hello");
+/// ```
+pub struct ShardedInterner {
+  shards: Box<[Arc<Interner>]>,
+  hasher: RandomState,
+}
+
+impl ShardedInterner {
+  /// Constructs a ShardedInterner with `shards` shards, each configured
+  /// like [Interner::new]
+  ///
+  /// # Panics
+  ///
+  /// Panics if `shards` is 0.
+  pub fn new(shards: usize) -> Self {
+    InternerBuilder::new().build_sharded(shards)
+  }
+
+  pub(crate) fn from_builder(builder: &InternerBuilder, shards: usize) -> Self {
+    assert!(shards > 0, "a sharded interner needs at least one shard");
+
+    Self {
+      shards: (0..shards).map(|_| builder.build()).collect(),
+      hasher: RandomState::new(),
+    }
+  }
+
+  /// Picks the shard for a string in canonical form
+  fn shard(&self, s: &str) -> &Arc<Interner> {
+    &self.shards[self.shard_index(s)]
+  }
+
+  fn shard_index(&self, s: &str) -> usize {
+    let hash = self.hasher.hash_one(s);
+    (hash % self.shards.len() as u64) as usize
+  }
+
+  /// Creates an interned string in the shard that it belongs to. Only
+  /// threads interning into the same shard wait for each other.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  pub fn intern<S: AsRef<str>>(&self, s: S) -> Intern {
+    match self.try_intern(s) {
+      Ok(intern) => intern,
+      Err(e) => panic!("{e}"),
+    }
+  }
+
+  /// Creates an interned string in the shard that it belongs to, like
+  /// [Interner::try_intern]. Fails with [InternError::StoreFull] if that
+  /// shard's store is full.
+  pub fn try_intern<S: AsRef<str>>(&self, s: S) -> Result<Intern, InternError> {
+    let spelling = s.as_ref();
+    // every shard is configured the same way
+    let s = self.shards[0].canonical(spelling);
+    self.shard(&s).try_intern_canonical(spelling, &s)
+  }
+
+  /// Finds a previously interned string, like [Interner::get]
+  pub fn get<S: AsRef<str>>(&self, s: S) -> Option<Intern> {
+    let spelling = s.as_ref();
+    let s = self.shards[0].canonical(spelling);
+    self.shard(&s).get_canonical(spelling, &s)
+  }
+
+  /// Creates interned strings for a whole batch at once, using every thread
+  /// of rayon's thread pool. The strings are put in canonical form in
+  /// parallel, then split up by shard, and each shard interns its part of
+  /// the batch under a single lock, alongside the others. The [Intern]s
+  /// come back in the same order as `strings`.
+  ///
+  /// # Panics
+  ///
+  /// Panics under the same conditions as [Interner::intern].
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::sync::ShardedInterner;
+  ///
+  /// let interner = ShardedInterner::new(4);
+  /// let lines = (0..1000).map(|i| format!("line {}", i % 10)).collect::<Vec<_>>();
+  ///
+  /// let interns = interner.par_intern_all(&lines);
+  ///
+  /// assert_eq!(interns[3], interns[13]);
+  /// assert_eq!(&*interns[3].get_ref(), "line 3");
+  /// assert_eq!(interner.stats().entries, 10);
+  /// ```
+  #[cfg(feature = "rayon")]
+  pub fn par_intern_all<S: AsRef<str> + Sync>(
+    &self,
+    strings: &[S],
+  ) -> Vec<Intern> {
+    use rayon::prelude::*;
+
+    // every shard is configured the same way
+    let canonical = strings
+      .par_iter()
+      .map(|s| self.shards[0].canonical(s.as_ref()))
+      .collect::<Vec<_>>();
+
+    let mut batches = vec![Vec::new(); self.shards.len()];
+    for (i, s) in canonical.iter().enumerate() {
+      batches[self.shard_index(s)].push(i);
+    }
+
+    let interned = batches
+      .par_iter()
+      .zip(&self.shards)
+      .map(|(batch, shard)| {
+        shard.intern_all_canonical(
+          batch
+            .iter()
+            .map(|&i| (strings[i].as_ref(), canonical[i].clone())),
+        )
+      })
+      .collect::<Vec<_>>();
+
+    let mut out = vec![None; strings.len()];
+    for (batch, interns) in batches.iter().zip(interned) {
+      for (&i, intern) in batch.iter().zip(interns) {
+        out[i] = Some(intern);
+      }
+    }
+    out.into_iter().map(Option::unwrap).collect()
+  }
+
+  /// The shards themselves, which can be used to reach the rest of the
+  /// [Interner] API
+  pub fn shards(&self) -> &[Arc<Interner>] {
+    &self.shards
+  }
+
+  /// Finds which of [ShardedInterner::shards] an [Intern] lives in, or
+  /// [None] if it came from another interner
+  pub fn shard_of(&self, intern: &Intern) -> Option<usize> {
+    self.shards.iter().position(|shard| intern.is_from(shard))
+  }
+
+  /// Runs [Interner::collect] on every shard, and returns how many bytes
+  /// were freed in total
+  pub fn collect(&self) -> usize {
+    self.shards.iter().map(|shard| shard.collect()).sum()
+  }
+
+  /// Adds up the [InternerStats] of every shard
+  pub fn stats(&self) -> InternerStats {
+    self.shards.iter().map(|shard| shard.stats()).fold(
+      InternerStats::default(),
+      |total, stats| InternerStats {
+        stored_bytes: total.stored_bytes + stats.stored_bytes,
+        allocated_bytes: total.allocated_bytes + stats.allocated_bytes,
+        entries: total.entries + stats.entries,
+        trie_nodes: total.trie_nodes + stats.trie_nodes,
+        automaton_states: total.automaton_states + stats.automaton_states,
+        outstanding_refs: total.outstanding_refs + stats.outstanding_refs,
+        hits: total.hits + stats.hits,
+        misses: total.misses + stats.misses,
+      },
+    )
+  }
+}
+
+impl fmt::Debug for ShardedInterner {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ShardedInterner")
+      .field("shards", &self.shards.len())
+      .finish_non_exhaustive()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::sync::CaseFolding;
+
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn sharded_interner() {
+    let interner = ShardedInterner::new(4);
+    let words = (0..100)
+      .map(|i| interner.intern(format!("word{i}")))
+      .collect::<Vec<_>>();
+
+    assert_eq!(interner.stats().entries, 100);
+    assert!(interner
+      .shards()
+      .iter()
+      .all(|shard| shard.stats().entries > 0));
+    for (i, word) in words.iter().enumerate() {
+      assert_eq!(*word, interner.intern(format!("word{i}")));
+      assert_eq!(Some(word), interner.get(format!("word{i}")).as_ref());
+      let shard = interner.shard_of(word).unwrap();
+      assert_eq!(
+        Some(word),
+        interner.shards()[shard].get(&*word.resolve()).as_ref()
+      );
+    }
+    assert_eq!(interner.get("missing"), None);
+    assert_eq!(interner.shard_of(&Interner::new().intern("word0")), None);
+
+    let stored = interner.stats().stored_bytes;
+    drop(words);
+    assert_eq!(interner.collect(), stored);
+    assert_eq!(interner.stats().stored_bytes, 0);
+  }
+
+  #[cfg(feature = "rayon")]
+  #[test]
+  fn sharded_interner_interns_in_parallel() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build_sharded(8);
+    let words = (0..10_000)
+      .map(|i| {
+        let word = format!("word{}", i % 500);
+        if i % 2 == 0 {
+          word.to_uppercase()
+        } else {
+          word
+        }
+      })
+      .collect::<Vec<_>>();
+
+    let interns = interner.par_intern_all(&words);
+
+    assert_eq!(interns.len(), words.len());
+    for (intern, word) in interns.iter().zip(&words) {
+      assert_eq!(*intern, interner.intern(word));
+      assert_eq!(*intern, word.to_lowercase().as_str());
+    }
+    assert_eq!(interner.stats().entries, 500);
+  }
+
+  #[test]
+  fn sharded_interner_folds_before_sharding() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build_sharded(16);
+
+    for i in 0..50 {
+      let upper = interner.intern(format!("WORD{i}"));
+      assert_eq!(upper, interner.intern(format!("word{i}")));
+    }
+  }
+
+  // contends for the lock, which single-threaded builds panic on instead
+  #[cfg(not(feature = "single-threaded"))]
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn sharded_interner_interns_across_threads() {
+    let interner = ShardedInterner::new(8);
+
+    let interns = std::thread::scope(|scope| {
+      let handles = (0..4)
+        .map(|_| {
+          scope.spawn(|| {
+            (0..100)
+              .map(|i| interner.intern(format!("word{i}")))
+              .collect::<Vec<_>>()
+          })
+        })
+        .collect::<Vec<_>>();
+      handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>()
+    });
+
+    assert!(interns.iter().all(|words| *words == interns[0]));
+    assert_eq!(interner.stats().entries, 100);
+  }
+}
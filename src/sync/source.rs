@@ -0,0 +1,102 @@
+use std::{iter, ops::Range};
+
+/// Names a source file interned with
+/// [Interner::intern_source](crate::sync::Interner::intern_source). Ids
+/// count up from 0 in the order files are interned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceId(pub u32);
+
+/// Where a byte offset lies in a source file, as a compiler would report
+/// it. Both count from 1, and columns count chars rather than bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineColumn {
+  pub line: usize,
+  pub column: usize,
+}
+
+/// Where each line of a source file starts, for turning byte offsets into
+/// a [LineColumn] without scanning the file again
+#[derive(Debug)]
+pub(crate) struct Lines {
+  /// Byte offset of the start of each line, the first being 0
+  starts: Vec<usize>,
+}
+
+impl Lines {
+  pub(crate) fn new(text: &str) -> Self {
+    Self {
+      starts: iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect(),
+    }
+  }
+
+  /// Finds the line and column of `offset` in `text`, which these lines
+  /// were made from, unless it is out of bounds or not on a char boundary
+  pub(crate) fn locate(&self, text: &str, offset: usize) -> Option<LineColumn> {
+    if !text.is_char_boundary(offset) {
+      return None;
+    }
+
+    let line = self.starts.partition_point(|&start| start <= offset);
+    let start = self.starts[line - 1];
+    Some(LineColumn {
+      line,
+      column: text[start..offset].chars().count() + 1,
+    })
+  }
+
+  /// Where line number `line` lies in `text`, leaving out its line ending
+  pub(crate) fn line(&self, text: &str, line: usize) -> Option<Range<usize>> {
+    let start = *self.starts.get(line.checked_sub(1)?)?;
+    let end = self.starts.get(line).map_or(text.len(), |&next| next - 1);
+    let end = match text[start..end].ends_with('\r') {
+      true => end - 1,
+      false => end,
+    };
+    Some(start..end)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lines_locate_offsets() {
+    let text = "fn main() {\r\n  let crab = '🦀';\n}";
+    let lines = Lines::new(text);
+
+    assert_eq!(
+      lines.locate(text, 0),
+      Some(LineColumn { line: 1, column: 1 })
+    );
+    assert_eq!(
+      lines.locate(text, 3),
+      Some(LineColumn { line: 1, column: 4 })
+    );
+    assert_eq!(
+      lines.locate(text, 31),
+      Some(LineColumn {
+        line: 2,
+        column: 16
+      })
+    );
+    assert_eq!(
+      lines.locate(text, 34),
+      Some(LineColumn { line: 3, column: 1 })
+    );
+    assert_eq!(
+      lines.locate(text, 35),
+      Some(LineColumn { line: 3, column: 2 })
+    );
+    assert_eq!(lines.locate(text, 28), None);
+    assert_eq!(lines.locate(text, 36), None);
+
+    assert_eq!(&text[lines.line(text, 1).unwrap()], "fn main() {");
+    assert_eq!(&text[lines.line(text, 2).unwrap()], "  let crab = '🦀';");
+    assert_eq!(&text[lines.line(text, 3).unwrap()], "}");
+    assert_eq!(lines.line(text, 0), None);
+    assert_eq!(lines.line(text, 4), None);
+  }
+}
@@ -0,0 +1,21 @@
+/// A snapshot of an [Interner](crate::sync::Interner)'s memory use and
+/// activity, produced by [Interner::stats](crate::sync::Interner::stats).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InternerStats {
+  /// Bytes of string data in the store
+  pub stored_bytes: usize,
+  /// Bytes allocated for the store, including room not yet used
+  pub allocated_bytes: usize,
+  /// Number of distinct strings that have been interned
+  pub entries: usize,
+  /// Number of nodes in the trie of interned strings
+  pub trie_nodes: usize,
+  /// Number of states in the automaton that finds reusable substrings
+  pub automaton_states: usize,
+  /// Number of [InternRef](crate::sync::InternRef)s currently alive
+  pub outstanding_refs: usize,
+  /// Number of interns that were satisfied by data already in the store
+  pub hits: u64,
+  /// Number of interns that had to add new data to the store
+  pub misses: u64,
+}
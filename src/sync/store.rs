@@ -0,0 +1,561 @@
+use crate::sync::{
+  lock::atomic::{AtomicPtr, AtomicUsize, Ordering},
+  trie::Span,
+};
+use std::{
+  alloc::{self, Layout},
+  fmt, ptr, slice,
+};
+
+/// log2 of the capacity of the first bucket
+const FIRST_BUCKET_BITS: u32 = 12;
+
+/// Enough buckets to address the whole of `usize`
+const BUCKETS: usize = (usize::BITS - FIRST_BUCKET_BITS) as usize;
+
+/// Set on spans that don't lie in the store at all, but point straight at a
+/// `&'static str`, whose address is the rest of the span. Offsets into the
+/// store never get this high, since that would take half the address space.
+const STATIC_BIT: usize = 1 << (usize::BITS - 1);
+
+/// Append-only byte storage made of geometrically growing buckets. Bucket `i`
+/// holds `2^(FIRST_BUCKET_BITS + i)` bytes and, once allocated, never moves,
+/// so a span handed out by [Store::push] stays valid for as long as the store
+/// lives. Readers only ever load a bucket pointer, so they never need a lock.
+///
+/// Every pushed string lives entirely inside one bucket. When a string does
+/// not fit in the remainder of the current bucket, the tail is skipped and the
+/// string is placed at the start of the next bucket that is large enough.
+pub(crate) struct Store {
+  buckets: [AtomicPtr<u8>; BUCKETS],
+  /// Size of each bucket's allocation, which is less than its capacity once
+  /// it has been shrunk
+  sizes: [AtomicUsize; BUCKETS],
+  len: AtomicUsize,
+  stored: AtomicUsize,
+  allocated: AtomicUsize,
+}
+
+fn bucket_start(bucket: usize) -> usize {
+  ((1 << bucket) - 1) << FIRST_BUCKET_BITS
+}
+
+fn bucket_capacity(bucket: usize) -> usize {
+  1 << (FIRST_BUCKET_BITS as usize + bucket)
+}
+
+fn bucket_of(offset: usize) -> usize {
+  let scaled = (offset >> FIRST_BUCKET_BITS) + 1;
+  (usize::BITS - 1 - scaled.leading_zeros()) as usize
+}
+
+fn layout(size: usize) -> Layout {
+  Layout::array::<u8>(size).expect("bucket too large")
+}
+
+/// Length of the longest prefix of `s` that `tail` ends with, found the way
+/// Knuth-Morris-Pratt searches for `s` in `tail`
+fn overlap(tail: &[u8], s: &[u8]) -> usize {
+  // how far the search falls back to after matching each prefix of `s`
+  let mut fallback = vec![0; s.len()];
+  let mut matched = 0;
+  for i in 1..s.len() {
+    while matched > 0 && s[i] != s[matched] {
+      matched = fallback[matched - 1];
+    }
+    if s[i] == s[matched] {
+      matched += 1;
+    }
+    fallback[i] = matched;
+  }
+
+  // `tail` is no longer than `s`, so `s` can only match in full at the end
+  let mut matched = 0;
+  for &b in tail {
+    while matched > 0 && b != s[matched] {
+      matched = fallback[matched - 1];
+    }
+    if b == s[matched] {
+      matched += 1;
+    }
+  }
+
+  matched
+}
+
+impl Store {
+  pub(crate) fn new() -> Self {
+    Self {
+      buckets: std::array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+      sizes: std::array::from_fn(|_| AtomicUsize::new(0)),
+      len: AtomicUsize::new(0),
+      stored: AtomicUsize::new(0),
+      allocated: AtomicUsize::new(0),
+    }
+  }
+
+  /// Makes a span that reads `s` where it already is, instead of copying it
+  /// into the store. Returns [None] if `s` lives too high in memory to be
+  /// told apart from the store's own spans, which only happens on some 32-bit
+  /// platforms.
+  pub(crate) fn static_span(s: &'static str) -> Option<Span> {
+    // the address is turned back into a pointer by `Store::get_bytes`
+    let start = s.as_ptr().expose_provenance();
+    let end = start + s.len();
+    (end & STATIC_BIT == 0).then_some((start | STATIC_BIT)..(end | STATIC_BIT))
+  }
+
+  /// Whether `span` was made by [Store::static_span], or is a sub-range of
+  /// such a span
+  pub(crate) fn is_static(span: &Span) -> bool {
+    span.start & STATIC_BIT != 0
+  }
+
+  /// Number of bytes pushed so far, not counting skipped tails
+  pub(crate) fn stored_bytes(&self) -> usize {
+    self.stored.load(Ordering::Relaxed)
+  }
+
+  /// Number of bytes held by allocated buckets
+  pub(crate) fn allocated_bytes(&self) -> usize {
+    self.allocated.load(Ordering::Relaxed)
+  }
+
+  fn bucket_base(&self, bucket: usize) -> *mut u8 {
+    let mut base = self.buckets[bucket].load(Ordering::Relaxed);
+    if base.is_null() {
+      let size = bucket_capacity(bucket);
+      // SAFETY: buckets are never empty
+      base = unsafe { alloc::alloc(layout(size)) };
+      if base.is_null() {
+        alloc::handle_alloc_error(layout(size));
+      }
+      self.buckets[bucket].store(base, Ordering::Release);
+      self.sizes[bucket].store(size, Ordering::Relaxed);
+      self.allocated.fetch_add(size, Ordering::Relaxed);
+    }
+
+    base
+  }
+
+  /// Deallocates a bucket, if it was allocated.
+  ///
+  /// # Safety
+  ///
+  /// Same as [Store::clear], but only for spans within this bucket.
+  unsafe fn free_bucket(&self, bucket: usize) {
+    let base = self.buckets[bucket].swap(ptr::null_mut(), Ordering::Relaxed);
+    if !base.is_null() {
+      let size = self.sizes[bucket].swap(0, Ordering::Relaxed);
+      // SAFETY: the bucket was allocated, or last reallocated, with this
+      //         size, and the caller promises nothing is reading from it
+      unsafe { alloc::dealloc(base, layout(size)) };
+      self.allocated.fetch_sub(size, Ordering::Relaxed);
+    }
+  }
+
+  /// Allocates enough buckets up front that roughly `additional` more bytes
+  /// can be pushed without allocating.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the store would grow past `usize::MAX` bytes.
+  ///
+  /// # Safety
+  ///
+  /// Same as [Store::push].
+  pub(crate) unsafe fn reserve(&self, additional: usize) {
+    let len = self.len.load(Ordering::Relaxed);
+    if additional == 0 {
+      return;
+    }
+
+    let end = len.checked_add(additional).expect("capacity overflow");
+    for bucket in bucket_of(len)..=bucket_of(end - 1) {
+      self.bucket_base(bucket);
+    }
+  }
+
+  /// Copies `s` into the store and returns where it lives.
+  ///
+  /// # Safety
+  ///
+  /// Pushes must not race with each other; the interner serializes them
+  /// behind its mutex.
+  pub(crate) unsafe fn push(&self, s: &[u8]) -> Span {
+    let start = self.len.load(Ordering::Relaxed);
+    // SAFETY: nothing has been staged past the end of the store, and the
+    //         caller promises that no other push is racing with this one
+    unsafe {
+      let span = self.append_staged(start..start, s);
+      self.commit(span.clone());
+      span
+    }
+  }
+
+  /// Copies `s` into the store like [Store::push], except that if the store
+  /// already ends with the start of `s`, only the rest of `s` is copied, and
+  /// the span begins inside the text before it. This way strings that run
+  /// on from each other, such as `config` and `figure`, share their overlap.
+  ///
+  /// # Safety
+  ///
+  /// Same as [Store::push].
+  pub(crate) unsafe fn push_overlapping(&self, s: &[u8]) -> Span {
+    let len = self.len.load(Ordering::Relaxed);
+    // the overlap has to lie in the same bucket as the rest of the string
+    let Some(bucket) = len.checked_sub(1).map(bucket_of) else {
+      // SAFETY: passed on from the caller
+      return unsafe { self.push(s) };
+    };
+    let bucket_end =
+      bucket_start(bucket) + self.sizes[bucket].load(Ordering::Relaxed);
+    // a bucket that was shrunk to fit ends before the end of the store
+    if len > bucket_end {
+      // SAFETY: passed on from the caller
+      return unsafe { self.push(s) };
+    }
+    let tail_start = bucket_start(bucket).max(len.saturating_sub(s.len()));
+    // SAFETY: everything in the bucket up to the end of the store was pushed
+    let tail = unsafe { self.get_bytes(tail_start..len) };
+    let overlap = overlap(tail, s);
+    if overlap == 0 || len + s.len() - overlap > bucket_end {
+      // SAFETY: passed on from the caller
+      return unsafe { self.push(s) };
+    }
+
+    // SAFETY: the rest fits in the bucket right after the end of the store,
+    //         so it is staged in place, next to the overlap
+    unsafe {
+      let rest = self.append_staged(len..len, &s[overlap..]);
+      self.commit(rest);
+    }
+    (len - overlap)..(len - overlap + s.len())
+  }
+
+  /// Starts staging text in the free space past the end of the store, so
+  /// that it can be written bit by bit and read back before deciding whether
+  /// to keep it.
+  ///
+  /// # Safety
+  ///
+  /// Same as [Store::push], for as long as the [Stager] is in use.
+  pub(crate) unsafe fn stager(&self) -> Stager<'_> {
+    let start = self.len.load(Ordering::Relaxed);
+    Stager {
+      store: self,
+      staged: start..start,
+    }
+  }
+
+  /// Copies `s` onto the end of the text staged at `staged`, moving all of it
+  /// to the next bucket that is large enough if it no longer fits, and returns
+  /// where the text lives now.
+  ///
+  /// # Safety
+  ///
+  /// Same as [Store::push]. `staged` must be the last span staged, with
+  /// nothing written since.
+  unsafe fn append_staged(&self, staged: Span, s: &[u8]) -> Span {
+    if s.is_empty() {
+      return staged;
+    }
+
+    let len = staged.len() + s.len();
+    let mut start = staged.start;
+    let mut bucket = bucket_of(start);
+    if start + len > bucket_start(bucket) + bucket_capacity(bucket) {
+      bucket += 1;
+      while bucket_capacity(bucket) < len {
+        bucket += 1;
+      }
+      start = bucket_start(bucket);
+    }
+
+    let base = self.bucket_base(bucket);
+
+    // SAFETY: the destination lies within the bucket and past every span
+    //         handed out so far, so no reader can be looking at it. When the
+    //         text moves, it moves to a different bucket.
+    unsafe {
+      let dst = base.add(start - bucket_start(bucket));
+      if start != staged.start {
+        let moved = self.get_bytes(staged.clone());
+        ptr::copy_nonoverlapping(moved.as_ptr(), dst, moved.len());
+      }
+      ptr::copy_nonoverlapping(s.as_ptr(), dst.add(staged.len()), s.len());
+    }
+
+    start..(start + len)
+  }
+
+  /// Makes staged text part of the store, so that it is never written over.
+  ///
+  /// # Safety
+  ///
+  /// Same as [Store::push]. `span` must be the last span staged, with
+  /// nothing written since.
+  pub(crate) unsafe fn commit(&self, span: Span) {
+    if span.is_empty() {
+      return;
+    }
+
+    self.len.store(span.end, Ordering::Release);
+    self.stored.fetch_add(span.len(), Ordering::Relaxed);
+  }
+
+  /// Frees every bucket, leaving the store empty.
+  ///
+  /// # Safety
+  ///
+  /// Nothing may be reading from the store, and no span handed out so far may
+  /// be read again afterwards. Clearing must not race with pushes either.
+  pub(crate) unsafe fn clear(&self) {
+    for bucket in 0..BUCKETS {
+      // SAFETY: passed on from the caller
+      unsafe { self.free_bucket(bucket) };
+    }
+
+    self.len.store(0, Ordering::Relaxed);
+    self.stored.store(0, Ordering::Relaxed);
+  }
+
+  /// Frees every bucket that nothing has been pushed to, and shrinks the
+  /// allocation of the last bucket in use down to the bytes it holds. The
+  /// next push then starts at the following bucket. Returns how many bytes
+  /// were released.
+  ///
+  /// # Safety
+  ///
+  /// Nothing may be reading from the store, since the last bucket may move.
+  /// Spans handed out so far stay valid. Shrinking must not race with pushes
+  /// either.
+  pub(crate) unsafe fn shrink_to_fit(&self) -> usize {
+    let before = self.allocated_bytes();
+    let len = self.len.load(Ordering::Relaxed);
+    let in_use = if len == 0 { 0 } else { bucket_of(len - 1) + 1 };
+
+    for bucket in in_use..BUCKETS {
+      // SAFETY: nothing has been pushed to these buckets, so no span lies
+      //         in them
+      unsafe { self.free_bucket(bucket) };
+    }
+
+    if let Some(last) = in_use.checked_sub(1) {
+      let used = len - bucket_start(last);
+      let size = self.sizes[last].load(Ordering::Relaxed);
+      if used < size {
+        let base = self.buckets[last].load(Ordering::Relaxed);
+        // SAFETY: the bucket was allocated with `size`, `used` isn't 0 since
+        //         the end of the store lies in it, and the caller promises
+        //         nothing is reading from it while it may move
+        let base = unsafe { alloc::realloc(base, layout(size), used) };
+        if base.is_null() {
+          alloc::handle_alloc_error(layout(used));
+        }
+
+        self.buckets[last].store(base, Ordering::Release);
+        self.sizes[last].store(used, Ordering::Relaxed);
+        self.allocated.fetch_sub(size - used, Ordering::Relaxed);
+        // the bucket is full now, so the next push goes to the next one
+        self.len.store(bucket_start(last + 1), Ordering::Relaxed);
+      }
+    }
+
+    before - self.allocated_bytes()
+  }
+
+  /// Reads back a span of text without taking any locks.
+  ///
+  /// # Safety
+  ///
+  /// `span` must have been produced by pushing a [str] onto this store or by
+  /// [Store::static_span], or be a char-aligned sub-range of such a span.
+  pub(crate) unsafe fn get(&self, span: Span) -> &str {
+    // SAFETY: passed on from the caller, and strs are only ever cut at char
+    //         boundaries, so the bytes are valid UTF-8
+    unsafe { std::str::from_utf8_unchecked(self.get_bytes(span)) }
+  }
+
+  /// Reads back a span of raw bytes.
+  ///
+  /// # Safety
+  ///
+  /// `span` must have been produced by [Store::push] on this store or by
+  /// [Store::static_span], or be a sub-range of such a span.
+  pub(crate) unsafe fn get_bytes(&self, span: Span) -> &[u8] {
+    if span.is_empty() {
+      return &[];
+    }
+
+    if Self::is_static(&span) {
+      let data = ptr::with_exposed_provenance::<u8>(span.start & !STATIC_BIT);
+      // SAFETY: the span was made from a &'static str, which lives forever
+      return unsafe { slice::from_raw_parts(data, span.len()) };
+    }
+
+    let bucket = bucket_of(span.start);
+    let base = self.buckets[bucket].load(Ordering::Acquire);
+    debug_assert!(!base.is_null());
+
+    // SAFETY: the bytes were fully written before the span was published and
+    //         buckets are never written over or moved
+    unsafe {
+      let data = base.add(span.start - bucket_start(bucket));
+      slice::from_raw_parts(data, span.len())
+    }
+  }
+}
+
+impl Drop for Store {
+  fn drop(&mut self) {
+    // SAFETY: nothing can borrow from the store while it is being dropped
+    unsafe { self.clear() };
+  }
+}
+
+/// Writes text past the end of a [Store] without pushing it. The text can be
+/// read back and then kept with [Store::commit], or simply abandoned, in which
+/// case the next write goes over it.
+pub(crate) struct Stager<'a> {
+  store: &'a Store,
+  staged: Span,
+}
+
+impl Stager<'_> {
+  /// Where the text written so far currently lives
+  pub(crate) fn finish(self) -> Span {
+    self.staged
+  }
+}
+
+impl fmt::Write for Stager<'_> {
+  fn write_str(&mut self, s: &str) -> fmt::Result {
+    // SAFETY: creating the stager promised that nothing else writes to the
+    //         store while it is in use
+    self.staged =
+      unsafe { self.store.append_staged(self.staged.clone(), s.as_bytes()) };
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+#[allow(clippy::undocumented_unsafe_blocks)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn store() {
+    let store = Store::new();
+    let hello = unsafe { store.push(b"hello") };
+    let big = "x".repeat(bucket_capacity(0));
+    let big_span = unsafe { store.push(big.as_bytes()) };
+
+    assert_eq!(0..5, hello);
+    assert_eq!(bucket_start(1), big_span.start);
+    assert_eq!("hello", unsafe { store.get(hello) });
+    assert_eq!(big, unsafe { store.get(big_span) });
+    assert_eq!("", unsafe { store.get(3..3) });
+
+    unsafe { store.clear() };
+    assert_eq!(0, store.stored_bytes());
+    assert_eq!(0, store.allocated_bytes());
+    assert_eq!(0..3, unsafe { store.push(b"abc") });
+  }
+
+  #[test]
+  fn store_pushes_overlapping_strings() {
+    let store = Store::new();
+    let config = unsafe { store.push_overlapping(b"config") };
+    let figure = unsafe { store.push_overlapping(b"figure") };
+    let reap = unsafe { store.push_overlapping(b"reap") };
+    let apart = unsafe { store.push_overlapping(b"apart") };
+    let zz = unsafe { store.push_overlapping(b"zz") };
+
+    assert_eq!(0..6, config);
+    assert_eq!(3..9, figure);
+    assert_eq!(7..11, reap);
+    assert_eq!(9..14, apart);
+    assert_eq!(14..16, zz);
+    assert_eq!("figure", unsafe { store.get(figure) });
+    assert_eq!("configureapartzz", unsafe { store.get(0..16) });
+    assert_eq!(16, store.stored_bytes());
+
+    let full = "x".repeat(bucket_capacity(0) - 16);
+    unsafe { store.push(full.as_bytes()) };
+    // the rest of the string wouldn't fit in the first bucket
+    let next = unsafe { store.push_overlapping(b"xy") };
+    assert_eq!(bucket_start(1), next.start);
+    assert_eq!(2, overlap(b"abab", b"abc"));
+    assert_eq!(0, overlap(b"", b"abc"));
+    assert_eq!(3, overlap(b"aaa", b"aaa"));
+  }
+
+  #[test]
+  fn store_reads_static_spans() {
+    let store = Store::new();
+    let keyword = Store::static_span("keyword").unwrap();
+
+    assert!(Store::is_static(&keyword));
+    assert!(!Store::is_static(&unsafe { store.push(b"hello") }));
+    assert_eq!("keyword", unsafe { store.get(keyword.clone()) });
+    let key = keyword.start..(keyword.start + 3);
+    assert_eq!("key", unsafe { store.get(key) });
+    assert_eq!(5, store.stored_bytes());
+  }
+
+  #[test]
+  #[should_panic = "capacity overflow"]
+  fn store_does_not_reserve_past_the_address_space() {
+    let store = Store::new();
+    unsafe { store.push(b"hello") };
+    unsafe { store.reserve(usize::MAX) };
+  }
+
+  #[test]
+  fn store_shrinks_to_fit() {
+    let store = Store::new();
+    unsafe { store.reserve(bucket_capacity(0) + bucket_capacity(1)) };
+    let hello = unsafe { store.push(b"hello") };
+
+    assert_eq!(3 * bucket_capacity(0), store.allocated_bytes());
+    assert_eq!(3 * bucket_capacity(0) - 5, unsafe { store.shrink_to_fit() });
+    assert_eq!(5, store.allocated_bytes());
+    assert_eq!("hello", unsafe { store.get(hello) });
+
+    // the shrunk bucket is full, so nothing can overlap with its end
+    let lower = unsafe { store.push_overlapping(b"lower") };
+    assert_eq!(bucket_start(1), lower.start);
+    assert_eq!("lower", unsafe { store.get(lower) });
+    assert_eq!(0, unsafe { Store::new().shrink_to_fit() });
+  }
+
+  #[test]
+  fn store_stages_text() {
+    use std::fmt::Write;
+
+    let store = Store::new();
+    let mut stager = unsafe { store.stager() };
+    let world = "world";
+    write!(stager, "hello {world}").unwrap();
+    let staged = stager.finish();
+    assert_eq!("hello world", unsafe { store.get(staged.clone()) });
+    assert_eq!(0, store.stored_bytes());
+
+    let mut stager = unsafe { store.stager() };
+    stager.write_str("hi").unwrap();
+    let hi = stager.finish();
+    assert_eq!(0..2, hi);
+    unsafe { store.commit(hi) };
+    assert_eq!(2..5, unsafe { store.push(b"abc") });
+    assert_eq!("hiabc", unsafe { store.get(0..5) });
+    assert_eq!(5, store.stored_bytes());
+
+    let mut stager = unsafe { store.stager() };
+    let big = "x".repeat(bucket_capacity(0));
+    write!(stager, "moved{big}").unwrap();
+    let moved = stager.finish();
+    assert_eq!(bucket_start(1), moved.start);
+    assert_eq!(format!("moved{big}"), unsafe { store.get(moved) });
+  }
+}
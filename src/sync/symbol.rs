@@ -0,0 +1,34 @@
+/// A dense integer that identifies one string in an
+/// [Interner](crate::sync::Interner), produced by
+/// [Intern::id](crate::sync::Intern::id). Ids count up from 0 in the order
+/// strings were first interned, and a string keeps its id across
+/// [Interner::collect](crate::sync::Interner::collect) and
+/// [Interner::save_to](crate::sync::Interner::save_to), so they can index
+/// side tables or be packed into other data.
+///
+/// Ids only make sense to the interner that handed them out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SymbolId(pub u32);
+
+/// A lightweight handle to an interned string, produced by
+/// [Interner::intern_symbol](crate::sync::Interner::intern_symbol) or
+/// [Intern::symbol](crate::sync::Intern::symbol). Where an
+/// [Intern](crate::sync::Intern) carries a reference to its interner, a
+/// symbol is just an integer, so it is [Copy] and cheap to store in large
+/// numbers. Reading it back goes through
+/// [Interner::resolve](crate::sync::Interner::resolve).
+///
+/// Symbols compare and hash by id, so they are only meaningful alongside the
+/// interner that handed them out. The strings they represent are never
+/// collected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(pub(crate) SymbolId);
+
+impl Symbol {
+  /// Gets the [SymbolId] of the string, which can be turned into an
+  /// [Intern](crate::sync::Intern) with
+  /// [Interner::from_id](crate::sync::Interner::from_id)
+  pub fn id(self) -> SymbolId {
+    self.0
+  }
+}
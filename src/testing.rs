@@ -0,0 +1,331 @@
+use crate::{sync, unsync};
+use arbitrary::{Arbitrary, Unstructured};
+use std::{collections::HashMap, ops::Range};
+
+/// Characters that generated strings are made of. There are few enough that
+/// strings collide and overlap often, and some take more than one byte, so
+/// slices land off char boundaries.
+const ALPHABET: [char; 4] = ['a', 'b', 'é', '🦀'];
+
+/// The longest string that [Op]s are generated with, in chars
+const MAX_CHARS: usize = 8;
+
+/// One step of a run against an interner and a [Model]. Interns that the run
+/// holds onto are picked by position, wrapping around, so any sequence of
+/// ops is valid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op {
+  /// Interns a string and holds onto it
+  Intern(String),
+  /// Looks up a string without interning it
+  Get(String),
+  /// Slices a held intern by byte offsets, and holds onto the part if the
+  /// range is valid
+  Slice { of: usize, range: Range<usize> },
+  /// Holds onto a held intern a second time
+  Clone(usize),
+  /// Lets go of a held intern
+  Drop(usize),
+  /// Runs [sync::Interner::collect]
+  Collect,
+  /// Runs [sync::Interner::shrink_to_fit]
+  ShrinkToFit,
+}
+
+impl<'a> Arbitrary<'a> for Op {
+  fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+    let op = match u.int_in_range(0..=9)? {
+      0..=2 => Op::Intern(arbitrary_string(u)?),
+      3 => Op::Get(arbitrary_string(u)?),
+      4 | 5 => {
+        let of = u.arbitrary()?;
+        let start = u.int_in_range(0..=MAX_CHARS * 4)?;
+        let end = u.int_in_range(0..=MAX_CHARS * 4)?;
+        Op::Slice {
+          of,
+          range: start..end,
+        }
+      },
+      6 => Op::Clone(u.arbitrary()?),
+      7 => Op::Drop(u.arbitrary()?),
+      8 => Op::Collect,
+      _ => Op::ShrinkToFit,
+    };
+    Ok(op)
+  }
+}
+
+fn arbitrary_string(u: &mut Unstructured<'_>) -> arbitrary::Result<String> {
+  let chars = u.int_in_range(0..=MAX_CHARS)?;
+  (0..chars).map(|_| u.choose(&ALPHABET).copied()).collect()
+}
+
+/// Decodes a sequence of [Op]s out of raw bytes, such as a fuzzer's input
+/// or a `Vec<u8>` generated by proptest. Every input decodes to something,
+/// and short inputs decode to short runs.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::testing::{check_sync, ops};
+///
+/// check_sync(&ops(b"any bytes at all"));
+/// ```
+pub fn ops(bytes: &[u8]) -> Vec<Op> {
+  let mut u = Unstructured::new(bytes);
+  let mut ops = Vec::new();
+  while !u.is_empty() {
+    match Op::arbitrary(&mut u) {
+      Ok(op) => ops.push(op),
+      Err(_) => break,
+    }
+  }
+  ops
+}
+
+/// What an interner should do, kept as plainly as possible: a map from each
+/// interned string to its id, and the strings that are held. Like
+/// [SymbolId](sync::SymbolId)s, ids are handed out in order and never
+/// reused, and a collection only forgets strings that nothing holds.
+#[derive(Clone, Debug, Default)]
+pub struct Model {
+  ids: HashMap<String, u32>,
+  next_id: u32,
+  held: Vec<String>,
+}
+
+impl Model {
+  /// Starts a model of an empty interner
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Interns `s` and holds onto it, returning its id
+  pub fn intern(&mut self, s: &str) -> u32 {
+    let next_id = &mut self.next_id;
+    let id = *self.ids.entry(s.to_owned()).or_insert_with(|| {
+      *next_id += 1;
+      *next_id - 1
+    });
+    self.held.push(s.to_owned());
+    id
+  }
+
+  /// The id of `s`, if it is interned
+  pub fn get(&self, s: &str) -> Option<u32> {
+    self.ids.get(s).copied()
+  }
+
+  /// Slices the held string at `of` and interns the part, or returns [None]
+  /// if the range doesn't lie on char boundaries within it
+  pub fn slice(&mut self, of: usize, range: Range<usize>) -> Option<u32> {
+    let part = self.held[of].get(range)?.to_owned();
+    Some(self.intern(&part))
+  }
+
+  /// Holds onto the held string at `i` a second time
+  pub fn clone_held(&mut self, i: usize) {
+    self.held.push(self.held[i].clone());
+  }
+
+  /// Lets go of the held string at `i`. Held strings are kept in the same
+  /// order as the interns that a run holds, so it removes them the same way.
+  pub fn drop_held(&mut self, i: usize) {
+    self.held.swap_remove(i);
+  }
+
+  /// Forgets every string that isn't held
+  pub fn collect(&mut self) {
+    let held = &self.held;
+    self.ids.retain(|s, _| held.contains(s));
+  }
+
+  /// The strings that are held, in the order they were taken
+  pub fn held(&self) -> &[String] {
+    &self.held
+  }
+}
+
+/// Runs `ops` against a [sync::Interner] and a [Model] side by side,
+/// checking after every step that each held intern reads back the right
+/// string with the right id, and that lookups agree.
+///
+/// # Panics
+///
+/// Panics if the interner and the model disagree.
+pub fn check_sync(ops: &[Op]) {
+  let interner = sync::Interner::new();
+  let mut model = Model::new();
+  let mut held = Vec::new();
+
+  for op in ops {
+    match op {
+      Op::Intern(s) => {
+        let id = model.intern(s);
+        let intern = interner.intern(s);
+        assert_eq!(intern.id(), sync::SymbolId(id), "{op:?}");
+        held.push(intern);
+      },
+      Op::Get(s) => {
+        let id = interner.get(s).map(|intern| intern.id().0);
+        assert_eq!(id, model.get(s), "{op:?}");
+      },
+      Op::Slice { of, range } => {
+        let Some(of) = pick(held.len(), *of) else {
+          continue;
+        };
+        let part = held[of].slice(range.clone());
+        match (part, model.slice(of, range.clone())) {
+          (Ok(part), Some(id)) => {
+            assert_eq!(part.id(), sync::SymbolId(id), "{op:?}");
+            held.push(part);
+          },
+          (part, id) => assert_eq!(part.ok(), None, "{op:?} {id:?}"),
+        }
+      },
+      Op::Clone(i) => {
+        if let Some(i) = pick(held.len(), *i) {
+          model.clone_held(i);
+          held.push(held[i].clone());
+        }
+      },
+      Op::Drop(i) => {
+        if let Some(i) = pick(held.len(), *i) {
+          model.drop_held(i);
+          held.swap_remove(i);
+        }
+      },
+      Op::Collect => {
+        model.collect();
+        interner.collect();
+      },
+      Op::ShrinkToFit => {
+        interner.shrink_to_fit();
+      },
+    }
+
+    for (intern, s) in held.iter().zip(model.held()) {
+      assert_eq!(&*intern.get_ref(), s, "after {op:?}");
+      assert_eq!(intern.id().0, model.get(s).unwrap(), "after {op:?}");
+      assert_eq!(interner.from_id(intern.id()).as_ref(), Some(intern));
+    }
+  }
+}
+
+/// Runs `ops` against an [unsync::Interner] and a [Model], like
+/// [check_sync]
+///
+/// # Panics
+///
+/// Panics if the interner and the model disagree.
+pub fn check_unsync(ops: &[Op]) {
+  let interner = unsync::Interner::new();
+  let mut model = Model::new();
+  let mut held = Vec::new();
+
+  for op in ops {
+    match op {
+      Op::Intern(s) => {
+        let id = model.intern(s);
+        let intern = interner.intern(s);
+        assert_eq!(intern.id(), unsync::SymbolId(id), "{op:?}");
+        held.push(intern);
+      },
+      Op::Get(s) => {
+        let id = interner.get(s).map(|intern| intern.id().0);
+        assert_eq!(id, model.get(s), "{op:?}");
+      },
+      Op::Slice { of, range } => {
+        let Some(of) = pick(held.len(), *of) else {
+          continue;
+        };
+        let part = held[of].slice(range.clone());
+        match (part, model.slice(of, range.clone())) {
+          (Ok(part), Some(id)) => {
+            assert_eq!(part.id(), unsync::SymbolId(id), "{op:?}");
+            held.push(part);
+          },
+          (part, id) => assert_eq!(part.ok(), None, "{op:?} {id:?}"),
+        }
+      },
+      Op::Clone(i) => {
+        if let Some(i) = pick(held.len(), *i) {
+          model.clone_held(i);
+          held.push(held[i].clone());
+        }
+      },
+      Op::Drop(i) => {
+        if let Some(i) = pick(held.len(), *i) {
+          model.drop_held(i);
+          held.swap_remove(i);
+        }
+      },
+      Op::Collect => {
+        model.collect();
+        interner.collect();
+      },
+      Op::ShrinkToFit => {
+        interner.shrink_to_fit();
+      },
+    }
+
+    for (intern, s) in held.iter().zip(model.held()) {
+      assert_eq!(&*intern.get_ref(), s, "after {op:?}");
+      assert_eq!(intern.id().0, model.get(s).unwrap(), "after {op:?}");
+      assert_eq!(interner.from_id(intern.id()).as_ref(), Some(intern));
+    }
+  }
+}
+
+/// Picks the held intern at `i`, wrapping around, or [None] if nothing is
+/// held
+fn pick(len: usize, i: usize) -> Option<usize> {
+  (len > 0).then(|| i % len)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Bytes from a fixed xorshift, so every run checks the same inputs
+  fn inputs() -> impl Iterator<Item = Vec<u8>> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      state
+    };
+    (0..200).map(move |_| {
+      let len = next() % 512;
+      (0..len).map(|_| next() as u8).collect()
+    })
+  }
+
+  #[test]
+  fn model_matches_interners() {
+    for input in inputs() {
+      let ops = ops(&input);
+      check_sync(&ops);
+      check_unsync(&ops);
+    }
+  }
+
+  #[test]
+  fn model_catches_collected_strings() {
+    let ops = [
+      Op::Intern("héllo".into()),
+      Op::Slice { of: 0, range: 0..2 },
+      Op::Slice { of: 0, range: 0..3 },
+      Op::Drop(0),
+      Op::Collect,
+      Op::Get("héllo".into()),
+      Op::Get("hé".into()),
+      Op::Intern("héllo".into()),
+      Op::ShrinkToFit,
+    ];
+
+    check_sync(&ops);
+    check_unsync(&ops);
+  }
+}
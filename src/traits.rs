@@ -0,0 +1,356 @@
+use crate::{sync, unsync};
+use std::{fmt::Debug, hash::Hash};
+
+/// A small [Copy] handle that stands for a string in some interner, such as
+/// [sync::Symbol] or, with the `lasso` feature, `lasso::Spur`. Every key is
+/// backed by an integer, so keys of one kind can be turned into another with
+/// [Key::try_into_key].
+pub trait Key: Copy + Eq + Hash + Debug {
+  /// The integer behind this key
+  fn into_usize(self) -> usize;
+
+  /// Makes the key that `n` is behind, or [None] if `n` is out of range for
+  /// this kind of key
+  fn try_from_usize(n: usize) -> Option<Self>;
+
+  /// Converts this key to another kind with the same integer behind it,
+  /// which is how keys are carried between interners that number their
+  /// strings the same way.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use str_interning::{sync::Interner, traits::Key, unsync};
+  ///
+  /// let interner = Interner::new();
+  /// let hello = interner.intern_symbol("hello");
+  ///
+  /// let key = hello.try_into_key::<unsync::Symbol>().unwrap();
+  ///
+  /// assert_eq!(key.id().0, hello.id().0);
+  /// ```
+  fn try_into_key<K: Key>(self) -> Option<K> {
+    K::try_from_usize(self.into_usize())
+  }
+}
+
+/// An interner that can read back the strings behind its keys, so that
+/// code can accept any interner, including those of other crates, without
+/// depending on which one it is.
+///
+/// Strings are handed to a closure rather than returned, since some
+/// interners, such as [sync::Interner], need to know when a string is no
+/// longer being read.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::{sync, traits::Resolver, unsync};
+///
+/// fn shout<R: Resolver>(resolver: &R, key: R::Key) -> String {
+///   resolver.resolve_with(key, str::to_uppercase)
+/// }
+///
+/// let interner = sync::Interner::new();
+/// assert_eq!(shout(&*interner, interner.intern_symbol("hi")), "HI");
+///
+/// let interner = unsync::Interner::new();
+/// assert_eq!(shout(&*interner, interner.intern_symbol("hey")), "HEY");
+/// ```
+pub trait Resolver {
+  /// The kind of key that stands for a string
+  type Key: Key;
+
+  /// Calls `f` on the string behind `key`, or returns [None] if `key` came
+  /// from a different interner
+  fn try_resolve_with<R>(
+    &self,
+    key: Self::Key,
+    f: impl FnOnce(&str) -> R,
+  ) -> Option<R>;
+
+  /// Number of distinct strings
+  fn len(&self) -> usize;
+
+  /// Calls `f` on the string behind `key`
+  ///
+  /// # Panics
+  ///
+  /// May panic if `key` came from a different interner.
+  fn resolve_with<R>(&self, key: Self::Key, f: impl FnOnce(&str) -> R) -> R {
+    self
+      .try_resolve_with(key, f)
+      .expect("key does not belong to this interner")
+  }
+
+  /// Copies out the string behind `key`
+  ///
+  /// # Panics
+  ///
+  /// May panic if `key` came from a different interner.
+  fn resolve_to_string(&self, key: Self::Key) -> String {
+    self.resolve_with(key, str::to_owned)
+  }
+
+  /// Whether `key` stands for a string in this interner
+  fn contains_key(&self, key: Self::Key) -> bool {
+    self.try_resolve_with(key, |_| ()).is_some()
+  }
+
+  /// Whether there are no strings at all
+  fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+/// An interner that hands out keys, which can be read back through its
+/// [Resolver] implementation. Interning goes through a shared reference,
+/// like it does for the interners in this crate, so one interner can be
+/// handed to several parts of a program at once.
+///
+/// # Examples
+///
+/// ```
+/// use str_interning::{
+///   sync,
+///   traits::{Interner, Resolver},
+/// };
+///
+/// fn tokens<I: Interner>(interner: &I, text: &str) -> Vec<I::Key> {
+///   text
+///     .split_whitespace()
+///     .map(|word| interner.get_or_intern(word))
+///     .collect()
+/// }
+///
+/// let interner = sync::Interner::new();
+/// let keys = tokens(&*interner, "to be or not to be");
+///
+/// assert_eq!(keys[0], keys[4]);
+/// assert_eq!(interner.get_key("not"), Some(keys[3]));
+/// assert_eq!(interner.len(), 4);
+/// ```
+pub trait Interner: Resolver {
+  /// Interns `s`, returning the key that stands for it
+  fn get_or_intern(&self, s: &str) -> Self::Key;
+
+  /// Finds the key of a previously interned string
+  fn get_key(&self, s: &str) -> Option<Self::Key>;
+}
+
+impl Key for sync::Symbol {
+  fn into_usize(self) -> usize {
+    self.id().0 as usize
+  }
+
+  fn try_from_usize(n: usize) -> Option<Self> {
+    Some(Self(sync::SymbolId(n.try_into().ok()?)))
+  }
+}
+
+/// Keys are [sync::Symbol]s, so strings that are resolved through this
+/// trait are never collected.
+impl Resolver for sync::Interner {
+  type Key = sync::Symbol;
+
+  #[cfg_attr(feature = "diagnostics", track_caller)]
+  fn try_resolve_with<R>(
+    &self,
+    key: Self::Key,
+    f: impl FnOnce(&str) -> R,
+  ) -> Option<R> {
+    self.try_resolve(key).map(|s| f(&s))
+  }
+
+  fn len(&self) -> usize {
+    self.stats().entries
+  }
+}
+
+impl Interner for sync::Interner {
+  fn get_or_intern(&self, s: &str) -> Self::Key {
+    self.intern_symbol(s)
+  }
+
+  fn get_key(&self, s: &str) -> Option<Self::Key> {
+    self.get_symbol(s)
+  }
+}
+
+impl Key for unsync::Symbol {
+  fn into_usize(self) -> usize {
+    self.id().0 as usize
+  }
+
+  fn try_from_usize(n: usize) -> Option<Self> {
+    Some(Self(unsync::SymbolId(n.try_into().ok()?)))
+  }
+}
+
+/// Keys are [unsync::Symbol]s, so strings that are resolved through this
+/// trait are never collected.
+impl Resolver for unsync::Interner {
+  type Key = unsync::Symbol;
+
+  fn try_resolve_with<R>(
+    &self,
+    key: Self::Key,
+    f: impl FnOnce(&str) -> R,
+  ) -> Option<R> {
+    self.try_resolve(key).map(|s| f(&s))
+  }
+
+  fn len(&self) -> usize {
+    self.stats().entries
+  }
+}
+
+impl Interner for unsync::Interner {
+  fn get_or_intern(&self, s: &str) -> Self::Key {
+    self.intern_symbol(s)
+  }
+
+  fn get_key(&self, s: &str) -> Option<Self::Key> {
+    self.get_symbol(s)
+  }
+}
+
+#[cfg(feature = "lasso")]
+mod lasso_impls {
+  use super::*;
+  use lasso::{
+    LargeSpur, MicroSpur, MiniSpur, RodeoReader, RodeoResolver, Spur,
+    ThreadedRodeo,
+  };
+  use std::hash::BuildHasher;
+
+  macro_rules! lasso_keys {
+    ($($key:ty),*) => {$(
+      impl Key for $key {
+        fn into_usize(self) -> usize {
+          lasso::Key::into_usize(self)
+        }
+
+        fn try_from_usize(n: usize) -> Option<Self> {
+          lasso::Key::try_from_usize(n)
+        }
+      }
+    )*};
+  }
+
+  lasso_keys!(Spur, MiniSpur, MicroSpur, LargeSpur);
+
+  impl<K, S> Resolver for ThreadedRodeo<K, S>
+  where
+    K: Key + lasso::Key,
+    S: BuildHasher + Clone,
+  {
+    type Key = K;
+
+    fn try_resolve_with<R>(
+      &self,
+      key: K,
+      f: impl FnOnce(&str) -> R,
+    ) -> Option<R> {
+      self.try_resolve(&key).map(f)
+    }
+
+    fn len(&self) -> usize {
+      self.len()
+    }
+  }
+
+  impl<K, S> Interner for ThreadedRodeo<K, S>
+  where
+    K: Key + lasso::Key,
+    S: BuildHasher + Clone,
+  {
+    fn get_or_intern(&self, s: &str) -> K {
+      self.get_or_intern(s)
+    }
+
+    fn get_key(&self, s: &str) -> Option<K> {
+      self.get(s)
+    }
+  }
+
+  impl<K, S> Resolver for RodeoReader<K, S>
+  where
+    K: Key + lasso::Key,
+  {
+    type Key = K;
+
+    fn try_resolve_with<R>(
+      &self,
+      key: K,
+      f: impl FnOnce(&str) -> R,
+    ) -> Option<R> {
+      self.try_resolve(&key).map(f)
+    }
+
+    fn len(&self) -> usize {
+      self.len()
+    }
+  }
+
+  impl<K: Key + lasso::Key> Resolver for RodeoResolver<K> {
+    type Key = K;
+
+    fn try_resolve_with<R>(
+      &self,
+      key: K,
+      f: impl FnOnce(&str) -> R,
+    ) -> Option<R> {
+      self.try_resolve(&key).map(f)
+    }
+
+    fn len(&self) -> usize {
+      self.len()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn round_trip<I: Interner>(interner: &I) {
+    let words = ["alpha", "beta", "alpha", "gamma"];
+    let keys = words.map(|word| interner.get_or_intern(word));
+
+    assert_eq!(keys[0], keys[2]);
+    assert_eq!(interner.len(), 3);
+    for (word, key) in words.iter().zip(keys) {
+      assert_eq!(interner.resolve_to_string(key), *word);
+      assert_eq!(interner.get_key(word), Some(key));
+      assert!(interner.contains_key(key));
+    }
+    assert_eq!(interner.get_key("delta"), None);
+    let missing = I::Key::try_from_usize(100).unwrap();
+    assert!(!interner.contains_key(missing));
+    assert_eq!(interner.try_resolve_with(missing, str::len), None);
+  }
+
+  #[test]
+  fn interners_resolve_through_traits() {
+    round_trip(&*sync::Interner::new());
+    round_trip(&*unsync::Interner::new());
+
+    let symbol = sync::Interner::new().intern_symbol("hello");
+    let key = symbol.try_into_key::<unsync::Symbol>().unwrap();
+    assert_eq!(key.try_into_key(), Some(symbol));
+    assert_eq!(sync::Symbol::try_from_usize(usize::MAX), None);
+  }
+
+  #[cfg(feature = "lasso")]
+  #[test]
+  fn lasso_interners_resolve_through_traits() {
+    let rodeo = lasso::ThreadedRodeo::<lasso::Spur>::new();
+    round_trip(&rodeo);
+
+    let spur = rodeo.get_or_intern("alpha");
+    let symbol = spur.try_into_key::<sync::Symbol>().unwrap();
+    assert_eq!(symbol.try_into_key(), Some(spur));
+    assert_eq!(rodeo.into_resolver().resolve_to_string(spur), "alpha");
+  }
+}
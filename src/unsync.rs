@@ -1,6 +1,55 @@
+mod automaton;
+mod builder;
+mod bytes;
+mod compact;
+mod dictionary;
 mod error;
+mod escape;
+mod folding;
+mod frozen;
+mod index;
 mod interning;
+#[cfg(feature = "aho-corasick")]
+mod matcher;
+mod merge;
+#[cfg(feature = "memmap2")]
+mod mmap;
+mod namespace;
+mod normalization;
+mod path;
+mod persistence;
+mod sequence;
+#[cfg(feature = "serde")]
+mod serialization;
+mod source;
+mod stats;
+mod store;
+mod symbol;
 mod trie;
 
-pub use error::InternError;
-pub use interning::{Intern, InternRef, Interner};
+pub use builder::{InternerBuilder, StoreFullPolicy};
+pub use bytes::{ByteInterner, InternBytes, InternBytesRef};
+pub use compact::{CompactIntern, INLINE_CAPACITY};
+pub use error::{InternError, PartsError, RefError, SliceError, UnescapeError};
+pub use escape::EscapeStyle;
+pub use folding::CaseFolding;
+pub use frozen::{FrozenIntern, FrozenInterner};
+pub use index::IndexBackend;
+pub use interning::{
+  BorrowedIntern, DetachedIntern, Intern, InternDisplay, InternRef,
+  InternScope, InternStr, Interner, InternerIter, WeakIntern,
+};
+#[cfg(feature = "aho-corasick")]
+pub use matcher::InternMatcher;
+pub use merge::MergeMap;
+#[cfg(feature = "memmap2")]
+pub use mmap::{MmapIntern, MmapInterner};
+pub use namespace::{NamespacedInterner, TaggedIntern};
+pub use normalization::Normalization;
+pub use path::InternPath;
+pub use sequence::{InternSequence, SequenceInterner};
+#[cfg(feature = "serde")]
+pub use serialization::InternSeed;
+pub use source::{LineColumn, SourceId};
+pub use stats::InternerStats;
+pub use symbol::{Symbol, SymbolId};
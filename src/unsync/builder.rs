@@ -0,0 +1,119 @@
+use crate::unsync::{
+  dictionary, CaseFolding, IndexBackend, Interner, Normalization,
+};
+use std::{io, rc::Rc};
+
+#[derive(Clone, Debug, Default)]
+pub struct InternerBuilder {
+  pub(crate) index: IndexBackend,
+  pub(crate) substring_sharing: Option<bool>,
+  pub(crate) capacity: (usize, usize),
+  pub(crate) case_folding: CaseFolding,
+  pub(crate) normalization: Normalization,
+  pub(crate) max_store_bytes: Option<usize>,
+  pub(crate) store_full: StoreFullPolicy,
+  pub(crate) preserve_case: bool,
+  pub(crate) record_spellings: bool,
+  pub(crate) keywords: Vec<String>,
+  pub(crate) dictionary: Vec<String>,
+  pub(crate) count_interns: bool,
+  pub(crate) max_outstanding_refs: Option<usize>,
+}
+
+impl InternerBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn index(mut self, backend: IndexBackend) -> Self {
+    self.index = backend;
+    self
+  }
+
+  // defaults to on for the trie backend and off for the hash backend
+  pub fn substring_sharing(mut self, enabled: bool) -> Self {
+    self.substring_sharing = Some(enabled);
+    self
+  }
+
+  pub fn capacity(mut self, bytes: usize, entries: usize) -> Self {
+    self.capacity = (bytes, entries);
+    self
+  }
+
+  pub fn case_folding(mut self, folding: CaseFolding) -> Self {
+    self.case_folding = folding;
+    self
+  }
+
+  pub fn normalization(mut self, normalization: Normalization) -> Self {
+    self.normalization = normalization;
+    self
+  }
+
+  // keeps the first spelling of each string to show instead of the folded one
+  pub fn preserve_case(mut self, enabled: bool) -> Self {
+    self.preserve_case = enabled;
+    self
+  }
+
+  // keeps every distinct spelling of each string, for Interner::spellings
+  pub fn record_spellings(mut self, enabled: bool) -> Self {
+    self.record_spellings = enabled;
+    self
+  }
+
+  pub fn max_store_bytes(mut self, limit: usize) -> Self {
+    self.max_store_bytes = Some(limit);
+    self
+  }
+
+  // what interning does once a new string would go over max_store_bytes
+  pub fn on_store_full(mut self, policy: StoreFullPolicy) -> Self {
+    self.store_full = policy;
+    self
+  }
+
+  // interned in order when the interner is built, so they get the symbol
+  // ids 0 to keywords.len() - 1
+  pub fn with_keywords(mut self, keywords: &[&str]) -> Self {
+    self.keywords = keywords.iter().map(|&keyword| keyword.into()).collect();
+    self
+  }
+
+  // seeded after the keywords, skipping strings that are already there
+  pub fn with_dictionary(mut self, dictionary: &[u8]) -> io::Result<Self> {
+    self.dictionary = dictionary::decode(dictionary)?;
+    Ok(self)
+  }
+
+  // counts how many times each string is interned, for Interner::counts
+  pub fn count_interns(mut self, enabled: bool) -> Self {
+    self.count_interns = enabled;
+    self
+  }
+
+  // how many InternRefs may be alive before Intern::try_get_ref refuses
+  pub fn max_outstanding_refs(mut self, limit: usize) -> Self {
+    self.max_outstanding_refs = Some(limit);
+    self
+  }
+
+  pub fn build(&self) -> Rc<Interner> {
+    Rc::new(Interner::from_builder(self))
+  }
+
+  // an interner owned directly, for use with Interner::intern_borrowed
+  pub fn build_owned(&self) -> Interner {
+    Interner::from_builder(self)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StoreFullPolicy {
+  #[default]
+  Fail,
+  // evicts strings that nothing holds, least recently used first, unless
+  // there are InternRefs
+  Evict,
+}
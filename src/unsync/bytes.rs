@@ -0,0 +1,141 @@
+use crate::unsync::{
+  index::{Index, IndexBackend},
+  store::Store,
+  trie::Span,
+};
+use std::{
+  cell::RefCell,
+  cmp::Ordering,
+  fmt,
+  hash::{Hash, Hasher},
+  ops::Deref,
+  rc::Rc,
+};
+
+pub struct ByteInterner {
+  index: RefCell<Index>,
+  store: Store,
+}
+
+impl ByteInterner {
+  pub fn new() -> Rc<Self> {
+    Rc::new(Self {
+      index: RefCell::new(Index::new(IndexBackend::Trie, true)),
+      store: Store::new(),
+    })
+  }
+
+  pub fn intern<B: AsRef<[u8]>>(self: &Rc<Self>, b: B) -> InternBytes {
+    let mut index = self.index.borrow_mut();
+
+    let b = b.as_ref();
+    let span = index.get_or_promote(b).unwrap_or_else(|| {
+      let span = self.store.push_overlapping(b);
+      index.insert(b, span)
+    });
+
+    InternBytes {
+      span,
+      interner: Rc::clone(self),
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct InternBytes {
+  span: Span,
+  interner: Rc<ByteInterner>,
+}
+
+impl InternBytes {
+  pub fn get_ref(&self) -> InternBytesRef<'_> {
+    InternBytesRef {
+      bytes: self.as_bytes(),
+    }
+  }
+
+  pub(crate) fn as_bytes(&self) -> &[u8] {
+    // SAFETY: our span was handed out by this interner's store
+    unsafe { self.interner.store.get_bytes(self.span.clone()) }
+  }
+
+  pub fn resolve(&self) -> Vec<u8> {
+    self.get_ref().to_vec()
+  }
+}
+
+impl fmt::Debug for InternBytes {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("InternBytes")
+      .field("span", &self.span)
+      .field("bytes", &&*self.get_ref())
+      .finish()
+  }
+}
+
+impl PartialEq for InternBytes {
+  fn eq(&self, other: &Self) -> bool {
+    Rc::ptr_eq(&self.interner, &other.interner) && self.span == other.span
+  }
+}
+impl Eq for InternBytes {}
+
+impl Ord for InternBytes {
+  fn cmp(&self, other: &Self) -> Ordering {
+    Rc::as_ptr(&self.interner)
+      .cmp(&Rc::as_ptr(&other.interner))
+      .then(self.span.start.cmp(&other.span.start))
+      .then(self.span.end.cmp(&other.span.end))
+  }
+}
+
+impl PartialOrd for InternBytes {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Hash for InternBytes {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    Rc::as_ptr(&self.interner).hash(state);
+    self.span.hash(state);
+  }
+}
+
+pub struct InternBytesRef<'a> {
+  bytes: &'a [u8],
+}
+
+impl fmt::Debug for InternBytesRef<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("InternBytesRef").field(&self.bytes).finish()
+  }
+}
+
+impl Deref for InternBytesRef<'_> {
+  type Target = [u8];
+
+  fn deref(&self) -> &Self::Target {
+    self.bytes
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::ptr;
+
+  #[test]
+  fn byte_interner() {
+    let interner = ByteInterner::new();
+    let invalid = interner.intern(b"\xc3\x28 tail");
+    let tail = interner.intern(b"tail");
+    let invalid2 = interner.intern(b"\xc3\x28 tail");
+
+    assert_eq!(&*invalid.get_ref(), b"\xc3\x28 tail");
+    assert_eq!(&*tail.get_ref(), b"tail");
+    assert_eq!(invalid, invalid2);
+    assert_ne!(invalid, tail);
+    assert!(ptr::addr_eq(&*invalid.get_ref(), &*invalid2.get_ref()));
+  }
+}
@@ -0,0 +1,212 @@
+use crate::unsync::{Intern, Interner};
+use std::{
+  fmt,
+  hash::{Hash, Hasher},
+  rc::Rc,
+  str,
+};
+
+pub const INLINE_CAPACITY: usize = 22;
+
+// short strings are kept in canonical form, and have no SymbolId. Whether a
+// string is kept inline only depends on its canonical form, so an inline one
+// never needs to equal a stored one
+#[derive(Clone)]
+pub struct CompactIntern {
+  repr: Repr,
+}
+
+#[derive(Clone)]
+enum Repr {
+  Inline {
+    interner: Rc<Interner>,
+    len: u8,
+    bytes: [u8; INLINE_CAPACITY],
+  },
+  Stored(Intern),
+}
+
+impl CompactIntern {
+  // s is in canonical form
+  pub(crate) fn new(
+    interner: &Rc<Interner>,
+    s: &str,
+    stored: impl FnOnce() -> Intern,
+  ) -> Self {
+    if s.len() > INLINE_CAPACITY {
+      return Self {
+        repr: Repr::Stored(stored()),
+      };
+    }
+
+    let mut bytes = [0; INLINE_CAPACITY];
+    bytes[..s.len()].copy_from_slice(s.as_bytes());
+    Self {
+      repr: Repr::Inline {
+        interner: Rc::clone(interner),
+        len: s.len() as u8,
+        bytes,
+      },
+    }
+  }
+
+  pub fn is_inline(&self) -> bool {
+    matches!(self.repr, Repr::Inline { .. })
+  }
+
+  pub fn with_str<R>(&self, f: impl FnOnce(&str) -> R) -> R {
+    match &self.repr {
+      Repr::Inline { len, bytes, .. } => f(inline_str(bytes, *len)),
+      Repr::Stored(intern) => intern.with_str(f),
+    }
+  }
+
+  pub fn resolve(&self) -> String {
+    self.with_str(str::to_owned)
+  }
+
+  pub fn len(&self) -> usize {
+    match &self.repr {
+      Repr::Inline { len, .. } => usize::from(*len),
+      Repr::Stored(intern) => intern.len(),
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn interner(&self) -> &Rc<Interner> {
+    match &self.repr {
+      Repr::Inline { interner, .. } => interner,
+      Repr::Stored(intern) => intern.interner(),
+    }
+  }
+
+  // interns the string in the store if it was kept inline
+  pub fn into_intern(self) -> Intern {
+    match self.repr {
+      Repr::Inline {
+        interner,
+        len,
+        bytes,
+      } => interner.intern(inline_str(&bytes, len)),
+      Repr::Stored(intern) => intern,
+    }
+  }
+}
+
+fn inline_str(bytes: &[u8; INLINE_CAPACITY], len: u8) -> &str {
+  // SAFETY: the bytes were copied from a str, which is only ever cut where
+  //         it ends
+  unsafe { str::from_utf8_unchecked(&bytes[..usize::from(len)]) }
+}
+
+impl From<Intern> for CompactIntern {
+  fn from(intern: Intern) -> Self {
+    let interner = Rc::clone(intern.interner());
+    let s = intern.with_str(|s| interner.canonical(s).into_owned());
+    Self::new(&interner, &s, || intern)
+  }
+}
+
+impl fmt::Debug for CompactIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.repr {
+      Repr::Inline { len, bytes, .. } => f
+        .debug_tuple("CompactIntern")
+        .field(&inline_str(bytes, *len))
+        .finish(),
+      Repr::Stored(intern) => {
+        f.debug_tuple("CompactIntern").field(intern).finish()
+      },
+    }
+  }
+}
+
+impl fmt::Display for CompactIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.repr {
+      Repr::Inline { len, bytes, .. } => f.pad(inline_str(bytes, *len)),
+      Repr::Stored(intern) => fmt::Display::fmt(intern, f),
+    }
+  }
+}
+
+impl PartialEq for CompactIntern {
+  fn eq(&self, other: &Self) -> bool {
+    match (&self.repr, &other.repr) {
+      (
+        Repr::Inline {
+          interner,
+          len,
+          bytes,
+        },
+        Repr::Inline {
+          interner: other_interner,
+          len: other_len,
+          bytes: other_bytes,
+        },
+      ) => {
+        Rc::ptr_eq(interner, other_interner)
+          && inline_str(bytes, *len) == inline_str(other_bytes, *other_len)
+      },
+      (Repr::Stored(intern), Repr::Stored(other)) => intern == other,
+      _ => false,
+    }
+  }
+}
+impl Eq for CompactIntern {}
+
+impl PartialEq<str> for CompactIntern {
+  fn eq(&self, other: &str) -> bool {
+    self.len() == other.len() && self.with_str(|s| s == other)
+  }
+}
+
+impl PartialEq<&str> for CompactIntern {
+  fn eq(&self, other: &&str) -> bool {
+    *self == **other
+  }
+}
+
+impl Hash for CompactIntern {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    match &self.repr {
+      Repr::Inline {
+        interner,
+        len,
+        bytes,
+      } => {
+        Rc::as_ptr(interner).hash(state);
+        inline_str(bytes, *len).hash(state);
+      },
+      Repr::Stored(intern) => intern.hash(state),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn compact_interns() {
+    let interner = Interner::new();
+    let words = ["", "x", "exactly_twenty_two_chr", "twenty_three_characters"]
+      .map(|word| interner.intern_compact(word));
+
+    assert_eq!(
+      words.each_ref().map(CompactIntern::is_inline),
+      [true, true, true, false]
+    );
+    assert_eq!(interner.stats().stored_bytes, 23);
+    for word in &words {
+      assert_eq!(*word, interner.intern_compact(word.resolve()));
+      assert_ne!(*word, Interner::new().intern_compact(word.resolve()));
+    }
+
+    let x = words[1].clone().into_intern();
+    assert_eq!(CompactIntern::from(x), words[1]);
+  }
+}
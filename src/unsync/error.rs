@@ -1,7 +1,65 @@
+use crate::unsync::SymbolId;
+use std::ops::Range;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum InternError {
-  #[error("Cannot intern while holding an InternRef")]
-  OutstandingRef(&'static std::panic::Location<'static>),
+  #[error("interning would grow the store past its limit of {limit} bytes")]
+  StoreFull { limit: usize },
+}
+
+#[derive(Debug, Error)]
+pub enum SliceError {
+  #[error(
+    "range {start}..{end} is out of bounds for a string of length {len}"
+  )]
+  OutOfBounds {
+    start: usize,
+    end: usize,
+    len: usize,
+  },
+  #[error("byte index {index} is not a char boundary")]
+  NotCharBoundary { index: usize },
+}
+
+#[derive(Debug, Error)]
+pub enum PartsError {
+  #[error(transparent)]
+  Slice(#[from] SliceError),
+  #[error("symbol id {} is given more than once", id.0)]
+  DuplicateId { id: SymbolId },
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum UnescapeError {
+  #[error("unknown escape at byte index {index}")]
+  UnknownEscape { index: usize },
+  #[error("malformed escape at byte index {index}")]
+  InvalidEscape { index: usize },
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum RefError {
+  #[error("the interner already has {limit} outstanding refs")]
+  TooManyRefs { limit: usize },
+}
+
+impl SliceError {
+  pub(crate) fn check(text: &str, range: &Range<usize>) -> Result<(), Self> {
+    if range.start > range.end || range.end > text.len() {
+      return Err(Self::OutOfBounds {
+        start: range.start,
+        end: range.end,
+        len: text.len(),
+      });
+    }
+
+    match [range.start, range.end]
+      .into_iter()
+      .find(|&index| !text.is_char_boundary(index))
+    {
+      Some(index) => Err(Self::NotCharBoundary { index }),
+      None => Ok(()),
+    }
+  }
 }
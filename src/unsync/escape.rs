@@ -0,0 +1,162 @@
+use crate::unsync::UnescapeError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EscapeStyle {
+  Rust,
+  Json,
+}
+
+pub(crate) fn unescape(
+  raw: &str,
+  style: EscapeStyle,
+  mut emit: impl FnMut(&str),
+) -> Result<(), UnescapeError> {
+  let mut rest = raw;
+  while let Some(i) = rest.find('\\') {
+    emit(&rest[..i]);
+    let index = raw.len() - rest.len() + i;
+    let escape = &rest[(i + 1)..];
+    let (c, len) = style.decode(escape, index)?;
+    if let Some(c) = c {
+      emit(c.encode_utf8(&mut [0; 4]));
+    }
+    rest = &escape[len..];
+  }
+
+  emit(rest);
+  Ok(())
+}
+
+pub(crate) fn unescape_to_string(
+  raw: &str,
+  style: EscapeStyle,
+) -> Result<String, UnescapeError> {
+  let mut text = String::with_capacity(raw.len());
+  unescape(raw, style, |piece| text.push_str(piece))?;
+  Ok(text)
+}
+
+impl EscapeStyle {
+  // the character the escape after a backslash stands for, if any, and how
+  // many bytes of `escape` it took up
+  fn decode(
+    self,
+    escape: &str,
+    index: usize,
+  ) -> Result<(Option<char>, usize), UnescapeError> {
+    let invalid = UnescapeError::InvalidEscape { index };
+    let Some(first) = escape.chars().next() else {
+      return Err(invalid);
+    };
+
+    let c = match (self, first) {
+      (_, 'n') => '\n',
+      (_, 'r') => '\r',
+      (_, 't') => '\t',
+      (_, '\\') => '\\',
+      (_, '"') => '"',
+      (EscapeStyle::Rust, '\'') => '\'',
+      (EscapeStyle::Rust, '0') => '\0',
+      (EscapeStyle::Rust, 'x') => {
+        let c = hex(escape.get(1..3))
+          .filter(|&c| c <= 0x7F)
+          .ok_or(invalid)?;
+        return Ok((Some(char::from(c as u8)), 3));
+      },
+      (EscapeStyle::Rust, 'u') => {
+        let digits = escape
+          .strip_prefix("u{")
+          .and_then(|digits| digits.split_once('}'))
+          .map(|(digits, _)| digits)
+          .filter(|digits| (1..=6).contains(&digits.len()));
+        let c = hex(digits).and_then(char::from_u32).ok_or(invalid)?;
+        return Ok((Some(c), digits.unwrap().len() + 3));
+      },
+      (EscapeStyle::Rust, '\n') => {
+        let skipped = escape[1..]
+          .find(|c: char| !matches!(c, ' ' | '\t' | '\n' | '\r'))
+          .unwrap_or(escape.len() - 1);
+        return Ok((None, skipped + 1));
+      },
+      (EscapeStyle::Json, '/') => '/',
+      (EscapeStyle::Json, 'b') => '\x08',
+      (EscapeStyle::Json, 'f') => '\x0C',
+      (EscapeStyle::Json, 'u') => {
+        let unit = hex(escape.get(1..5)).ok_or(invalid)?;
+        if !(0xD800..0xDC00).contains(&unit) {
+          return Ok((Some(char::from_u32(unit).ok_or(invalid)?), 5));
+        }
+
+        // a high surrogate, which has to be followed by a low one
+        let low = escape
+          .get(5..11)
+          .and_then(|low| low.strip_prefix("\\u"))
+          .and_then(|low| hex(Some(low)))
+          .filter(|low| (0xDC00..0xE000).contains(low))
+          .ok_or(invalid)?;
+        let c = 0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00);
+        return Ok((Some(char::from_u32(c).ok_or(invalid)?), 11));
+      },
+      _ => return Err(UnescapeError::UnknownEscape { index }),
+    };
+
+    Ok((Some(c), 1))
+  }
+}
+
+fn hex(digits: Option<&str>) -> Option<u32> {
+  let digits = digits.filter(|digits| {
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_hexdigit())
+  })?;
+  u32::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rust_escapes() {
+    let unescape = |raw| unescape_to_string(raw, EscapeStyle::Rust);
+
+    assert_eq!(
+      unescape(r#"a\tb\n\"\'\\\0\x41\u{1F600}\u{e9}"#).unwrap(),
+      "a\tb\n\"'\\\0A😀é"
+    );
+    assert_eq!(unescape("one \\\n    two").unwrap(), "one two");
+    assert_eq!(unescape("plain").unwrap(), "plain");
+
+    let invalid = |index| Err(UnescapeError::InvalidEscape { index });
+    assert_eq!(unescape(r"ab\x80"), invalid(2));
+    assert_eq!(unescape(r"\x4"), invalid(0));
+    assert_eq!(unescape(r"\u{D800}"), invalid(0));
+    assert_eq!(unescape(r"\u{1234567}"), invalid(0));
+    assert_eq!(unescape(r"\u{+12}"), invalid(0));
+    assert_eq!(unescape(r"\u41"), invalid(0));
+    assert_eq!(unescape("end\\"), invalid(3));
+    assert_eq!(
+      unescape(r"\/"),
+      Err(UnescapeError::UnknownEscape { index: 0 })
+    );
+  }
+
+  #[test]
+  fn json_escapes() {
+    let unescape = |raw| unescape_to_string(raw, EscapeStyle::Json);
+
+    assert_eq!(
+      unescape(r#"\"a\/b\"\b\fé😀"#).unwrap(),
+      "\"a/b\"\x08\x0Cé😀"
+    );
+
+    let invalid = |index| Err(UnescapeError::InvalidEscape { index });
+    assert_eq!(unescape(r"x\ud83d"), invalid(1));
+    assert_eq!(unescape(r"\ud83dA"), invalid(0));
+    assert_eq!(unescape(r"\ude00"), invalid(0));
+    assert_eq!(unescape(r"\u00g0"), invalid(0));
+    assert_eq!(
+      unescape(r"\x41"),
+      Err(UnescapeError::UnknownEscape { index: 0 })
+    );
+  }
+}
@@ -0,0 +1,137 @@
+use crate::unsync::{
+  error::{PartsError, SliceError},
+  folding::CaseFolding,
+  normalization::Normalization,
+  symbol::SymbolId,
+  trie::Span,
+};
+use std::ops::Range;
+
+#[derive(Clone, Debug)]
+pub struct FrozenInterner {
+  text: Box<str>,
+  spans: Box<[Span]>,
+  // the lowest id of each string in spans
+  symbols: Box<[SymbolId]>,
+  // sorted by id
+  ids: Box<[(SymbolId, FrozenIntern)]>,
+  case_folding: CaseFolding,
+  normalization: Normalization,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FrozenIntern(usize);
+
+impl FrozenInterner {
+  // each id must only appear once, but a string may have more than one
+  pub(crate) fn new<'a>(
+    strings: impl IntoIterator<Item = (SymbolId, &'a str)>,
+    case_folding: CaseFolding,
+    normalization: Normalization,
+  ) -> Self {
+    let mut strings = strings.into_iter().collect::<Vec<_>>();
+    strings.sort_unstable_by(|(a_id, a), (b_id, b)| (a, a_id).cmp(&(b, b_id)));
+
+    let mut text = String::new();
+    let mut spans = Vec::<Span>::new();
+    let mut symbols = Vec::new();
+    let mut ids = Vec::with_capacity(strings.len());
+    for (id, s) in strings {
+      if spans.last().is_none_or(|span| text[span.clone()] != *s) {
+        let start = text.len();
+        text.push_str(s);
+        spans.push(start..text.len());
+        symbols.push(id);
+      }
+      ids.push((id, FrozenIntern(spans.len() - 1)));
+    }
+    ids.sort_unstable();
+
+    Self {
+      text: text.into_boxed_str(),
+      spans: spans.into_boxed_slice(),
+      symbols: symbols.into_boxed_slice(),
+      ids: ids.into_boxed_slice(),
+      case_folding,
+      normalization,
+    }
+  }
+  // looks strings up exactly as they are, without folding or normalization
+  pub fn from_parts(
+    text: &str,
+    spans: &[(SymbolId, Range<usize>)],
+  ) -> Result<Self, PartsError> {
+    let mut strings = Vec::with_capacity(spans.len());
+    for (id, span) in spans {
+      SliceError::check(text, span)?;
+      strings.push((*id, &text[span.clone()]));
+    }
+
+    let mut ids = strings.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+    ids.sort_unstable();
+    if let Some(pair) = ids.windows(2).find(|pair| pair[0] == pair[1]) {
+      return Err(PartsError::DuplicateId { id: pair[0] });
+    }
+
+    Ok(Self::new(strings, CaseFolding::Off, Normalization::Off))
+  }
+
+  pub fn len(&self) -> usize {
+    self.spans.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.spans.is_empty()
+  }
+  pub fn get<S: AsRef<str>>(&self, s: S) -> Option<FrozenIntern> {
+    let s = self.normalization.canonical(self.case_folding, s.as_ref());
+
+    self
+      .spans
+      .binary_search_by(|span| self.text[span.clone()].cmp(&s))
+      .ok()
+      .map(FrozenIntern)
+  }
+  pub fn resolve(&self, intern: FrozenIntern) -> &str {
+    &self.text[self.spans[intern.0].clone()]
+  }
+  pub fn resolve_id(&self, id: SymbolId) -> Option<&str> {
+    let found = self.ids.binary_search_by_key(&id, |&(id, _)| id).ok()?;
+    Some(self.resolve(self.ids[found].1))
+  }
+  // the lowest id if the string has more than one
+  pub fn id_of<S: AsRef<str>>(&self, s: S) -> Option<SymbolId> {
+    self.get(s).map(|intern| self.symbols[intern.0])
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn frozen_interner() {
+    let frozen = FrozenInterner::new(
+      [
+        (SymbolId(0), "b"),
+        (SymbolId(1), "a"),
+        (SymbolId(2), "ab"),
+        (SymbolId(3), ""),
+      ],
+      CaseFolding::Off,
+      Normalization::Off,
+    );
+    let a = frozen.get("a").unwrap();
+    let ab = frozen.get("ab").unwrap();
+    let b = frozen.get("b").unwrap();
+
+    assert_eq!(frozen.len(), 4);
+    assert_eq!(frozen.resolve(a), "a");
+    assert_eq!(frozen.resolve(ab), "ab");
+    assert!(a < ab && ab < b);
+    assert_eq!(frozen.resolve(frozen.get("").unwrap()), "");
+    assert_eq!(frozen.get("ba"), None);
+    assert_eq!(frozen.resolve_id(SymbolId(0)), Some("b"));
+    assert_eq!(frozen.resolve_id(SymbolId(4)), None);
+    assert_eq!(frozen.id_of("ab"), Some(SymbolId(2)));
+  }
+}
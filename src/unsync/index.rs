@@ -0,0 +1,235 @@
+use crate::unsync::{
+  automaton::SuffixAutomaton,
+  trie::{self, Span, Trie},
+};
+use std::{collections::HashMap, ops::RangeBounds};
+
+/// Selects the data structure an interner uses to find strings it has
+/// already interned. Chosen with
+/// [InternerBuilder::index](crate::unsync::InternerBuilder::index).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IndexBackend {
+  /// A radix trie of interned strings. By default, it is backed by a suffix
+  /// automaton so that new strings can reuse any matching data already in
+  /// the store.
+  #[default]
+  Trie,
+  /// A hash map of interned strings. Lookups are a single hash instead of a
+  /// walk through the trie, but by default only exact matches are reused.
+  Hash,
+}
+
+/// Everything an interner knows about what is in its store: the strings that
+/// have been interned, and, when substring sharing is on, every substring of
+/// the store that a new string could reuse.
+#[derive(Debug)]
+pub(crate) struct Index {
+  entries: Entries,
+  substrings: Option<SuffixAutomaton>,
+}
+
+#[derive(Debug)]
+enum Entries {
+  Trie(Trie<u8>),
+  Hash(HashMap<Box<[u8]>, Span>),
+}
+
+impl Index {
+  pub(crate) fn new(backend: IndexBackend, substring_sharing: bool) -> Self {
+    let entries = match backend {
+      IndexBackend::Trie => Entries::Trie(Trie::new()),
+      IndexBackend::Hash => Entries::Hash(HashMap::new()),
+    };
+
+    Self {
+      entries,
+      substrings: substring_sharing.then(SuffixAutomaton::new),
+    }
+  }
+
+  /// Creates an empty index configured the same way as this one
+  pub(crate) fn emptied(&self) -> Self {
+    Self::new(self.backend(), self.shares_substrings())
+  }
+
+  pub(crate) fn backend(&self) -> IndexBackend {
+    match self.entries {
+      Entries::Trie(_) => IndexBackend::Trie,
+      Entries::Hash(_) => IndexBackend::Hash,
+    }
+  }
+
+  pub(crate) fn shares_substrings(&self) -> bool {
+    self.substrings.is_some()
+  }
+
+  pub(crate) fn reserve(&mut self, entries: usize) {
+    match &mut self.entries {
+      // the trie branches on bytes, so no node has more children than this
+      Entries::Trie(trie) => trie.reserve(entries.min(1 << u8::BITS)),
+      Entries::Hash(map) => map.reserve(entries),
+    }
+  }
+
+  pub(crate) fn shrink_to_fit(&mut self) {
+    match &mut self.entries {
+      Entries::Trie(trie) => trie.shrink_to_fit(),
+      Entries::Hash(map) => map.shrink_to_fit(),
+    }
+
+    if let Some(substrings) = &mut self.substrings {
+      substrings.shrink_to_fit();
+    }
+  }
+
+  pub(crate) fn entries(&self) -> usize {
+    match &self.entries {
+      Entries::Trie(trie) => trie.entries(),
+      Entries::Hash(map) => map.len(),
+    }
+  }
+
+  pub(crate) fn trie_nodes(&self) -> usize {
+    match &self.entries {
+      Entries::Trie(trie) => trie.nodes(),
+      Entries::Hash(_) => 0,
+    }
+  }
+
+  pub(crate) fn automaton_states(&self) -> usize {
+    self.substrings.as_ref().map_or(0, SuffixAutomaton::states)
+  }
+
+  /// Finds a previously interned key
+  pub(crate) fn get(&self, key: &[u8]) -> Option<Span> {
+    match &self.entries {
+      Entries::Trie(trie) => trie.get(key),
+      Entries::Hash(map) => map.get(key).cloned(),
+    }
+  }
+
+  /// Finds the span of the longest interned key that `key` starts with
+  pub(crate) fn longest_prefix(&self, key: &[u8]) -> Option<Span> {
+    match &self.entries {
+      Entries::Trie(trie) => trie.longest_prefix(key),
+      Entries::Hash(map) => (0..=key.len())
+        .rev()
+        .find_map(|len| map.get(&key[..len]).cloned()),
+    }
+  }
+
+  /// Finds the spans of every interned key that starts with `prefix`, in no
+  /// particular order
+  pub(crate) fn with_prefix(&self, prefix: &[u8]) -> Vec<Span> {
+    match &self.entries {
+      Entries::Trie(trie) => trie.with_prefix(prefix),
+      Entries::Hash(map) => map
+        .iter()
+        .filter(|(key, _)| key.starts_with(prefix))
+        .map(|(_, span)| span.clone())
+        .collect(),
+    }
+  }
+
+  /// Finds the spans of every interned key within `range`, sorted by key
+  pub(crate) fn range(&self, range: impl RangeBounds<[u8]>) -> Vec<Span> {
+    match &self.entries {
+      Entries::Trie(trie) => trie.range(range),
+      Entries::Hash(map) => {
+        let mut found = map
+          .iter()
+          .filter(|(key, _)| range.contains(&key[..]))
+          .collect::<Vec<_>>();
+        found.sort_unstable_by_key(|(key, _)| *key);
+        found.into_iter().map(|(_, span)| span.clone()).collect()
+      },
+    }
+  }
+
+  /// Finds the spans of every key within `max_dist` edits of `query`, along
+  /// with how far away each one is, in no particular order
+  pub(crate) fn fuzzy(
+    &self,
+    query: &str,
+    max_dist: usize,
+  ) -> Vec<(Span, usize)> {
+    match &self.entries {
+      Entries::Trie(trie) => trie.fuzzy(query, max_dist),
+      Entries::Hash(map) => map
+        .iter()
+        .filter_map(|(key, span)| {
+          let key = std::str::from_utf8(key).ok()?;
+          let dist = trie::edit_distance(key, query, max_dist)?;
+          Some((span.clone(), dist))
+        })
+        .collect(),
+    }
+  }
+
+  /// Finds a key anywhere in the store, registering it as interned if it was
+  /// only present as a substring
+  pub(crate) fn get_or_promote(&mut self, key: &[u8]) -> Option<Span> {
+    if let Some(span) = self.get(key) {
+      return Some(span);
+    }
+
+    let span = self.substrings.as_ref()?.find(key)?;
+    Some(self.insert_entry(key, span))
+  }
+
+  /// Registers a key that was just pushed to the store at `span`
+  pub(crate) fn insert(&mut self, key: &[u8], span: Span) -> Span {
+    if let Some(substrings) = &mut self.substrings {
+      substrings.extend(key, span.start);
+    }
+
+    self.insert_entry(key, span)
+  }
+
+  /// Registers a key that already lies in the store at `span`, such as part
+  /// of a longer string
+  pub(crate) fn insert_in_place(&mut self, key: &[u8], span: Span) -> Span {
+    self.insert_entry(key, span)
+  }
+
+  fn insert_entry(&mut self, key: &[u8], span: Span) -> Span {
+    match &mut self.entries {
+      Entries::Trie(trie) => trie.insert(key, span),
+      Entries::Hash(map) => map.entry(key.into()).or_insert(span).clone(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn backends_agree_on_exact_matches() {
+    for backend in [IndexBackend::Trie, IndexBackend::Hash] {
+      let mut index = Index::new(backend, true);
+      index.insert(b"hello", 0..5);
+      index.insert(b"help", 5..9);
+
+      assert_eq!(Some(0..5), index.get(b"hello"));
+      assert_eq!(Some(5..9), index.get_or_promote(b"help"));
+      assert_eq!(None, index.get(b"hel"));
+      assert_eq!(2, index.entries());
+    }
+  }
+
+  #[test]
+  fn substring_sharing_is_independent_of_backend() {
+    for backend in [IndexBackend::Trie, IndexBackend::Hash] {
+      let mut shared = Index::new(backend, true);
+      let mut exact = Index::new(backend, false);
+      shared.insert(b"hello", 0..5);
+      exact.insert(b"hello", 0..5);
+
+      assert_eq!(Some(1..4), shared.get_or_promote(b"ell"));
+      assert_eq!(Some(1..4), shared.get(b"ell"));
+      assert_eq!(None, exact.get_or_promote(b"ell"));
+      assert_eq!(0, exact.automaton_states());
+    }
+  }
+}
@@ -1,192 +1,3722 @@
+#[cfg(feature = "aho-corasick")]
+use crate::unsync::matcher::InternMatcher;
 use crate::unsync::{
-  error::InternError,
-  trie::{Span, Trie},
+  builder::{InternerBuilder, StoreFullPolicy},
+  compact::CompactIntern,
+  dictionary,
+  error::{InternError, RefError, SliceError, UnescapeError},
+  escape::{self, EscapeStyle},
+  folding::CaseFolding,
+  frozen::FrozenInterner,
+  index::{Index, IndexBackend},
+  merge::MergeMap,
+  normalization::Normalization,
+  path::{InternPath, PathTable},
+  persistence::Snapshot,
+  source::{LineColumn, Lines, SourceId},
+  stats::InternerStats,
+  store::{Stager, Store},
+  symbol::{Symbol, SymbolId},
+  trie::Span,
 };
+#[cfg(feature = "regex")]
+use regex::Regex;
 use std::{
-  cell::{Cell, UnsafeCell},
-  fmt,
-  ops::Deref,
-  panic::Location,
-  rc::Rc,
+  borrow::{Borrow, Cow},
+  cell::{Cell, OnceCell, RefCell},
+  cmp::{Ordering, Reverse},
+  collections::HashMap,
+  fmt::{self, Write as _},
+  hash::{Hash, Hasher},
+  io::{self, BufRead, Read, Write},
+  iter,
+  ops::{Bound, Deref, Range, RangeBounds},
+  path::Path,
+  ptr,
+  rc::{Rc, Weak},
+  sync::Arc,
+  vec,
 };
 
 #[derive(Debug)]
 pub struct Interner {
-  index: UnsafeCell<Trie<char>>,
-  store: UnsafeCell<String>,
+  index: RefCell<Index>,
+  ids: RefCell<HashMap<Span, usize>>,
+  // indexed by id; collected entries leave a gap since ids aren't reused
+  symbols: RefCell<Vec<Option<Rc<Entry>>>>,
+  store: Store,
   refs: Cell<usize>,
-  last_ref: Cell<Option<&'static Location<'static>>>,
+  case_folding: CaseFolding,
+  normalization: Normalization,
+  counting: bool,
+  max_refs: Option<usize>,
+  max_store_bytes: Option<usize>,
+  store_full: StoreFullPolicy,
+  preserve_case: bool,
+  // every spelling of each string by id, first seen first, if recorded
+  spellings: Option<RefCell<HashMap<usize, Vec<Box<str>>>>>,
+  hits: Cell<u64>,
+  misses: Cell<u64>,
+  // ticks whenever an entry is used, to tell which were used least recently
+  clock: Cell<u64>,
+  // kept apart from the strings, since paths needn't be UTF-8
+  paths: OnceCell<PathTable>,
+  // files from intern_source, indexed by SourceId
+  sources: RefCell<Vec<Source>>,
+  // where strings that aren't here are looked for
+  parent: Option<Rc<Interner>>,
+}
+
+// the entry keeps the contents from being collected without keeping the
+// interner alive like an Intern would
+#[derive(Debug)]
+struct Source {
+  name: Rc<str>,
+  contents: Rc<Entry>,
+  lines: Lines,
+}
+
+// shared by every Intern of one string, so collect can tell whether the string
+// is still used and move it by rewriting a single span
+#[derive(Debug)]
+struct Entry {
+  id: usize,
+  start: Cell<usize>,
+  end: Cell<usize>,
+  // a first spelling that differs from the folded string is stored separately
+  // and shown instead
+  spelled: bool,
+  shown_start: Cell<usize>,
+  shown_end: Cell<usize>,
+  len: usize,
+  chars: usize,
+  // symbols don't keep the string alive, so it is kept forever instead
+  pinned: Cell<bool>,
+  // when the string was last interned or looked up, by the interner's clock
+  used: Cell<u64>,
+  // only counted if the interner was built with count_interns
+  interned: Cell<u64>,
+}
+
+impl Entry {
+  // text is whichever of the string and its spelling is shown
+  fn new(id: usize, span: Span, spelling: Option<Span>, text: &str) -> Self {
+    let shown = spelling.clone().unwrap_or(span.clone());
+    Self {
+      id,
+      start: Cell::new(span.start),
+      end: Cell::new(span.end),
+      spelled: spelling.is_some(),
+      shown_start: Cell::new(shown.start),
+      shown_end: Cell::new(shown.end),
+      len: text.len(),
+      chars: text.chars().count(),
+      pinned: Cell::new(false),
+      used: Cell::new(0),
+      interned: Cell::new(0),
+    }
+  }
+
+  fn symbol_id(&self) -> SymbolId {
+    SymbolId(u32::try_from(self.id).expect("ran out of symbol ids"))
+  }
+
+  fn pin(&self) -> Symbol {
+    self.pinned.set(true);
+    Symbol(self.symbol_id())
+  }
+
+  fn span(&self) -> Span {
+    self.start.get()..self.end.get()
+  }
+
+  fn set_span(&self, span: Span) {
+    if !self.spelled {
+      self.set_shown(span.clone());
+    }
+    self.start.set(span.start);
+    self.end.set(span.end);
+  }
+
+  fn shown(&self) -> Span {
+    self.shown_start.get()..self.shown_end.get()
+  }
+
+  fn set_shown(&self, span: Span) {
+    self.shown_start.set(span.start);
+    self.shown_end.set(span.end);
+  }
+
+  // the symbol table holds one reference, so any more belong to Interns
+  fn in_use(entry: &Rc<Self>) -> bool {
+    entry.pinned.get() || Rc::strong_count(entry) > 1
+  }
+
+  // at most, once compacted
+  fn stored_len(&self) -> usize {
+    let span = self.span();
+    let len = if Store::is_static(&span) {
+      0
+    } else {
+      span.len()
+    };
+    len + if self.spelled { self.shown().len() } else { 0 }
+  }
+}
+
+impl Default for Interner {
+  // an interner owned directly, for use with intern_borrowed
+  fn default() -> Self {
+    Self::from_builder(&InternerBuilder::new())
+  }
 }
 
 impl Interner {
   pub fn new() -> Rc<Self> {
-    Rc::new(Self {
-      index: UnsafeCell::new(Trie::new()),
-      store: UnsafeCell::new(String::new()),
+    InternerBuilder::new().build()
+  }
+
+  pub(crate) fn from_builder(builder: &InternerBuilder) -> Self {
+    let substring_sharing = builder
+      .substring_sharing
+      .unwrap_or(builder.index == IndexBackend::Trie);
+
+    let interner = Self {
+      index: RefCell::new(Index::new(builder.index, substring_sharing)),
+      ids: RefCell::new(HashMap::new()),
+      symbols: RefCell::new(Vec::new()),
+      store: Store::new(),
       refs: Cell::new(0),
-      last_ref: Cell::new(None),
+      case_folding: builder.case_folding,
+      normalization: builder.normalization,
+      counting: builder.count_interns,
+      max_refs: builder.max_outstanding_refs,
+      max_store_bytes: builder.max_store_bytes,
+      store_full: builder.store_full,
+      preserve_case: builder.preserve_case,
+      spellings: builder
+        .record_spellings
+        .then(|| RefCell::new(HashMap::new())),
+      hits: Cell::new(0),
+      misses: Cell::new(0),
+      clock: Cell::new(0),
+      paths: OnceCell::new(),
+      sources: RefCell::new(Vec::new()),
+      parent: None,
+    };
+
+    let (bytes, entries) = builder.capacity;
+    interner.reserve(bytes, entries);
+    interner.seed(builder);
+    interner
+  }
+
+  // keywords must get the ids 0 to keywords.len() - 1; neither they nor the
+  // dictionary are counted as interned
+  fn seed(&self, builder: &InternerBuilder) {
+    let seed = |s: &str| {
+      let span = match self.intern_making_room(&self.canonical(s)) {
+        Ok(span) => span,
+        Err(e) => panic!("{e}"),
+      };
+      self.entry(span, s).pin().id()
+    };
+
+    for (i, keyword) in builder.keywords.iter().enumerate() {
+      let id = seed(keyword);
+      assert!(id.0 as usize == i, "keyword {keyword:?} appears twice");
+    }
+    for s in &builder.dictionary {
+      seed(s);
+    }
+  }
+
+  pub fn with_capacity(bytes: usize, entries: usize) -> Rc<Self> {
+    InternerBuilder::new().capacity(bytes, entries).build()
+  }
+
+  // layered over parent, which strings are looked up in before being stored
+  // here; the parent is never written to, and its interns are handed out for
+  // strings found there
+  pub fn new_child(parent: &Rc<Interner>) -> Rc<Self> {
+    let builder = InternerBuilder::new()
+      .case_folding(parent.case_folding)
+      .normalization(parent.normalization)
+      .preserve_case(parent.preserve_case);
+    Rc::new(Self {
+      parent: Some(Rc::clone(parent)),
+      ..Self::from_builder(&builder)
     })
   }
 
+  pub fn parent(&self) -> Option<&Rc<Interner>> {
+    self.parent.as_ref()
+  }
+
+  // finds s in the parents, unless this interner has it itself
+  fn in_parent(&self, spelling: &str, s: &str) -> Option<Intern> {
+    let parent = self.parent.as_ref()?;
+    if self.index.borrow().get(s.as_bytes()).is_some() {
+      return None;
+    }
+
+    parent.get_canonical(spelling, s)
+  }
+
+  // stores the longest strings first so shorter ones inside them can share
+  // their data, but hands out ids in corpus order
+  pub fn from_corpus<I>(corpus: I) -> (Rc<Self>, Vec<Intern>)
+  where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+  {
+    let strings = corpus.into_iter().collect::<Vec<_>>();
+    let bytes = strings.iter().map(|s| s.as_ref().len()).sum();
+    let interner = Self::with_capacity(bytes, strings.len());
+
+    let mut order = (0..strings.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| Reverse(strings[i].as_ref().len()));
+
+    let mut spans = vec![0..0; strings.len()];
+    for i in order {
+      match interner.intern_uncontested(strings[i].as_ref()) {
+        Ok(span) => spans[i] = span,
+        Err(e) => panic!("{e}"),
+      }
+    }
+
+    let interns = spans
+      .into_iter()
+      .zip(&strings)
+      .map(|(span, s)| interner.intern_at(span, s.as_ref()))
+      .collect();
+
+    (interner, interns)
+  }
+
+  // panics if the store would grow past usize::MAX bytes
+  pub fn reserve(&self, bytes: usize, entries: usize) {
+    self.index.borrow_mut().reserve(entries);
+    self.store.reserve(bytes);
+  }
+
+  // collect and shrink_to_fit only move strings while this is 0
+  pub fn outstanding_refs(&self) -> usize {
+    self.refs.get()
+  }
+
+  // most often interned first, then in the order strings were first interned;
+  // empty unless the interner was built with count_interns
+  pub fn counts(self: &Rc<Self>) -> Vec<(Intern, u64)> {
+    if !self.counting {
+      return Vec::new();
+    }
+
+    self.ranked()
+  }
+
+  // the k strings that come first in counts, or the first k to be interned
+  // without count_interns, for InternerBuilder::with_dictionary
+  pub fn export_dictionary(self: &Rc<Self>, k: usize) -> Vec<u8> {
+    let strings = self
+      .ranked()
+      .into_iter()
+      .take(k)
+      .map(|(intern, _)| intern.resolve())
+      .collect::<Vec<_>>();
+    dictionary::encode(&strings)
+  }
+
+  fn ranked(self: &Rc<Self>) -> Vec<(Intern, u64)> {
+    let mut counts = self
+      .symbols
+      .borrow()
+      .iter()
+      .flatten()
+      .map(|entry| {
+        let intern = Intern {
+          entry: Rc::clone(entry),
+          interner: Rc::clone(self),
+        };
+        (intern, entry.interned.get())
+      })
+      .collect::<Vec<_>>();
+
+    counts.sort_by_key(|(intern, count)| (Reverse(*count), intern.entry.id));
+    counts
+  }
+
+  pub fn stats(&self) -> InternerStats {
+    let index = self.index.borrow();
+
+    InternerStats {
+      stored_bytes: self.store.stored_bytes(),
+      allocated_bytes: self.store.allocated_bytes(),
+      entries: index.entries(),
+      trie_nodes: index.trie_nodes(),
+      automaton_states: index.automaton_states(),
+      outstanding_refs: self.outstanding_refs(),
+      hits: self.hits.get(),
+      misses: self.misses.get(),
+    }
+  }
+
   pub fn extract_store(self) -> String {
-    self.store.into_inner()
+    self.store.concat()
   }
 
   pub fn intern<S: AsRef<str>>(self: &Rc<Self>, s: S) -> Intern {
     match self.try_intern(s) {
       Ok(intern) => intern,
-      Err(InternError::OutstandingRef(loc)) => {
-        panic!(
-          concat!(
-            "Cannot intern while holding an InternRef\n",
-            " --> last InternRef created at {}:{}:{}",
-          ),
-          loc.file(),
-          loc.line(),
-          loc.column()
-        );
-      },
+      Err(e) => panic!("{e}"),
     }
   }
 
-  // SAFETY: We do not allow mutation of the store if there
-  //         are existing InternRefs
+  // InternRefs point into store buckets that never move, so they may be held
+  // across calls to this method
   pub fn try_intern<S: AsRef<str>>(
     self: &Rc<Self>,
     s: S,
   ) -> Result<Intern, InternError> {
-    if self.refs.get() > 0 {
-      let lr = self.last_ref.get().unwrap();
-      return Err(InternError::OutstandingRef(lr));
+    let spelling = s.as_ref();
+    let s = self.canonical(spelling);
+    if let Some(intern) = self.in_parent(spelling, &s) {
+      return Ok(intern);
+    }
+
+    let span = self.intern_making_room(&s)?;
+    Ok(self.interned_at(span, spelling))
+  }
+
+  // for interners owned directly instead of through an Rc, such as one
+  // embedded in another struct; the intern borrows the interner instead
+  pub fn intern_borrowed<S: AsRef<str>>(&self, s: S) -> BorrowedIntern<'_> {
+    match self.try_intern_borrowed(s) {
+      Ok(intern) => intern,
+      Err(e) => panic!("{e}"),
+    }
+  }
+
+  pub fn try_intern_borrowed<S: AsRef<str>>(
+    &self,
+    s: S,
+  ) -> Result<BorrowedIntern<'_>, InternError> {
+    let spelling = s.as_ref();
+    let span = self.intern_making_room(&self.canonical(spelling))?;
+    Ok(BorrowedIntern {
+      entry: self.interned(span, spelling),
+      interner: self,
+    })
+  }
+
+  // reads the string where it is instead of copying it into the store, unless
+  // it is already interned, or folding or normalization would change it
+  pub fn intern_static(self: &Rc<Self>, s: &'static str) -> Intern {
+    match (self.canonical(s), Store::static_span(s)) {
+      (Cow::Borrowed(_), Some(span)) => {
+        if let Some(intern) = self.in_parent(s, s) {
+          return intern;
+        }
+
+        let span = self.intern_static_span(span);
+        self.interned_at(span, s)
+      },
+      _ => self.intern(s),
+    }
+  }
+
+  // re-interns the text of an Intern from another interner, by this
+  // interner's folding and normalization
+  pub fn adopt(self: &Rc<Self>, intern: &Intern) -> Intern {
+    if Rc::ptr_eq(&intern.interner, self) {
+      return intern.clone();
+    }
+
+    intern.with_str(|s| self.intern(s))
+  }
+
+  // interns every string in other, keeping symbols pinned, and maps their old
+  // ids to the new ones
+  pub fn merge(self: &Rc<Self>, other: Rc<Interner>) -> MergeMap {
+    let old = other
+      .symbols
+      .borrow()
+      .iter()
+      .flatten()
+      .map(|entry| Intern {
+        entry: Rc::clone(entry),
+        interner: Rc::clone(&other),
+      })
+      .collect::<Vec<_>>();
+
+    let mut interns = Vec::new();
+    for old in old {
+      let new = self.adopt(&old);
+      if old.entry.pinned.get() {
+        new.symbol();
+      }
+      let id = old.entry.id;
+      interns.resize(interns.len().max(id + 1), None);
+      interns[id] = Some(new);
     }
 
-    let index = unsafe { &mut *self.index.get() };
-    let store = unsafe { &mut *self.store.get() };
-    let span = index.get(s.as_ref().chars()).unwrap_or_else(|| {
-      let start = store.len();
-      store.push_str(s.as_ref());
-      index.insert(s.as_ref().chars(), start)
+    MergeMap::new(interns)
+  }
+
+  // an independent copy in which every string keeps its id, spelling, pin and
+  // counts; a child's copy shares its parent, but paths and sources aren't
+  // copied
+  pub fn deep_clone(&self) -> (Rc<Interner>, MergeMap) {
+    let index = self.index.borrow();
+    let mut builder = InternerBuilder::new()
+      .index(index.backend())
+      .substring_sharing(index.shares_substrings())
+      .case_folding(self.case_folding)
+      .normalization(self.normalization)
+      .preserve_case(self.preserve_case)
+      .record_spellings(self.spellings.is_some())
+      .count_interns(self.counting)
+      .on_store_full(self.store_full)
+      .capacity(self.store.stored_bytes(), self.ids.borrow().len());
+    builder.max_store_bytes = self.max_store_bytes;
+    builder.max_outstanding_refs = self.max_refs;
+    drop(index);
+    let copy = Rc::new(Self {
+      parent: self.parent.clone(),
+      ..Self::from_builder(&builder)
     });
 
-    Ok(Intern {
-      span,
-      interner: Rc::clone(self),
+    let entries = self
+      .symbols
+      .borrow()
+      .iter()
+      .flatten()
+      .map(|entry| {
+        // SAFETY: the spans were handed out by the store
+        let text = unsafe { self.store.get(entry.span()) }.to_owned();
+        let spelling = entry
+          .spelled
+          // SAFETY: see above
+          .then(|| unsafe { self.store.get(entry.shown()) }.to_owned());
+        let shown = spelling.as_deref().unwrap_or(&text);
+        // static strings keep pointing at the same &'static str
+        let copied = Entry::new(
+          entry.id,
+          entry.span(),
+          entry.spelled.then_some(0..0),
+          shown,
+        );
+        copied.used.set(entry.used.get());
+        copied.interned.set(entry.interned.get());
+        copied.pinned.set(entry.pinned.get());
+        (Rc::new(copied), text, spelling)
+      })
+      .collect();
+
+    let len = self.symbols.borrow().len();
+    copy.symbols.borrow_mut().resize(len, None);
+    if let (Some(from), Some(to)) = (&self.spellings, &copy.spellings) {
+      to.borrow_mut().clone_from(&from.borrow());
+    }
+    copy.hits.set(self.hits.get());
+    copy.misses.set(self.misses.get());
+    copy.clock.set(self.clock.get());
+    copy.rebuild(entries);
+
+    let interns = copy
+      .symbols
+      .borrow()
+      .iter()
+      .map(|slot| {
+        Some(Intern {
+          entry: Rc::clone(slot.as_ref()?),
+          interner: Rc::clone(&copy),
+        })
+      })
+      .collect();
+
+    (copy, MergeMap::new(interns))
+  }
+
+  pub fn intern_symbol<S: AsRef<str>>(&self, s: S) -> Symbol {
+    let spelling = s.as_ref();
+    match self.intern_making_room(&self.canonical(spelling)) {
+      Ok(span) => self.interned(span, spelling).pin(),
+      Err(e) => panic!("{e}"),
+    }
+  }
+
+  // short strings are kept in the handle instead of the store
+  pub fn intern_compact<S: AsRef<str>>(self: &Rc<Self>, s: S) -> CompactIntern {
+    let spelling = s.as_ref();
+    let s = self.canonical(spelling);
+    CompactIntern::new(self, &s, || match self.intern_making_room(&s) {
+      Ok(span) => self.interned_at(span, spelling),
+      Err(e) => panic!("{e}"),
     })
   }
-}
 
-#[derive(Clone)]
-pub struct Intern {
-  span: Span,
-  interner: Rc<Interner>,
-}
+  // writes the pieces straight into the store instead of joining them first
+  pub fn intern_concat<S: AsRef<str>>(self: &Rc<Self>, pieces: &[S]) -> Intern {
+    self.intern_written(
+      |stager| {
+        pieces
+          .iter()
+          .try_for_each(|piece| stager.write_str(piece.as_ref()))
+      },
+      || pieces.iter().map(AsRef::as_ref).collect(),
+    )
+  }
 
-impl Intern {
-  // SAFETY: we disallow store modification while InternRefs exist
-  #[track_caller]
-  pub fn get_ref(&self) -> InternRef<'_> {
-    // register last caller location for helpful panics
-    #[cfg(debug_assertions)]
-    self.interner.last_ref.set(Some(Location::caller()));
+  // writes the parts and separators straight into the store, like
+  // intern_concat
+  pub fn join(self: &Rc<Self>, parts: &[Intern], separator: &str) -> Intern {
+    self.intern_written(
+      |stager| {
+        parts.iter().enumerate().try_for_each(|(i, part)| {
+          if i > 0 {
+            stager.write_str(separator)?;
+          }
+          part.with_str(|s| stager.write_str(s))
+        })
+      },
+      || {
+        let parts = parts.iter().map(Intern::resolve).collect::<Vec<_>>();
+        parts.join(separator)
+      },
+    )
+  }
+
+  // the pieces are slices of the intern, so they aren't copied either
+  pub fn split_intern(
+    self: &Rc<Self>,
+    intern: &Intern,
+    separator: &str,
+  ) -> Vec<Intern> {
+    let intern = self.adopt(intern);
+    let ranges = intern.with_str(|text| {
+      text
+        .split(separator)
+        .map(|piece| {
+          let start = piece.as_ptr() as usize - text.as_ptr() as usize;
+          start..(start + piece.len())
+        })
+        .collect::<Vec<_>>()
+    });
+
+    ranges
+      .into_iter()
+      .map(|range| intern.slice(range).unwrap())
+      .collect()
+  }
+
+  // formats straight into the store; see also intern_format!
+  pub fn intern_fmt(self: &Rc<Self>, args: fmt::Arguments<'_>) -> Intern {
+    self.intern_written(|stager| stager.write_fmt(args), || args.to_string())
+  }
+
+  // decodes straight into the store; nothing is interned if `raw` has an
+  // unknown or malformed escape
+  pub fn intern_unescaped(
+    self: &Rc<Self>,
+    raw: &str,
+    style: EscapeStyle,
+  ) -> Result<Intern, UnescapeError> {
+    escape::unescape(raw, style, |_| {})?;
+
+    Ok(self.intern_written(
+      |stager| {
+        let mut written = Ok(());
+        let _ = escape::unescape(raw, style, |piece| {
+          written = written.and_then(|()| stager.write_str(piece));
+        });
+        written
+      },
+      || escape::unescape_to_string(raw, style).unwrap(),
+    ))
+  }
+
+  // falls back to interning what `fallback` makes if writing fails or was
+  // interrupted, or if folding or normalization would change the text
+  fn intern_written(
+    self: &Rc<Self>,
+    write: impl FnOnce(&mut Stager<'_>) -> fmt::Result,
+    fallback: impl FnOnce() -> String,
+  ) -> Intern {
+    let mut stager = self.store.stager();
+    let staged = match write(&mut stager) {
+      Ok(()) => stager.finish(),
+      Err(_) => None,
+    };
+    let Some(staged) = staged else {
+      return self.intern(fallback());
+    };
+
+    // SAFETY: only strs were written, so the staged text is a str too
+    let text = unsafe { self.store.get(staged.clone()) };
+    if let Cow::Owned(_) = self.canonical(text) {
+      // copied out first, since interning writes over the staged text
+      let text = text.to_owned();
+      return self.intern(text);
+    }
+
+    let span = match self.intern_staged(staged) {
+      Ok(span) => span,
+      // evicting would clear the staged text along with the store
+      Err(InternError::StoreFull { .. })
+        if self.store_full == StoreFullPolicy::Evict =>
+      {
+        let text = text.to_owned();
+        return self.intern(text);
+      },
+      Err(e) => panic!("{e}"),
+    };
+    // SAFETY: the span was handed out by the store
+    let text = unsafe { self.store.get(span.clone()) };
+    self.interned_at(span, text)
+  }
+
+  pub fn resolve(&self, symbol: Symbol) -> InternRef<'_> {
+    self
+      .try_resolve(symbol)
+      .expect("symbol does not belong to this interner")
+  }
+
+  // None if the symbol came from a different interner
+  pub fn try_resolve(&self, symbol: Symbol) -> Option<InternRef<'_>> {
+    let entry = usize::try_from(symbol.id().0)
+      .ok()
+      .and_then(|id| self.symbols.borrow().get(id)?.clone())
+      .filter(|entry| entry.pinned.get())?;
 
-    // increment refcount
-    self.interner.refs.set(self.interner.refs.get() + 1);
+    Some(self.read(&entry))
+  }
+
+  fn read(&self, entry: &Entry) -> InternRef<'_> {
+    self.read_span(entry.shown())
+  }
 
-    let store = unsafe { &*self.interner.store.get() };
-    let text = &store[self.span.clone()];
+  fn read_span(&self, span: Span) -> InternRef<'_> {
+    // SAFETY: the span was handed out by this interner's store
+    let text = unsafe { self.store.get(span) };
+    self.refs.set(self.refs.get() + 1);
     InternRef {
       text,
-      interner: Rc::clone(&self.interner),
+      refs: &self.refs,
     }
   }
-}
 
-impl fmt::Debug for Intern {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.debug_struct("Intern")
-      .field("span", &self.span)
-      .field("text", &&*self.get_ref())
-      .finish()
+  // never collected, and components are shared between paths
+  pub fn intern_path<P: AsRef<Path>>(&self, path: P) -> InternPath {
+    self.paths.get_or_init(PathTable::new).intern(path.as_ref())
   }
-}
 
-impl fmt::Display for Intern {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.write_str(&*self.get_ref())
+  // stores the file as one string that tokens can be sliced out of, and
+  // remembers where its lines start; offsets refer to the stored text
+  pub fn intern_source(
+    self: &Rc<Self>,
+    name: &str,
+    contents: &str,
+  ) -> SourceId {
+    let contents = self.intern(contents);
+    let lines = contents.with_str(Lines::new);
+
+    let mut sources = self.sources.borrow_mut();
+    let id = u32::try_from(sources.len())
+      .map(SourceId)
+      .expect("ran out of source ids");
+    sources.push(Source {
+      name: name.into(),
+      contents: Rc::clone(&contents.entry),
+      lines,
+    });
+    id
   }
-}
 
-impl PartialEq for Intern {
-  fn eq(&self, other: &Self) -> bool {
-    Rc::ptr_eq(&self.interner, &other.interner) && self.span == other.span
+  pub fn source(self: &Rc<Self>, id: SourceId) -> Option<Intern> {
+    let sources = self.sources.borrow();
+    let source = sources.get(usize::try_from(id.0).ok()?)?;
+
+    Some(Intern {
+      entry: Rc::clone(&source.contents),
+      interner: Rc::clone(self),
+    })
   }
-}
-impl Eq for Intern {}
 
-pub struct InternRef<'a> {
-  text: &'a str,
-  interner: Rc<Interner>,
-}
+  pub fn source_name(&self, id: SourceId) -> Option<Rc<str>> {
+    let sources = self.sources.borrow();
+    let source = sources.get(usize::try_from(id.0).ok()?)?;
+    Some(Rc::clone(&source.name))
+  }
 
-impl fmt::Debug for InternRef<'_> {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.debug_tuple("InternRef").field(&self.text).finish()
+  pub fn line_column(
+    self: &Rc<Self>,
+    id: SourceId,
+    offset: usize,
+  ) -> Option<LineColumn> {
+    let contents = self.source(id)?;
+    let sources = self.sources.borrow();
+    let lines = &sources[usize::try_from(id.0).ok()?].lines;
+    contents.with_str(|text| lines.locate(text, offset))
   }
-}
 
-impl fmt::Display for InternRef<'_> {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.write_str(self.text)
+  // counts from 1, and leaves out the line ending
+  pub fn source_line(
+    self: &Rc<Self>,
+    id: SourceId,
+    line: usize,
+  ) -> Option<Intern> {
+    let contents = self.source(id)?;
+    let range = {
+      let sources = self.sources.borrow();
+      let lines = &sources[usize::try_from(id.0).ok()?].lines;
+      contents.with_str(|text| lines.line(text, line))?
+    };
+    Some(contents.slice(range).unwrap())
   }
-}
 
-impl Deref for InternRef<'_> {
-  type Target = str;
+  pub fn intern_substring<S: AsRef<str>>(
+    self: &Rc<Self>,
+    s: S,
+  ) -> Option<Intern> {
+    let spelling = s.as_ref();
+    let span = self.intern_existing(&self.canonical(spelling))?;
+    Some(self.interned_at(span, spelling))
+  }
 
-  fn deref(&self) -> &Self::Target {
-    self.text
+  pub fn get<S: AsRef<str>>(self: &Rc<Self>, s: S) -> Option<Intern> {
+    let spelling = s.as_ref();
+    self.get_canonical(spelling, &self.canonical(spelling))
   }
-}
 
-impl Drop for InternRef<'_> {
-  fn drop(&mut self) {
-    if self.interner.refs.get() == 0 {
-      panic!("Interner reference count overflow");
-    } else {
-      self.interner.refs.set(self.interner.refs.get() - 1);
+  fn get_canonical(self: &Rc<Self>, spelling: &str, s: &str) -> Option<Intern> {
+    let span = self.index.borrow().get(s.as_bytes());
+    match span {
+      Some(span) => Some(self.intern_at(span, spelling)),
+      None => self.parent.as_ref()?.get_canonical(spelling, s),
     }
   }
-}
 
-#[cfg(test)]
-mod tests {
-  use super::*;
-  use std::ptr;
+  // pins the string, like intern_symbol
+  pub fn get_symbol<S: AsRef<str>>(&self, s: S) -> Option<Symbol> {
+    let spelling = s.as_ref();
+    let s = self.canonical(spelling);
+    let span = self.index.borrow().get(s.as_bytes())?;
+    Some(self.entry(span, spelling).pin())
+  }
 
-  #[test]
-  fn interner() {
-    let interner = Interner::new();
-    let hello1 = interner.intern("hello");
-    let goodbye = interner.intern("goodbye");
-    let hello2 = interner.intern("hello");
+  // counts as a reader until dropped, so nothing is collected meanwhile
+  pub fn iter(&self) -> InternerIter<'_> {
+    let entries = self
+      .symbols
+      .borrow()
+      .iter()
+      .flatten()
+      .map(|entry| (entry.symbol_id(), entry.shown()))
+      .collect::<Vec<_>>();
+    self.refs.set(self.refs.get() + 1);
 
-    assert_eq!(&*hello1.get_ref(), "hello");
-    assert_eq!(&*hello2.get_ref(), "hello");
-    assert_eq!(&*goodbye.get_ref(), "goodbye");
+    InternerIter {
+      entries: entries.into_iter(),
+      interner: self,
+    }
+  }
 
-    assert_eq!(*hello1.get_ref(), *hello2.get_ref());
-    assert_ne!(*hello1.get_ref(), *goodbye.get_ref());
+  // reads any number of strings while counting as a single reader
+  pub fn scope<R>(&self, f: impl FnOnce(&InternScope<'_>) -> R) -> R {
+    self.refs.set(self.refs.get() + 1);
+    f(&InternScope { interner: self })
+  }
 
-    assert!(ptr::addr_eq(&*hello1.get_ref(), &*hello2.get_ref()));
-    assert!(!ptr::addr_eq(&*hello1.get_ref(), &*goodbye.get_ref()));
+  // runs f on the strings of every intern, in order, as one reader
+  pub fn resolve_many<R>(
+    &self,
+    interns: &[Intern],
+    f: impl FnOnce(&[&str]) -> R,
+  ) -> R {
+    self.scope(|scope| {
+      let strs = interns
+        .iter()
+        .map(|intern| scope.get(intern))
+        .collect::<Vec<_>>();
+      f(&strs)
+    })
   }
 
-  #[test]
-  #[should_panic]
-  fn interner_errors_while_holding_refs() {
-    let interner = Interner::new();
-    let hello = interner.intern("hello");
-    let _hello_ref = hello.get_ref();
-    interner.intern("hello"); // this should panic
+  // sorted by text; the hash backend has to check every string
+  pub fn find_with_prefix<S: AsRef<str>>(
+    self: &Rc<Self>,
+    prefix: S,
+  ) -> Vec<Intern> {
+    let prefix = self.canonical(prefix.as_ref());
+    let mut spans = self.index.borrow().with_prefix(prefix.as_bytes());
+    // SAFETY: the spans were handed out by the store
+    spans.sort_unstable_by_key(|span| unsafe { self.store.get(span.clone()) });
+
+    spans
+      .into_iter()
+      .map(|span| {
+        // SAFETY: see above
+        let text = unsafe { self.store.get(span.clone()) };
+        self.intern_at(span, text)
+      })
+      .collect()
+  }
+
+  // sorted after case folding and normalization; gathered up front
+  pub fn iter_sorted(self: &Rc<Self>) -> vec::IntoIter<Intern> {
+    self.interns_in((Bound::Unbounded, Bound::Unbounded))
+  }
+
+  // the trie only visits the parts that overlap the range
+  pub fn range<S: AsRef<str>>(
+    self: &Rc<Self>,
+    range: impl RangeBounds<S>,
+  ) -> vec::IntoIter<Intern> {
+    let start = range.start_bound().map(|s| self.canonical(s.as_ref()));
+    let end = range.end_bound().map(|s| self.canonical(s.as_ref()));
+    self.interns_in((
+      start.as_ref().map(|s| s.as_bytes()),
+      end.as_ref().map(|s| s.as_bytes()),
+    ))
+  }
+
+  fn interns_in(
+    self: &Rc<Self>,
+    range: (Bound<&[u8]>, Bound<&[u8]>),
+  ) -> vec::IntoIter<Intern> {
+    let spans = self.index.borrow().range(range);
+    spans
+      .into_iter()
+      .map(|span| {
+        // SAFETY: the spans were handed out by the store
+        let text = unsafe { self.store.get(span.clone()) };
+        self.intern_at(span, text)
+      })
+      .collect::<Vec<_>>()
+      .into_iter()
+  }
+
+  // sorted by distance, then text; the trie skips branches that are too far
+  // off, the hash backend has to measure every string
+  pub fn fuzzy_search<S: AsRef<str>>(
+    self: &Rc<Self>,
+    query: S,
+    max_dist: u32,
+  ) -> Vec<(Intern, u32)> {
+    let query = self.canonical(query.as_ref());
+    let mut found = self.index.borrow().fuzzy(&query, max_dist as usize);
+    found.sort_unstable_by_key(|(span, dist)| {
+      // SAFETY: the spans were handed out by the store
+      (*dist, unsafe { self.store.get(span.clone()) })
+    });
+
+    found
+      .into_iter()
+      .map(|(span, dist)| {
+        // SAFETY: see above
+        let text = unsafe { self.store.get(span.clone()) };
+        (self.intern_at(span, text), dist as u32)
+      })
+      .collect()
+  }
+
+  // patterns are indexed text; with case folding on, ASCII letters match
+  // regardless of case
+  #[cfg(feature = "aho-corasick")]
+  pub fn build_matcher(self: &Rc<Self>) -> InternMatcher {
+    let symbols = self.symbols.borrow();
+    let patterns = symbols.iter().flatten().map(|entry| {
+      // SAFETY: the span was handed out by the store
+      let text = unsafe { self.store.get(entry.span()) };
+      let intern = Intern {
+        entry: Rc::clone(entry),
+        interner: Rc::clone(self),
+      };
+      (text, intern)
+    });
+
+    InternMatcher::new(patterns, self.case_folding != CaseFolding::Off)
+  }
+
+  // matches each string on its own, so anchors match at the ends of strings
+  #[cfg(feature = "regex")]
+  pub fn grep(self: &Rc<Self>, re: &Regex) -> Vec<Intern> {
+    self
+      .symbols
+      .borrow()
+      .iter()
+      .flatten()
+      // SAFETY: the span was handed out by the store
+      .filter(|entry| re.is_match(unsafe { self.store.get(entry.shown()) }))
+      .map(|entry| Intern {
+        entry: Rc::clone(entry),
+        interner: Rc::clone(self),
+      })
+      .collect()
+  }
+
+  // the length is of the part of `input` that matched, which folding and
+  // normalization may make differ from the length of the match itself
+  pub fn longest_prefix<S: AsRef<str>>(
+    self: &Rc<Self>,
+    input: S,
+  ) -> Option<(Intern, usize)> {
+    let input = input.as_ref();
+    let canonical = self.canonical(input);
+    let span = self.index.borrow().longest_prefix(canonical.as_bytes())?;
+    let len = match canonical {
+      Cow::Borrowed(_) => span.len(),
+      Cow::Owned(_) => {
+        // SAFETY: the span was handed out by the store
+        let text = unsafe { self.store.get(span.clone()) };
+        self.canonical_prefix_len(input, text)?
+      },
+    };
+
+    Some((self.intern_at(span, &input[..len]), len))
+  }
+
+  pub fn intern_all<I>(self: &Rc<Self>, iter: I) -> Vec<Intern>
+  where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+  {
+    iter.into_iter().map(|s| self.intern(s)).collect()
+  }
+
+  // without line endings; the buffer is reused, so nothing is allocated per
+  // line besides new strings in the store
+  pub fn intern_lines<R: BufRead>(
+    self: &Rc<Self>,
+    reader: R,
+  ) -> impl Iterator<Item = io::Result<Intern>> {
+    self.intern_records(reader, b'\n', true)
+  }
+
+  pub fn intern_split<R: BufRead>(
+    self: &Rc<Self>,
+    reader: R,
+    delimiter: u8,
+  ) -> impl Iterator<Item = io::Result<Intern>> {
+    self.intern_records(reader, delimiter, false)
+  }
+
+  // drops a carriage return before the delimiter as well for lines
+  fn intern_records<R: BufRead>(
+    self: &Rc<Self>,
+    mut reader: R,
+    delimiter: u8,
+    lines: bool,
+  ) -> impl Iterator<Item = io::Result<Intern>> {
+    let interner = Rc::clone(self);
+    let mut buffer = Vec::new();
+
+    iter::from_fn(move || {
+      buffer.clear();
+      match reader.read_until(delimiter, &mut buffer) {
+        Ok(0) => return None,
+        Ok(_) => {},
+        Err(e) => return Some(Err(e)),
+      }
+
+      let mut record = &buffer[..];
+      if let Some(rest) = record.strip_suffix(&[delimiter]) {
+        record = rest;
+        if lines {
+          record = record.strip_suffix(b"\r").unwrap_or(record);
+        }
+      }
+      Some(match std::str::from_utf8(record) {
+        Ok(s) => Ok(interner.intern(s)),
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+      })
+    })
+  }
+
+  // every row is interned, including any header, and quoted fields are
+  // unquoted first
+  #[cfg(feature = "csv")]
+  pub fn intern_column<R: Read>(
+    self: &Rc<Self>,
+    reader: R,
+    column: usize,
+    delimiter: u8,
+  ) -> io::Result<Vec<Intern>> {
+    let mut reader = csv::ReaderBuilder::new()
+      .delimiter(delimiter)
+      .has_headers(false)
+      .flexible(true)
+      .from_reader(reader);
+    let mut record = csv::StringRecord::new();
+    let mut interns = Vec::new();
+
+    let read = |e: csv::Error| {
+      if e.is_io_error() {
+        io::Error::from(e)
+      } else {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+      }
+    };
+
+    while reader.read_record(&mut record).map_err(read)? {
+      let Some(field) = record.get(column) else {
+        return Err(io::Error::new(
+          io::ErrorKind::InvalidData,
+          format!("row {} has no column {column}", interns.len()),
+        ));
+      };
+      interns.push(self.intern(field));
+    }
+
+    Ok(interns)
+  }
+
+  pub fn from_id(self: &Rc<Self>, id: SymbolId) -> Option<Intern> {
+    let symbols = self.symbols.borrow();
+    let entry = symbols.get(usize::try_from(id.0).ok()?)?.as_ref()?;
+
+    Some(Intern {
+      entry: Rc::clone(entry),
+      interner: Rc::clone(self),
+    })
+  }
+
+  // strings move while compacting, so nothing happens while InternRefs exist
+  pub fn collect(&self) -> usize {
+    if self.refs.get() != 0 {
+      return 0;
+    }
+
+    for slot in self.symbols.borrow_mut().iter_mut() {
+      if slot.as_ref().is_some_and(|entry| !Entry::in_use(entry)) {
+        *slot = None;
+      }
+    }
+
+    self.repack()
+  }
+
+  // like collect, but keeps every string, so only sharing that was missed
+  // when strings were first interned is saved
+  pub fn defragment(&self) -> usize {
+    if self.refs.get() != 0 {
+      return 0;
+    }
+
+    self.repack()
+  }
+
+  // drops unused strings, least recently used first, until the rest would
+  // take up no more than `room` bytes, then compacts like collect
+  fn evict(&self, room: usize) -> usize {
+    if self.refs.get() != 0 {
+      return 0;
+    }
+
+    let mut symbols = self.symbols.borrow_mut();
+    let mut kept = symbols
+      .iter()
+      .flatten()
+      .map(|e| e.stored_len())
+      .sum::<usize>();
+    let mut unused = symbols
+      .iter()
+      .flatten()
+      .filter(|entry| !Entry::in_use(entry))
+      .map(|entry| (entry.used.get(), entry.id))
+      .collect::<Vec<_>>();
+    unused.sort_unstable();
+
+    for (_, id) in unused {
+      if kept <= room {
+        break;
+      }
+
+      let entry = symbols[id].take().unwrap();
+      kept -= entry.stored_len();
+    }
+
+    drop(symbols);
+    self.repack()
+  }
+
+  // rebuilds the store and index out of the entries left in the symbol table
+  fn repack(&self) -> usize {
+    let live = self
+      .symbols
+      .borrow()
+      .iter()
+      .flatten()
+      .map(|entry| {
+        // SAFETY: the spans were handed out by the store, and the text is
+        //         copied out before it is cleared
+        let text = unsafe { self.store.get(entry.span()) }.to_owned();
+        let spelling = entry
+          .spelled
+          // SAFETY: see above
+          .then(|| unsafe { self.store.get(entry.shown()) }.to_owned());
+        (Rc::clone(entry), text, spelling)
+      })
+      .collect::<Vec<_>>();
+
+    let before = self.store.stored_bytes();
+    // SAFETY: there are no InternRefs, and every span is rewritten below
+    unsafe { self.store.clear() };
+    let emptied = self.index.borrow().emptied();
+    *self.index.borrow_mut() = emptied;
+    self.ids.borrow_mut().clear();
+    self.rebuild(live);
+    if let Some(spellings) = &self.spellings {
+      let symbols = self.symbols.borrow();
+      spellings
+        .borrow_mut()
+        .retain(|&id, _| symbols.get(id).is_some_and(Option::is_some));
+    }
+
+    // overlapping strings may not line up the same way again
+    before.saturating_sub(self.store.stored_bytes())
+  }
+
+  pub fn save_to<W: Write>(&self, writer: W) -> io::Result<()> {
+    self.snapshot().write_to(writer)
+  }
+
+  // sorted by id; strings inside other strings point into them instead of
+  // being copied again. FrozenInterner::from_parts puts them back together
+  pub fn into_parts(self: Rc<Self>) -> (String, Vec<(SymbolId, Range<usize>)>) {
+    let snapshot = self.snapshot();
+    let spans = snapshot
+      .entries
+      .into_iter()
+      .map(|(id, span)| {
+        (
+          SymbolId(u32::try_from(id).expect("ran out of symbol ids")),
+          span,
+        )
+      })
+      .collect();
+
+    (snapshot.text, spans)
+  }
+
+  fn snapshot(&self) -> Snapshot {
+    let index = self.index.borrow();
+    let symbols = self.symbols.borrow();
+    let entries = symbols
+      .iter()
+      .flatten()
+      .map(|entry| (entry.id, entry.span()))
+      .collect();
+
+    Snapshot::new(
+      index.backend(),
+      index.shares_substrings(),
+      self.case_folding,
+      self.normalization,
+      entries,
+      // SAFETY: the spans were handed out by the store
+      |span| unsafe { self.store.get(span) },
+    )
+  }
+
+  pub fn load_from<R: Read>(reader: R) -> io::Result<Rc<Self>> {
+    let snapshot = Snapshot::read_from(reader)?;
+    let interner = InternerBuilder::new()
+      .index(snapshot.backend)
+      .substring_sharing(snapshot.substring_sharing)
+      .case_folding(snapshot.case_folding)
+      .normalization(snapshot.normalization)
+      .capacity(snapshot.text.len(), snapshot.entries.len())
+      .build();
+
+    interner.symbols.borrow_mut().resize(snapshot.next_id, None);
+    interner.rebuild(
+      snapshot
+        .entries
+        .iter()
+        .map(|(id, span)| {
+          let text = snapshot.text[span.clone()].to_owned();
+          (Rc::new(Entry::new(*id, 0..0, None, &text)), text, None)
+        })
+        .collect(),
+    );
+    if interner.ids.borrow().len() != snapshot.entries.len() {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "saved interner has duplicate strings",
+      ));
+    }
+
+    Ok(interner)
+  }
+
+  // stores each entry's string, which must not already be in the index, and
+  // its spelling if it has one. Static strings are left where they are
+  fn rebuild(&self, mut live: Vec<(Rc<Entry>, String, Option<String>)>) {
+    // index static strings first, and store longer strings before shorter
+    // ones, so shorter ones can share their data
+    live.sort_by_key(|(entry, text, _)| {
+      (!Store::is_static(&entry.span()), Reverse(text.len()))
+    });
+
+    let mut index = self.index.borrow_mut();
+    let mut ids = self.ids.borrow_mut();
+    let mut symbols = self.symbols.borrow_mut();
+    for (entry, text, spelling) in live {
+      let text = text.as_bytes();
+      let span = index.get_or_promote(text).unwrap_or_else(|| {
+        let span = match entry.span() {
+          span if Store::is_static(&span) => span,
+          _ if index.shares_substrings() => self.store.push_overlapping(text),
+          _ => self.store.push(text),
+        };
+        index.insert(text, span)
+      });
+
+      if symbols.len() <= entry.id {
+        symbols.resize(entry.id + 1, None);
+      }
+      entry.set_span(span.clone());
+      if let Some(spelling) = spelling {
+        entry.set_shown(self.store.push(spelling.as_bytes()));
+      }
+      ids.insert(span, entry.id);
+      let id = entry.id;
+      symbols[id] = Some(entry);
+    }
+  }
+
+  // the store may move, so it is left alone while InternRefs exist
+  pub fn shrink_to_fit(&self) -> usize {
+    self.index.borrow_mut().shrink_to_fit();
+    self.ids.borrow_mut().shrink_to_fit();
+    self.symbols.borrow_mut().shrink_to_fit();
+
+    if self.refs.get() != 0 {
+      return 0;
+    }
+
+    // SAFETY: there are no InternRefs
+    unsafe { self.store.shrink_to_fit() }
+  }
+
+  pub fn freeze(self: Rc<Self>) -> FrozenInterner {
+    let symbols = self.symbols.borrow();
+    let strings = symbols.iter().flatten().map(|entry| {
+      let id = u32::try_from(entry.id).expect("ran out of symbol ids");
+      // SAFETY: the spans were handed out by the store
+      (SymbolId(id), unsafe { self.store.get(entry.span()) })
+    });
+
+    FrozenInterner::new(strings, self.case_folding, self.normalization)
+  }
+
+  fn intern_at(self: &Rc<Self>, span: Span, spelling: &str) -> Intern {
+    Intern {
+      entry: self.entry(span, spelling),
+      interner: Rc::clone(self),
+    }
+  }
+
+  // like intern_at, but counts the string as interned once more
+  fn interned_at(self: &Rc<Self>, span: Span, spelling: &str) -> Intern {
+    Intern {
+      entry: self.interned(span, spelling),
+      interner: Rc::clone(self),
+    }
+  }
+
+  fn interned(&self, span: Span, spelling: &str) -> Rc<Entry> {
+    let entry = self.entry(span, spelling);
+    if self.counting {
+      entry.interned.set(entry.interned.get() + 1);
+    }
+    entry
+  }
+
+  // spelling is how the string was written before it was folded
+  fn entry(&self, span: Span, spelling: &str) -> Rc<Entry> {
+    let mut symbols = self.symbols.borrow_mut();
+    let id = *self
+      .ids
+      .borrow_mut()
+      .entry(span.clone())
+      .or_insert_with(|| {
+        let id = symbols.len();
+        // SAFETY: the span was handed out by the store
+        let text = unsafe { self.store.get(span.clone()) };
+        let entry = if self.preserve_case && spelling != text {
+          let spelled = self.store.push(spelling.as_bytes());
+          Entry::new(id, span, Some(spelled), spelling)
+        } else {
+          Entry::new(id, span, None, text)
+        };
+        symbols.push(Some(Rc::new(entry)));
+        id
+      });
+
+    if let Some(spellings) = &self.spellings {
+      let mut spellings = spellings.borrow_mut();
+      let seen = spellings.entry(id).or_default();
+      if !seen.iter().any(|seen| **seen == *spelling) {
+        seen.push(spelling.into());
+      }
+    }
+
+    let entry = symbols[id].as_ref().unwrap();
+    entry.used.set(self.clock.get());
+    self.clock.set(self.clock.get() + 1);
+    Rc::clone(entry)
+  }
+
+  // every distinct spelling, first seen first, if the interner was built with
+  // record_spellings; otherwise just the string as it reads
+  pub fn spellings(&self, symbol: Symbol) -> Vec<String> {
+    let shown = self.resolve(symbol).to_owned();
+    let id = symbol.id().0 as usize;
+    let spellings = self.spellings.as_ref().map(RefCell::borrow);
+    match spellings.as_ref().and_then(|spellings| spellings.get(&id)) {
+      Some(spellings) => spellings.iter().map(ToString::to_string).collect(),
+      None => vec![shown],
+    }
+  }
+
+  pub(crate) fn canonical<'a>(&self, s: &'a str) -> Cow<'a, str> {
+    self.normalization.canonical(self.case_folding, s)
+  }
+
+  // how many bytes from the start of `input` are put in the form `canonical`
+  fn canonical_prefix_len(
+    &self,
+    input: &str,
+    canonical: &str,
+  ) -> Option<usize> {
+    input
+      .char_indices()
+      .map(|(i, _)| i)
+      .chain([input.len()])
+      .map(|end| (end, self.canonical(&input[..end])))
+      .take_while(|(_, prefix)| prefix.len() <= canonical.len())
+      .find(|(_, prefix)| prefix == canonical)
+      .map(|(end, _)| end)
+  }
+
+  fn intern_existing(&self, s: &str) -> Option<Span> {
+    let span = self.index.borrow_mut().get_or_promote(s.as_bytes())?;
+    self.hits.set(self.hits.get() + 1);
+    Some(span)
+  }
+
+  fn intern_uncontested(&self, s: &str) -> Result<Span, InternError> {
+    if let Some(span) = self.intern_existing(s) {
+      return Ok(span);
+    }
+
+    if let Some(limit) = self.max_store_bytes {
+      if self.store.stored_bytes() + s.len() > limit {
+        return Err(InternError::StoreFull { limit });
+      }
+    }
+
+    self.misses.set(self.misses.get() + 1);
+    let span = self.push(s.as_bytes());
+    Ok(self.index.borrow_mut().insert(s.as_bytes(), span))
+  }
+
+  // with substring sharing, a new string may also start inside the end of the
+  // store, if the store ends with the start of the string
+  fn push(&self, s: &[u8]) -> Span {
+    if self.index.borrow().shares_substrings() {
+      self.store.push_overlapping(s)
+    } else {
+      self.store.push(s)
+    }
+  }
+
+  // like intern_uncontested, but evicts unused strings to make room for `s`
+  // first if the store is full and the policy allows it
+  fn intern_making_room(&self, s: &str) -> Result<Span, InternError> {
+    match self.intern_uncontested(s) {
+      Err(InternError::StoreFull { limit })
+        if self.store_full == StoreFullPolicy::Evict =>
+      {
+        self.evict(limit.saturating_sub(s.len()));
+        self.intern_uncontested(s)
+      },
+      result => result,
+    }
+  }
+
+  // `span` already lies in the store, such as part of a longer string
+  fn intern_in_place(&self, span: Span) -> Span {
+    // SAFETY: the span lies within a span handed out by the store
+    let s = unsafe { self.store.get(span.clone()) };
+    if let Some(existing) = self.intern_existing(s) {
+      return existing;
+    }
+
+    self.misses.set(self.misses.get() + 1);
+    self.index.borrow_mut().insert_in_place(s.as_bytes(), span)
+  }
+
+  // `span` was made by Store::static_span
+  fn intern_static_span(&self, span: Span) -> Span {
+    // SAFETY: the span points at a &'static str
+    let s = unsafe { self.store.get(span.clone()) };
+    if let Some(existing) = self.intern_existing(s) {
+      return existing;
+    }
+
+    self.misses.set(self.misses.get() + 1);
+    self.index.borrow_mut().insert(s.as_bytes(), span)
+  }
+
+  // `staged` must be the last span staged, with nothing written since
+  fn intern_staged(&self, staged: Span) -> Result<Span, InternError> {
+    // SAFETY: the staged bytes were copied out of strs
+    let s = unsafe { self.store.get(staged.clone()) };
+    if let Some(span) = self.intern_existing(s) {
+      return Ok(span);
+    }
+
+    if let Some(limit) = self.max_store_bytes {
+      if self.store.stored_bytes() + s.len() > limit {
+        return Err(InternError::StoreFull { limit });
+      }
+    }
+
+    self.misses.set(self.misses.get() + 1);
+    self.store.commit(staged.clone());
+    Ok(self.index.borrow_mut().insert(s.as_bytes(), staged))
+  }
+}
+
+#[derive(Clone)]
+pub struct Intern {
+  entry: Rc<Entry>,
+  interner: Rc<Interner>,
+}
+
+impl Intern {
+  pub(crate) fn interner(&self) -> &Rc<Interner> {
+    &self.interner
+  }
+
+  pub fn get_ref(&self) -> InternRef<'_> {
+    self.interner.read(&self.entry)
+  }
+
+  // fails instead of adding a reader past max_outstanding_refs
+  pub fn try_get_ref(&self) -> Result<InternRef<'_>, RefError> {
+    let refs = self.interner.refs.get();
+    match self.interner.max_refs {
+      Some(limit) if refs >= limit => Err(RefError::TooManyRefs { limit }),
+      _ => Ok(self.get_ref()),
+    }
+  }
+
+  // the string as indexed, even if preserve_case shows it as first spelled
+  pub fn folded(&self) -> InternRef<'_> {
+    self.interner.read_span(self.entry.span())
+  }
+
+  // known with preserve_case or record_spellings; otherwise the string as
+  // it reads
+  pub fn first_spelling(&self) -> String {
+    let first = self.interner.spellings.as_ref().and_then(|spellings| {
+      spellings.borrow().get(&self.entry.id)?.first().cloned()
+    });
+    first.map_or_else(|| self.resolve(), String::from)
+  }
+
+  pub fn with_str<R>(&self, f: impl FnOnce(&str) -> R) -> R {
+    f(&self.get_ref())
+  }
+
+  // the string queries only read the string for as long as they take
+  pub fn starts_with(&self, prefix: &str) -> bool {
+    self.with_str(|s| s.starts_with(prefix))
+  }
+
+  pub fn ends_with(&self, suffix: &str) -> bool {
+    self.with_str(|s| s.ends_with(suffix))
+  }
+
+  pub fn contains(&self, needle: &str) -> bool {
+    self.with_str(|s| s.contains(needle))
+  }
+
+  pub fn find(&self, needle: &str) -> Option<usize> {
+    self.with_str(|s| s.find(needle))
+  }
+
+  pub fn resolve(&self) -> String {
+    self.get_ref().to_owned()
+  }
+
+  pub fn resolve_rc(&self) -> Rc<str> {
+    Rc::from(&*self.get_ref())
+  }
+
+  pub fn len(&self) -> usize {
+    self.entry.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn char_len(&self) -> usize {
+    self.entry.chars
+  }
+
+  // an interner never stores the same string twice, so interns from the same
+  // one can be compared by pointer
+  pub fn text_eq(&self, other: &Self) -> bool {
+    if Rc::ptr_eq(&self.interner, &other.interner) {
+      return self == other;
+    }
+
+    self.len() == other.len() && *self == *other.get_ref()
+  }
+
+  pub fn cmp_text(&self, other: &Self) -> Ordering {
+    if self == other {
+      return Ordering::Equal;
+    }
+
+    str::cmp(&self.get_ref(), &other.get_ref())
+  }
+
+  // in bytes, always ending on a char boundary
+  pub fn common_prefix_len(&self, other: &Self) -> usize {
+    if self == other {
+      return self.len();
+    }
+
+    let (a, b) = (self.get_ref(), other.get_ref());
+    a.chars()
+      .zip(b.chars())
+      .take_while(|(a, b)| a == b)
+      .map(|(c, _)| c.len_utf8())
+      .sum()
+  }
+
+  pub fn common_suffix_len(&self, other: &Self) -> usize {
+    if self == other {
+      return self.len();
+    }
+
+    let (a, b) = (self.get_ref(), other.get_ref());
+    a.chars()
+      .rev()
+      .zip(b.chars().rev())
+      .take_while(|(a, b)| a == b)
+      .map(|(c, _)| c.len_utf8())
+      .sum()
+  }
+
+  // doesn't copy anything unless folding or normalization change the part
+  pub fn slice(&self, range: Range<usize>) -> Result<Intern, SliceError> {
+    let interner = &self.interner;
+    let shown = self.entry.shown();
+    // SAFETY: the span was handed out by the store
+    let text = unsafe { interner.store.get(shown.clone()) };
+    SliceError::check(text, &range)?;
+
+    let part = &text[range.clone()];
+    let (span, part) = match interner.canonical(part) {
+      Cow::Borrowed(_) => {
+        let span = (shown.start + range.start)..(shown.start + range.end);
+        (interner.intern_in_place(span), Cow::Borrowed(part))
+      },
+      Cow::Owned(s) => {
+        // making room for `s` may move the text that `part` lies in
+        let part = part.to_owned();
+        match interner.intern_making_room(&s) {
+          Ok(span) => (span, Cow::Owned(part)),
+          Err(e) => panic!("{e}"),
+        }
+      },
+    };
+
+    Ok(interner.interned_at(span, &part))
+  }
+
+  pub fn map(&self, f: impl FnOnce(&str) -> String) -> Intern {
+    self.interner.intern(self.with_str(f))
+  }
+
+  pub fn into_str(self) -> InternStr {
+    InternStr::from(self)
+  }
+
+  pub fn detach(self) -> DetachedIntern {
+    DetachedIntern {
+      text: Arc::from(&*self.get_ref()),
+    }
+  }
+
+  // formats the text with both {} and {:?}, reading it only when formatted
+  pub fn display(&self) -> InternDisplay<'_> {
+    InternDisplay { intern: self }
+  }
+
+  pub fn downgrade(&self) -> WeakIntern {
+    WeakIntern {
+      entry: Rc::downgrade(&self.entry),
+      interner: Rc::downgrade(&self.interner),
+    }
+  }
+
+  pub fn id(&self) -> SymbolId {
+    self.entry.symbol_id()
+  }
+
+  pub fn symbol(&self) -> Symbol {
+    self.entry.pin()
+  }
+}
+
+impl fmt::Debug for Intern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Intern")
+      .field("id", &self.entry.id)
+      .field("text", &&*self.get_ref())
+      .finish()
+  }
+}
+
+impl fmt::Display for Intern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad(&self.get_ref())
+  }
+}
+
+impl PartialEq for Intern {
+  fn eq(&self, other: &Self) -> bool {
+    Rc::ptr_eq(&self.entry, &other.entry)
+  }
+}
+impl Eq for Intern {}
+
+impl PartialEq<str> for Intern {
+  fn eq(&self, other: &str) -> bool {
+    self.len() == other.len() && self.with_str(|s| s == other)
+  }
+}
+
+impl PartialEq<&str> for Intern {
+  fn eq(&self, other: &&str) -> bool {
+    *self == **other
+  }
+}
+
+impl PartialEq<Intern> for str {
+  fn eq(&self, other: &Intern) -> bool {
+    *other == *self
+  }
+}
+
+impl PartialEq<Intern> for &str {
+  fn eq(&self, other: &Intern) -> bool {
+    *other == **self
+  }
+}
+
+impl PartialEq<String> for Intern {
+  fn eq(&self, other: &String) -> bool {
+    *self == **other
+  }
+}
+
+impl PartialEq<Intern> for String {
+  fn eq(&self, other: &Intern) -> bool {
+    *other == **self
+  }
+}
+
+impl PartialEq<Cow<'_, str>> for Intern {
+  fn eq(&self, other: &Cow<'_, str>) -> bool {
+    *self == **other
+  }
+}
+
+impl PartialEq<Intern> for Cow<'_, str> {
+  fn eq(&self, other: &Intern) -> bool {
+    *other == **self
+  }
+}
+
+impl From<Intern> for String {
+  fn from(intern: Intern) -> Self {
+    intern.resolve()
+  }
+}
+
+impl From<&Intern> for String {
+  fn from(intern: &Intern) -> Self {
+    intern.resolve()
+  }
+}
+
+impl PartialOrd<str> for Intern {
+  fn partial_cmp(&self, other: &str) -> Option<Ordering> {
+    Some(self.with_str(|s| s.cmp(other)))
+  }
+}
+
+impl PartialOrd<&str> for Intern {
+  fn partial_cmp(&self, other: &&str) -> Option<Ordering> {
+    self.partial_cmp(*other)
+  }
+}
+
+impl PartialOrd<Intern> for str {
+  fn partial_cmp(&self, other: &Intern) -> Option<Ordering> {
+    other.partial_cmp(self).map(Ordering::reverse)
+  }
+}
+
+impl PartialOrd<Intern> for &str {
+  fn partial_cmp(&self, other: &Intern) -> Option<Ordering> {
+    other.partial_cmp(*self).map(Ordering::reverse)
+  }
+}
+
+// cheap and consistent with Eq, but not alphabetical; see Intern::cmp_text
+impl Ord for Intern {
+  fn cmp(&self, other: &Self) -> Ordering {
+    Rc::as_ptr(&self.interner)
+      .cmp(&Rc::as_ptr(&other.interner))
+      .then(self.entry.id.cmp(&other.entry.id))
+  }
+}
+
+impl PartialOrd for Intern {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Hash for Intern {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    Rc::as_ptr(&self.entry).hash(state);
+  }
+}
+
+// an Intern that borrows its interner rather than sharing it through an Rc.
+// It reads and compares like an Intern, but leaves out everything that
+// interns a new string, such as slice and map, or that needs the Rc, such
+// as downgrade and into_str
+#[derive(Clone)]
+pub struct BorrowedIntern<'i> {
+  entry: Rc<Entry>,
+  interner: &'i Interner,
+}
+
+impl<'i> BorrowedIntern<'i> {
+  pub fn get_ref(&self) -> InternRef<'i> {
+    self.interner.read(&self.entry)
+  }
+
+  pub fn with_str<R>(&self, f: impl FnOnce(&str) -> R) -> R {
+    f(&self.get_ref())
+  }
+
+  pub fn resolve(&self) -> String {
+    self.get_ref().to_owned()
+  }
+
+  pub fn len(&self) -> usize {
+    self.entry.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entry.len == 0
+  }
+
+  pub fn char_len(&self) -> usize {
+    self.entry.chars
+  }
+
+  pub fn detach(self) -> DetachedIntern {
+    DetachedIntern {
+      text: Arc::from(&*self.get_ref()),
+    }
+  }
+
+  pub fn id(&self) -> SymbolId {
+    self.entry.symbol_id()
+  }
+
+  pub fn symbol(&self) -> Symbol {
+    self.entry.pin()
+  }
+
+  pub fn interner(&self) -> &'i Interner {
+    self.interner
+  }
+}
+
+impl fmt::Debug for BorrowedIntern<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("BorrowedIntern")
+      .field("id", &self.entry.id)
+      .field("text", &&*self.get_ref())
+      .finish()
+  }
+}
+
+impl fmt::Display for BorrowedIntern<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad(&self.get_ref())
+  }
+}
+
+impl PartialEq for BorrowedIntern<'_> {
+  fn eq(&self, other: &Self) -> bool {
+    Rc::ptr_eq(&self.entry, &other.entry)
+  }
+}
+impl Eq for BorrowedIntern<'_> {}
+
+impl PartialEq<str> for BorrowedIntern<'_> {
+  fn eq(&self, other: &str) -> bool {
+    self.len() == other.len() && self.with_str(|s| s == other)
+  }
+}
+
+impl PartialEq<&str> for BorrowedIntern<'_> {
+  fn eq(&self, other: &&str) -> bool {
+    *self == **other
+  }
+}
+
+impl PartialEq<BorrowedIntern<'_>> for str {
+  fn eq(&self, other: &BorrowedIntern<'_>) -> bool {
+    *other == *self
+  }
+}
+
+impl PartialEq<BorrowedIntern<'_>> for &str {
+  fn eq(&self, other: &BorrowedIntern<'_>) -> bool {
+    *other == **self
+  }
+}
+
+impl From<BorrowedIntern<'_>> for String {
+  fn from(intern: BorrowedIntern<'_>) -> Self {
+    intern.resolve()
+  }
+}
+
+impl Ord for BorrowedIntern<'_> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    ptr::from_ref(self.interner)
+      .cmp(&ptr::from_ref(other.interner))
+      .then(self.entry.id.cmp(&other.entry.id))
+  }
+}
+
+impl PartialOrd for BorrowedIntern<'_> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Hash for BorrowedIntern<'_> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    Rc::as_ptr(&self.entry).hash(state);
+  }
+}
+
+// doesn't keep the string alive, so it can't be upgraded once collect frees it
+#[derive(Clone)]
+pub struct WeakIntern {
+  entry: Weak<Entry>,
+  interner: Weak<Interner>,
+}
+
+impl WeakIntern {
+  pub fn upgrade(&self) -> Option<Intern> {
+    Some(Intern {
+      entry: self.entry.upgrade()?,
+      interner: self.interner.upgrade()?,
+    })
+  }
+}
+
+impl fmt::Debug for WeakIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("(WeakIntern)")
+  }
+}
+
+// the text copied out of an Intern, so it can outlive the interner or be sent
+// to another thread
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DetachedIntern {
+  text: Arc<str>,
+}
+
+impl DetachedIntern {
+  pub fn as_str(&self) -> &str {
+    &self.text
+  }
+}
+
+impl Deref for DetachedIntern {
+  type Target = str;
+
+  fn deref(&self) -> &Self::Target {
+    &self.text
+  }
+}
+
+impl AsRef<str> for DetachedIntern {
+  fn as_ref(&self) -> &str {
+    &self.text
+  }
+}
+
+impl Borrow<str> for DetachedIntern {
+  fn borrow(&self) -> &str {
+    &self.text
+  }
+}
+
+impl From<DetachedIntern> for Arc<str> {
+  fn from(detached: DetachedIntern) -> Self {
+    detached.text
+  }
+}
+
+impl fmt::Debug for DetachedIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("DetachedIntern").field(&&*self.text).finish()
+  }
+}
+
+impl fmt::Display for DetachedIntern {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad(&self.text)
+  }
+}
+
+impl PartialEq<str> for DetachedIntern {
+  fn eq(&self, other: &str) -> bool {
+    *self.text == *other
+  }
+}
+
+#[derive(Clone, Copy)]
+pub struct InternDisplay<'a> {
+  intern: &'a Intern,
+}
+
+impl fmt::Debug for InternDisplay<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Debug::fmt(&*self.intern.get_ref(), f)
+  }
+}
+
+impl fmt::Display for InternDisplay<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Display::fmt(self.intern, f)
+  }
+}
+
+impl PartialEq<&str> for DetachedIntern {
+  fn eq(&self, other: &&str) -> bool {
+    *self.text == **other
+  }
+}
+
+pub struct InternRef<'a> {
+  text: &'a str,
+  refs: &'a Cell<usize>,
+}
+
+impl Drop for InternRef<'_> {
+  fn drop(&mut self) {
+    self.refs.set(self.refs.get() - 1);
+  }
+}
+
+impl fmt::Debug for InternRef<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("InternRef").field(&self.text).finish()
+  }
+}
+
+impl fmt::Display for InternRef<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad(self.text)
+  }
+}
+
+impl Deref for InternRef<'_> {
+  type Target = str;
+
+  fn deref(&self) -> &Self::Target {
+    self.text
+  }
+}
+
+// counts as a reader for as long as it lives, so strings can't move under it
+pub struct InternStr {
+  intern: Intern,
+  span: Span,
+}
+
+impl InternStr {
+  pub fn intern(&self) -> &Intern {
+    &self.intern
+  }
+
+  // stops reading, so collect can run again
+  pub fn downgrade(self) -> Intern {
+    self.intern.clone()
+  }
+}
+
+impl From<Intern> for InternStr {
+  fn from(intern: Intern) -> Self {
+    let refs = &intern.interner.refs;
+    refs.set(refs.get() + 1);
+    let span = intern.entry.shown();
+
+    Self { intern, span }
+  }
+}
+
+impl Clone for InternStr {
+  fn clone(&self) -> Self {
+    Self::from(self.intern.clone())
+  }
+}
+
+impl Drop for InternStr {
+  fn drop(&mut self) {
+    let refs = &self.intern.interner.refs;
+    refs.set(refs.get() - 1);
+  }
+}
+
+impl Deref for InternStr {
+  type Target = str;
+
+  fn deref(&self) -> &Self::Target {
+    // SAFETY: the span was handed out by the store, and this has been
+    //         counted in `refs` ever since, so it can't have moved
+    unsafe { self.intern.interner.store.get(self.span.clone()) }
+  }
+}
+
+impl AsRef<str> for InternStr {
+  fn as_ref(&self) -> &str {
+    self
+  }
+}
+
+impl Borrow<str> for InternStr {
+  fn borrow(&self) -> &str {
+    self
+  }
+}
+
+// an Intern can't be borrowed as a Cow, since nothing would keep its string
+// from moving, so this goes through InternStr
+impl<'a> From<&'a InternStr> for Cow<'a, str> {
+  fn from(s: &'a InternStr) -> Self {
+    Cow::Borrowed(s)
+  }
+}
+
+impl fmt::Debug for InternStr {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("InternStr").field(&&**self).finish()
+  }
+}
+
+impl fmt::Display for InternStr {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad(self)
+  }
+}
+
+impl PartialEq for InternStr {
+  fn eq(&self, other: &Self) -> bool {
+    self.intern.text_eq(&other.intern)
+  }
+}
+impl Eq for InternStr {}
+
+impl Ord for InternStr {
+  fn cmp(&self, other: &Self) -> Ordering {
+    str::cmp(self, other)
+  }
+}
+
+impl PartialOrd for InternStr {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+// must hash like str for Borrow
+impl Hash for InternStr {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    str::hash(self, state);
+  }
+}
+
+pub struct InternerIter<'a> {
+  entries: vec::IntoIter<(SymbolId, Span)>,
+  interner: &'a Interner,
+}
+
+impl<'a> Iterator for InternerIter<'a> {
+  type Item = (SymbolId, InternRef<'a>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (id, span) = self.entries.next()?;
+    let refs = &self.interner.refs;
+    refs.set(refs.get() + 1);
+
+    // SAFETY: the span was handed out by the store, and it can't move while
+    //         it's counted in `refs`
+    let text = unsafe { self.interner.store.get(span) };
+    Some((id, InternRef { text, refs }))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.entries.size_hint()
+  }
+}
+
+impl ExactSizeIterator for InternerIter<'_> {}
+
+impl Drop for InternerIter<'_> {
+  fn drop(&mut self) {
+    let refs = &self.interner.refs;
+    refs.set(refs.get() - 1);
+  }
+}
+
+impl fmt::Debug for InternerIter<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("InternerIter")
+      .field("remaining", &self.entries.len())
+      .finish_non_exhaustive()
+  }
+}
+
+pub struct InternScope<'a> {
+  interner: &'a Interner,
+}
+
+impl InternScope<'_> {
+  pub fn get(&self, intern: &Intern) -> &str {
+    assert!(
+      ptr::eq(&*intern.interner, self.interner),
+      "intern does not belong to this interner"
+    );
+
+    // SAFETY: the span was handed out by the interner's store, and nothing
+    //         is collected while the scope is counted as a reader
+    unsafe { self.interner.store.get(intern.entry.shown()) }
+  }
+}
+
+impl Drop for InternScope<'_> {
+  fn drop(&mut self) {
+    let refs = &self.interner.refs;
+    refs.set(refs.get() - 1);
+  }
+}
+
+impl fmt::Debug for InternScope<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("InternScope").finish_non_exhaustive()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::unsync::PartsError;
+  use std::{collections::HashMap, ptr};
+
+  #[test]
+  fn interner() {
+    let interner = Interner::new();
+    let hello1 = interner.intern("hello");
+    let goodbye = interner.intern("goodbye");
+    let hello2 = interner.intern("hello");
+
+    assert_eq!(&*hello1.get_ref(), "hello");
+    assert_eq!(&*hello2.get_ref(), "hello");
+    assert_eq!(&*goodbye.get_ref(), "goodbye");
+
+    assert_eq!(*hello1.get_ref(), *hello2.get_ref());
+    assert_ne!(*hello1.get_ref(), *goodbye.get_ref());
+
+    assert!(ptr::addr_eq(&*hello1.get_ref(), &*hello2.get_ref()));
+    assert!(!ptr::addr_eq(&*hello1.get_ref(), &*goodbye.get_ref()));
+  }
+
+  #[test]
+  fn intern_resolves_owned_copies() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+
+    assert_eq!(hello.resolve(), "hello");
+    assert_eq!(&*hello.resolve_rc(), "hello");
+  }
+
+  #[test]
+  fn interns_work_as_map_keys() {
+    let interner = Interner::new();
+    let other = Interner::new();
+    let mut counts = HashMap::new();
+
+    for word in ["a", "b", "a", "c", "a"] {
+      *counts.entry(interner.intern(word)).or_insert(0) += 1;
+    }
+    counts.insert(other.intern("a"), 10);
+
+    assert_eq!(counts[&interner.intern("a")], 3);
+    assert_eq!(counts[&interner.intern("b")], 1);
+    assert_eq!(counts[&other.intern("a")], 10);
+    assert_eq!(counts.len(), 4);
+  }
+
+  #[test]
+  fn interns_order_by_age_or_text() {
+    let interner = Interner::new();
+    let b = interner.intern("b");
+    let a = interner.intern("a");
+
+    assert!(b < a);
+    assert_eq!(b.cmp_text(&a), Ordering::Greater);
+    assert_eq!(a.cmp_text(&interner.intern("a")), Ordering::Equal);
+  }
+
+  #[test]
+  fn interner_interns_batches() {
+    let interner = Interner::new();
+    let single = interner.intern("b");
+    let batch = interner.intern_all(["a", "b"]);
+
+    assert_eq!(&*batch[0].get_ref(), "a");
+    assert_eq!(batch[1], single);
+  }
+
+  #[test]
+  fn interner_reuses_substrings() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let ell = interner.intern_substring("ell").unwrap();
+
+    assert!(ptr::addr_eq(&hello.get_ref()[1..4], &*ell.get_ref()));
+    assert_eq!(interner.intern("ell"), ell);
+    assert_eq!(interner.intern_substring("help"), None);
+    assert_eq!(interner.stats().stored_bytes, 5);
+  }
+
+  #[test]
+  fn interner_overlaps_strings_with_the_end_of_the_store() {
+    let interner = Interner::new();
+    let config = interner.intern("config");
+    let figure = interner.intern("figure");
+
+    assert_eq!(&*figure.get_ref(), "figure");
+    assert!(ptr::addr_eq(&config.get_ref()[3..], &figure.get_ref()[..3]));
+    assert_eq!(interner.stats().stored_bytes, 9);
+
+    drop(config);
+    interner.collect();
+    assert_eq!(&*figure.get_ref(), "figure");
+    assert_eq!(interner.stats().stored_bytes, 6);
+
+    // collecting may not find the same overlaps again
+    let (ab, bcd) = (interner.intern("ab"), interner.intern("bcd"));
+    assert_eq!(interner.collect(), 0);
+    assert_eq!((&*ab.get_ref(), &*bcd.get_ref()), ("ab", "bcd"));
+
+    let exact = InternerBuilder::new().substring_sharing(false).build();
+    exact.intern("config");
+    exact.intern("figure");
+    assert_eq!(exact.stats().stored_bytes, 12);
+  }
+
+  #[test]
+  fn interner_gets_without_inserting() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+
+    assert_eq!(interner.get("hello"), Some(hello));
+    assert_eq!(interner.get("ell"), None);
+
+    let ell = interner.intern("ell");
+    assert_eq!(interner.get("ell"), Some(ell));
+  }
+
+  #[test]
+  fn interner_stats() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    interner.intern("ell");
+    interner.intern("hello");
+    let _hello_ref = hello.get_ref();
+
+    let stats = interner.stats();
+    assert_eq!(stats.stored_bytes, 5);
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.trie_nodes, 2);
+    assert_eq!(stats.automaton_states, 7);
+    assert_eq!(stats.outstanding_refs, 1);
+    assert_eq!((stats.hits, stats.misses), (2, 1));
+  }
+
+  #[test]
+  fn interner_with_hash_index() {
+    let interner = InternerBuilder::new().index(IndexBackend::Hash).build();
+    let hello = interner.intern("hello");
+    let ell = interner.intern("ell");
+
+    assert_eq!(interner.intern("hello"), hello);
+    assert_eq!(&*ell.get_ref(), "ell");
+    assert_eq!(interner.intern_substring("hel"), None);
+    assert_eq!(interner.stats().stored_bytes, 8);
+  }
+
+  #[test]
+  fn interner_toggles_substring_sharing() {
+    let exact = InternerBuilder::new().substring_sharing(false).build();
+    let shared = InternerBuilder::new()
+      .index(IndexBackend::Hash)
+      .substring_sharing(true)
+      .build();
+    exact.intern("hello");
+    shared.intern("hello");
+
+    assert_eq!(exact.intern_substring("ell"), None);
+    assert_eq!(exact.stats().automaton_states, 0);
+    assert_eq!(&*shared.intern_substring("ell").unwrap().get_ref(), "ell");
+  }
+
+  #[test]
+  fn interner_folds_ascii_case() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let hello = interner.intern("Hello");
+
+    assert_eq!(interner.intern("HELLO"), hello);
+    assert_eq!(interner.get("hELLo"), Some(hello.clone()));
+    assert_eq!(&*hello.get_ref(), "hello");
+    assert_eq!(&*interner.intern("ÀB").get_ref(), "Àb");
+  }
+
+  #[cfg(feature = "caseless")]
+  #[test]
+  fn interner_folds_unicode_case() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Unicode)
+      .build();
+    let street = interner.intern("Straße");
+
+    assert_eq!(interner.intern("STRASSE"), street);
+    assert_eq!(&*street.get_ref(), "strasse");
+    assert_eq!(interner.get("ÀB"), None);
+    assert_eq!(interner.intern("ÀB"), interner.intern("àb"));
+  }
+
+  #[cfg(feature = "unicode-normalization")]
+  #[test]
+  fn interner_normalizes_unicode() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .normalization(Normalization::Nfkc)
+      .build();
+    let cafe = interner.intern("cafe\u{301}");
+
+    assert_eq!(interner.intern("café"), cafe);
+    assert_eq!(interner.intern("CAFE\u{301}"), cafe);
+    assert_eq!(&*cafe.get_ref(), "café");
+    assert_eq!(interner.intern("ﬁle"), interner.intern("file"));
+
+    let mut saved = Vec::new();
+    interner.save_to(&mut saved).unwrap();
+    let reloaded = Interner::load_from(&saved[..]).unwrap();
+    assert!(reloaded.get("Cafe\u{301}").is_some());
+    assert!(reloaded.clone().freeze().get("Cafe\u{301}").is_some());
+  }
+
+  #[test]
+  fn interner_preserves_first_casing() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let header = interner.intern("Content-Type");
+    let lower = interner.intern("accept");
+
+    assert_eq!(interner.intern("CONTENT-TYPE"), header);
+    assert_eq!(interner.get("content-type"), Some(header.clone()));
+    assert_eq!(&*header.get_ref(), "Content-Type");
+    assert_eq!(header.resolve(), "Content-Type");
+    assert_eq!(&*interner.intern("ACCEPT").get_ref(), "accept");
+    // only the differing spelling takes up extra room
+    assert_eq!(interner.stats().stored_bytes, 30);
+
+    drop(interner.intern("garbage"));
+    interner.collect();
+    assert_eq!(&*header.get_ref(), "Content-Type");
+    assert_eq!(&*lower.get_ref(), "accept");
+    assert_eq!(interner.stats().stored_bytes, 30);
+  }
+
+  #[test]
+  fn interner_records_spellings() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .record_spellings(true)
+      .build();
+    let header = interner.intern_symbol("Content-Type");
+    let accept = interner.intern("accept");
+    interner.intern("content-type");
+    interner.intern("CONTENT-TYPE");
+    interner.intern("Content-Type");
+    interner.intern("ACCEPT");
+
+    assert_eq!(
+      interner.spellings(header),
+      ["Content-Type", "content-type", "CONTENT-TYPE"]
+    );
+    assert_eq!(interner.spellings(accept.symbol()), ["accept", "ACCEPT"]);
+    assert_eq!(accept.first_spelling(), "accept");
+    assert_eq!(&*accept.get_ref(), "accept");
+
+    let shout = interner.intern("SHOUT");
+    assert_eq!(shout.first_spelling(), "SHOUT");
+    assert_eq!(&*shout.folded(), "shout");
+    let id = shout.id().0 as usize;
+    drop(shout);
+    interner.collect();
+    let spellings = interner.spellings.as_ref().unwrap().borrow();
+    assert!(!spellings.contains_key(&id));
+    drop(spellings);
+
+    let preserving = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let html = preserving.intern("<DIV>");
+    assert_eq!(html.first_spelling(), "<DIV>");
+    assert_eq!(&*html.folded(), "<div>");
+    let div = preserving.intern_symbol("<div>");
+    assert_eq!(preserving.spellings(div), ["<DIV>"]);
+  }
+
+  #[test]
+  fn interner_interns_static_strs_in_place() {
+    static KEYWORD: &str = "keyword";
+    let interner = Interner::new();
+    let existing = interner.intern("fn");
+    let keyword = interner.intern_static(KEYWORD);
+    let key = interner.intern("key");
+
+    assert_eq!(keyword, interner.intern("keyword"));
+    assert_eq!(existing, interner.intern_static("fn"));
+    assert_eq!(interner.stats().stored_bytes, 2);
+    assert!(ptr::eq(keyword.get_ref().as_ptr(), KEYWORD.as_ptr()));
+
+    drop(existing);
+    interner.collect();
+    assert_eq!(interner.stats().stored_bytes, 0);
+    assert!(ptr::eq(keyword.get_ref().as_ptr(), KEYWORD.as_ptr()));
+    assert_eq!(&*key.get_ref(), "key");
+    assert_eq!(keyword.slice(3..7).unwrap(), interner.intern("word"));
+    assert_eq!(interner.stats().stored_bytes, 0);
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    assert_eq!(&*folded.intern_static("Keyword").get_ref(), "keyword");
+    assert_eq!(folded.stats().stored_bytes, 7);
+  }
+
+  #[test]
+  fn interner_limits_store_size() {
+    let interner = InternerBuilder::new().max_store_bytes(8).build();
+    interner.intern("hello");
+
+    assert!(matches!(
+      interner.try_intern("world"),
+      Err(InternError::StoreFull { limit: 8 })
+    ));
+    assert!(interner.try_intern("ell").is_ok());
+    assert!(interner.try_intern("abc").is_ok());
+    assert_eq!(interner.stats().stored_bytes, 8);
+  }
+
+  #[test]
+  #[should_panic(expected = "limit of 0 bytes")]
+  fn intern_panics_when_store_is_full() {
+    InternerBuilder::new()
+      .max_store_bytes(0)
+      .build()
+      .intern("a");
+  }
+
+  #[test]
+  fn interner_evicts_least_recently_used_strings() {
+    let interner = InternerBuilder::new()
+      .max_store_bytes(15)
+      .on_store_full(StoreFullPolicy::Evict)
+      .build();
+    let kept = interner.intern("kept!");
+    drop(interner.intern("older"));
+    drop(interner.intern("newer"));
+    assert!(interner.get("older").is_some());
+
+    let extra = interner.intern("extra");
+    assert_eq!(interner.get("newer"), None);
+    assert!(interner.get("older").is_some());
+    assert_eq!(&*kept.get_ref(), "kept!");
+    assert_eq!(&*extra.get_ref(), "extra");
+    assert_eq!(interner.stats().stored_bytes, 15);
+
+    let reading = kept.get_ref();
+    assert!(matches!(
+      interner.try_intern("more"),
+      Err(InternError::StoreFull { limit: 15 })
+    ));
+    drop(reading);
+    assert_eq!(interner.intern("more"), "more");
+    assert_eq!(interner.get("older"), None);
+  }
+
+  #[test]
+  fn interner_from_corpus() {
+    let (interner, words) =
+      Interner::from_corpus(["ell", "b", "hello", "ell", "a b"]);
+
+    assert_eq!(words.len(), 5);
+    assert_eq!(words[0], words[3]);
+    assert_eq!(words[2], interner.intern("hello"));
+    assert_eq!(&*words[1].get_ref(), "b");
+    assert_eq!(interner.stats().stored_bytes, 8);
+    assert_eq!(
+      words.iter().map(Intern::id).collect::<Vec<_>>(),
+      [0, 1, 2, 0, 3].map(SymbolId)
+    );
+  }
+
+  #[test]
+  fn interner_iterates_over_strings() {
+    let interner = Interner::new();
+    let _words = interner.intern_all(["hello", "ell", "world", "hello"]);
+    drop(interner.intern("garbage"));
+    interner.collect();
+
+    let strings = interner.iter();
+    assert_eq!(strings.len(), 3);
+    assert_eq!(interner.collect(), 0);
+    let strings = strings.collect::<Vec<_>>();
+    assert_eq!(interner.stats().outstanding_refs, 3);
+    assert_eq!(
+      strings
+        .iter()
+        .map(|(id, s)| (id.0, &**s))
+        .collect::<Vec<_>>(),
+      [(0, "hello"), (1, "ell"), (2, "world")]
+    );
+
+    drop(strings);
+    assert_eq!(interner.stats().outstanding_refs, 0);
+  }
+
+  #[test]
+  fn interner_concatenates_pieces() {
+    let interner = Interner::new();
+    let hello = interner.intern_concat(&["hel", "lo ", "world"]);
+    let stored = interner.stats().stored_bytes;
+    let again =
+      interner.intern_concat(&[String::from("hello"), " world".into()]);
+    let world = interner.intern_concat(&["wor", "", "ld"]);
+
+    assert_eq!(&*hello.get_ref(), "hello world");
+    assert_eq!(hello, again);
+    assert_eq!(hello, interner.intern("hello world"));
+    assert_eq!(&*world.get_ref(), "world");
+    assert_eq!(interner.stats().stored_bytes, stored);
+    assert_eq!(&*interner.intern_concat::<&str>(&[]).get_ref(), "");
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let header = folded.intern_concat(&["Content", "-Type"]);
+    assert_eq!(header, folded.intern("content-type"));
+    assert_eq!(&*header.get_ref(), "content-type");
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn interner_interns_formatted_text() {
+    let interner = Interner::new();
+    let temp = interner.intern_fmt(format_args!("tmp{}", 3));
+    let stored = interner.stats().stored_bytes;
+    let again = crate::intern_format!(interner, "tmp{}", 1 + 2);
+    assert_eq!(interner.stats().stored_bytes, stored);
+    let long = crate::intern_format!(interner, "{:x<5000}", "long");
+
+    assert_eq!(&*temp.get_ref(), "tmp3");
+    assert_eq!(temp, again);
+    assert_eq!(long.len(), 5000);
+    assert!(long.get_ref().starts_with("longxxx"));
+
+    // interning while formatting would clobber the staged text
+    struct Sneaky(Rc<Interner>);
+    impl fmt::Display for Sneaky {
+      fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sneaky ")?;
+        self.0.intern("interloper");
+        f.write_str("formatting")
+      }
+    }
+
+    let sneaky =
+      interner.intern_fmt(format_args!("{}", Sneaky(Rc::clone(&interner))));
+    assert_eq!(&*sneaky.get_ref(), "sneaky formatting");
+    assert_eq!(&*interner.intern("interloper").get_ref(), "interloper");
+  }
+
+  #[test]
+  fn interner_interns_unescaped_literals() {
+    let interner = Interner::new();
+    let line = interner
+      .intern_unescaped(r#"say \"hi\"\n"#, EscapeStyle::Rust)
+      .unwrap();
+    let stored = interner.stats().stored_bytes;
+    let json = interner
+      .intern_unescaped(r#"say \"hi\"\u000a"#, EscapeStyle::Json)
+      .unwrap();
+
+    assert_eq!(&*line.get_ref(), "say \"hi\"\n");
+    assert_eq!(line, json);
+    assert_eq!(interner.stats().stored_bytes, stored);
+
+    let entries = interner.stats().entries;
+    assert_eq!(
+      interner.intern_unescaped(r"tab\ta\z", EscapeStyle::Rust),
+      Err(UnescapeError::UnknownEscape { index: 6 })
+    );
+    assert_eq!(
+      interner.intern_unescaped(r"\ud800", EscapeStyle::Json),
+      Err(UnescapeError::InvalidEscape { index: 0 })
+    );
+    assert_eq!(interner.stats().entries, entries);
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let shout = folded.intern_unescaped(r"\x48I", EscapeStyle::Rust);
+    assert_eq!(shout.unwrap(), folded.intern("hi"));
+  }
+
+  #[test]
+  fn interner_joins_and_splits() {
+    let interner = Interner::new();
+    let path = interner.intern("a::bc::d");
+    let stored = interner.stats().stored_bytes;
+
+    let segments = interner.split_intern(&path, "::");
+    assert_eq!(segments, ["a", "bc", "d"].map(|s| interner.intern(s)));
+    assert_eq!(interner.stats().stored_bytes, stored);
+    assert_eq!(interner.join(&segments, "::"), path);
+    assert_eq!(interner.join(&segments[1..], ""), interner.intern("bcd"));
+    assert_eq!(interner.join(&[], "::"), interner.intern(""));
+
+    let other = Interner::new();
+    let foreign = other.intern("std");
+    let joined = interner.join(&[foreign.clone(), segments[0].clone()], "/");
+    assert_eq!(joined, interner.intern("std/a"));
+    assert_eq!(
+      interner.split_intern(&foreign, "t")[1],
+      interner.intern("d")
+    );
+  }
+
+  #[test]
+  fn interner_interns_sources() {
+    let interner = Interner::new();
+    let text = "let x = 1;\nlet y = x;\n";
+    let lib = interner.intern_source("lib.rs", text);
+    let copy = interner.intern_source("copy.rs", text);
+
+    assert_eq!((lib, copy), (SourceId(0), SourceId(1)));
+    assert_eq!(interner.source(lib), interner.source(copy));
+    assert_eq!(&*interner.source_name(copy).unwrap(), "copy.rs");
+    assert_eq!(interner.source(SourceId(2)), None);
+    assert_eq!(
+      interner.line_column(lib, 19),
+      Some(LineColumn { line: 2, column: 9 })
+    );
+    assert_eq!(interner.line_column(lib, 23), None);
+    let y = interner.source_line(lib, 2).unwrap().slice(4..5).unwrap();
+    assert_eq!(y, interner.intern("y"));
+
+    drop(interner.intern("gone"));
+    interner.collect();
+    assert_eq!(&*interner.source(lib).unwrap().get_ref(), text);
+  }
+
+  #[test]
+  fn interns_slice_without_copying() {
+    let interner = Interner::new();
+    let line = interner.intern("let crab = \"🦀\";");
+    let stored = interner.stats().stored_bytes;
+    let crab = line.slice(4..8).unwrap();
+    let emoji = line.slice(12..16).unwrap();
+
+    assert_eq!(&*crab.get_ref(), "crab");
+    assert_eq!(crab, interner.intern("crab"));
+    assert_eq!(emoji, interner.intern("🦀"));
+    assert_eq!(line.slice(0..0).unwrap(), interner.intern(""));
+    assert_eq!(interner.stats().stored_bytes, stored);
+    assert!(matches!(
+      line.slice(4..40),
+      Err(SliceError::OutOfBounds {
+        start: 4,
+        end: 40,
+        len: 18
+      })
+    ));
+    assert!(matches!(
+      line.slice(13..16),
+      Err(SliceError::NotCharBoundary { index: 13 })
+    ));
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let header = folded.intern("Content-Type");
+    let content = header.slice(0..7).unwrap();
+    assert_eq!(content, folded.intern("content"));
+    assert_eq!(&*content.get_ref(), "Content");
+  }
+
+  #[test]
+  fn interns_map_to_computed_strings() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let name = interner.intern("Point");
+    let held = name.get_ref();
+
+    let getter = name.map(|s| format!("get{s}"));
+    assert_eq!(&*getter.get_ref(), "getPoint");
+    assert_eq!(getter, interner.intern("GETPOINT"));
+    assert_eq!(name.map(str::to_owned), name);
+    assert_eq!(&*held, "Point");
+  }
+
+  #[test]
+  fn interns_answer_string_queries() {
+    let interner = Interner::new();
+    let path = interner.intern("src/main.rs");
+
+    assert!(path.starts_with("src/"));
+    assert!(!path.starts_with("main"));
+    assert!(path.ends_with(".rs"));
+    assert!(!path.ends_with("src"));
+    assert!(path.contains("main"));
+    assert!(!path.contains("lib"));
+    assert_eq!(path.find("/"), Some(3));
+    assert_eq!(path.find("lib"), None);
+    assert_eq!(interner.stats().outstanding_refs, 0);
+  }
+
+  #[cfg(feature = "aho-corasick")]
+  #[test]
+  fn interner_builds_matchers() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let words = interner.intern_all(["Let", "letter", "", "X"]);
+    let matcher = interner.build_matcher();
+    let late = interner.intern("late");
+
+    assert_eq!(matcher.len(), 3);
+    let hits = matcher
+      .find_iter("let x = LETTERS; late")
+      .collect::<Vec<_>>();
+    assert_eq!(
+      hits,
+      [
+        (0..3, words[0].clone()),
+        (4..5, words[3].clone()),
+        (8..14, words[1].clone()),
+      ]
+    );
+    drop((matcher, hits, words, late));
+    interner.collect();
+    assert!(interner.build_matcher().is_empty());
+  }
+
+  #[cfg(feature = "regex")]
+  #[test]
+  fn interner_greps_strings() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .build();
+    let words = interner.intern_all(["getName", "name", "SetName", "getter"]);
+    drop(interner.intern("getGone"));
+    interner.collect();
+
+    let grep = |re| interner.grep(&Regex::new(re).unwrap());
+    assert_eq!(grep("^get"), [words[0].clone(), words[3].clone()]);
+    assert_eq!(grep("Name$"), [words[0].clone(), words[2].clone()]);
+    assert_eq!(grep("^name$"), [words[1].clone()]);
+    // strings aren't run together
+    assert!(grep("megetter").is_empty());
+    assert!(grep("Gone").is_empty());
+  }
+
+  #[test]
+  fn interner_finds_strings_with_prefix() {
+    for backend in [IndexBackend::Trie, IndexBackend::Hash] {
+      let interner = InternerBuilder::new()
+        .index(backend)
+        .case_folding(CaseFolding::Ascii)
+        .build();
+      let _words = interner.intern_all(["println", "print", "parse", "printf"]);
+
+      let found = interner.find_with_prefix("PRINT");
+      assert_eq!(
+        found.iter().map(Intern::resolve).collect::<Vec<_>>(),
+        ["print", "printf", "println"]
+      );
+      assert_eq!(interner.find_with_prefix("p").len(), 4);
+      assert_eq!(interner.find_with_prefix("").len(), 4);
+      assert!(interner.find_with_prefix("printer").is_empty());
+    }
+  }
+
+  #[test]
+  fn interner_finds_strings_within_edit_distance() {
+    for backend in [IndexBackend::Trie, IndexBackend::Hash] {
+      let interner = InternerBuilder::new()
+        .index(backend)
+        .case_folding(CaseFolding::Ascii)
+        .build();
+      let _words = interner.intern_all(["length", "lengths", "lent", "height"]);
+
+      let found = interner.fuzzy_search("LENGHT", 2);
+      assert_eq!(
+        found
+          .iter()
+          .map(|(intern, dist)| (intern.resolve(), *dist))
+          .collect::<Vec<_>>(),
+        [
+          ("height".to_string(), 2),
+          ("length".to_string(), 2),
+          ("lengths".to_string(), 2),
+          ("lent".to_string(), 2)
+        ]
+      );
+      let found = interner.fuzzy_search("length", 1);
+      assert_eq!(found.len(), 2);
+      assert_eq!(found[0], (interner.intern("length"), 0));
+      assert_eq!(found[1], (interner.intern("lengths"), 1));
+      assert!(interner.fuzzy_search("width", 1).is_empty());
+    }
+  }
+
+  #[test]
+  fn interner_iterates_in_sorted_order() {
+    for backend in [IndexBackend::Trie, IndexBackend::Hash] {
+      let interner = InternerBuilder::new()
+        .index(backend)
+        .case_folding(CaseFolding::Ascii)
+        .build();
+      let _words = interner.intern_all(["pear", "apple", "fig", "peach", "a"]);
+
+      let resolved = |interns: vec::IntoIter<Intern>| {
+        interns.map(|intern| intern.resolve()).collect::<Vec<_>>()
+      };
+      assert_eq!(
+        resolved(interner.iter_sorted()),
+        ["a", "apple", "fig", "peach", "pear"]
+      );
+      assert_eq!(resolved(interner.range("b".."PEAR")), ["fig", "peach"]);
+      assert_eq!(
+        resolved(interner.range("b"..="PEAR")),
+        ["fig", "peach", "pear"]
+      );
+      assert_eq!(resolved(interner.range(.."apple")), ["a"]);
+      assert_eq!(resolved(interner.range("peaches"..)), ["pear"]);
+      assert!(resolved(interner.range("q"..)).is_empty());
+    }
+  }
+
+  #[test]
+  fn interner_finds_longest_prefix() {
+    for backend in [IndexBackend::Trie, IndexBackend::Hash] {
+      let interner = InternerBuilder::new().index(backend).build();
+      let _ops = interner.intern_all(["=", "==", "====", "=>"]);
+
+      let (op, len) = interner.longest_prefix("=== x").unwrap();
+      assert_eq!(&*op.get_ref(), "==");
+      assert_eq!(len, 2);
+      assert_eq!(interner.longest_prefix("=>").unwrap().1, 2);
+      assert_eq!(interner.longest_prefix("=").unwrap().1, 1);
+      assert_eq!(interner.longest_prefix("x"), None);
+    }
+
+    let folded = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let _keywords = folded.intern_all(["let", "letter"]);
+    let (keyword, len) = folded.longest_prefix("LET x").unwrap();
+    assert_eq!(keyword, folded.intern("let"));
+    assert_eq!(len, 3);
+  }
+
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn interner_with_capacity() {
+    let interner = Interner::with_capacity(100_000, 1000);
+    let long = "x".repeat(50_000);
+
+    assert_eq!(&*interner.intern(&long).get_ref(), long);
+  }
+
+  #[test]
+  fn interner_allows_interning_while_holding_refs() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let hello_ref = hello.get_ref();
+    let world = interner.intern("world");
+
+    assert_eq!(&*hello_ref, "hello");
+    assert_eq!(&*world.get_ref(), "world");
+  }
+
+  #[test]
+  fn interner_collects_unused_strings() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let ell = interner.intern("ell");
+    let world = interner.intern("world");
+    drop(interner.intern("goodbye"));
+
+    assert_eq!(interner.collect(), 7);
+    assert_eq!(&*world.get_ref(), "world");
+    assert_eq!(interner.intern("world"), world);
+
+    let ell_ref = ell.get_ref();
+    drop(hello);
+    assert_eq!(interner.collect(), 0);
+
+    drop(ell_ref);
+    assert_eq!(interner.collect(), 2);
+    assert_eq!(&*ell.get_ref(), "ell");
+    assert_eq!(interner.stats().stored_bytes, 8);
+  }
+
+  #[test]
+  fn interner_defragments_without_freeing() {
+    let interner = Interner::new();
+    let cat = interner.intern("cat");
+    let symbol = interner.intern_symbol("nate");
+    let word = interner.intern("concatenate");
+    drop(interner.intern("dog"));
+    assert_eq!(interner.stats().stored_bytes, 21);
+
+    let cat_ref = cat.get_ref();
+    assert_eq!(interner.defragment(), 0);
+    drop(cat_ref);
+
+    assert_eq!(interner.defragment(), 7);
+    assert_eq!(interner.stats().stored_bytes, 14);
+    assert_eq!(interner.stats().entries, 4);
+    assert_eq!(&*cat.get_ref(), "cat");
+    assert_eq!(&*interner.resolve(symbol), "nate");
+    assert_eq!(interner.intern("concatenate"), word);
+    assert_eq!(interner.defragment(), 0);
+  }
+
+  #[test]
+  fn interner_shrinks_to_fit() {
+    let interner = Interner::with_capacity(1 << 16, 100);
+    let hello = interner.intern("hello");
+    let hello_ref = hello.get_ref();
+    let allocated = interner.stats().allocated_bytes;
+
+    assert_eq!(interner.shrink_to_fit(), 0);
+
+    drop(hello_ref);
+    assert_eq!(interner.shrink_to_fit(), allocated - 5);
+    assert_eq!(&*hello.get_ref(), "hello");
+    assert_eq!(&*interner.intern("world").get_ref(), "world");
+  }
+
+  #[test]
+  fn interner_freezes() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    interner.intern("ell");
+
+    let frozen = interner.freeze();
+    let frozen_hello = frozen.get("hello").unwrap();
+
+    assert_eq!(frozen.len(), 2);
+    assert_eq!(frozen.resolve(frozen_hello), "hello");
+    assert_eq!(frozen.get("hell"), None);
+    assert_eq!(&*hello.get_ref(), "hello");
+  }
+
+  #[test]
+  fn interner_saves_and_loads() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let world = interner.intern("world");
+    interner.intern("ell");
+
+    let mut saved = Vec::new();
+    interner.save_to(&mut saved).unwrap();
+    let reloaded = Interner::load_from(&saved[..]).unwrap();
+    let reloaded_hello = reloaded.get("hello").unwrap();
+    let reloaded_world = reloaded.get("world").unwrap();
+
+    assert_eq!(reloaded.stats().stored_bytes, 10);
+    assert_eq!(&*reloaded.get("ell").unwrap().get_ref(), "ell");
+    assert_eq!(hello.cmp(&world), reloaded_hello.cmp(&reloaded_world));
+    assert!(Interner::load_from(&saved[1..]).is_err());
+  }
+
+  #[test]
+  fn interner_looks_up_symbol_ids() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    drop(interner.intern("goodbye"));
+    let ell = interner.intern("ell");
+
+    assert_eq!((hello.id(), ell.id()), (SymbolId(0), SymbolId(2)));
+    assert_eq!(interner.from_id(SymbolId(1)).unwrap().resolve(), "goodbye");
+
+    interner.collect();
+    assert_eq!(interner.from_id(SymbolId(0)), Some(hello.clone()));
+    assert_eq!(interner.from_id(SymbolId(1)), None);
+    assert_eq!(interner.from_id(SymbolId(2)), Some(ell));
+    assert_eq!(interner.intern("goodbye").id(), SymbolId(3));
+
+    let mut saved = Vec::new();
+    interner.save_to(&mut saved).unwrap();
+    let reloaded = Interner::load_from(&saved[..]).unwrap();
+    assert_eq!(reloaded.from_id(SymbolId(0)).unwrap().resolve(), "hello");
+    assert_eq!(reloaded.from_id(SymbolId(1)), None);
+    assert_eq!(reloaded.intern("new").id(), SymbolId(4));
+  }
+
+  #[test]
+  fn interner_resolves_symbols() {
+    let interner = Interner::new();
+    let hello = interner.intern_symbol("hello");
+    let world = interner.intern("world");
+    let world_symbol = world.symbol();
+    drop(world);
+
+    assert_eq!(interner.intern_symbol("hello"), hello);
+    assert_eq!(interner.intern("hello").symbol(), hello);
+    assert_eq!(world_symbol.id(), SymbolId(1));
+
+    // symbols keep their strings from being collected
+    assert_eq!(interner.collect(), 0);
+    assert_eq!(&*interner.resolve(hello), "hello");
+    assert_eq!(&*interner.resolve(world_symbol), "world");
+    assert_eq!(interner.from_id(hello.id()), interner.get("hello"));
+  }
+
+  #[test]
+  #[should_panic = "symbol does not belong to this interner"]
+  fn interner_rejects_foreign_symbols() {
+    let interner = Interner::new();
+    let symbol = Interner::new().intern_symbol("hello");
+
+    interner.resolve(symbol);
+  }
+
+  #[test]
+  fn weak_interns_do_not_keep_strings_alive() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let weak = hello.downgrade();
+
+    assert_eq!(weak.upgrade(), Some(hello.clone()));
+
+    drop(hello);
+    assert_eq!(interner.collect(), 5);
+    assert_eq!(weak.upgrade(), None);
+
+    let world = interner.intern("world").downgrade();
+    drop(interner);
+    assert_eq!(world.upgrade(), None);
+  }
+
+  #[test]
+  fn interner_adopts_interns_from_other_interners() {
+    let first = Interner::new();
+    let second = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let hello = first.intern("Hello");
+    let adopted = second.adopt(&hello);
+
+    assert_eq!(adopted, second.intern("HELLO"));
+    assert_eq!(second.adopt(&adopted), adopted);
+    assert_eq!(first.adopt(&hello), hello);
+    assert_eq!(first.adopt(&adopted), first.intern("hello"));
+    assert_eq!(second.stats().entries, 1);
+  }
+
+  #[test]
+  fn child_interner_falls_back_to_parent() {
+    let parent = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let fn_keyword = parent.intern("fn");
+    let let_keyword = parent.intern("let");
+
+    let child = Interner::new_child(&parent);
+    let main = child.intern("main");
+    let late = child.intern("late");
+    parent.intern("late");
+
+    assert!(Rc::ptr_eq(child.parent().unwrap(), &parent));
+    assert_eq!(child.intern("FN"), fn_keyword);
+    assert_eq!(child.get("let"), Some(let_keyword.clone()));
+    assert_eq!(child.intern_static("fn"), fn_keyword);
+    assert_eq!(
+      child.intern_all(["LET", "main"]),
+      [let_keyword, main.clone()]
+    );
+    assert_eq!(child.intern("late"), late);
+    assert_eq!(parent.get("main"), None);
+
+    let grandchild = Interner::new_child(&child);
+    assert_eq!(grandchild.intern("fn"), fn_keyword);
+    assert_eq!(grandchild.get("Main"), Some(main));
+    assert_eq!(grandchild.stats().entries, 0);
+
+    assert_eq!(child.stats().entries, 2);
+    let stored = parent.stats().stored_bytes;
+    drop((child, grandchild, late));
+    assert_eq!(parent.stats().stored_bytes, stored);
+    assert_eq!(parent.collect(), 7);
+  }
+
+  crate::keywords! {
+    const KEYWORDS;
+    const KW_FN: SymbolId = "fn";
+    const KW_LET: SymbolId = "let";
+    const KW_MATCH: SymbolId = "match";
+  }
+
+  #[test]
+  fn keywords_get_dense_ids() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .with_keywords(KEYWORDS)
+      .build();
+
+    assert_eq!(interner.stats().entries, 3);
+    assert_eq!(interner.collect(), 0);
+    assert_eq!(interner.intern("Match").id(), KW_MATCH);
+    assert_eq!(interner.intern_symbol("fn").id(), KW_FN);
+    assert_eq!(interner.intern("main").id(), SymbolId(3));
+    let keyword =
+      |s| matches!(interner.intern(s).id(), KW_FN | KW_LET | KW_MATCH);
+    assert!(keyword("LET") && !keyword("main"));
+  }
+
+  #[test]
+  #[should_panic = "keyword \"FN\" appears twice"]
+  fn keywords_must_be_distinct() {
+    InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .with_keywords(&["fn", "let", "FN"])
+      .build();
+  }
+
+  mod symbols {
+    crate::symbols! {
+      unsync;
+      Kw { Fn: "fn", Let: "let", Match: "match" },
+      Sym { Fn: "Fn", Len: "len" },
+      Empty {},
+    }
+  }
+
+  #[test]
+  fn symbols_are_grouped_in_order() {
+    use symbols::{Empty, Kw, Sym};
+
+    let interner = symbols::interner();
+    let kind = |s| match interner.intern(s).id() {
+      Kw::Fn | Kw::Let => "item",
+      id if Kw::contains(id) => "keyword",
+      id if Sym::contains(id) => "symbol",
+      _ => "identifier",
+    };
+
+    assert_eq!(symbols::SYMBOLS, ["fn", "let", "match", "Fn", "len"]);
+    assert_eq!(Sym::ALL, ["Fn", "len"]);
+    assert_eq!((Kw::Match, Sym::Fn), (SymbolId(2), SymbolId(3)));
+    assert_eq!(interner.intern_symbol("len").id(), Sym::Len);
+    assert_eq!(
+      ["let", "match", "Fn", "main"].map(kind),
+      ["item", "keyword", "symbol", "identifier"]
+    );
+    assert!(Empty::ALL.is_empty() && !Empty::contains(Sym::Len));
+  }
+
+  #[test]
+  fn interns_format_with_flags() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+
+    assert_eq!(format!("[{hello:>7}]"), "[  hello]");
+    assert_eq!(format!("[{hello:-<7.4}]"), "[hell---]");
+    assert_eq!(format!("[{:^9}]", hello.get_ref()), "[  hello  ]");
+    assert_eq!(format!("[{:>7}]", hello.clone().into_str()), "[  hello]");
+    assert_eq!(format!("[{:>7}]", hello.clone().detach()), "[  hello]");
+    assert_eq!(format!("[{:>3}]", interner.intern_compact("x")), "[  x]");
+
+    let display = hello.display();
+    assert_eq!(format!("{display}"), "hello");
+    assert_eq!(format!("{display:?}"), format!("{:?}", "hello"));
+    assert_eq!(format!("{display:#?}"), format!("{:#?}", "hello"));
+    assert_eq!(format!("[{display:>3.2}]"), "[ he]");
+    assert_eq!(format!("{:?}", [display, display]), r#"["hello", "hello"]"#);
+  }
+
+  #[test]
+  fn interns_convert_to_std_strings() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let (owned, cow) = (String::from("hello"), Cow::Borrowed("hello"));
+    let (other, other_cow) = (String::from("hell"), Cow::from("hello!"));
+
+    assert_eq!(hello, owned);
+    assert_eq!(owned, hello);
+    assert_eq!(hello, cow);
+    assert_eq!(cow, hello);
+    assert_ne!(hello, other);
+    assert_ne!(other_cow, hello);
+    assert_eq!(String::from(&hello), "hello");
+    assert_eq!(String::from(hello.clone()), "hello");
+
+    let hello = hello.into_str();
+    assert!(matches!(Cow::from(&hello), Cow::Borrowed("hello")));
+  }
+
+  #[test]
+  fn interner_interns_lines_from_readers() {
+    let interner = Interner::new();
+    let input = b"GET /\r\n\nGET /\n\xff\nGET /about";
+
+    let mut lines = interner.intern_lines(&input[..]);
+    let get = lines.next().unwrap().unwrap();
+    assert_eq!(get, "GET /");
+    assert_eq!(lines.next().unwrap().unwrap(), "");
+    assert_eq!(lines.next().unwrap().unwrap(), get);
+    let invalid = lines.next().unwrap().unwrap_err();
+    assert_eq!(invalid.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(lines.next().unwrap().unwrap(), "GET /about");
+    assert!(lines.next().is_none());
+    assert_eq!(interner.stats().entries, 3);
+
+    let fields = interner
+      .intern_split(&b"a\r,b,a,"[..], b',')
+      .collect::<io::Result<Vec<_>>>()
+      .unwrap();
+    assert_eq!(fields, interner.intern_all(["a\r", "b", "a"]));
+  }
+
+  #[cfg(feature = "csv")]
+  #[test]
+  fn interner_interns_csv_columns() {
+    let interner = Interner::new();
+    let tsv = "id\tname\n1\tada\n2\t\"grace\thopper\"\n3\tada\textra\n";
+
+    let names = interner.intern_column(tsv.as_bytes(), 1, b'\t').unwrap();
+    assert_eq!(names.len(), 4);
+    assert_eq!(names[1], names[3]);
+    assert_eq!(names[2], "grace\thopper");
+    assert_eq!(interner.stats().entries, 3);
+
+    let error = interner
+      .intern_column(tsv.as_bytes(), 2, b'\t')
+      .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(error.to_string(), "row 0 has no column 2");
+    let invalid = interner.intern_column(&b"a,\xff"[..], 1, b',');
+    assert_eq!(invalid.unwrap_err().kind(), io::ErrorKind::InvalidData);
+  }
+
+  #[test]
+  fn interner_counts_interns() {
+    let interner = InternerBuilder::new()
+      .count_interns(true)
+      .with_keywords(&["fn"])
+      .build();
+    let words = interner.intern_all("b a b c b a".split(' '));
+    let c = interner.intern_symbol("c");
+    interner.get("a");
+    interner.intern_static("fn");
+
+    let counts = interner
+      .counts()
+      .into_iter()
+      .map(|(intern, count)| (intern.resolve(), count))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      counts,
+      [
+        ("b".into(), 3),
+        ("a".into(), 2),
+        ("c".into(), 2),
+        ("fn".into(), 1)
+      ]
+    );
+    assert_eq!(c, words[3].symbol());
+    assert!(Interner::new().counts().is_empty());
+  }
+
+  #[test]
+  fn dictionaries_warm_start_interners() {
+    let learned = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .count_interns(true)
+      .build();
+    learned.intern_all("If it is to be it is up to me".split(' '));
+
+    let dictionary = learned.export_dictionary(3);
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .with_keywords(&["IS"])
+      .with_dictionary(&dictionary)
+      .unwrap()
+      .build();
+    assert_eq!(interner.stats().entries, 3);
+    assert_eq!(interner.intern("it").id(), SymbolId(1));
+    assert_eq!(interner.intern("to").id(), SymbolId(2));
+    assert_eq!(interner.collect(), 0);
+    assert!(interner.counts().is_empty());
+
+    let sync = crate::sync::InternerBuilder::new()
+      .with_dictionary(&Interner::new().export_dictionary(1))
+      .unwrap()
+      .build();
+    assert_eq!(sync.stats().entries, 0);
+    let unranked = Interner::new();
+    unranked.intern_all(["b", "a", "a"]);
+    let dictionary = unranked.export_dictionary(usize::MAX);
+    let interner = InternerBuilder::new()
+      .with_dictionary(&dictionary)
+      .unwrap()
+      .build();
+    assert_eq!(interner.from_id(SymbolId(0)).unwrap(), "b");
+    assert!(InternerBuilder::new().with_dictionary(b"STRNDICT").is_err());
+  }
+  #[test]
+  fn interns_refuse_refs_past_limit() {
+    let interner = InternerBuilder::new().max_outstanding_refs(2).build();
+    let hello = interner.intern("hello");
+    let world = InternStr::from(interner.intern("world"));
+
+    let hello_ref = hello.try_get_ref().unwrap();
+    assert_eq!(&*hello_ref, "hello");
+    assert_eq!(
+      hello.try_get_ref().unwrap_err(),
+      RefError::TooManyRefs { limit: 2 }
+    );
+    // only try_get_ref is limited
+    assert_eq!(&*hello.get_ref(), "hello");
+
+    let world = world.downgrade();
+    assert_eq!(interner.stats().outstanding_refs, 1);
+    assert_eq!(&*world.try_get_ref().unwrap(), "world");
+    drop(hello_ref);
+    assert_eq!(interner.stats().outstanding_refs, 0);
+    assert!(Interner::new().intern("unlimited").try_get_ref().is_ok());
+  }
+
+  #[test]
+  fn interner_deep_clones() {
+    static STATIC: &str = "static";
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .preserve_case(true)
+      .count_interns(true)
+      .build();
+    drop(interner.intern("gone"));
+    let header = interner.intern("Content-Type");
+    let fn_symbol = interner.intern_symbol("fn");
+    let fixed = interner.intern_static(STATIC);
+    interner.intern("content-type");
+    interner.collect();
+
+    let (copy, map) = interner.deep_clone();
+    interner.intern("original");
+    copy.intern("copy");
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.id(SymbolId(0)), None);
+    assert_eq!(map.id(header.id()), Some(header.id()));
+    let copied = map.intern(header.id()).unwrap();
+    assert_eq!(&*copied.get_ref(), "Content-Type");
+    assert_eq!(copy.intern("CONTENT-TYPE"), *copied);
+    assert_eq!(&*copy.resolve(fn_symbol), "fn");
+    assert_eq!(copy.get("original"), None);
+    assert_eq!(interner.get("copy"), None);
+    assert_eq!(copy.counts()[0], (copied.clone(), 3));
+    assert_eq!(interner.counts()[0], (header.clone(), 2));
+    let text = map.intern(fixed.id()).unwrap().get_ref().as_ptr();
+    assert!(ptr::eq(text, STATIC.as_ptr()));
+
+    drop(map);
+    copy.collect();
+    assert_eq!(&*copy.resolve(fn_symbol), "fn");
+    assert_eq!(copy.get("static"), None);
+    assert_eq!(interner.get("static"), Some(fixed));
+  }
+
+  #[test]
+  fn detached_interns_outlive_interner() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let detached = hello.clone().detach();
+
+    assert_eq!(detached, "hello");
+    assert_eq!(detached, hello.clone().detach());
+
+    drop(hello);
+    assert_eq!(interner.collect(), 5);
+    drop(interner);
+
+    let sent = std::thread::spawn(move || detached).join().unwrap();
+    assert_eq!(sent.as_str(), "hello");
+    assert_eq!(Interner::new().intern(&sent).resolve(), "hello");
+  }
+
+  #[test]
+  fn interner_handles_multibyte_text() {
+    let interner = Interner::new();
+    let crab = interner.intern("crabs 🦀 ahoy");
+    let emoji = interner.intern("🦀");
+    let ahoy = interner.intern("ahoy");
+
+    assert_eq!(&*crab.get_ref(), "crabs 🦀 ahoy");
+    assert_eq!(&*emoji.get_ref(), "🦀");
+    assert_eq!(&*ahoy.get_ref(), "ahoy");
+  }
+
+  #[test]
+  fn interns_run_closures_on_their_text() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    drop(interner.intern("garbage"));
+
+    assert_eq!(hello.with_str(str::len), 5);
+    assert!(hello.with_str(|s| s.starts_with("he")));
+    // nothing is left reading, so the garbage can be collected
+    assert_eq!(interner.collect(), 7);
+  }
+
+  #[test]
+  fn interner_reads_many_strings_in_one_scope() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+    let world = interner.intern("world");
+    drop(interner.intern("garbage"));
+
+    let (greeting, collected) = interner.scope(|scope| {
+      let greeting = format!("{} {}", scope.get(&hello), scope.get(&world));
+      (greeting, interner.collect())
+    });
+
+    assert_eq!(greeting, "hello world");
+    assert_eq!(collected, 0);
+    assert_eq!(interner.collect(), 7);
+
+    let words = [&hello, &world, &hello].map(Intern::clone);
+    let joined = interner.resolve_many(&words, |strs| strs.join(" "));
+    assert_eq!(joined, "hello world hello");
+  }
+
+  #[test]
+  fn interner_comes_apart_and_back_together() {
+    let interner = Interner::new();
+    for word in ["hello", "ell", "world", "hello"] {
+      interner.intern(word);
+    }
+
+    let (text, spans) = interner.into_parts();
+    let frozen = FrozenInterner::from_parts(&text, &spans).unwrap();
+
+    assert_eq!(text, "helloworld");
+    assert_eq!(
+      spans,
+      [
+        (SymbolId(0), 0..5),
+        (SymbolId(1), 1..4),
+        (SymbolId(2), 5..10)
+      ]
+    );
+    assert_eq!(frozen.len(), 3);
+    assert_eq!(frozen.resolve(frozen.get("ell").unwrap()), "ell");
+    for (id, span) in &spans {
+      assert_eq!(frozen.resolve_id(*id), Some(&text[span.clone()]));
+      assert_eq!(frozen.id_of(&text[span.clone()]), Some(*id));
+    }
+    assert!(FrozenInterner::from_parts("🦀", &[(SymbolId(0), 0..1)]).is_err());
+    assert!(matches!(
+      FrozenInterner::from_parts(
+        &text,
+        &[(SymbolId(0), 0..5), (SymbolId(0), 5..10)]
+      ),
+      Err(PartsError::DuplicateId { id: SymbolId(0) })
+    ));
+  }
+
+  #[test]
+  fn interner_can_be_owned_directly() {
+    struct Parser {
+      interner: Interner,
+    }
+
+    let parser = Parser {
+      interner: Interner::default(),
+    };
+    let hello = parser.interner.intern_borrowed("hello");
+    let world = parser.interner.intern_borrowed("world");
+    drop(parser.interner.intern_borrowed("garbage"));
+
+    assert_eq!(hello, parser.interner.intern_borrowed("hello"));
+    assert_ne!(hello, world);
+    assert_eq!(&*hello.get_ref(), "hello");
+    assert_eq!(world.to_string(), "world");
+    assert_eq!(hello.id(), SymbolId(0));
+    assert_eq!(parser.interner.collect(), 7);
+    assert_eq!(world.with_str(str::len), 5);
+    assert!(ptr::eq(hello.interner(), &parser.interner));
+
+    assert_eq!(hello, "hello");
+    assert_eq!("world", world);
+    assert_eq!(hello.resolve(), "hello");
+    assert_eq!(String::from(world.clone()), "world");
+    assert_eq!(&*parser.interner.resolve(hello.symbol()), "hello");
+    assert_eq!(world.clone().detach(), "world");
+    assert_eq!(world.char_len(), 5);
+  }
+
+  #[test]
+  fn interns_compare_text_across_interners() {
+    let interner = Interner::new();
+    let other = Interner::new();
+    let hello = interner.intern("hello");
+
+    assert!(hello.text_eq(&interner.intern("hello")));
+    assert!(!hello.text_eq(&interner.intern("world")));
+    assert_ne!(hello, other.intern("hello"));
+    assert!(hello.text_eq(&other.intern("hello")));
+    assert!(!hello.text_eq(&other.intern("jello")));
+    assert!(!hello.text_eq(&other.intern("hell")));
+  }
+
+  #[test]
+  fn interns_measure_common_affixes() {
+    let interner = Interner::new();
+    let other = Interner::new();
+    let old = interner.intern("let crab = 🦀;");
+    let new = interner.intern("let crab = 🦞;");
+
+    assert_eq!(old.common_prefix_len(&new), 11);
+    assert_eq!(old.common_suffix_len(&new), 1);
+    assert_eq!(old.common_prefix_len(&old), old.len());
+    assert_eq!(old.common_suffix_len(&other.intern("🦀;")), 5);
+    assert_eq!(old.common_prefix_len(&interner.intern("")), 0);
+    assert_eq!(old.common_suffix_len(&interner.intern("x")), 0);
+  }
+
+  #[test]
+  fn intern_strs_borrow_as_strs() {
+    let interner = Interner::new();
+    let mut counts = HashMap::new();
+    for word in ["a", "b", "a"] {
+      *counts.entry(interner.intern(word).into_str()).or_insert(0) += 1;
+    }
+    let hello = interner.intern("hello").into_str();
+    drop(interner.intern("garbage"));
+
+    assert_eq!(counts.get("a"), Some(&2));
+    assert_eq!(counts.get("b"), Some(&1));
+    assert_eq!(&*hello, "hello");
+    assert_eq!(hello.as_ref(), "hello");
+    assert_eq!(hello.intern(), &interner.intern("hello"));
+    assert_eq!(hello, Interner::new().intern("hello").into_str());
+    assert_eq!(interner.collect(), 0);
+
+    // nothing refers to any of the strings any more
+    drop((counts, hello.clone(), hello));
+    assert_eq!(interner.collect(), 14);
+  }
+
+  #[test]
+  fn interns_compare_with_strs() {
+    let interner = Interner::new();
+    let hello = interner.intern("hello");
+
+    assert!(hello == "hello");
+    assert!(hello == *"hello");
+    assert!("hello" == hello);
+    assert!(hello != "hell");
+    assert!(hello != "jello");
+    assert!(hello < "help");
+    assert!(hello > "hell");
+    assert!("help" > hello);
+    assert_eq!(hello.partial_cmp("hello"), Some(Ordering::Equal));
+  }
+
+  #[test]
+  fn interns_know_their_length() {
+    let interner = Interner::new();
+    let crab = interner.intern("crabs 🦀");
+    let emoji = interner.intern("🦀");
+    let empty = interner.intern("");
+
+    assert_eq!((crab.len(), crab.char_len()), (10, 7));
+    assert_eq!((emoji.len(), emoji.char_len()), (4, 1));
+    assert!(!crab.is_empty());
+    assert!(empty.is_empty());
+    assert_eq!(empty.char_len(), 0);
+
+    // lengths survive strings moving around
+    drop(interner.intern("garbage"));
+    interner.collect();
+    assert_eq!((emoji.len(), emoji.char_len()), (4, 1));
   }
 }
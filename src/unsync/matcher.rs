@@ -0,0 +1,56 @@
+use crate::unsync::Intern;
+use aho_corasick::{AhoCorasick, MatchKind};
+use std::{fmt, ops::Range};
+
+// finds interned strings in other text, longest first where they start at
+// the same place; holds the strings interned when it was built
+pub struct InternMatcher {
+  automaton: AhoCorasick,
+  // indexed by pattern id
+  interns: Vec<Intern>,
+}
+
+impl InternMatcher {
+  pub(crate) fn new<'a>(
+    patterns: impl IntoIterator<Item = (&'a str, Intern)>,
+    ascii_case_insensitive: bool,
+  ) -> Self {
+    let (patterns, interns): (Vec<_>, Vec<_>) = patterns
+      .into_iter()
+      .filter(|(pattern, _)| !pattern.is_empty())
+      .unzip();
+    let automaton = AhoCorasick::builder()
+      .match_kind(MatchKind::LeftmostLongest)
+      .ascii_case_insensitive(ascii_case_insensitive)
+      .build(patterns)
+      .expect("too many strings to build a matcher");
+
+    Self { automaton, interns }
+  }
+
+  pub fn find_iter<'a>(
+    &'a self,
+    haystack: &'a str,
+  ) -> impl Iterator<Item = (Range<usize>, Intern)> + 'a {
+    self
+      .automaton
+      .find_iter(haystack)
+      .map(|hit| (hit.range(), self.interns[hit.pattern().as_usize()].clone()))
+  }
+
+  pub fn len(&self) -> usize {
+    self.interns.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.interns.is_empty()
+  }
+}
+
+impl fmt::Debug for InternMatcher {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("InternMatcher")
+      .field("patterns", &self.interns.len())
+      .finish_non_exhaustive()
+  }
+}
@@ -0,0 +1,100 @@
+use crate::unsync::persistence::Layout;
+use memmap2::Mmap;
+use std::{cmp::Ordering, fmt, fs::File, io, path::Path};
+
+pub struct MmapInterner {
+  map: Mmap,
+  layout: Layout,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MmapIntern(usize);
+
+impl MmapInterner {
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    let file = File::open(path)?;
+    // SAFETY: the file mustn't change while mapped, and its contents are
+    //         checked before anything reads them
+    let map = unsafe { Mmap::map(&file)? };
+    let layout = Layout::check(&map)?;
+
+    Ok(Self { map, layout })
+  }
+  pub fn len(&self) -> usize {
+    self.layout.len
+  }
+  pub fn is_empty(&self) -> bool {
+    self.layout.len == 0
+  }
+  pub fn get<S: AsRef<str>>(&self, s: S) -> Option<MmapIntern> {
+    let layout = &self.layout;
+    let s = layout
+      .normalization
+      .canonical(layout.case_folding, s.as_ref());
+
+    let (mut low, mut high) = (0, self.layout.len);
+    while low < high {
+      let middle = low + (high - low) / 2;
+      let entry = self.layout.sorted(&self.map, middle);
+      match self.resolve(MmapIntern(entry)).cmp(&s) {
+        Ordering::Less => low = middle + 1,
+        Ordering::Greater => high = middle,
+        Ordering::Equal => return Some(MmapIntern(entry)),
+      }
+    }
+
+    None
+  }
+  pub fn resolve(&self, intern: MmapIntern) -> &str {
+    assert!(intern.0 < self.layout.len, "intern is out of range");
+    let (_, span) = self.layout.entry(&self.map, intern.0);
+
+    // SAFETY: the map was checked when it was opened, which makes sure every
+    //         entry's span is on char boundaries
+    unsafe { self.layout.text(&self.map).get_unchecked(span) }
+  }
+}
+
+impl fmt::Debug for MmapInterner {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("MmapInterner")
+      .field("len", &self.layout.len)
+      .finish_non_exhaustive()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::unsync::{CaseFolding, InternerBuilder};
+  use std::{env, fs, process};
+
+  #[test]
+  fn mmap_interner() {
+    let path =
+      env::temp_dir().join(format!("unsync_mmap_{}.interner", process::id()));
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    for word in ["hello", "ell", "world", "crabs 🦀"] {
+      interner.intern(word);
+    }
+    interner.save_to(fs::File::create(&path).unwrap()).unwrap();
+
+    let garbage_path = path.with_extension("garbage");
+    fs::write(&garbage_path, b"garbage").unwrap();
+
+    let mapped = MmapInterner::open(&path).unwrap();
+    let garbage = MmapInterner::open(&garbage_path);
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&garbage_path).unwrap();
+
+    assert_eq!(mapped.len(), 4);
+    for word in ["hello", "ell", "world", "crabs 🦀"] {
+      assert_eq!(mapped.resolve(mapped.get(word).unwrap()), word);
+    }
+    assert_eq!(mapped.get("HELLO"), mapped.get("hello"));
+    assert_eq!(mapped.get("hell"), None);
+    assert_eq!(garbage.unwrap_err().kind(), io::ErrorKind::InvalidData);
+  }
+}
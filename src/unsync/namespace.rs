@@ -0,0 +1,168 @@
+use crate::unsync::{Intern, InternRef, Interner, Symbol};
+use std::{
+  any,
+  cmp::Ordering,
+  fmt,
+  hash::{Hash, Hasher},
+  marker::PhantomData,
+  rc::Rc,
+};
+
+// tags strings with the marker type N, so namespaces share a store but
+// their interns can't be compared with each other
+pub struct NamespacedInterner<N> {
+  interner: Rc<Interner>,
+  namespace: PhantomData<fn() -> N>,
+}
+
+impl<N> NamespacedInterner<N> {
+  pub fn new(interner: &Rc<Interner>) -> Self {
+    Self {
+      interner: Rc::clone(interner),
+      namespace: PhantomData,
+    }
+  }
+
+  pub fn intern<S: AsRef<str>>(&self, s: S) -> TaggedIntern<N> {
+    TaggedIntern::new(self.interner.intern(s))
+  }
+
+  pub fn get<S: AsRef<str>>(&self, s: S) -> Option<TaggedIntern<N>> {
+    self.interner.get(s).map(TaggedIntern::new)
+  }
+
+  pub fn tag(&self, intern: &Intern) -> TaggedIntern<N> {
+    TaggedIntern::new(self.interner.adopt(intern))
+  }
+
+  pub fn interner(&self) -> &Rc<Interner> {
+    &self.interner
+  }
+}
+
+impl<N> Clone for NamespacedInterner<N> {
+  fn clone(&self) -> Self {
+    Self::new(&self.interner)
+  }
+}
+
+impl<N> fmt::Debug for NamespacedInterner<N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("NamespacedInterner")
+      .field("namespace", &any::type_name::<N>())
+      .finish_non_exhaustive()
+  }
+}
+
+pub struct TaggedIntern<N> {
+  intern: Intern,
+  namespace: PhantomData<fn() -> N>,
+}
+
+impl<N> TaggedIntern<N> {
+  fn new(intern: Intern) -> Self {
+    Self {
+      intern,
+      namespace: PhantomData,
+    }
+  }
+
+  pub fn get_ref(&self) -> InternRef<'_> {
+    self.intern.get_ref()
+  }
+
+  pub fn resolve(&self) -> String {
+    self.intern.resolve()
+  }
+
+  // the same in every namespace
+  pub fn symbol(&self) -> Symbol {
+    self.intern.symbol()
+  }
+
+  pub fn intern(&self) -> &Intern {
+    &self.intern
+  }
+
+  pub fn into_intern(self) -> Intern {
+    self.intern
+  }
+}
+
+impl<N> Clone for TaggedIntern<N> {
+  fn clone(&self) -> Self {
+    Self::new(self.intern.clone())
+  }
+}
+
+impl<N> fmt::Debug for TaggedIntern<N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("TaggedIntern")
+      .field("namespace", &any::type_name::<N>())
+      .field("intern", &self.intern)
+      .finish()
+  }
+}
+
+impl<N> fmt::Display for TaggedIntern<N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Display::fmt(&self.intern, f)
+  }
+}
+
+impl<N> PartialEq for TaggedIntern<N> {
+  fn eq(&self, other: &Self) -> bool {
+    self.intern == other.intern
+  }
+}
+impl<N> Eq for TaggedIntern<N> {}
+
+impl<N> Ord for TaggedIntern<N> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.intern.cmp(&other.intern)
+  }
+}
+
+impl<N> PartialOrd for TaggedIntern<N> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl<N> Hash for TaggedIntern<N> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.intern.hash(state);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::unsync::{CaseFolding, InternerBuilder};
+
+  enum Ident {}
+  enum Literal {}
+
+  #[test]
+  fn namespaces_share_one_store() {
+    let interner = InternerBuilder::new()
+      .case_folding(CaseFolding::Ascii)
+      .build();
+    let idents = NamespacedInterner::<Ident>::new(&interner);
+    let literals = NamespacedInterner::<Literal>::new(&interner);
+
+    let x = idents.intern("x");
+    let literal = literals.intern("X");
+    assert_eq!(x, idents.intern("X"));
+    assert_eq!(x.intern(), literal.intern());
+    assert_eq!(x.symbol(), literal.symbol());
+    assert_eq!(&*literal.get_ref(), "x");
+    assert_eq!(interner.stats().entries, 1);
+
+    assert_eq!(literals.get("x"), Some(literal.clone()));
+    assert_eq!(idents.get("y"), None);
+    assert_eq!(idents.tag(literal.intern()), x);
+    let other = Interner::new().intern("y");
+    assert_eq!(idents.tag(&other).into_intern(), interner.intern("y"));
+  }
+}
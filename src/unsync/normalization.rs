@@ -0,0 +1,70 @@
+use crate::unsync::folding::CaseFolding;
+use std::borrow::Cow;
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::{is_nfc, is_nfkc, UnicodeNormalization};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Normalization {
+  #[default]
+  Off,
+  #[cfg(feature = "unicode-normalization")]
+  Nfc,
+  #[cfg(feature = "unicode-normalization")]
+  Nfkc,
+}
+
+impl Normalization {
+  pub(crate) fn normalize(self, s: &str) -> Cow<'_, str> {
+    match self {
+      #[cfg(feature = "unicode-normalization")]
+      Normalization::Nfc if !is_nfc(s) => Cow::Owned(s.nfc().collect()),
+      #[cfg(feature = "unicode-normalization")]
+      Normalization::Nfkc if !is_nfkc(s) => Cow::Owned(s.nfkc().collect()),
+      _ => Cow::Borrowed(s),
+    }
+  }
+
+  // case folded, then normalized
+  pub(crate) fn canonical(
+    self,
+    case_folding: CaseFolding,
+    s: &str,
+  ) -> Cow<'_, str> {
+    let folded = case_folding.fold(s);
+    match self.normalize(&folded) {
+      Cow::Borrowed(_) => folded,
+      Cow::Owned(normalized) => Cow::Owned(normalized),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn normalization_off() {
+    let decomposed = "e\u{301}";
+    assert_eq!(Normalization::Off.normalize(decomposed), decomposed);
+    assert_eq!(
+      Normalization::Off.canonical(CaseFolding::Ascii, "E\u{301}"),
+      decomposed
+    );
+  }
+
+  #[cfg(feature = "unicode-normalization")]
+  #[test]
+  fn normalization() {
+    assert_eq!(Normalization::Nfc.normalize("e\u{301}"), "é");
+    assert_eq!(Normalization::Nfc.normalize("ﬁ"), "ﬁ");
+    assert_eq!(Normalization::Nfkc.normalize("ﬁ"), "fi");
+    assert!(matches!(
+      Normalization::Nfc.normalize("é"),
+      Cow::Borrowed(_)
+    ));
+    assert_eq!(
+      Normalization::Nfc.canonical(CaseFolding::Ascii, "E\u{301}"),
+      "é"
+    );
+  }
+}
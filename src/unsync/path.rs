@@ -0,0 +1,111 @@
+use crate::unsync::{
+  bytes::{ByteInterner, InternBytes},
+  sequence::{InternSequence, SequenceInterner},
+};
+use std::{
+  ffi::OsStr,
+  fmt,
+  path::{Path, PathBuf},
+  rc::Rc,
+};
+
+// components are interned once as bytes, and paths as sequences of them
+pub(crate) struct PathTable {
+  components: Rc<ByteInterner>,
+  paths: Rc<SequenceInterner<InternBytes>>,
+}
+
+impl PathTable {
+  pub(crate) fn new() -> Self {
+    Self {
+      components: ByteInterner::new(),
+      paths: SequenceInterner::new(),
+    }
+  }
+
+  pub(crate) fn intern(&self, path: &Path) -> InternPath {
+    let components = path
+      .components()
+      .map(|component| {
+        self
+          .components
+          .intern(component.as_os_str().as_encoded_bytes())
+      })
+      .collect::<Vec<_>>();
+
+    InternPath {
+      components: self.paths.intern(components),
+    }
+  }
+}
+
+impl fmt::Debug for PathTable {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("PathTable")
+      .field("paths", &self.paths.len())
+      .finish_non_exhaustive()
+  }
+}
+
+// split as Path::components does, so a/./b is the same path as a/b
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InternPath {
+  components: InternSequence<InternBytes>,
+}
+
+impl InternPath {
+  pub fn components(
+    &self,
+  ) -> impl DoubleEndedIterator<Item = &OsStr> + ExactSizeIterator {
+    self.components.get_ref().iter().map(|component| {
+      // SAFETY: the bytes came from `OsStr::as_encoded_bytes` in this process
+      unsafe { OsStr::from_encoded_bytes_unchecked(component.as_bytes()) }
+    })
+  }
+
+  pub fn to_path_buf(&self) -> PathBuf {
+    self.components().collect()
+  }
+
+  pub fn len(&self) -> usize {
+    self.components.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.components.is_empty()
+  }
+
+  // both paths have to come from the same interner
+  pub fn starts_with(&self, base: &InternPath) -> bool {
+    self
+      .components
+      .get_ref()
+      .starts_with(base.components.get_ref())
+  }
+}
+
+impl fmt::Debug for InternPath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("InternPath")
+      .field(&self.to_path_buf())
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::unsync::Interner;
+  use std::path::Path;
+
+  #[test]
+  fn interner_interns_paths() {
+    let interner = Interner::new();
+    let lib = interner.intern_path("/src/crate/lib.rs");
+    let dir = interner.intern_path("/src/crate");
+
+    assert_eq!(lib, interner.intern_path("/src//crate/./lib.rs"));
+    assert_eq!(lib.to_path_buf(), Path::new("/src/crate/lib.rs"));
+    assert!(lib.starts_with(&dir) && !dir.starts_with(&lib));
+    assert_eq!(interner.stats().stored_bytes, 0);
+  }
+}
@@ -0,0 +1,209 @@
+use crate::unsync::trie::{Span, Trie};
+use std::{
+  cell::RefCell,
+  cmp::Ordering,
+  fmt,
+  hash::{Hash, Hasher},
+  rc::Rc,
+  slice,
+};
+
+const MIN_CHUNK: usize = 256;
+
+pub struct SequenceInterner<T: Clone + Eq + Hash> {
+  internal: RefCell<SequenceInternal<T>>,
+}
+
+struct SequenceInternal<T: Clone + Eq + Hash> {
+  trie: Trie<T>,
+  // chunks are never pushed to past their capacity, so they never move
+  chunks: Vec<Chunk<T>>,
+}
+
+struct Chunk<T> {
+  start: usize,
+  elements: Vec<T>,
+}
+
+impl<T: Clone + Eq + Hash> SequenceInterner<T> {
+  pub fn new() -> Rc<Self> {
+    Rc::new(Self {
+      internal: RefCell::new(SequenceInternal {
+        trie: Trie::new(),
+        chunks: Vec::new(),
+      }),
+    })
+  }
+
+  pub fn intern<S: AsRef<[T]>>(self: &Rc<Self>, s: S) -> InternSequence<T> {
+    let s = s.as_ref();
+    let mut internal = self.internal.borrow_mut();
+
+    let span = match internal.trie.get(s) {
+      Some(span) => span,
+      None => {
+        let span = internal.push(s);
+        internal.trie.insert(s, span)
+      },
+    };
+
+    InternSequence {
+      span,
+      interner: Rc::clone(self),
+    }
+  }
+
+  pub fn get<S: AsRef<[T]>>(
+    self: &Rc<Self>,
+    s: S,
+  ) -> Option<InternSequence<T>> {
+    let span = self.internal.borrow().trie.get(s.as_ref())?;
+
+    Some(InternSequence {
+      span,
+      interner: Rc::clone(self),
+    })
+  }
+
+  pub fn len(&self) -> usize {
+    self.internal.borrow().trie.entries()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+impl<T: Clone + Eq + Hash> SequenceInternal<T> {
+  fn push(&mut self, s: &[T]) -> Span {
+    let fits = self.chunks.last().is_some_and(|chunk| {
+      chunk.elements.capacity() - chunk.elements.len() >= s.len()
+    });
+    if !fits {
+      let (start, last) = self.chunks.last().map_or((0, 0), |chunk| {
+        (
+          chunk.start + chunk.elements.capacity(),
+          chunk.elements.capacity(),
+        )
+      });
+      let capacity = (last * 2).max(s.len()).max(MIN_CHUNK);
+      self.chunks.push(Chunk {
+        start,
+        elements: Vec::with_capacity(capacity),
+      });
+    }
+
+    let chunk = self.chunks.last_mut().unwrap();
+    let start = chunk.start + chunk.elements.len();
+    chunk.elements.extend_from_slice(s);
+    start..(start + s.len())
+  }
+
+  fn get(&self, span: Span) -> *const T {
+    let chunk = self
+      .chunks
+      .partition_point(|chunk| chunk.start <= span.start);
+    let chunk = &self.chunks[chunk - 1];
+    chunk.elements[span.start - chunk.start..].as_ptr()
+  }
+}
+
+pub struct InternSequence<T: Clone + Eq + Hash> {
+  span: Span,
+  interner: Rc<SequenceInterner<T>>,
+}
+
+impl<T: Clone + Eq + Hash> InternSequence<T> {
+  pub fn get_ref(&self) -> &[T] {
+    if self.span.is_empty() {
+      return &[];
+    }
+
+    let elements = self.interner.internal.borrow().get(self.span.clone());
+    // SAFETY: the elements won't be touched until the interner is dropped
+    unsafe { slice::from_raw_parts(elements, self.span.len()) }
+  }
+
+  pub fn resolve(&self) -> Vec<T> {
+    self.get_ref().to_vec()
+  }
+
+  pub fn len(&self) -> usize {
+    self.span.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.span.is_empty()
+  }
+}
+
+impl<T: Clone + Eq + Hash> Clone for InternSequence<T> {
+  fn clone(&self) -> Self {
+    Self {
+      span: self.span.clone(),
+      interner: Rc::clone(&self.interner),
+    }
+  }
+}
+
+impl<T: Clone + Eq + Hash + fmt::Debug> fmt::Debug for InternSequence<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("InternSequence")
+      .field("span", &self.span)
+      .field("elements", &self.get_ref())
+      .finish()
+  }
+}
+
+impl<T: Clone + Eq + Hash> PartialEq for InternSequence<T> {
+  fn eq(&self, other: &Self) -> bool {
+    Rc::ptr_eq(&self.interner, &other.interner) && self.span == other.span
+  }
+}
+impl<T: Clone + Eq + Hash> Eq for InternSequence<T> {}
+
+impl<T: Clone + Eq + Hash> Ord for InternSequence<T> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    Rc::as_ptr(&self.interner)
+      .cmp(&Rc::as_ptr(&other.interner))
+      .then(self.span.start.cmp(&other.span.start))
+      .then(self.span.end.cmp(&other.span.end))
+  }
+}
+
+impl<T: Clone + Eq + Hash> PartialOrd for InternSequence<T> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl<T: Clone + Eq + Hash> Hash for InternSequence<T> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    Rc::as_ptr(&self.interner).hash(state);
+    self.span.hash(state);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  #[cfg_attr(miri, ignore = "too slow under Miri")]
+  fn sequence_interner() {
+    let interner = SequenceInterner::new();
+    let sequences = (0..1000u32)
+      .map(|i| interner.intern((0..i % 300).collect::<Vec<_>>()))
+      .collect::<Vec<_>>();
+
+    assert_eq!(interner.len(), 300);
+    for (i, sequence) in sequences.iter().enumerate() {
+      let expected = (0..i as u32 % 300).collect::<Vec<_>>();
+      assert_eq!(sequence.get_ref(), expected);
+      assert_eq!(*sequence, sequences[i % 300]);
+      assert_eq!(interner.get(&expected).as_ref(), Some(sequence));
+    }
+    assert!(sequences[0].is_empty());
+    assert_eq!(interner.get([1]), None);
+  }
+}
@@ -0,0 +1,75 @@
+use std::{iter, ops::Range};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceId(pub u32);
+
+// both count from 1, and columns count chars
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineColumn {
+  pub line: usize,
+  pub column: usize,
+}
+
+// byte offsets of the start of each line, the first being 0
+#[derive(Debug)]
+pub(crate) struct Lines {
+  starts: Vec<usize>,
+}
+
+impl Lines {
+  pub(crate) fn new(text: &str) -> Self {
+    Self {
+      starts: iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect(),
+    }
+  }
+
+  pub(crate) fn locate(&self, text: &str, offset: usize) -> Option<LineColumn> {
+    if !text.is_char_boundary(offset) {
+      return None;
+    }
+
+    let line = self.starts.partition_point(|&start| start <= offset);
+    let start = self.starts[line - 1];
+    Some(LineColumn {
+      line,
+      column: text[start..offset].chars().count() + 1,
+    })
+  }
+
+  // leaves out the line ending
+  pub(crate) fn line(&self, text: &str, line: usize) -> Option<Range<usize>> {
+    let start = *self.starts.get(line.checked_sub(1)?)?;
+    let end = self.starts.get(line).map_or(text.len(), |&next| next - 1);
+    let end = match text[start..end].ends_with('\r') {
+      true => end - 1,
+      false => end,
+    };
+    Some(start..end)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lines_locate_offsets() {
+    let text = "a\r\n🦀b\n";
+    let lines = Lines::new(text);
+
+    assert_eq!(
+      lines.locate(text, 7),
+      Some(LineColumn { line: 2, column: 2 })
+    );
+    assert_eq!(
+      lines.locate(text, 9),
+      Some(LineColumn { line: 3, column: 1 })
+    );
+    assert_eq!(lines.locate(text, 4), None);
+    assert_eq!(&text[lines.line(text, 1).unwrap()], "a");
+    assert_eq!(&text[lines.line(text, 2).unwrap()], "🦀b");
+    assert_eq!(lines.line(text, 4), None);
+  }
+}
@@ -0,0 +1,11 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InternerStats {
+  pub stored_bytes: usize,
+  pub allocated_bytes: usize,
+  pub entries: usize,
+  pub trie_nodes: usize,
+  pub automaton_states: usize,
+  pub outstanding_refs: usize,
+  pub hits: u64,
+  pub misses: u64,
+}
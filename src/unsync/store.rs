@@ -0,0 +1,502 @@
+use crate::unsync::trie::Span;
+use std::{
+  alloc::{self, Layout},
+  cell::Cell,
+  ptr, slice,
+};
+
+const FIRST_BUCKET_BITS: u32 = 12;
+const BUCKETS: usize = (usize::BITS - FIRST_BUCKET_BITS) as usize;
+
+// set on spans that point straight at a &'static str instead of the store
+const STATIC_BIT: usize = 1 << (usize::BITS - 1);
+
+/// Append-only byte storage made of geometrically growing buckets that never
+/// move once allocated, so pushing never invalidates a previously returned
+/// span. See `sync::store::Store` for the layout.
+pub(crate) struct Store {
+  buckets: [Cell<*mut u8>; BUCKETS],
+  sizes: [Cell<usize>; BUCKETS],
+  filled: [Cell<usize>; BUCKETS],
+  len: Cell<usize>,
+  stored: Cell<usize>,
+  allocated: Cell<usize>,
+  // bumped whenever stored text changes, so that a Stager can tell if it was
+  // interrupted by something else writing to the store
+  generation: Cell<usize>,
+}
+
+fn bucket_start(bucket: usize) -> usize {
+  ((1 << bucket) - 1) << FIRST_BUCKET_BITS
+}
+
+fn bucket_capacity(bucket: usize) -> usize {
+  1 << (FIRST_BUCKET_BITS as usize + bucket)
+}
+
+fn bucket_of(offset: usize) -> usize {
+  let scaled = (offset >> FIRST_BUCKET_BITS) + 1;
+  (usize::BITS - 1 - scaled.leading_zeros()) as usize
+}
+
+fn layout(size: usize) -> Layout {
+  Layout::array::<u8>(size).expect("bucket too large")
+}
+
+// length of the longest prefix of `s` that `tail` ends with, using the
+// Knuth-Morris-Pratt failure function of `s`
+fn overlap(tail: &[u8], s: &[u8]) -> usize {
+  let mut fallback = vec![0; s.len()];
+  let mut matched = 0;
+  for i in 1..s.len() {
+    while matched > 0 && s[i] != s[matched] {
+      matched = fallback[matched - 1];
+    }
+    if s[i] == s[matched] {
+      matched += 1;
+    }
+    fallback[i] = matched;
+  }
+
+  // `tail` is no longer than `s`, so `s` can only match in full at the end
+  let mut matched = 0;
+  for &b in tail {
+    while matched > 0 && b != s[matched] {
+      matched = fallback[matched - 1];
+    }
+    if b == s[matched] {
+      matched += 1;
+    }
+  }
+
+  matched
+}
+
+impl Store {
+  pub(crate) fn new() -> Self {
+    Self {
+      buckets: std::array::from_fn(|_| Cell::new(ptr::null_mut())),
+      sizes: std::array::from_fn(|_| Cell::new(0)),
+      filled: std::array::from_fn(|_| Cell::new(0)),
+      len: Cell::new(0),
+      stored: Cell::new(0),
+      allocated: Cell::new(0),
+      generation: Cell::new(0),
+    }
+  }
+
+  // None if `s` lives too high in memory, which only happens on some 32-bit
+  // platforms
+  pub(crate) fn static_span(s: &'static str) -> Option<Span> {
+    // the address is turned back into a pointer by `Store::get_bytes`
+    let start = s.as_ptr().expose_provenance();
+    let end = start + s.len();
+    (end & STATIC_BIT == 0).then_some((start | STATIC_BIT)..(end | STATIC_BIT))
+  }
+
+  pub(crate) fn is_static(span: &Span) -> bool {
+    span.start & STATIC_BIT != 0
+  }
+
+  pub(crate) fn stored_bytes(&self) -> usize {
+    self.stored.get()
+  }
+
+  pub(crate) fn allocated_bytes(&self) -> usize {
+    self.allocated.get()
+  }
+
+  fn bucket_base(&self, bucket: usize) -> *mut u8 {
+    let mut base = self.buckets[bucket].get();
+    if base.is_null() {
+      let size = bucket_capacity(bucket);
+      // SAFETY: buckets are never empty
+      base = unsafe { alloc::alloc(layout(size)) };
+      if base.is_null() {
+        alloc::handle_alloc_error(layout(size));
+      }
+      self.buckets[bucket].set(base);
+      self.sizes[bucket].set(size);
+      self.allocated.set(self.allocated.get() + size);
+    }
+
+    base
+  }
+
+  unsafe fn free_bucket(&self, bucket: usize) {
+    let base = self.buckets[bucket].replace(ptr::null_mut());
+    if !base.is_null() {
+      let size = self.sizes[bucket].replace(0);
+      // SAFETY: the bucket was allocated, or last reallocated, with this
+      //         size, and the caller promises nothing reads from it again
+      unsafe { alloc::dealloc(base, layout(size)) };
+      self.allocated.set(self.allocated.get() - size);
+    }
+    self.filled[bucket].set(0);
+  }
+
+  pub(crate) fn reserve(&self, additional: usize) {
+    let len = self.len.get();
+    if additional == 0 {
+      return;
+    }
+
+    let end = len.checked_add(additional).expect("capacity overflow");
+    for bucket in bucket_of(len)..=bucket_of(end - 1) {
+      self.bucket_base(bucket);
+    }
+  }
+
+  pub(crate) fn push(&self, s: &[u8]) -> Span {
+    let start = self.len.get();
+    let span = self.append_staged(start..start, s);
+    self.commit(span.clone());
+    span
+  }
+
+  // like push, but if the store already ends with the start of `s`, only the
+  // rest is copied and the span begins inside the text before it
+  pub(crate) fn push_overlapping(&self, s: &[u8]) -> Span {
+    let len = self.len.get();
+    let Some(bucket) = len.checked_sub(1).map(bucket_of) else {
+      return self.push(s);
+    };
+    let bucket_end = bucket_start(bucket) + self.sizes[bucket].get();
+    // a bucket that was shrunk to fit ends before the end of the store
+    if len > bucket_end {
+      return self.push(s);
+    }
+    let tail_start = bucket_start(bucket).max(len.saturating_sub(s.len()));
+    // SAFETY: everything in the bucket up to the end of the store was pushed
+    let tail = unsafe { self.get_bytes(tail_start..len) };
+    let overlap = overlap(tail, s);
+    if overlap == 0 || len + s.len() - overlap > bucket_end {
+      return self.push(s);
+    }
+
+    let rest = self.append_staged(len..len, &s[overlap..]);
+    self.commit(rest);
+    (len - overlap)..(len - overlap + s.len())
+  }
+
+  // stages text past the end of the store, to be read back before deciding
+  // whether to keep it
+  pub(crate) fn stager(&self) -> Stager<'_> {
+    let start = self.len.get();
+    Stager {
+      store: self,
+      generation: self.generation.get(),
+      staged: start..start,
+    }
+  }
+
+  // `staged` must be the last span staged, with nothing written since. Text
+  // that no longer fits moves to the next bucket that is large enough.
+  fn append_staged(&self, staged: Span, s: &[u8]) -> Span {
+    if s.is_empty() {
+      return staged;
+    }
+
+    let len = staged.len() + s.len();
+    let mut start = staged.start;
+    let mut bucket = bucket_of(start);
+    if start + len > bucket_start(bucket) + bucket_capacity(bucket) {
+      bucket += 1;
+      while bucket_capacity(bucket) < len {
+        bucket += 1;
+      }
+      start = bucket_start(bucket);
+    }
+
+    let base = self.bucket_base(bucket);
+
+    // SAFETY: the destination lies within the bucket and past every span
+    //         handed out so far, so no InternRef can be looking at it. When
+    //         the text moves, it moves to a different bucket.
+    unsafe {
+      let dst = base.add(start - bucket_start(bucket));
+      if start != staged.start {
+        let moved = self.get_bytes(staged.clone());
+        ptr::copy_nonoverlapping(moved.as_ptr(), dst, moved.len());
+      }
+      ptr::copy_nonoverlapping(s.as_ptr(), dst.add(staged.len()), s.len());
+    }
+
+    start..(start + len)
+  }
+
+  // `span` must be the last one staged, with nothing written since
+  pub(crate) fn commit(&self, span: Span) {
+    if span.is_empty() {
+      return;
+    }
+
+    let bucket = bucket_of(span.start);
+    self.filled[bucket].set(span.end - bucket_start(bucket));
+    self.len.set(span.end);
+    self.stored.set(self.stored.get() + span.len());
+    self.generation.set(self.generation.get() + 1);
+  }
+
+  /// # Safety
+  ///
+  /// No span handed out so far may be read again afterwards.
+  pub(crate) unsafe fn clear(&self) {
+    for bucket in 0..BUCKETS {
+      // SAFETY: passed on from the caller
+      unsafe { self.free_bucket(bucket) };
+    }
+
+    self.len.set(0);
+    self.stored.set(0);
+    self.generation.set(self.generation.get() + 1);
+  }
+
+  /// # Safety
+  ///
+  /// No InternRefs may be alive, since the last bucket in use may move.
+  pub(crate) unsafe fn shrink_to_fit(&self) -> usize {
+    self.generation.set(self.generation.get() + 1);
+    let before = self.allocated_bytes();
+    let len = self.len.get();
+    let in_use = if len == 0 { 0 } else { bucket_of(len - 1) + 1 };
+
+    for bucket in in_use..BUCKETS {
+      // SAFETY: nothing has been pushed to these buckets, so no span lies
+      //         in them
+      unsafe { self.free_bucket(bucket) };
+    }
+
+    if let Some(last) = in_use.checked_sub(1) {
+      let used = self.filled[last].get();
+      let size = self.sizes[last].get();
+      if used < size {
+        // SAFETY: the bucket was allocated with `size`, `used` isn't 0 since
+        //         the end of the store lies in it, and the caller promises
+        //         no InternRef is reading from it while it may move
+        let base = unsafe {
+          alloc::realloc(self.buckets[last].get(), layout(size), used)
+        };
+        if base.is_null() {
+          alloc::handle_alloc_error(layout(used));
+        }
+
+        self.buckets[last].set(base);
+        self.sizes[last].set(used);
+        self.allocated.set(self.allocated.get() - (size - used));
+        // the bucket is full now, so the next push goes to the next one
+        self.len.set(bucket_start(last + 1));
+      }
+    }
+
+    before - self.allocated_bytes()
+  }
+
+  /// # Safety
+  ///
+  /// `span` must have been produced by pushing a [str] onto this store or by
+  /// [Store::static_span], or be a char-aligned sub-range of such a span.
+  pub(crate) unsafe fn get(&self, span: Span) -> &str {
+    // SAFETY: passed on from the caller, and strs are only ever cut at char
+    //         boundaries, so the bytes are valid UTF-8
+    unsafe { std::str::from_utf8_unchecked(self.get_bytes(span)) }
+  }
+
+  /// Reads back a span of raw bytes.
+  ///
+  /// # Safety
+  ///
+  /// `span` must have been produced by [Store::push] on this store or by
+  /// [Store::static_span], or be a sub-range of such a span.
+  pub(crate) unsafe fn get_bytes(&self, span: Span) -> &[u8] {
+    if span.is_empty() {
+      return &[];
+    }
+
+    if Self::is_static(&span) {
+      let data = ptr::with_exposed_provenance::<u8>(span.start & !STATIC_BIT);
+      // SAFETY: the span was made from a &'static str, which lives forever
+      return unsafe { slice::from_raw_parts(data, span.len()) };
+    }
+
+    let bucket = bucket_of(span.start);
+    let base = self.buckets[bucket].get();
+    debug_assert!(!base.is_null());
+
+    // SAFETY: the bytes were written before the span was handed out, and
+    //         buckets are only moved while nothing is reading them
+    unsafe {
+      let data = base.add(span.start - bucket_start(bucket));
+      slice::from_raw_parts(data, span.len())
+    }
+  }
+
+  /// Copies every stored string out into one contiguous [String]
+  pub(crate) fn concat(&self) -> String {
+    let mut out = String::new();
+    for bucket in 0..BUCKETS {
+      let filled = self.filled[bucket].get();
+      if filled > 0 {
+        let start = bucket_start(bucket);
+        // SAFETY: the filled part of every bucket was pushed as strs
+        out.push_str(unsafe { self.get(start..(start + filled)) });
+      }
+    }
+
+    out
+  }
+}
+
+impl Drop for Store {
+  fn drop(&mut self) {
+    // SAFETY: nothing can borrow from the store while it is being dropped
+    unsafe { self.clear() };
+  }
+}
+
+impl std::fmt::Debug for Store {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Store")
+      .field("len", &self.len.get())
+      .finish()
+  }
+}
+
+// writes text past the end of a Store without pushing it, so the text can be
+// read back and then committed or abandoned
+pub(crate) struct Stager<'a> {
+  store: &'a Store,
+  generation: usize,
+  staged: Span,
+}
+
+impl Stager<'_> {
+  fn interrupted(&self) -> bool {
+    self.store.generation.get() != self.generation
+  }
+
+  // gives up if anything else wrote to the store in the meantime, such as a
+  // Display impl that interns into the same interner
+  pub(crate) fn finish(self) -> Option<Span> {
+    (!self.interrupted()).then_some(self.staged)
+  }
+}
+
+impl std::fmt::Write for Stager<'_> {
+  fn write_str(&mut self, s: &str) -> std::fmt::Result {
+    if self.interrupted() {
+      return Err(std::fmt::Error);
+    }
+
+    self.staged = self.store.append_staged(self.staged.clone(), s.as_bytes());
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+#[allow(clippy::undocumented_unsafe_blocks)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn store() {
+    let store = Store::new();
+    let hello = store.push(b"hello");
+    let big = "x".repeat(bucket_capacity(0));
+    let big_span = store.push(big.as_bytes());
+
+    assert_eq!(0..5, hello);
+    assert_eq!(bucket_start(1), big_span.start);
+    assert_eq!("hello", unsafe { store.get(hello) });
+    assert_eq!(big, unsafe { store.get(big_span) });
+    assert_eq!(format!("hello{big}"), store.concat());
+
+    unsafe { store.clear() };
+    assert_eq!(0, store.allocated_bytes());
+    assert_eq!(0..3, store.push(b"abc"));
+    assert_eq!("abc", store.concat());
+  }
+
+  #[test]
+  fn store_pushes_overlapping_strings() {
+    let store = Store::new();
+    let config = store.push_overlapping(b"config");
+    let figure = store.push_overlapping(b"figure");
+    let zz = store.push_overlapping(b"zz");
+
+    assert_eq!(0..6, config);
+    assert_eq!(3..9, figure);
+    assert_eq!(9..11, zz);
+    assert_eq!("figure", unsafe { store.get(figure) });
+    assert_eq!("configurezz", store.concat());
+    assert_eq!(11, store.stored_bytes());
+    assert_eq!(2, overlap(b"abab", b"abc"));
+  }
+
+  #[test]
+  fn store_reads_static_spans() {
+    let store = Store::new();
+    let keyword = Store::static_span("keyword").unwrap();
+
+    assert!(Store::is_static(&keyword));
+    assert_eq!("keyword", unsafe { store.get(keyword.clone()) });
+    let key = keyword.start..(keyword.start + 3);
+    assert_eq!("key", unsafe { store.get(key) });
+    assert_eq!("", store.concat());
+  }
+
+  #[test]
+  #[should_panic = "capacity overflow"]
+  fn store_does_not_reserve_past_the_address_space() {
+    let store = Store::new();
+    store.push(b"hello");
+    store.reserve(usize::MAX);
+  }
+
+  #[test]
+  fn store_shrinks_to_fit() {
+    let store = Store::new();
+    store.reserve(bucket_capacity(0) + bucket_capacity(1));
+    store.push(b"hello");
+
+    assert_eq!(3 * bucket_capacity(0) - 5, unsafe { store.shrink_to_fit() });
+    assert_eq!(5, store.allocated_bytes());
+    assert_eq!(bucket_start(1), store.push_overlapping(b"lower").start);
+    assert_eq!("hellolower", store.concat());
+  }
+
+  #[test]
+  fn store_stages_text() {
+    use std::fmt::Write;
+
+    let store = Store::new();
+    let mut stager = store.stager();
+    let world = "world";
+    write!(stager, "hello {world}").unwrap();
+    let staged = stager.finish().unwrap();
+    assert_eq!("hello world", unsafe { store.get(staged.clone()) });
+    assert_eq!(0, store.stored_bytes());
+
+    let mut stager = store.stager();
+    stager.write_str("hi").unwrap();
+    let hi = stager.finish().unwrap();
+    assert_eq!(0..2, hi);
+    store.commit(hi);
+    assert_eq!(2..5, store.push(b"abc"));
+    assert_eq!("hiabc", unsafe { store.get(0..5) });
+    assert_eq!(5, store.stored_bytes());
+
+    let mut stager = store.stager();
+    let big = "x".repeat(bucket_capacity(0));
+    write!(stager, "moved{big}").unwrap();
+    let moved = stager.finish().unwrap();
+    assert_eq!(bucket_start(1), moved.start);
+    assert_eq!(format!("moved{big}"), unsafe { store.get(moved) });
+
+    let mut stager = store.stager();
+    stager.write_str("interrupted").unwrap();
+    store.push(b"def");
+    assert!(stager.write_str("!").is_err());
+    assert_eq!(None, stager.finish());
+  }
+}
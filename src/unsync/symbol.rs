@@ -0,0 +1,13 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SymbolId(pub u32);
+
+// doesn't keep a reference to the interner, so the strings these are handed
+// out for are never collected
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(pub(crate) SymbolId);
+
+impl Symbol {
+  pub fn id(self) -> SymbolId {
+    self.0
+  }
+}
@@ -1,113 +1,470 @@
-use std::{collections::HashMap, hash::Hash, ops::Range};
+use std::{
+  cmp::Reverse,
+  collections::HashMap,
+  hash::Hash,
+  mem,
+  ops::{Bound, Range, RangeBounds},
+};
 
 pub(crate) type Span = Range<usize>;
 
+#[cfg(test)]
 pub(crate) fn span(start: usize, length: usize) -> Span {
   start..(start + length)
 }
 
+/// Maps every interned sequence to where it lives in the store. This is a
+/// radix trie, so runs of elements without branches share a single node.
 #[derive(Debug)]
 pub(crate) struct Trie<T: Clone + Eq + Hash> {
-  span: Span,
-  leaf_map: HashMap<T, Trie<T>>,
+  root: Node<T>,
+  nodes: usize,
+  entries: usize,
+}
+
+#[derive(Debug)]
+struct Node<T: Clone + Eq + Hash> {
+  /// The elements on the edge leading into this node
+  label: Vec<T>,
+  span: Option<Span>,
+  /// Children keyed by the first element of their label
+  leaf_map: HashMap<T, Node<T>>,
+}
+
+impl<T: Clone + Eq + Hash> Node<T> {
+  fn new(label: Vec<T>) -> Self {
+    Self {
+      label,
+      span: None,
+      leaf_map: HashMap::new(),
+    }
+  }
+}
+
+fn common_prefix_len<T: Eq>(a: &[T], b: &[T]) -> usize {
+  a.iter().zip(b).take_while(|(a, b)| a == b).count()
 }
 
 impl<T: Clone + Eq + Hash> Trie<T> {
   pub(crate) fn new() -> Self {
     Self {
-      span: span(0, 0),
-      leaf_map: HashMap::new(),
+      root: Node::new(Vec::new()),
+      nodes: 0,
+      entries: 0,
     }
   }
 
-  pub(crate) fn get<A: Iterator<Item = T> + Clone>(
-    &mut self,
-    mut key: A,
-  ) -> Option<Span> {
-    let mut cursor = self;
+  /// Number of nodes below the root
+  pub(crate) fn nodes(&self) -> usize {
+    self.nodes
+  }
 
-    loop {
-      match key.next() {
-        None => return Some(cursor.span.clone()),
-        Some(k) => {
-          cursor = cursor.leaf_map.get_mut(&k)?;
-          continue;
-        },
+  /// Number of sequences that have been inserted
+  pub(crate) fn entries(&self) -> usize {
+    self.entries
+  }
+
+  /// Makes room for at least `additional` more distinct first elements
+  pub(crate) fn reserve(&mut self, additional: usize) {
+    self.root.leaf_map.reserve(additional);
+  }
+
+  /// Releases the spare capacity held by every node
+  pub(crate) fn shrink_to_fit(&mut self) {
+    let mut stack = vec![&mut self.root];
+    while let Some(node) = stack.pop() {
+      node.label.shrink_to_fit();
+      node.leaf_map.shrink_to_fit();
+      stack.extend(node.leaf_map.values_mut());
+    }
+  }
+
+  pub(crate) fn get(&self, key: &[T]) -> Option<Span> {
+    let mut cursor = &self.root;
+    let mut rest = key;
+    while let Some(first) = rest.first() {
+      cursor = cursor.leaf_map.get(first)?;
+      rest = rest.strip_prefix(&cursor.label[..])?;
+    }
+
+    cursor.span.clone()
+  }
+
+  /// Finds the span of the longest sequence that `key` starts with
+  pub(crate) fn longest_prefix(&self, key: &[T]) -> Option<Span> {
+    let mut cursor = &self.root;
+    let mut rest = key;
+    let mut found = cursor.span.clone();
+    while let Some(first) = rest.first() {
+      let Some(child) = cursor.leaf_map.get(first) else {
+        break;
+      };
+      let Some(after) = rest.strip_prefix(&child.label[..]) else {
+        break;
+      };
+
+      cursor = child;
+      rest = after;
+      if cursor.span.is_some() {
+        found = cursor.span.clone();
       }
     }
+
+    found
   }
 
-  pub(crate) fn insert<A: Iterator<Item = T> + Clone>(
-    &mut self,
-    mut key: A,
-    mut start: usize,
-  ) -> Span {
-    let span = self.insert_one(key.clone(), start);
+  /// Finds the spans of every sequence that starts with `prefix`, in no
+  /// particular order
+  pub(crate) fn with_prefix(&self, prefix: &[T]) -> Vec<Span> {
+    let mut cursor = &self.root;
+    let mut rest = prefix;
+    while let Some(first) = rest.first() {
+      cursor = match cursor.leaf_map.get(first) {
+        Some(child) => child,
+        None => return Vec::new(),
+      };
 
-    // insert all substrings
-    loop {
-      start += 1;
-      if key.next().is_none() {
+      // the prefix may end partway along an edge
+      if cursor.label.starts_with(rest) {
         break;
       }
+      rest = match rest.strip_prefix(&cursor.label[..]) {
+        Some(rest) => rest,
+        None => return Vec::new(),
+      };
+    }
 
-      self.insert_one(key.clone(), start);
+    let mut found = Vec::new();
+    let mut stack = vec![cursor];
+    while let Some(node) = stack.pop() {
+      found.extend(node.span.clone());
+      stack.extend(node.leaf_map.values());
     }
 
-    span
-  }
-
-  fn insert_one<A: Iterator<Item = T> + Clone>(
-    &mut self,
-    key: A,
-    start: usize,
-  ) -> Span {
-    let mut cursor = self;
-    let mut span_len = 0;
-
-    for ref t in key {
-      span_len += 1;
-
-      // do not overwrite old entries
-      if !cursor.leaf_map.contains_key(&t) {
-        cursor.leaf_map.insert(
-          t.clone(),
-          Trie {
-            span: span(start, span_len),
-            leaf_map: HashMap::new(),
-          },
-        );
+    found
+  }
+
+  /// Inserts a sequence, returning the span it maps to. Existing entries are
+  /// never overwritten.
+  pub(crate) fn insert(&mut self, key: &[T], span: Span) -> Span {
+    let mut cursor = &mut self.root;
+    let mut rest = key;
+    while let Some(first) = rest.first() {
+      if !cursor.leaf_map.contains_key(first) {
+        cursor
+          .leaf_map
+          .insert(first.clone(), Node::new(rest.to_vec()));
+        self.nodes += 1;
+        cursor = cursor.leaf_map.get_mut(first).unwrap();
+        break;
+      }
+
+      let child = cursor.leaf_map.get_mut(first).unwrap();
+      let common = common_prefix_len(&child.label, rest);
+
+      // split the edge where the key diverges from it
+      if common < child.label.len() {
+        let upper = Node::new(child.label[..common].to_vec());
+        let mut lower = mem::replace(child, upper);
+        lower.label.drain(..common);
+        child.leaf_map.insert(lower.label[0].clone(), lower);
+        self.nodes += 1;
       }
 
-      cursor = cursor.leaf_map.get_mut(&t).unwrap();
+      cursor = child;
+      rest = &rest[common..];
     }
 
-    cursor.span.clone()
+    cursor
+      .span
+      .get_or_insert_with(|| {
+        self.entries += 1;
+        span
+      })
+      .clone()
   }
 }
 
+// long keys make for deep tries, so avoid dropping them recursively
+impl<T: Clone + Eq + Hash> Drop for Trie<T> {
+  fn drop(&mut self) {
+    let mut stack = vec![mem::take(&mut self.root.leaf_map)];
+    while let Some(mut leaf_map) = stack.pop() {
+      stack.extend(
+        leaf_map
+          .drain()
+          .map(|(_, mut node)| mem::take(&mut node.leaf_map)),
+      );
+    }
+  }
+}
+
+impl Trie<u8> {
+  /// Finds the spans of every key that is at most `max_dist` edits away
+  /// from `query`, along with how far away each one is. Edits are inserted,
+  /// deleted or replaced chars. Branches are given up on as soon as every
+  /// key down them must be too far away.
+  pub(crate) fn fuzzy(
+    &self,
+    query: &str,
+    max_dist: usize,
+  ) -> Vec<(Span, usize)> {
+    let query = query.chars().collect::<Vec<_>>();
+    let mut found = Vec::new();
+    // each node, with the distances from the query's prefixes to the key
+    // up to and including its label, and the bytes of any char that its
+    // label ends partway through
+    let row = (0..=query.len()).collect::<Vec<_>>();
+    let mut stack = vec![(&self.root, row, Vec::new())];
+    'nodes: while let Some((node, mut row, mut partial)) = stack.pop() {
+      for &b in &node.label {
+        partial.push(b);
+        let c = match std::str::from_utf8(&partial) {
+          Ok(c) => c.chars().next().unwrap(),
+          // the char goes on into the next byte
+          Err(e) if e.error_len().is_none() => continue,
+          Err(_) => continue 'nodes,
+        };
+        partial.clear();
+        row = next_row(&row, &query, c);
+        if row.iter().all(|&dist| dist > max_dist) {
+          continue 'nodes;
+        }
+      }
+
+      let dist = row[query.len()];
+      if let (Some(span), true) = (&node.span, dist <= max_dist) {
+        if partial.is_empty() {
+          found.push((span.clone(), dist));
+        }
+      }
+      stack.extend(
+        node
+          .leaf_map
+          .values()
+          .map(|child| (child, row.clone(), partial.clone())),
+      );
+    }
+
+    found
+  }
+
+  /// Finds the spans of every key within `range`, sorted by key. Branches
+  /// whose keys all fall before or after the range are skipped.
+  pub(crate) fn range(&self, range: impl RangeBounds<[u8]>) -> Vec<Span> {
+    let mut found = Vec::new();
+    // each node with the whole key leading to it. Children are pushed last
+    // to first, so nodes come off the stack in order.
+    let mut stack = vec![(&self.root, Vec::new())];
+    while let Some((node, key)) = stack.pop() {
+      // keys only grow further down, and the rest of the stack comes later
+      let past_end = match range.end_bound() {
+        Bound::Included(end) => key[..] > *end,
+        Bound::Excluded(end) => key[..] >= *end,
+        Bound::Unbounded => false,
+      };
+      if past_end {
+        break;
+      }
+
+      let before_start = match range.start_bound() {
+        Bound::Included(start) | Bound::Excluded(start) => {
+          key[..] < *start && !start.starts_with(&key)
+        },
+        Bound::Unbounded => false,
+      };
+      if before_start {
+        continue;
+      }
+
+      if let (Some(span), true) = (&node.span, range.contains(&key[..])) {
+        found.push(span.clone());
+      }
+      let mut children = node.leaf_map.iter().collect::<Vec<_>>();
+      children.sort_unstable_by_key(|(&first, _)| Reverse(first));
+      stack.extend(
+        children
+          .into_iter()
+          .map(|(_, child)| (child, [&key[..], &child.label[..]].concat())),
+      );
+    }
+
+    found
+  }
+}
+
+/// How far `key` is from `query` in edits, like [Trie::fuzzy], or [None] if
+/// it is more than `max_dist` edits away
+pub(crate) fn edit_distance(
+  key: &str,
+  query: &str,
+  max_dist: usize,
+) -> Option<usize> {
+  let query = query.chars().collect::<Vec<_>>();
+  let mut row = (0..=query.len()).collect::<Vec<_>>();
+  for c in key.chars() {
+    row = next_row(&row, &query, c);
+    if row.iter().all(|&dist| dist > max_dist) {
+      return None;
+    }
+  }
+
+  Some(row[query.len()]).filter(|&dist| dist <= max_dist)
+}
+
+/// Steps a row of the Levenshtein table past one more char of the key.
+/// `row[i]` is how many edits it takes to turn the key so far into the
+/// first `i` chars of `query`.
+fn next_row(row: &[usize], query: &[char], c: char) -> Vec<usize> {
+  let mut next = Vec::with_capacity(row.len());
+  next.push(row[0] + 1);
+  for (i, &q) in query.iter().enumerate() {
+    let replace = row[i] + usize::from(q != c);
+    next.push(replace.min(row[i + 1] + 1).min(next[i] + 1));
+  }
+  next
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  fn chars(s: &str) -> Vec<char> {
+    s.chars().collect()
+  }
+
   #[test]
   fn trie() {
     let mut trie = Trie::new();
 
-    let hello_span = trie.insert("hello".chars(), 0);
+    let hello_span = trie.insert(&chars("hello"), span(0, 5));
 
     assert_eq!(span(0, 5), hello_span);
-    assert_eq!(Some(span(0, 4)), trie.get("hell".chars()));
-    assert_eq!(Some(span(2, 2)), trie.get("ll".chars()));
-    assert_eq!(None, trie.get("hohoho".chars()));
-
-    let hoho_span = trie.insert("hoho".chars(), 3);
-
-    assert_eq!(span(3, 4), hoho_span);
-    assert_eq!(Some(span(0, 1)), trie.get("h".chars()));
-    assert_eq!(Some(span(3, 2)), trie.get("ho".chars()));
-    assert_eq!(Some(span(4, 2)), trie.get("oh".chars()));
-    assert_eq!(Some(span(0, 2)), trie.get("he".chars()));
-    assert_eq!(None, trie.get("hi".chars()));
+    assert_eq!(Some(span(0, 5)), trie.get(&chars("hello")));
+    assert_eq!(None, trie.get(&chars("hell")));
+    assert_eq!(None, trie.get(&chars("hohoho")));
+    assert_eq!(None, trie.get(&chars("help")));
+
+    let hell_span = trie.insert(&chars("hell"), span(0, 4));
+    let hello_again = trie.insert(&chars("hello"), span(9, 5));
+
+    assert_eq!(span(0, 4), hell_span);
+    assert_eq!(span(0, 5), hello_again);
+    assert_eq!(Some(span(0, 4)), trie.get(&chars("hell")));
+    assert_eq!(Some(span(0, 5)), trie.get(&chars("hello")));
+  }
+
+  #[test]
+  fn trie_counts() {
+    let mut trie = Trie::new();
+    trie.insert(&chars("abc"), span(0, 3));
+    trie.insert(&chars("bc"), span(1, 2));
+    trie.insert(&chars("ab"), span(0, 2));
+    trie.insert(&chars("abd"), span(3, 3));
+
+    // "ab" -> {"c", "d"}, "bc"
+    assert_eq!(4, trie.nodes());
+    assert_eq!(4, trie.entries());
+    assert_eq!(Some(span(0, 3)), trie.get(&chars("abc")));
+    assert_eq!(Some(span(3, 3)), trie.get(&chars("abd")));
+    assert_eq!(Some(span(0, 2)), trie.get(&chars("ab")));
+    assert_eq!(None, trie.get(&chars("a")));
+  }
+
+  #[test]
+  fn trie_finds_prefixes() {
+    let mut trie = Trie::new();
+    trie.insert(&chars("hello"), span(0, 5));
+    trie.insert(&chars("help"), span(5, 4));
+    trie.insert(&chars("hell"), span(0, 4));
+    trie.insert(&chars("world"), span(9, 5));
+
+    let mut found = trie.with_prefix(&chars("hel"));
+    found.sort_by_key(|span| (span.start, span.end));
+    assert_eq!(vec![span(0, 4), span(0, 5), span(5, 4)], found);
+
+    assert_eq!(2, trie.with_prefix(&chars("hell")).len());
+    assert_eq!(4, trie.with_prefix(&[]).len());
+    assert_eq!(vec![span(9, 5)], trie.with_prefix(&chars("wo")));
+    assert!(trie.with_prefix(&chars("hex")).is_empty());
+    assert!(trie.with_prefix(&chars("helloo")).is_empty());
+  }
+
+  #[test]
+  fn trie_finds_longest_prefix() {
+    let mut trie = Trie::new();
+    trie.insert(&chars("="), span(0, 1));
+    trie.insert(&chars("=="), span(1, 2));
+    trie.insert(&chars("===="), span(3, 4));
+
+    assert_eq!(Some(span(1, 2)), trie.longest_prefix(&chars("=== x")));
+    assert_eq!(Some(span(3, 4)), trie.longest_prefix(&chars("=====")));
+    assert_eq!(Some(span(0, 1)), trie.longest_prefix(&chars("=")));
+    assert_eq!(None, trie.longest_prefix(&chars("x")));
+    assert_eq!(None, trie.longest_prefix(&[]));
+  }
+
+  #[test]
+  fn trie_finds_keys_within_edit_distance() {
+    let mut trie = Trie::new();
+    for (i, key) in ["length", "lengths", "lent", "height", "längth"]
+      .into_iter()
+      .enumerate()
+    {
+      trie.insert(key.as_bytes(), span(i, 1));
+    }
+
+    let mut found = trie.fuzzy("lenght", 2);
+    found.sort_by_key(|(span, _)| span.start);
+    assert_eq!(
+      found,
+      [
+        (span(0, 1), 2),
+        (span(1, 1), 2),
+        (span(2, 1), 2),
+        (span(3, 1), 2)
+      ]
+    );
+    let mut found = trie.fuzzy("length", 1);
+    found.sort_by_key(|(span, _)| span.start);
+    assert_eq!(found, [(span(0, 1), 0), (span(1, 1), 1), (span(4, 1), 1)]);
+    assert!(trie.fuzzy("xyz", 1).is_empty());
+
+    assert_eq!(edit_distance("length", "lenght", 2), Some(2));
+    assert_eq!(edit_distance("längth", "length", 1), Some(1));
+    assert_eq!(edit_distance("width", "lenght", 2), None);
+  }
+
+  #[test]
+  fn trie_finds_keys_in_range_in_order() {
+    let mut trie = Trie::new();
+    for (i, key) in ["b", "abc", "ab", "ba", "c", "a", "bab"]
+      .into_iter()
+      .enumerate()
+    {
+      trie.insert(key.as_bytes(), span(i, 1));
+    }
+
+    let starts = |spans: Vec<Span>| {
+      spans.into_iter().map(|span| span.start).collect::<Vec<_>>()
+    };
+    let range = |start: Bound<&str>, end: Bound<&str>| {
+      starts(trie.range((start.map(str::as_bytes), end.map(str::as_bytes))))
+    };
+    assert_eq!(starts(trie.range(..)), [5, 2, 1, 0, 3, 6, 4]);
+    assert_eq!(
+      range(Bound::Included("ab"), Bound::Excluded("bab")),
+      [2, 1, 0, 3]
+    );
+    assert_eq!(
+      range(Bound::Included("ab"), Bound::Included("bab")),
+      [2, 1, 0, 3, 6]
+    );
+    assert_eq!(
+      range(Bound::Included("abd"), Bound::Unbounded),
+      [0, 3, 6, 4]
+    );
+    assert_eq!(range(Bound::Excluded("b"), Bound::Unbounded), [3, 6, 4]);
+    assert!(range(Bound::Included("d"), Bound::Unbounded).is_empty());
   }
 }